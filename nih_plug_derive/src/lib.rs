@@ -1,6 +1,7 @@
 use proc_macro::TokenStream;
 
 mod enums;
+mod param_id;
 mod params;
 
 /// Derive the `Enum` trait for simple enum parameters. See `EnumParam` for more information.
@@ -10,7 +11,7 @@ pub fn derive_enum(input: TokenStream) -> TokenStream {
 }
 
 /// Derive the `Params` trait for your plugin's parameters struct. See the `Plugin` trait.
-#[proc_macro_derive(Params, attributes(id, persist, nested))]
+#[proc_macro_derive(Params, attributes(id, persist, persist_blob, nested))]
 pub fn derive_params(input: TokenStream) -> TokenStream {
     params::derive_params(input)
 }
@@ -0,0 +1,20 @@
+// This file is also included in `nih_plug::wrapper::util` using `include!()`, so the derive macro's
+// compile time checks and the wrappers always use the same parameter ID rules and hashes.
+
+/// The maximum length of a parameter ID in bytes. Parameter IDs are stored in the plugin's state
+/// and hashed to the numeric IDs the plugin APIs use, so they should be short and stable.
+pub const MAX_PARAM_ID_LEN: usize = 64;
+
+/// A Rabin fingerprint based string hash for parameter ID strings.
+pub fn hash_param_id(id: &str) -> u32 {
+    let mut hash: u32 = 0;
+    for char in id.bytes() {
+        hash = hash.wrapping_mul(31).wrapping_add(char as u32);
+    }
+
+    // In VST3 the last bit is reserved for parameters provided by the host
+    // https://developer.steinberg.help/display/VST/Parameters+and+Automation
+    hash &= !(1 << 31);
+
+    hash
+}
@@ -2,6 +2,8 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::spanned::Spanned;
 
+use crate::param_id::{hash_param_id, MAX_PARAM_ID_LEN};
+
 pub fn derive_params(input: TokenStream) -> TokenStream {
     let ast = syn::parse_macro_input!(input as syn::DeriveInput);
 
@@ -22,17 +24,21 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         }
     };
 
-    // We only care about fields with `id`, `persist`, and `nested` attributes. For the `id` fields
-    // we'll build a mapping function that creates a hashmap containing pointers to those
-    // parameters. For the `persist` function we'll create functions that serialize and deserialize
-    // those fields individually (so they can be added and removed independently of eachother) using
-    // JSON. The `nested` fields should also implement the `Params` trait and their fields will be
-    // inherited and added to this field's param mapping list. The order follows the declaration
-    // order We'll also enforce that there are no duplicate keys for `id` fields at compile time.
-    // TODO: This duplication check doesn't work for nested fields since we don't know anything
-    //       about the fields on the nested structs
+    // We only care about fields with `id`, `persist`, `persist_blob`, and `nested` attributes. For
+    // the `id` fields we'll build a mapping function that creates a hashmap containing pointers to
+    // those parameters. For the `persist` function we'll create functions that serialize and
+    // deserialize those fields individually (so they can be added and removed independently of
+    // eachother) using JSON. `persist_blob` fields are handled the same way, except that their
+    // bytes are passed through as is. The `nested` fields should also implement the `Params` trait
+    // and their fields will be inherited and added to this field's param mapping list. The order
+    // follows the declaration order We'll also enforce that there are no duplicate keys or hash
+    // collisions for `id` fields at compile time, and that the IDs are valid. These checks don't
+    // work for nested fields since we don't know anything about the fields on the nested structs.
+    // The wrappers will check the fully expanded parameter list again at runtime using
+    // `nih_debug_assert!()`s.
     let mut params: Vec<Param> = Vec::new();
     let mut persistent_fields: Vec<PersistentField> = Vec::new();
+    let mut persistent_blobs: Vec<PersistentField> = Vec::new();
     for field in fields.named {
         let field_name = match &field.ident {
             Some(ident) => ident,
@@ -58,19 +64,40 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                             .into();
                         }
 
+                        if let Err(err) = validate_param_id(&s) {
+                            return err.to_compile_error().into();
+                        }
+
                         // This is a vector since we want to preserve the order. If structs get
                         // large enough to the point where a linear search starts being expensive,
                         // then the plugin should probably start splitting up their parameters.
-                        if params.iter().any(|p| match p {
-                            Param::Single { id, .. } => &s == id,
-                            _ => false,
-                        }) {
-                            return syn::Error::new(
-                                field.span(),
-                                "Multiple parameters with the same ID found",
-                            )
-                            .to_compile_error()
-                            .into();
+                        let id_hash = hash_param_id(&s.value());
+                        for p in &params {
+                            match p {
+                                Param::Single { id, .. } if &s == id => {
+                                    return syn::Error::new(
+                                        field.span(),
+                                        "Multiple parameters with the same ID found",
+                                    )
+                                    .to_compile_error()
+                                    .into();
+                                }
+                                Param::Single { id, .. }
+                                    if hash_param_id(&id.value()) == id_hash =>
+                                {
+                                    return syn::Error::new(
+                                        s.span(),
+                                        format!(
+                                            "This parameter ID has the same hash as '{}', \
+                                             consider using a different ID",
+                                            id.value()
+                                        ),
+                                    )
+                                    .to_compile_error()
+                                    .into();
+                                }
+                                _ => (),
+                            }
                         }
 
                         params.push(Param::Single {
@@ -90,47 +117,41 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                         .into()
                     }
                 };
-            } else if attr.path.is_ident("persist") {
-                match attr.parse_meta() {
-                    Ok(syn::Meta::NameValue(syn::MetaNameValue {
-                        lit: syn::Lit::Str(s),
-                        ..
-                    })) => {
-                        if processed_attribute {
-                            return syn::Error::new(
-                                attr.span(),
-                                "Duplicate or incompatible attribute found",
-                            )
-                            .to_compile_error()
-                            .into();
-                        }
-
-                        if persistent_fields.iter().any(|p| p.key == s) {
-                            return syn::Error::new(
-                                field.span(),
-                                "Multiple persistent fields with the same key found",
-                            )
-                            .to_compile_error()
-                            .into();
-                        }
+            } else if attr.path.is_ident("persist") || attr.path.is_ident("persist_blob") {
+                if processed_attribute {
+                    return syn::Error::new(
+                        attr.span(),
+                        "Duplicate or incompatible attribute found",
+                    )
+                    .to_compile_error()
+                    .into();
+                }
 
-                        persistent_fields.push(PersistentField {
-                            key: s,
-                            field: field_name.clone(),
-                        });
+                let is_blob = attr.path.is_ident("persist_blob");
+                let persistent_field = match PersistentField::parse(attr, field_name, is_blob) {
+                    Ok(persistent_field) => persistent_field,
+                    Err(err) => return err.to_compile_error().into(),
+                };
 
-                        processed_attribute = true;
-                    }
-                    _ => {
-                        return syn::Error::new(
-                            attr.span(),
-                            "The persist attribute should be a key-value pair with a string \
-                             argument: #[persist = \"foo_bar\"]",
-                        )
-                        .to_compile_error()
-                        .into()
-                    }
+                let (existing_fields, kind) = if is_blob {
+                    (&mut persistent_blobs, "blobs")
+                } else {
+                    (&mut persistent_fields, "fields")
                 };
+                if existing_fields
+                    .iter()
+                    .any(|p| p.key == persistent_field.key)
+                {
+                    return syn::Error::new(
+                        field.span(),
+                        format!("Multiple persistent {kind} with the same key found"),
+                    )
+                    .to_compile_error()
+                    .into();
+                }
+
+                existing_fields.push(persistent_field);
+                processed_attribute = true;
             } else if attr.path.is_ident("nested") {
                 // This one is more complicated. Supports an `array` attribute, an `id_prefix =
                 // "foo"` attribute, and a `group = "group name"` attribute. All are optional, and
@@ -168,6 +189,27 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
                                         ..
                                     },
                                 )) if path.is_ident("id_prefix") => {
+                                    if let Err(err) = validate_param_id(&s) {
+                                        return err.to_compile_error().into();
+                                    }
+
+                                    // Two nested structs with the same prefix would very likely
+                                    // result in duplicate parameter IDs
+                                    if params.iter().any(|p| match p {
+                                        Param::Nested(NestedParams::Prefixed {
+                                            id_prefix, ..
+                                        }) => id_prefix == &s,
+                                        _ => false,
+                                    }) {
+                                        return syn::Error::new(
+                                            s.span(),
+                                            "Multiple nested parameter structs with the same ID \
+                                             prefix found",
+                                        )
+                                        .to_compile_error()
+                                        .into();
+                                    }
+
                                     nested_id_prefix = Some(s.clone());
                                 }
                                 syn::NestedMeta::Meta(syn::Meta::NameValue(
@@ -273,27 +315,31 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         let (serialize_fields_self_tokens, deserialize_fields_match_self_tokens): (Vec<_>, Vec<_>) =
             persistent_fields
                 .into_iter()
-                .map(|PersistentField { field, key }| {
+                .map(|persistent_field| {
+                    let condition = persistent_field.context_condition_tokens();
+                    let PersistentField { field, key, .. } = persistent_field;
                     (
                         quote! {
-                            match ::nih_plug::params::persist::PersistentField::map(
-                                &self.#field,
-                                ::nih_plug::params::persist::serialize_field,
-                            ) {
-                                Ok(data) => {
-                                    serialized.insert(String::from(#key), data);
-                                }
-                                Err(err) => {
-                                    ::nih_plug::nih_debug_assert_failure!(
-                                        "Could not serialize '{}': {}",
-                                        #key,
-                                        err
-                                    )
-                                }
-                            };
+                            if #condition {
+                                match ::nih_plug::params::persist::PersistentField::map(
+                                    &self.#field,
+                                    ::nih_plug::params::persist::serialize_field,
+                                ) {
+                                    Ok(data) => {
+                                        serialized.insert(String::from(#key), data);
+                                    }
+                                    Err(err) => {
+                                        ::nih_plug::nih_debug_assert_failure!(
+                                            "Could not serialize '{}': {}",
+                                            #key,
+                                            err
+                                        )
+                                    }
+                                };
+                            }
                         },
                         quote! {
-                            #key => {
+                            #key => if #condition {
                                 match ::nih_plug::params::persist::deserialize_field(&data) {
                                     Ok(deserialized) => {
                                         ::nih_plug::params::persist::PersistentField::set(
@@ -320,67 +366,11 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
             params
                 .iter()
                 .filter_map(|p| match p {
-                    Param::Single { .. } => None,
-                    Param::Nested(nested) => Some(nested),
-                })
-                .map(|nested| match nested {
-                    NestedParams::Inline { field, .. } => (
-                        quote! { serialized.extend(self.#field.serialize_fields()); },
-                        quote! { self.#field.deserialize_fields(serialized); },
-                    ),
-                    NestedParams::Prefixed {
-                        field, id_prefix, ..
-                    } => (
-                        quote! {
-                            let prefixed = self
-                                .#field
-                                .serialize_fields()
-                                .into_iter()
-                                .map(|(key, value)| (format!("{}_{}", #id_prefix, key), value));
-
-                            serialized.extend(prefixed);
-                        },
-                        quote! {
-                            let prefix = format!("{}_", #id_prefix);
-                            let matching_fields = serialized
-                                .iter()
-                                .filter_map(|(key, value)| {
-                                    let original_key = key.strip_prefix(&prefix)?;
-                                    Some((original_key.to_owned(), value.to_owned()))
-                                })
-                                .collect();
-
-                            self.#field.deserialize_fields(&matching_fields);
-                        },
-                    ),
-                    NestedParams::Array { field, .. } => (
-                        quote! {
-                            for (field_idx, field) in self.#field.iter().enumerate() {
-                                let idx = field_idx + 1;
-                                let suffixed = field
-                                    .serialize_fields()
-                                    .into_iter()
-                                    .map(|(key, value)| (format!("{}_{}", key, idx), value));
-
-                                serialized.extend(suffixed);
-                            }
-                        },
-                        quote! {
-                            for (field_idx, field) in self.#field.iter().enumerate() {
-                                let idx = field_idx + 1;
-                                let suffix = format!("_{}", idx);
-                                let matching_fields = serialized
-                                    .iter()
-                                    .filter_map(|(key, value)| {
-                                        let original_key = key.strip_suffix(&suffix)?;
-                                        Some((original_key.to_owned(), value.to_owned()))
-                                    })
-                                    .collect();
-
-                                field.deserialize_fields(&matching_fields);
-                            }
-                        },
+                    Param::Nested(nested) => Some(
+                        nested
+                            .persist_tokens(&quote!(serialize_fields), &quote!(deserialize_fields)),
                     ),
+                    Param::Single { .. } => None,
                 })
                 .unzip();
 
@@ -412,24 +402,144 @@ pub fn derive_params(input: TokenStream) -> TokenStream {
         (serialize_fields_tokens, deserialize_fields_tokens)
     };
 
+    let (serialize_blobs_tokens, deserialize_blobs_tokens) = {
+        // This works the same way as the persistent fields, except that the bytes don't need to be
+        // converted. Blobs can be large, so instead of copying them, each struct removes the blobs
+        // it uses from the map.
+        let (serialize_blobs_self_tokens, deserialize_blobs_self_tokens): (Vec<_>, Vec<_>) =
+            persistent_blobs
+                .into_iter()
+                .map(|persistent_blob| {
+                    let condition = persistent_blob.context_condition_tokens();
+                    let PersistentField { field, key, .. } = persistent_blob;
+                    (
+                        quote! {
+                            if #condition {
+                                serialized.insert(
+                                    String::from(#key),
+                                    ::nih_plug::params::persist::PersistentBlob::map_bytes(
+                                        &self.#field,
+                                        <[u8]>::to_vec,
+                                    ),
+                                );
+                            }
+                        },
+                        quote! {
+                            if let Some(data) = serialized.remove(#key) {
+                                if #condition {
+                                    ::nih_plug::params::persist::PersistentBlob::set_bytes(
+                                        &self.#field,
+                                        data,
+                                    );
+                                }
+                            }
+                        },
+                    )
+                })
+                .unzip();
+
+        let (serialize_blobs_nested_tokens, deserialize_blobs_nested_tokens): (Vec<_>, Vec<_>) =
+            params
+                .iter()
+                .filter_map(|p| match p {
+                    Param::Nested(nested) => Some((
+                        nested
+                            .persist_tokens(&quote!(serialize_blobs), &quote!(deserialize_blobs))
+                            .0,
+                        nested.deserialize_blobs_tokens(),
+                    )),
+                    Param::Single { .. } => None,
+                })
+                .unzip();
+
+        let serialize_blobs_tokens = quote! {
+            #[allow(unused_mut)]
+            let mut serialized = ::std::collections::BTreeMap::new();
+            #(#serialize_blobs_self_tokens);*
+
+            #(#serialize_blobs_nested_tokens);*
+
+            serialized
+        };
+
+        let deserialize_blobs_tokens = quote! {
+            #(#deserialize_blobs_self_tokens)*
+
+            #(#deserialize_blobs_nested_tokens)*
+        };
+
+        (serialize_blobs_tokens, deserialize_blobs_tokens)
+    };
+
     quote! {
         unsafe impl #impl_generics Params for #struct_name #ty_generics #where_clause {
             fn param_map(&self) -> Vec<(String, nih_plug::prelude::ParamPtr, String)> {
                 #param_map_tokens
             }
 
-            fn serialize_fields(&self) -> ::std::collections::BTreeMap<String, String> {
+            #[allow(unused_variables)]
+            fn serialize_fields(
+                &self,
+                context: ::nih_plug::prelude::StateContext,
+            ) -> ::std::collections::BTreeMap<String, String> {
                 #serialize_fields_tokens
             }
 
-            fn deserialize_fields(&self, serialized: &::std::collections::BTreeMap<String, String>) {
+            #[allow(unused_variables)]
+            fn deserialize_fields(
+                &self,
+                serialized: &::std::collections::BTreeMap<String, String>,
+                context: ::nih_plug::prelude::StateContext,
+            ) {
                 #deserialize_fields_tokens
             }
+
+            #[allow(unused_variables)]
+            fn serialize_blobs(
+                &self,
+                context: ::nih_plug::prelude::StateContext,
+            ) -> ::std::collections::BTreeMap<String, Vec<u8>> {
+                #serialize_blobs_tokens
+            }
+
+            #[allow(unused_variables)]
+            fn deserialize_blobs(
+                &self,
+                serialized: &mut ::std::collections::BTreeMap<String, Vec<u8>>,
+                context: ::nih_plug::prelude::StateContext,
+            ) {
+                #deserialize_blobs_tokens
+            }
         }
     }
     .into()
 }
 
+/// Check whether a parameter ID or ID prefix is valid. This uses the same rules as
+/// `nih_plug::wrapper::util::check_param_id()`.
+fn validate_param_id(id: &syn::LitStr) -> syn::Result<()> {
+    let value = id.value();
+    if value.is_empty() {
+        Err(syn::Error::new(id.span(), "Parameter IDs cannot be empty"))
+    } else if value.len() > MAX_PARAM_ID_LEN {
+        Err(syn::Error::new(
+            id.span(),
+            format!("Parameter IDs cannot be longer than {MAX_PARAM_ID_LEN} bytes"),
+        ))
+    } else if !value
+        .bytes()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.'))
+    {
+        Err(syn::Error::new(
+            id.span(),
+            "Parameter IDs may only contain ASCII letters, digits, underscores, hyphens, and \
+             periods",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
 /// A parameter defined on this struct using the `#[id = "..."]` attribute, or another object that
 /// also implements `Params` tagged with one of the variations on the `#[nested]` attribute.
 #[derive(Debug)]
@@ -466,6 +576,77 @@ struct PersistentField {
     field: syn::Ident,
     /// The field's unique key.
     key: syn::LitStr,
+    /// Whether the field should be left out of presets.
+    skip_presets: bool,
+}
+
+impl PersistentField {
+    /// Parse a `#[persist = "key"]` or `#[persist(key = "key", skip_presets)]` attribute, or the
+    /// `persist_blob` equivalents if `is_blob` is set.
+    fn parse(attr: &syn::Attribute, field: &syn::Ident, is_blob: bool) -> syn::Result<Self> {
+        let attr_name = if is_blob { "persist_blob" } else { "persist" };
+        let format_error = || {
+            syn::Error::new(
+                attr.span(),
+                format!(
+                    "The {attr_name} attribute should be a key-value pair with a string argument \
+                     or a list containing a key and optional flags: #[{attr_name} = \"foo_bar\"] \
+                     or #[{attr_name}(key = \"foo_bar\", skip_presets)]"
+                ),
+            )
+        };
+
+        match attr.parse_meta() {
+            Ok(syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(key),
+                ..
+            })) => Ok(PersistentField {
+                field: field.clone(),
+                key,
+                skip_presets: false,
+            }),
+            Ok(syn::Meta::List(syn::MetaList { nested, .. })) => {
+                let mut key = None;
+                let mut skip_presets = false;
+                for nested_attr in nested {
+                    match nested_attr {
+                        syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                            path,
+                            lit: syn::Lit::Str(s),
+                            ..
+                        })) if path.is_ident("key") => key = Some(s),
+                        syn::NestedMeta::Meta(syn::Meta::Path(p)) if p.is_ident("skip_presets") => {
+                            skip_presets = true
+                        }
+                        _ => {
+                            return Err(syn::Error::new(
+                                nested_attr.span(),
+                                "Unknown attribute. See the Params trait documentation for more \
+                                 information.",
+                            ))
+                        }
+                    }
+                }
+
+                Ok(PersistentField {
+                    field: field.clone(),
+                    key: key.ok_or_else(format_error)?,
+                    skip_presets,
+                })
+            }
+            _ => Err(format_error()),
+        }
+    }
+
+    /// A condition that's true when this field should be saved or loaded in the current
+    /// `context`.
+    fn context_condition_tokens(&self) -> proc_macro2::TokenStream {
+        if self.skip_presets {
+            quote! { context != ::nih_plug::prelude::StateContext::Preset }
+        } else {
+            quote! { true }
+        }
+    }
 }
 
 /// A field containing another object whose parameters and persistent fields should be added to this
@@ -582,4 +763,119 @@ impl NestedParams {
             },
         }
     }
+
+    /// Generate the tokens needed to move the nested struct's blobs out of the `serialized` map and
+    /// to restore them using `deserialize_blobs()`. Unlike with
+    /// [`persist_tokens()`][Self::persist_tokens()], the blobs are moved instead of copied.
+    fn deserialize_blobs_tokens(&self) -> proc_macro2::TokenStream {
+        match self {
+            NestedParams::Inline { field, .. } => quote! {
+                self.#field.deserialize_blobs(serialized, context);
+            },
+            NestedParams::Prefixed {
+                field, id_prefix, ..
+            } => quote! {
+                let prefix = format!("{}_", #id_prefix);
+                let (matching_blobs, other_blobs): (::std::collections::BTreeMap<_, _>, _) =
+                    ::std::mem::take(serialized)
+                        .into_iter()
+                        .partition(|(key, _)| key.starts_with(&prefix));
+                *serialized = other_blobs;
+
+                let mut matching_blobs = matching_blobs
+                    .into_iter()
+                    .map(|(key, data)| (key[prefix.len()..].to_owned(), data))
+                    .collect();
+                self.#field.deserialize_blobs(&mut matching_blobs, context);
+            },
+            NestedParams::Array { field, .. } => quote! {
+                for (field_idx, field) in self.#field.iter().enumerate() {
+                    let idx = field_idx + 1;
+                    let suffix = format!("_{}", idx);
+                    let (matching_blobs, other_blobs): (::std::collections::BTreeMap<_, _>, _) =
+                        ::std::mem::take(serialized)
+                            .into_iter()
+                            .partition(|(key, _)| key.ends_with(&suffix));
+                    *serialized = other_blobs;
+
+                    let mut matching_blobs = matching_blobs
+                        .into_iter()
+                        .map(|(key, data)| (key[..key.len() - suffix.len()].to_owned(), data))
+                        .collect();
+                    field.deserialize_blobs(&mut matching_blobs, context);
+                }
+            },
+        }
+    }
+
+    /// Generate the tokens needed to serialize and deserialize the nested struct's persistent
+    /// fields or blobs, using the `Params` methods with the names `serialize` and `deserialize`.
+    /// Keys get the same prefixes and suffixes as parameter IDs. Returns a `(serialize,
+    /// deserialize)` pair of token streams, which respectively add to and read from a `serialized`
+    /// map.
+    fn persist_tokens(
+        &self,
+        serialize: &proc_macro2::TokenStream,
+        deserialize: &proc_macro2::TokenStream,
+    ) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+        match self {
+            NestedParams::Inline { field, .. } => (
+                quote! { serialized.extend(self.#field.#serialize(context)); },
+                quote! { self.#field.#deserialize(serialized, context); },
+            ),
+            NestedParams::Prefixed {
+                field, id_prefix, ..
+            } => (
+                quote! {
+                    let prefixed = self
+                        .#field
+                        .#serialize(context)
+                        .into_iter()
+                        .map(|(key, value)| (format!("{}_{}", #id_prefix, key), value));
+
+                    serialized.extend(prefixed);
+                },
+                quote! {
+                    let prefix = format!("{}_", #id_prefix);
+                    let matching_fields = serialized
+                        .iter()
+                        .filter_map(|(key, value)| {
+                            let original_key = key.strip_prefix(&prefix)?;
+                            Some((original_key.to_owned(), value.to_owned()))
+                        })
+                        .collect();
+
+                    self.#field.#deserialize(&matching_fields, context);
+                },
+            ),
+            NestedParams::Array { field, .. } => (
+                quote! {
+                    for (field_idx, field) in self.#field.iter().enumerate() {
+                        let idx = field_idx + 1;
+                        let suffixed = field
+                            .#serialize(context)
+                            .into_iter()
+                            .map(|(key, value)| (format!("{}_{}", key, idx), value));
+
+                        serialized.extend(suffixed);
+                    }
+                },
+                quote! {
+                    for (field_idx, field) in self.#field.iter().enumerate() {
+                        let idx = field_idx + 1;
+                        let suffix = format!("_{}", idx);
+                        let matching_fields = serialized
+                            .iter()
+                            .filter_map(|(key, value)| {
+                                let original_key = key.strip_suffix(&suffix)?;
+                                Some((original_key.to_owned(), value.to_owned()))
+                            })
+                            .collect();
+
+                        field.#deserialize(&matching_fields, context);
+                    }
+                },
+            ),
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, RwLock};
 
 use nih_plug::prelude::*;
 
@@ -15,6 +16,26 @@ struct ArrayWrapperParams {
     pub inners: [InnerParams; 3],
 }
 
+#[derive(Params, Default)]
+struct ContextParams {
+    #[persist = "name"]
+    pub name: RwLock<String>,
+    #[persist(key = "seed", skip_presets)]
+    pub seed: AtomicU32,
+    #[persist_blob = "samples"]
+    pub samples: RwLock<Vec<u8>>,
+    #[persist_blob(key = "cache", skip_presets)]
+    pub cache: RwLock<Vec<u8>>,
+}
+
+#[derive(Params, Default)]
+struct NestedContextParams {
+    #[nested(id_prefix = "foo")]
+    pub prefixed: ContextParams,
+    #[nested(array)]
+    pub array: [ContextParams; 2],
+}
+
 #[derive(Default)]
 struct InnerParams {
     /// The value `deserialize()` has been called with so we can check that the prefix has been
@@ -27,7 +48,7 @@ unsafe impl Params for InnerParams {
         Vec::new()
     }
 
-    fn serialize_fields(&self) -> BTreeMap<String, String> {
+    fn serialize_fields(&self, _context: StateContext) -> BTreeMap<String, String> {
         // When nested in another struct, the ID prefix will be added to `bar`
         let mut data = BTreeMap::new();
         data.insert(String::from("bar"), String::from("baz"));
@@ -35,7 +56,7 @@ unsafe impl Params for InnerParams {
         data
     }
 
-    fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {
+    fn deserialize_fields(&self, serialized: &BTreeMap<String, String>, _context: StateContext) {
         *self.deserialize_called_with.lock().unwrap() = Some(serialized.clone());
     }
 }
@@ -50,7 +71,7 @@ mod persist {
             let params = WrapperParams::default();
 
             // This should have had a prefix added to the serialized value
-            let serialized = params.serialize_fields();
+            let serialized = params.serialize_fields(StateContext::Project);
             assert_eq!(serialized.len(), 1);
             assert_eq!(serialized["foo_bar"], "baz");
         }
@@ -61,7 +82,7 @@ mod persist {
             serialized.insert(String::from("foo_bar"), String::from("aaa"));
 
             let params = WrapperParams::default();
-            params.deserialize_fields(&serialized);
+            params.deserialize_fields(&serialized, StateContext::Project);
 
             // This contains the values passed to the inner struct's deserialize function
             let deserialized = params
@@ -85,7 +106,7 @@ mod persist {
             );
 
             let params = WrapperParams::default();
-            params.deserialize_fields(&serialized);
+            params.deserialize_fields(&serialized, StateContext::Project);
 
             // The `something` key should not be passed to the child struct
             let deserialized = params
@@ -107,7 +128,7 @@ mod persist {
         fn serialize() {
             let params = ArrayWrapperParams::default();
 
-            let serialized = params.serialize_fields();
+            let serialized = params.serialize_fields(StateContext::Project);
             assert_eq!(serialized.len(), 3);
             assert_eq!(serialized["bar_1"], "baz");
             assert_eq!(serialized["bar_2"], "baz");
@@ -122,7 +143,7 @@ mod persist {
             serialized.insert(String::from("bar_3"), String::from("ccc"));
 
             let params = ArrayWrapperParams::default();
-            params.deserialize_fields(&serialized);
+            params.deserialize_fields(&serialized, StateContext::Project);
             for (inner, expected_value) in params.inners.into_iter().zip(["aaa", "bbb", "ccc"]) {
                 let deserialized = inner
                     .deserialize_called_with
@@ -135,4 +156,85 @@ mod persist {
             }
         }
     }
+
+    mod skip_presets {
+        use super::super::*;
+
+        fn make_params() -> ContextParams {
+            let params = ContextParams::default();
+            *params.name.write().unwrap() = String::from("foo");
+            params.seed.store(1234, Ordering::Relaxed);
+            *params.samples.write().unwrap() = vec![1, 2, 3];
+            *params.cache.write().unwrap() = vec![4, 5];
+
+            params
+        }
+
+        #[test]
+        fn serialize() {
+            let params = make_params();
+
+            let project_fields = params.serialize_fields(StateContext::Project);
+            assert_eq!(project_fields.len(), 2);
+            assert_eq!(project_fields["seed"], "1234");
+            let project_blobs = params.serialize_blobs(StateContext::Project);
+            assert_eq!(project_blobs.len(), 2);
+            assert_eq!(project_blobs["cache"], [4, 5]);
+
+            let preset_fields = params.serialize_fields(StateContext::Preset);
+            assert_eq!(preset_fields.len(), 1);
+            assert_eq!(preset_fields["name"], "\"foo\"");
+            let preset_blobs = params.serialize_blobs(StateContext::Preset);
+            assert_eq!(preset_blobs.len(), 1);
+            assert_eq!(preset_blobs["samples"], [1, 2, 3]);
+        }
+
+        #[test]
+        fn deserialize() {
+            let source = make_params();
+            let fields = source.serialize_fields(StateContext::Project);
+            let blobs = source.serialize_blobs(StateContext::Project);
+
+            let params = ContextParams::default();
+            params.deserialize_fields(&fields, StateContext::Preset);
+            params.deserialize_blobs(&mut blobs.clone(), StateContext::Preset);
+            assert_eq!(*params.name.read().unwrap(), "foo");
+            assert_eq!(params.seed.load(Ordering::Relaxed), 0);
+            assert_eq!(*params.samples.read().unwrap(), [1, 2, 3]);
+            assert!(params.cache.read().unwrap().is_empty());
+
+            params.deserialize_fields(&fields, StateContext::Duplicate);
+            params.deserialize_blobs(&mut blobs.clone(), StateContext::Duplicate);
+            assert_eq!(params.seed.load(Ordering::Relaxed), 1234);
+            assert_eq!(*params.cache.read().unwrap(), [4, 5]);
+        }
+    }
+
+    mod nested_blobs {
+        use super::super::*;
+
+        #[test]
+        fn deserialize() {
+            let source = NestedContextParams::default();
+            *source.prefixed.samples.write().unwrap() = vec![1];
+            *source.array[0].samples.write().unwrap() = vec![2];
+            *source.array[1].cache.write().unwrap() = vec![3];
+
+            let mut blobs = source.serialize_blobs(StateContext::Project);
+            assert_eq!(blobs.len(), 6);
+            assert_eq!(blobs["foo_samples"], [1]);
+            assert_eq!(blobs["samples_1"], [2]);
+            assert_eq!(blobs["cache_2"], [3]);
+            blobs.insert(String::from("unknown"), vec![4]);
+
+            // The blobs are moved out of the map, so only the unknown blob should be left
+            let params = NestedContextParams::default();
+            params.deserialize_blobs(&mut blobs, StateContext::Project);
+            assert_eq!(*params.prefixed.samples.read().unwrap(), [1]);
+            assert_eq!(*params.array[0].samples.read().unwrap(), [2]);
+            assert_eq!(*params.array[1].cache.read().unwrap(), [3]);
+            assert_eq!(blobs.len(), 1);
+            assert!(blobs.contains_key("unknown"));
+        }
+    }
 }
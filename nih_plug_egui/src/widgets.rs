@@ -5,8 +5,12 @@
 //! None of these widgets are finalized, and their sizes or looks can change at any point. Feel free
 //! to copy the widgets and modify them to your personal taste.
 
+mod ab_toggle;
 pub mod generic_ui;
 mod param_slider;
+mod preset_browser;
 pub mod util;
 
+pub use ab_toggle::AbToggle;
 pub use param_slider::ParamSlider;
+pub use preset_browser::{PresetBrowser, PresetBrowserState};
@@ -0,0 +1,36 @@
+use egui_baseview::egui::{Response, Ui, Widget};
+use nih_plug::prelude::{AbSlot, ParamSetter};
+
+/// A pair of buttons for switching between the two A/B comparison states using
+/// [`GuiContext::select_ab_slot()`][nih_plug::prelude::GuiContext::select_ab_slot()]. The active
+/// slot is highlighted. Switching slots stores the current state in the active slot and recalls the
+/// other slot's state.
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct AbToggle<'a> {
+    setter: &'a ParamSetter<'a>,
+}
+
+impl<'a> AbToggle<'a> {
+    /// Create a new A/B toggle. The setter's [`GuiContext`][nih_plug::prelude::GuiContext] is
+    /// used to switch between the slots.
+    pub fn new(setter: &'a ParamSetter<'a>) -> Self {
+        Self { setter }
+    }
+}
+
+impl Widget for AbToggle<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let context = self.setter.raw_context;
+        let active_slot = context.active_ab_slot();
+
+        ui.horizontal(|ui| {
+            for slot in [AbSlot::A, AbSlot::B] {
+                let response = ui.selectable_label(active_slot == slot, slot.snapshot_name());
+                if response.clicked() {
+                    context.select_ab_slot(slot);
+                }
+            }
+        })
+        .response
+    }
+}
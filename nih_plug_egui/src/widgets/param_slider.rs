@@ -2,8 +2,8 @@ use std::sync::{Arc, LazyLock};
 
 use egui_baseview::egui::emath::GuiRounding;
 use egui_baseview::egui::{
-    self, emath, vec2, Key, Response, Sense, Stroke, TextEdit, TextStyle, Ui, Vec2, Widget,
-    WidgetText,
+    self, emath, vec2, Color32, Key, Response, Sense, Stroke, TextEdit, TextStyle, Ui, Vec2,
+    Widget, WidgetText,
 };
 use nih_plug::prelude::{Param, ParamSetter};
 use parking_lot::Mutex;
@@ -21,7 +21,9 @@ static VALUE_ENTRY_MEMORY_ID: LazyLock<egui::Id> = LazyLock::new(|| egui::Id::ne
 
 /// A slider widget similar to [`egui::widgets::Slider`] that knows about NIH-plug parameters ranges
 /// and can get values for it. The slider supports double click and control click to reset,
-/// shift+drag for granular dragging, text value entry by clicking on the value text.
+/// shift+drag for granular dragging, text value entry by clicking on the value text. Right clicking
+/// on the slider shows the host's context menu for the parameter if the host supports it, and the
+/// slider's border uses the color of the parameter's controller mapping if the host sent one.
 ///
 /// TODO: Vertical orientation
 /// TODO: Check below for more input methods that should be added
@@ -130,6 +132,23 @@ impl<'a, P: Param> ParamSlider<'a, P> {
             .set_parameter(self.param, self.param.default_plain_value());
     }
 
+    /// Ask the host to show its context menu for the parameter at the pointer's position. Returns
+    /// false if the host could not show the menu.
+    fn show_context_menu(&self, ui: &Ui, response: &Response) -> bool {
+        match response.interact_pointer_pos() {
+            Some(pos) => {
+                // The host expects physical pixels
+                let pixels_per_point = ui.ctx().pixels_per_point();
+                self.setter.show_context_menu(
+                    self.param,
+                    (pos.x * pixels_per_point).round() as i32,
+                    (pos.y * pixels_per_point).round() as i32,
+                )
+            }
+            None => false,
+        }
+    }
+
     fn granular_drag(&self, ui: &Ui, drag_delta: Vec2) {
         // Remember the intial position when we started with the granular drag. This value gets
         // reset whenever we have a normal itneraction with the slider.
@@ -184,7 +203,20 @@ impl<'a, P: Param> ParamSlider<'a, P> {
             self.begin_drag();
             Self::set_drag_amount_memory(ui, 0.0);
         }
-        if let Some(click_pos) = response.interact_pointer_pos() {
+        // Right clicking shows the host's context menu instead of changing the value. If the host
+        // doesn't support that, then right clicking resets the parameter instead.
+        let secondary_interaction =
+            response.secondary_clicked() || ui.input(|i| i.pointer.secondary_down());
+        if response.secondary_clicked() && !self.show_context_menu(ui, response) {
+            self.begin_drag();
+            self.reset_param();
+            self.end_drag();
+            response.mark_changed();
+        }
+        if let Some(click_pos) = response
+            .interact_pointer_pos()
+            .filter(|_| !secondary_interaction)
+        {
             if ui.input(|i| i.modifiers.command) {
                 // Like double clicking, Ctrl+Click should reset the parameter
                 self.reset_param();
@@ -233,10 +265,23 @@ impl<'a, P: Param> ParamSlider<'a, P> {
                 ui.painter().rect_filled(filled_rect, 0.0, filled_bg);
             }
 
+            // If the host mapped the parameter to a controller, then the border uses that mapping's
+            // color
+            let border_color = self
+                .setter
+                .param_indication(self.param)
+                .mapping
+                .and_then(|mapping| mapping.color)
+                .map(|color| {
+                    Color32::from_rgba_unmultiplied(color.red, color.green, color.blue, color.alpha)
+                });
             ui.painter().rect_stroke(
                 response.rect,
                 0.0,
-                Stroke::new(1.0, ui.visuals().widgets.active.bg_fill),
+                Stroke::new(
+                    1.0,
+                    border_color.unwrap_or(ui.visuals().widgets.active.bg_fill),
+                ),
                 egui::StrokeKind::Middle,
             );
         }
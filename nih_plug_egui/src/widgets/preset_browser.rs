@@ -0,0 +1,202 @@
+use egui_baseview::egui::{
+    self, Color32, ComboBox, Id, Response, ScrollArea, TextEdit, Ui, Widget,
+};
+use nih_plug::prelude::{GuiContext, ParamSetter};
+use nih_plug::preset::{PresetLibrary, PresetNavigator};
+use std::fmt::Display;
+use std::hash::Hash;
+
+/// The height of the preset list, in logical pixels.
+const LIST_HEIGHT: f32 = 150.0;
+
+/// A preset browser for the plugin's factory and user presets. This shows the current preset with
+/// buttons to step through the presets, a search field, a category filter, a list of the presets
+/// matching the search query and the category, and a text field for saving the current state as a
+/// new user preset.
+#[must_use = "You should put this widget in an ui with `ui.add(widget);`"]
+pub struct PresetBrowser<'a> {
+    state: &'a mut PresetBrowserState,
+    setter: &'a ParamSetter<'a>,
+    /// The base ID for the browser's combo box and scroll area.
+    id: Id,
+}
+
+/// State for a [`PresetBrowser`]. This should be stored in the editor's state so it persists
+/// between frames.
+pub struct PresetBrowserState {
+    navigator: PresetNavigator,
+    /// The contents of the save-as text field.
+    save_name: String,
+    /// The last error that occurred while loading or saving a preset, shown below the browser.
+    error: Option<String>,
+}
+
+impl PresetBrowserState {
+    /// Create the state for a preset browser showing the presets from `library`. This is usually
+    /// [`PresetLibrary::new()`].
+    pub fn new(library: PresetLibrary) -> Self {
+        Self {
+            navigator: PresetNavigator::new(library),
+            save_name: String::new(),
+            error: None,
+        }
+    }
+
+    /// The navigator containing the browser's presets and selection.
+    pub fn navigator(&self) -> &PresetNavigator {
+        &self.navigator
+    }
+
+    /// A mutable reference to the browser's navigator. This can for instance be used to rescan the
+    /// presets.
+    pub fn navigator_mut(&mut self) -> &mut PresetNavigator {
+        &mut self.navigator
+    }
+
+    /// Store an error from loading or saving a preset so it can be shown in the browser.
+    fn handle_result<E: Display>(&mut self, result: Result<(), E>) {
+        self.error = result.err().map(|err| format!("{err:#}"));
+    }
+}
+
+impl<'a> PresetBrowser<'a> {
+    /// Create a new preset browser. The setter's [`GuiContext`] is used to load and save presets.
+    pub fn new(state: &'a mut PresetBrowserState, setter: &'a ParamSetter<'a>) -> Self {
+        Self {
+            state,
+            setter,
+            id: Id::new("preset_browser"),
+        }
+    }
+
+    /// Use a different ID source for the browser's widgets. This is needed when showing more than
+    /// one preset browser in the same UI.
+    pub fn id_source(mut self, id_source: impl Hash) -> Self {
+        self.id = Id::new(id_source);
+        self
+    }
+
+    /// The current preset's name with buttons to step to the previous and next preset.
+    fn navigation_ui(state: &mut PresetBrowserState, context: &dyn GuiContext, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("<").clicked() {
+                let result = state.navigator.load_previous(context);
+                state.handle_result(result);
+            }
+            if ui.button(">").clicked() {
+                let result = state.navigator.load_next(context);
+                state.handle_result(result);
+            }
+
+            match state.navigator.current_preset() {
+                Some(entry) => ui.strong(&entry.metadata.name),
+                None => ui.weak("No preset loaded"),
+            };
+        });
+    }
+
+    /// The search field and the category filter.
+    fn filter_ui(state: &mut PresetBrowserState, id: Id, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let mut search = String::from(state.navigator.search());
+            if ui
+                .add(TextEdit::singleline(&mut search).hint_text("Search"))
+                .changed()
+            {
+                state.navigator.set_search(search);
+            }
+
+            let mut category = state.navigator.category().map(String::from);
+            ComboBox::from_id_salt(id.with("category"))
+                .selected_text(category.as_deref().unwrap_or("All categories"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut category, None, "All categories");
+                    for available_category in state.navigator.library().categories() {
+                        ui.selectable_value(
+                            &mut category,
+                            Some(String::from(available_category)),
+                            available_category,
+                        );
+                    }
+                });
+            if category.as_deref() != state.navigator.category() {
+                state.navigator.set_category(category);
+            }
+        });
+    }
+
+    /// The list of visible presets. Clicking on a preset loads it.
+    fn list_ui(state: &mut PresetBrowserState, id: Id, context: &dyn GuiContext, ui: &mut Ui) {
+        let mut clicked_preset = None;
+        ScrollArea::vertical()
+            .id_salt(id.with("list"))
+            .max_height(LIST_HEIGHT)
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                let presets = state.navigator.library().presets();
+                for &idx in state.navigator.visible() {
+                    let entry = &presets[idx];
+                    let label = if entry.metadata.category.is_empty() {
+                        entry.metadata.name.clone()
+                    } else {
+                        format!("{} ({})", entry.metadata.name, entry.metadata.category)
+                    };
+
+                    let is_current = state.navigator.current() == Some(idx);
+                    if ui.selectable_label(is_current, label).clicked() {
+                        clicked_preset = Some(idx);
+                    }
+                }
+            });
+
+        if let Some(idx) = clicked_preset {
+            let result = state.navigator.load(idx, context);
+            state.handle_result(result);
+        }
+    }
+
+    /// A text field and a button for saving the current state as a new user preset.
+    fn save_ui(state: &mut PresetBrowserState, context: &dyn GuiContext, ui: &mut Ui) {
+        let can_save = state.navigator.library().user_dir().is_some();
+        ui.add_enabled_ui(can_save, |ui| {
+            ui.horizontal(|ui| {
+                let response =
+                    ui.add(TextEdit::singleline(&mut state.save_name).hint_text("Preset name"));
+                let submitted =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                let has_name = !state.save_name.trim().is_empty();
+                let clicked = ui
+                    .add_enabled(has_name, egui::Button::new("Save as"))
+                    .clicked();
+
+                if has_name && (clicked || submitted) {
+                    let result = state.navigator.save_as(&state.save_name, context);
+                    if result.is_ok() {
+                        state.save_name.clear();
+                    }
+                    state.handle_result(result);
+                }
+            });
+        });
+    }
+}
+
+impl Widget for PresetBrowser<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let context = self.setter.raw_context;
+        let state = self.state;
+        let id = self.id;
+
+        ui.vertical(|ui| {
+            Self::navigation_ui(state, context, ui);
+            Self::filter_ui(state, id, ui);
+            Self::list_ui(state, id, context, ui);
+            Self::save_ui(state, context, ui);
+
+            if let Some(error) = &state.error {
+                ui.colored_label(Color32::LIGHT_RED, error);
+            }
+        })
+        .response
+    }
+}
@@ -107,6 +107,10 @@ impl<E: IcedEditor> Editor for IcedEditorWrapper<E> {
                 flags: (
                     context,
                     self.parameter_updates_receiver.clone(),
+                    // When the host doesn't set a scale factor baseview uses the system's scale
+                    // factor, which iced_baseview doesn't expose. Context menu positions are then
+                    // passed to the host as is.
+                    scaling_factor.unwrap_or(1.0),
                     self.initialization_flags.clone(),
                 ),
             },
@@ -149,6 +153,11 @@ impl<E: IcedEditor> Editor for IcedEditorWrapper<E> {
     fn param_values_changed(&self) {
         let _ = self.parameter_updates_sender.try_send(ParameterUpdate);
     }
+
+    fn param_indication_changed(&self, _id: &str) {
+        // This causes the GUI to be redrawn so widgets can pick up the new indication
+        let _ = self.parameter_updates_sender.try_send(ParameterUpdate);
+    }
 }
 
 /// The window handle used for [`IcedEditorWrapper`].
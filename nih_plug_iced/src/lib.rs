@@ -220,7 +220,7 @@ pub trait IcedEditor: 'static + Send + Sync + Sized {
         }
     }
 
-    /// Handle a parameter update using the GUI context.
+    /// Handle a parameter update or an A/B slot change using the GUI context.
     fn handle_param_message(&self, message: ParamMessage) {
         // We can't use the fancy ParamSetter here because this needs to be type erased
         let context = self.context();
@@ -230,6 +230,23 @@ pub trait IcedEditor: 'static + Send + Sync + Sized {
                 context.raw_set_parameter_normalized(p, v)
             },
             ParamMessage::EndSetParameter(p) => unsafe { context.raw_end_set_parameter(p) },
+            ParamMessage::ShowContextMenu(p, x, y) => {
+                // The widgets emit logical positions, while the host expects physical pixels
+                let scale_factor = wrapper::window_scale_factor();
+                let x = (x as f32 * scale_factor).round() as i32;
+                let y = (y as f32 * scale_factor).round() as i32;
+
+                // Right clicking used to reset the parameter, so we'll keep doing that for hosts
+                // that don't support parameter context menus
+                if !context.raw_show_param_context_menu(p, x, y) {
+                    unsafe {
+                        context.raw_begin_set_parameter(p);
+                        context.raw_set_parameter_normalized(p, p.default_normalized_value());
+                        context.raw_end_set_parameter(p);
+                    }
+                }
+            }
+            ParamMessage::SelectAbSlot(slot) => context.select_ab_slot(slot),
         }
     }
 }
@@ -5,15 +5,19 @@
 //! None of these widgets are finalized, and their sizes or looks can change at any point. Feel free
 //! to copy the widgets and modify them to your personal taste.
 
-use nih_plug::prelude::ParamPtr;
+use nih_plug::prelude::{AbSlot, ParamPtr};
 
+pub mod ab_toggle;
 pub mod generic_ui;
 pub mod param_slider;
 pub mod peak_meter;
+pub mod preset_browser;
 pub mod util;
 
+pub use ab_toggle::AbToggle;
 pub use param_slider::ParamSlider;
 pub use peak_meter::PeakMeter;
+pub use preset_browser::PresetBrowser;
 
 /// A message to update a parameter value. Since NIH-plug manages the parameters, interacting with
 /// parameter values with iced works a little different from updating any other state. This main
@@ -29,4 +33,13 @@ pub enum ParamMessage {
     SetParameterNormalized(ParamPtr, f32),
     /// End an automation gesture for a parameter.
     EndSetParameter(ParamPtr),
+    /// Ask the host to show its context menu for a parameter at the specified position in logical
+    /// pixels relative to the window. The position is converted to the physical pixels the host
+    /// expects using the window's scale factor when this message is handled. If the host cannot
+    /// show a context menu, then the parameter is reset to its default value instead.
+    ShowContextMenu(ParamPtr, i32, i32),
+    /// Switch to another A/B comparison slot using
+    /// [`GuiContext::select_ab_slot()`][nih_plug::prelude::GuiContext::select_ab_slot()]. Emitted
+    /// by the [`AbToggle`] widget.
+    SelectAbSlot(AbSlot),
 }
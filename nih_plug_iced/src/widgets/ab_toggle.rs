@@ -0,0 +1,67 @@
+//! A toggle for switching between two A/B comparison states.
+
+use nih_plug::prelude::AbSlot;
+
+use super::ParamMessage;
+use crate::widget::{button, Button};
+use crate::{Element, Row, Text};
+
+/// A pair of buttons for switching between the two A/B comparison states using
+/// [`GuiContext::select_ab_slot()`][nih_plug::prelude::GuiContext::select_ab_slot()]. The button
+/// for the currently active slot is disabled. Clicking on the other button emits a
+/// [`ParamMessage::SelectAbSlot`] message, which should be passed through to
+/// [`IcedEditor::handle_param_message()`][crate::IcedEditor::handle_param_message()].
+pub struct AbToggle<'a> {
+    state: &'a mut State,
+
+    active_slot: AbSlot,
+    padding: u16,
+}
+
+/// State for an [`AbToggle`].
+#[derive(Debug, Default)]
+pub struct State {
+    a_button_state: button::State,
+    b_button_state: button::State,
+}
+
+impl<'a> AbToggle<'a> {
+    /// Create a new A/B toggle. `active_slot` should be the slot returned by
+    /// [`GuiContext::active_ab_slot()`][nih_plug::prelude::GuiContext::active_ab_slot()].
+    pub fn new(state: &'a mut State, active_slot: AbSlot) -> Self {
+        Self {
+            state,
+
+            active_slot,
+            padding: 5,
+        }
+    }
+
+    /// Set the padding of the two buttons.
+    pub fn padding(mut self, padding: u16) -> Self {
+        self.padding = padding;
+        self
+    }
+}
+
+impl<'a> From<AbToggle<'a>> for Element<'a, ParamMessage> {
+    fn from(widget: AbToggle<'a>) -> Self {
+        let State {
+            a_button_state,
+            b_button_state,
+        } = widget.state;
+
+        let mut row = Row::new();
+        for (slot, button_state) in [(AbSlot::A, a_button_state), (AbSlot::B, b_button_state)] {
+            let mut button =
+                Button::new(button_state, Text::new(slot.snapshot_name())).padding(widget.padding);
+            if slot != widget.active_slot {
+                button = button.on_press(ParamMessage::SelectAbSlot(slot));
+            }
+
+            row = row.push(button);
+        }
+
+        row.into()
+    }
+}
@@ -1,7 +1,7 @@
 //! A slider that integrates with NIH-plug's [`Param`] types.
 
 use atomic_refcell::AtomicRefCell;
-use nih_plug::prelude::Param;
+use nih_plug::prelude::{Param, ParamIndication};
 use std::borrow::Borrow;
 
 use crate::backend::widget;
@@ -23,7 +23,8 @@ const GRANULAR_DRAG_MULTIPLIER: f32 = 0.1;
 /// The thickness of this widget's borders.
 const BORDER_WIDTH: f32 = 1.0;
 
-/// A slider that integrates with NIH-plug's [`Param`] types.
+/// A slider that integrates with NIH-plug's [`Param`] types. Right clicking on the slider shows the
+/// host's context menu for the parameter, or resets the parameter if the host doesn't support that.
 ///
 /// TODO: There are currently no styling options at all
 /// TODO: Handle scrolling for steps (and shift+scroll for smaller steps?)
@@ -36,6 +37,9 @@ pub struct ParamSlider<'a, P: Param> {
     width: Length,
     text_size: Option<u16>,
     font: Font,
+    /// The color of the host's hardware controller mapping for this parameter. Set through
+    /// [`param_indication()`][Self::param_indication()].
+    mapping_color: Option<Color>,
 }
 
 /// State for a [`ParamSlider`].
@@ -109,6 +113,7 @@ impl<'a, P: Param> ParamSlider<'a, P> {
             height: Length::Units(30),
             text_size: None,
             font: <Renderer as TextRenderer>::Font::default(),
+            mapping_color: None,
         }
     }
 
@@ -136,6 +141,26 @@ impl<'a, P: Param> ParamSlider<'a, P> {
         self
     }
 
+    /// Use the host's indication for the parameter, as returned by
+    /// [`ParamSetter::param_indication()`][nih_plug::prelude::ParamSetter::param_indication()].
+    /// If the host mapped the parameter to a hardware controller, then the [`ParamSlider`]'s border
+    /// is drawn in that mapping's color.
+    pub fn param_indication(mut self, indication: &ParamIndication) -> Self {
+        self.mapping_color = indication
+            .mapping
+            .as_ref()
+            .and_then(|mapping| mapping.color)
+            .map(|color| {
+                Color::from_rgba8(
+                    color.red,
+                    color.green,
+                    color.blue,
+                    color.alpha as f32 / 255.0,
+                )
+            });
+        self
+    }
+
     /// Create a temporary [`TextInput`] hooked up to [`State::text_input_value`] and outputting
     /// [`TextInputMessage`] messages and do something with it. This can be used to
     fn with_text_input<T, R, F>(&self, layout: Layout, renderer: R, current_value: &str, f: F) -> T
@@ -346,6 +371,17 @@ impl<'a, P: Param> Widget<ParamMessage, Renderer> for ParamSlider<'a, P> {
                     return event::Status::Captured;
                 }
             }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                if bounds.contains(cursor_position) {
+                    shell.publish(ParamMessage::ShowContextMenu(
+                        self.param.as_ptr(),
+                        cursor_position.x.round() as i32,
+                        cursor_position.y.round() as i32,
+                    ));
+
+                    return event::Status::Captured;
+                }
+            }
             Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
             | Event::Touch(touch::Event::FingerLifted { .. } | touch::Event::FingerLost { .. }) => {
                 if self.state.drag_active {
@@ -462,7 +498,7 @@ impl<'a, P: Param> Widget<ParamMessage, Renderer> for ParamSlider<'a, P> {
         renderer.fill_quad(
             renderer::Quad {
                 bounds,
-                border_color: Color::BLACK,
+                border_color: self.mapping_color.unwrap_or(Color::BLACK),
                 border_width: BORDER_WIDTH,
                 border_radius: 0.0,
             },
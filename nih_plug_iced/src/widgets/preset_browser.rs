@@ -0,0 +1,305 @@
+//! A browser for the plugin's factory and user presets.
+
+use nih_plug::prelude::GuiContext;
+use nih_plug::preset::{PresetLibrary, PresetNavigator};
+use std::fmt::Display;
+
+use crate::widget::{
+    button, pick_list, scrollable, text_input, Button, PickList, Scrollable, TextInput,
+};
+use crate::{Alignment, Color, Column, Element, Length, Row, Text};
+
+/// The height of the preset list, in logical pixels.
+const LIST_HEIGHT: u16 = 150;
+
+/// A preset browser for the plugin's factory and user presets. This shows the current preset with
+/// buttons to step through the presets, a search field, a category filter, a list of the presets
+/// matching the search query and the category, and a text field for saving the current state as a
+/// new user preset. The browser emits [`PresetMessage`]s, which should be passed through to
+/// [`State::update()`] in the editor's [`update()`][crate::IcedEditor::update()] function.
+pub struct PresetBrowser<'a> {
+    state: &'a mut State,
+
+    padding: u16,
+    spacing: u16,
+}
+
+/// State for a [`PresetBrowser`]. This also contains the browser's presets and selection.
+pub struct State {
+    navigator: PresetNavigator,
+    /// The contents of the save-as text field.
+    save_name: String,
+    /// The last error that occurred while loading or saving a preset, shown below the browser.
+    error: Option<String>,
+
+    previous_button_state: button::State,
+    next_button_state: button::State,
+    search_input_state: text_input::State,
+    category_list_state: pick_list::State<CategoryFilter>,
+    scrollable_state: scrollable::State,
+    /// One button state per visible preset.
+    preset_button_states: Vec<button::State>,
+    save_input_state: text_input::State,
+    save_button_state: button::State,
+}
+
+/// A message emitted by the [`PresetBrowser`]. These should be passed to [`State::update()`].
+#[derive(Debug, Clone)]
+pub enum PresetMessage {
+    /// Load the visible preset before the current one.
+    LoadPrevious,
+    /// Load the visible preset after the current one.
+    LoadNext,
+    /// Load the preset with this index in
+    /// [`PresetLibrary::presets()`][nih_plug::preset::PresetLibrary::presets()].
+    Load(usize),
+    /// Change the search query.
+    SetSearch(String),
+    /// Change the category filter.
+    SetCategory(CategoryFilter),
+    /// Change the contents of the save-as text field.
+    SetSaveName(String),
+    /// Save the current state as a user preset with the name from the save-as text field.
+    SaveAs,
+}
+
+/// An option in the [`PresetBrowser`]'s category filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CategoryFilter {
+    /// Show presets from all categories.
+    All,
+    /// Only show presets in this category.
+    Category(String),
+}
+
+impl Display for CategoryFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CategoryFilter::All => write!(f, "All categories"),
+            CategoryFilter::Category(category) => write!(f, "{category}"),
+        }
+    }
+}
+
+impl State {
+    /// Create the state for a preset browser showing the presets from `library`. This is usually
+    /// [`PresetLibrary::new()`].
+    pub fn new(library: PresetLibrary) -> Self {
+        Self {
+            navigator: PresetNavigator::new(library),
+            save_name: String::new(),
+            error: None,
+
+            previous_button_state: Default::default(),
+            next_button_state: Default::default(),
+            search_input_state: Default::default(),
+            category_list_state: Default::default(),
+            scrollable_state: Default::default(),
+            preset_button_states: Vec::new(),
+            save_input_state: Default::default(),
+            save_button_state: Default::default(),
+        }
+    }
+
+    /// The navigator containing the browser's presets and selection.
+    pub fn navigator(&self) -> &PresetNavigator {
+        &self.navigator
+    }
+
+    /// A mutable reference to the browser's navigator. This can for instance be used to rescan the
+    /// presets.
+    pub fn navigator_mut(&mut self) -> &mut PresetNavigator {
+        &mut self.navigator
+    }
+
+    /// Handle a message emitted by the [`PresetBrowser`]. Presets are loaded and saved using
+    /// `context`.
+    pub fn update(&mut self, message: PresetMessage, context: &dyn GuiContext) {
+        let result = match message {
+            PresetMessage::LoadPrevious => self.navigator.load_previous(context),
+            PresetMessage::LoadNext => self.navigator.load_next(context),
+            PresetMessage::Load(idx) => self.navigator.load(idx, context),
+            PresetMessage::SetSearch(query) => {
+                self.navigator.set_search(query);
+                return;
+            }
+            PresetMessage::SetCategory(CategoryFilter::All) => {
+                self.navigator.set_category(None);
+                return;
+            }
+            PresetMessage::SetCategory(CategoryFilter::Category(category)) => {
+                self.navigator.set_category(Some(category));
+                return;
+            }
+            PresetMessage::SetSaveName(name) => {
+                self.save_name = name;
+                return;
+            }
+            PresetMessage::SaveAs if self.save_name.trim().is_empty() => return,
+            PresetMessage::SaveAs => {
+                let result = self.navigator.save_as(&self.save_name, context);
+                if result.is_ok() {
+                    self.save_name.clear();
+                }
+
+                result
+            }
+        };
+
+        self.error = result.err().map(|err| format!("{err:#}"));
+    }
+}
+
+impl<'a> PresetBrowser<'a> {
+    /// Create a new preset browser.
+    pub fn new(state: &'a mut State) -> Self {
+        Self {
+            state,
+
+            padding: 5,
+            spacing: 5,
+        }
+    }
+
+    /// Set the padding of the browser's buttons and text fields.
+    pub fn padding(mut self, padding: u16) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set the spacing between the browser's rows.
+    pub fn spacing(mut self, spacing: u16) -> Self {
+        self.spacing = spacing;
+        self
+    }
+}
+
+impl<'a> From<PresetBrowser<'a>> for Element<'a, PresetMessage> {
+    fn from(widget: PresetBrowser<'a>) -> Self {
+        let padding = widget.padding;
+        let spacing = widget.spacing;
+
+        let num_visible = widget.state.navigator.visible().len();
+        widget
+            .state
+            .preset_button_states
+            .resize_with(num_visible, Default::default);
+
+        let State {
+            navigator,
+            save_name,
+            error,
+
+            previous_button_state,
+            next_button_state,
+            search_input_state,
+            category_list_state,
+            scrollable_state,
+            preset_button_states,
+            save_input_state,
+            save_button_state,
+        } = widget.state;
+
+        let current_name = match navigator.current_preset() {
+            Some(entry) => entry.metadata.name.clone(),
+            None => String::from("No preset loaded"),
+        };
+        let navigation_row = Row::new()
+            .spacing(spacing)
+            .align_items(Alignment::Center)
+            .push(
+                Button::new(previous_button_state, Text::new("<"))
+                    .padding(padding)
+                    .on_press(PresetMessage::LoadPrevious),
+            )
+            .push(
+                Button::new(next_button_state, Text::new(">"))
+                    .padding(padding)
+                    .on_press(PresetMessage::LoadNext),
+            )
+            .push(Text::new(current_name));
+
+        let categories: Vec<CategoryFilter> = std::iter::once(CategoryFilter::All)
+            .chain(
+                navigator
+                    .library()
+                    .categories()
+                    .into_iter()
+                    .map(|category| CategoryFilter::Category(String::from(category))),
+            )
+            .collect();
+        let selected_category = match navigator.category() {
+            Some(category) => CategoryFilter::Category(String::from(category)),
+            None => CategoryFilter::All,
+        };
+        let filter_row = Row::new()
+            .spacing(spacing)
+            .push(
+                TextInput::new(
+                    search_input_state,
+                    "Search",
+                    navigator.search(),
+                    PresetMessage::SetSearch,
+                )
+                .padding(padding),
+            )
+            .push(
+                PickList::new(
+                    category_list_state,
+                    categories,
+                    Some(selected_category),
+                    PresetMessage::SetCategory,
+                )
+                .padding(padding),
+            );
+
+        let presets = navigator.library().presets();
+        let mut preset_list = Scrollable::new(scrollable_state)
+            .width(Length::Fill)
+            .height(Length::Units(LIST_HEIGHT));
+        for (&idx, button_state) in navigator.visible().iter().zip(preset_button_states) {
+            let name = &presets[idx].metadata.name;
+            let label = if navigator.current() == Some(idx) {
+                format!("> {name}")
+            } else {
+                name.clone()
+            };
+
+            preset_list = preset_list.push(
+                Button::new(button_state, Text::new(label))
+                    .width(Length::Fill)
+                    .padding(padding)
+                    .on_press(PresetMessage::Load(idx)),
+            );
+        }
+
+        let mut save_button = Button::new(save_button_state, Text::new("Save as")).padding(padding);
+        let mut save_input = TextInput::new(
+            save_input_state,
+            "Preset name",
+            save_name,
+            PresetMessage::SetSaveName,
+        )
+        .padding(padding);
+        if navigator.library().user_dir().is_some() && !save_name.trim().is_empty() {
+            save_button = save_button.on_press(PresetMessage::SaveAs);
+            save_input = save_input.on_submit(PresetMessage::SaveAs);
+        }
+        let save_row = Row::new()
+            .spacing(spacing)
+            .push(save_input)
+            .push(save_button);
+
+        let mut column = Column::new()
+            .spacing(spacing)
+            .push(navigation_row)
+            .push(filter_row)
+            .push(preset_list)
+            .push(save_row);
+        if let Some(error) = error {
+            column = column.push(Text::new(error.as_str()).color(Color::from_rgb(0.75, 0.1, 0.1)));
+        }
+
+        column.into()
+    }
+}
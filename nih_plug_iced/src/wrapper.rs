@@ -3,6 +3,7 @@
 
 use crossbeam::channel;
 use nih_plug::prelude::GuiContext;
+use std::cell::Cell;
 use std::sync::Arc;
 
 use crate::futures::FutureExt;
@@ -20,6 +21,22 @@ pub(crate) struct IcedEditorWrapperApplication<E: IcedEditor> {
     /// update gets sent, we will trigger a [`Message::parameterUpdate`] which causes the UI to be
     /// redrawn.
     parameter_updates_receiver: Arc<channel::Receiver<ParameterUpdate>>,
+    /// The scale factor the window was opened with. This is made available through
+    /// [`window_scale_factor()`] while the editor handles a message.
+    scale_factor: f32,
+}
+
+thread_local! {
+    /// The scale factor of the window whose editor is currently handling a message. Multiple
+    /// editors can share the same GUI thread, so this is set for the duration of every
+    /// [`IcedEditor::update()`] call.
+    static WINDOW_SCALE_FACTOR: Cell<f32> = const { Cell::new(1.0) };
+}
+
+/// The scale factor of the window whose editor is currently handling a message. Used to convert
+/// the logical positions emitted by the widgets to the physical pixels the host expects.
+pub(crate) fn window_scale_factor() -> f32 {
+    WINDOW_SCALE_FACTOR.with(Cell::get)
 }
 
 /// This wraps around `E::Message` to add a parameter update message which can be handled directly
@@ -54,11 +71,12 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
     type Flags = (
         Arc<dyn GuiContext>,
         Arc<channel::Receiver<ParameterUpdate>>,
+        f32,
         E::InitializationFlags,
     );
 
     fn new(
-        (context, parameter_updates_receiver, flags): Self::Flags,
+        (context, parameter_updates_receiver, scale_factor, flags): Self::Flags,
     ) -> (Self, Command<Self::Message>) {
         let (editor, command) = E::new(flags, context);
 
@@ -66,6 +84,7 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
             Self {
                 editor,
                 parameter_updates_receiver,
+                scale_factor,
             },
             command.map(Message::EditorMessage),
         )
@@ -78,10 +97,14 @@ impl<E: IcedEditor> Application for IcedEditorWrapperApplication<E> {
         message: Self::Message,
     ) -> Command<Self::Message> {
         match message {
-            Message::EditorMessage(message) => self
-                .editor
-                .update(window, message)
-                .map(Message::EditorMessage),
+            Message::EditorMessage(message) => {
+                let previous_scale_factor = WINDOW_SCALE_FACTOR
+                    .with(|scale_factor| scale_factor.replace(self.scale_factor));
+                let command = self.editor.update(window, message);
+                WINDOW_SCALE_FACTOR.with(|scale_factor| scale_factor.set(previous_scale_factor));
+
+                command.map(Message::EditorMessage)
+            }
             // This message only exists to force a redraw
             Message::ParameterUpdate => Command::none(),
         }
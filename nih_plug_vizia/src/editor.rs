@@ -76,6 +76,7 @@ impl Editor for ViziaEditor {
                     current_inner_window_size.width,
                     current_inner_window_size.height,
                 )),
+                active_ab_slot: context.active_ab_slot(),
             }
             .build(cx);
 
@@ -157,6 +158,13 @@ impl Editor for ViziaEditor {
         self.emit_parameters_changed_event
             .store(true, Ordering::Relaxed);
     }
+
+    fn param_indication_changed(&self, _id: &str) {
+        // Widgets that show the host's parameter indications can check for changes when they
+        // receive the parameters changed event
+        self.emit_parameters_changed_event
+            .store(true, Ordering::Relaxed);
+    }
 }
 
 /// The window handle used for [`ViziaEditor`].
@@ -7,24 +7,28 @@
 
 use crossbeam::atomic::AtomicCell;
 use nih_plug::debug::*;
-use nih_plug::prelude::{GuiContext, Param, ParamPtr};
+use nih_plug::prelude::{AbSlot, GuiContext, Param, ParamPtr};
 use std::sync::Arc;
 use vizia::prelude::*;
 
 use super::ViziaState;
 
+mod ab_toggle;
 mod generic_ui;
 pub mod param_base;
 mod param_button;
 mod param_slider;
 mod peak_meter;
+mod preset_browser;
 mod resize_handle;
 pub mod util;
 
+pub use ab_toggle::AbToggle;
 pub use generic_ui::GenericUi;
 pub use param_button::{ParamButton, ParamButtonExt};
 pub use param_slider::{ParamSlider, ParamSliderExt, ParamSliderStyle};
 pub use peak_meter::PeakMeter;
+pub use preset_browser::PresetBrowser;
 pub use resize_handle::ResizeHandle;
 
 /// Register the default theme for the widgets exported by this module. This is automatically called
@@ -66,12 +70,19 @@ pub enum RawParamEvent {
     SetParameterNormalized(ParamPtr, f32),
     /// End an automation gesture for a parameter.
     EndSetParameter(ParamPtr),
-    /// Sent by the wrapper to indicate that one or more parameter values have changed. Useful when
-    /// using properties based on a parameter's value that are computed inside of an event handler.
+    /// Ask the host to show its context menu for a parameter at the specified position in logical
+    /// pixels relative to the window. The position is converted to the physical pixels the host
+    /// expects using the window's scale factor when this event is handled. If the host cannot show
+    /// a context menu, then the parameter is reset to its default value instead.
+    ShowContextMenu(ParamPtr, i32, i32),
+    /// Sent by the wrapper to indicate that one or more parameter values or the host's indications
+    /// for those parameters have changed. Useful when using properties based on a parameter's value
+    /// that are computed inside of an event handler.
     ParametersChanged,
 }
 
-/// Events that directly interact with the [`GuiContext`]. Used to trigger resizes.
+/// Events that directly interact with the [`GuiContext`]. Used to trigger resizes and to switch
+/// between A/B comparison states.
 pub enum GuiContextEvent {
     /// Resize the window to match the current size reported by the [`ViziaState`]'s size function.
     /// By changing the plugin's state that is used to determine the window's size before emitting
@@ -106,6 +117,9 @@ pub enum GuiContextEvent {
     /// }
     /// ```
     Resize,
+    /// Switch to another A/B comparison slot using [`GuiContext::select_ab_slot()`]. This is
+    /// emitted by the [`AbToggle`] widget.
+    SelectAbSlot(AbSlot),
 }
 
 /// Handles parameter updates for VIZIA GUIs. Registered in
@@ -124,10 +138,13 @@ pub(crate) struct WindowModel {
     /// The last known unscaled logical window size. Used to prevent sending duplicate resize
     /// requests.
     pub last_inner_window_size: AtomicCell<(u32, u32)>,
+    /// The currently active A/B comparison slot. Updated when handling
+    /// [`GuiContextEvent::SelectAbSlot`].
+    pub active_ab_slot: AbSlot,
 }
 
 impl Model for ParamModel {
-    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+    fn event(&mut self, cx: &mut EventContext, event: &mut Event) {
         // `ParamEvent` gets downcast into `NormalizedParamEvent` by the `Message`
         // implementation below
         event.map(|param_event, _| match *param_event {
@@ -138,6 +155,23 @@ impl Model for ParamModel {
                 self.context.raw_set_parameter_normalized(p, v)
             },
             RawParamEvent::EndSetParameter(p) => unsafe { self.context.raw_end_set_parameter(p) },
+            RawParamEvent::ShowContextMenu(p, x, y) => {
+                // The host expects physical pixels
+                let dpi_factor = cx.scale_factor();
+                let x = (x as f32 * dpi_factor).round() as i32;
+                let y = (y as f32 * dpi_factor).round() as i32;
+
+                // Right clicking used to reset the parameter, so we'll keep doing that for hosts
+                // that don't support parameter context menus
+                if !self.context.raw_show_param_context_menu(p, x, y) {
+                    unsafe {
+                        self.context.raw_begin_set_parameter(p);
+                        self.context
+                            .raw_set_parameter_normalized(p, p.default_normalized_value());
+                        self.context.raw_end_set_parameter(p);
+                    }
+                }
+            }
             // This can be used by widgets to be notified when parameter values have changed
             RawParamEvent::ParametersChanged => (),
         });
@@ -153,6 +187,12 @@ impl Model for WindowModel {
                 let (width, height) = self.vizia_state.inner_logical_size();
                 cx.set_window_size(WindowSize { width, height });
 
+                meta.consume();
+            }
+            GuiContextEvent::SelectAbSlot(slot) => {
+                self.context.select_ab_slot(*slot);
+                self.active_ab_slot = self.context.active_ab_slot();
+
                 meta.consume();
             }
         });
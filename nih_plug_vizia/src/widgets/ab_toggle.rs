@@ -0,0 +1,34 @@
+//! A toggle for switching between two A/B comparison states.
+
+use nih_plug::prelude::AbSlot;
+use vizia::prelude::*;
+
+use super::{GuiContextEvent, WindowModel};
+
+/// A pair of buttons for switching between the two A/B comparison states using
+/// [`GuiContext::select_ab_slot()`][nih_plug::prelude::GuiContext::select_ab_slot()]. The button
+/// for the currently active slot has the `:checked` pseudoclass. Switching slots stores the current
+/// state in the active slot and recalls the other slot's state.
+pub struct AbToggle;
+
+impl AbToggle {
+    /// Create a new A/B toggle. Clicking on the inactive slot emits a
+    /// [`GuiContextEvent::SelectAbSlot`] event.
+    pub fn new(cx: &mut Context) -> Handle<Self> {
+        // Styling is done in the style sheet
+        Self.build(cx, |cx| {
+            for slot in [AbSlot::A, AbSlot::B] {
+                Label::new(cx, slot.snapshot_name())
+                    .class("ab-toggle__slot")
+                    .checked(WindowModel::active_ab_slot.map(move |active| *active == slot))
+                    .on_press(move |cx| cx.emit(GuiContextEvent::SelectAbSlot(slot)));
+            }
+        })
+    }
+}
+
+impl View for AbToggle {
+    fn element(&self) -> Option<&'static str> {
+        Some("ab-toggle")
+    }
+}
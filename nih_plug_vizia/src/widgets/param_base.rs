@@ -3,7 +3,7 @@
 use nih_plug::prelude::*;
 use vizia::prelude::*;
 
-use super::RawParamEvent;
+use super::{ParamModel, RawParamEvent};
 
 /// A helper for creating parameter widgets. The general idea is that a parameter widget struct can
 /// adds a `ParamWidgetBase` field on its struct, and then calls [`ParamWidgetBase::view()`] in its
@@ -227,6 +227,29 @@ impl ParamWidgetBase {
         cx.emit(RawParamEvent::EndSetParameter(self.param_ptr));
     }
 
+    /// Ask the host to show its context menu for the parameter at the mouse cursor's position. If
+    /// the host does not support this, then the parameter is reset to its default value instead.
+    /// Usually this is done on right click.
+    pub fn show_context_menu(&self, cx: &mut EventContext) {
+        // The cursor position is in physical pixels, while the event uses logical pixels
+        let dpi_factor = cx.scale_factor();
+        let x = (cx.mouse().cursorx / dpi_factor).round() as i32;
+        let y = (cx.mouse().cursory / dpi_factor).round() as i32;
+        cx.emit(RawParamEvent::ShowContextMenu(self.param_ptr, x, y));
+    }
+
+    /// Get the host's indication for the parameter, like whether it's mapped to a hardware
+    /// controller. A [`RawParamEvent::ParametersChanged`] event is emitted when this changes.
+    pub fn param_indication(&self, cx: &impl DataContext) -> ParamIndication {
+        match cx.data::<ParamModel>() {
+            Some(param_model) => param_model.context.raw_param_indication(self.param_ptr),
+            None => {
+                nih_debug_assert_failure!("The parameter model is missing from the context");
+                ParamIndication::default()
+            }
+        }
+    }
+
     param_ptr_forward!(pub fn name(&self) -> &str);
     param_ptr_forward!(pub fn unit(&self) -> &'static str);
     param_ptr_forward!(pub fn poly_modulation_id(&self) -> Option<u32>);
@@ -1,10 +1,11 @@
 //! A slider that integrates with NIH-plug's [`Param`] types.
 
-use nih_plug::prelude::Param;
+use nih_plug::prelude::{Param, ParamIndication};
 use vizia::prelude::*;
 
 use super::param_base::ParamWidgetBase;
 use super::util::{self, ModifiersExt};
+use super::RawParamEvent;
 
 /// When shift+dragging a parameter, one pixel dragged corresponds to this much change in the
 /// normalized parameter.
@@ -12,6 +13,9 @@ const GRANULAR_DRAG_MULTIPLIER: f32 = 0.1;
 
 /// A slider that integrates with NIH-plug's [`Param`] types. Use the
 /// [`set_style()`][ParamSliderExt::set_style()] method to change how the value gets displayed.
+/// Right clicking on the slider shows the host's context menu for the parameter, or resets the
+/// parameter if the host doesn't support that. If the host mapped the parameter to a hardware
+/// controller, then the slider is outlined using that mapping's color.
 #[derive(Lens)]
 pub struct ParamSlider {
     param_base: ParamWidgetBase,
@@ -26,6 +30,10 @@ pub struct ParamSlider {
     /// dragging for higher precision dragging. This is a `None` value when granular dragging is not
     /// active.
     granular_drag_status: Option<GranularDragStatus>,
+    /// The color of the host's hardware controller mapping for this parameter, if the host mapped
+    /// the parameter to a controller and sent a color for it. Updated whenever a
+    /// [`RawParamEvent::ParametersChanged`] event is received.
+    mapping_color: Option<Color>,
 
     // These fields are set through modifiers:
     /// Whether or not to listen to scroll events for changing the parameter's value in steps.
@@ -101,12 +109,15 @@ impl ParamSlider {
         // We'll visualize the difference between the current value and the default value if the
         // default value lies somewhere in the middle and the parameter is continuous. Otherwise
         // this approach looks a bit jarring.
+        let param_base = ParamWidgetBase::new(cx, params, params_to_param);
+        let mapping_color = Self::host_mapping_color(&param_base.param_indication(cx));
         Self {
-            param_base: ParamWidgetBase::new(cx, params, params_to_param),
+            param_base,
 
             text_input_active: false,
             drag_active: false,
             granular_drag_status: None,
+            mapping_color,
 
             use_scroll_wheel: true,
             scrolled_lines: 0.0,
@@ -178,6 +189,7 @@ impl ParamSlider {
                                         make_preview_value_lens,
                                         ParamSlider::label_override,
                                     );
+                                    Self::mapping_indicator_view(cx);
                                 })
                                 .hoverable(false);
                             }
@@ -309,6 +321,29 @@ impl ParamSlider {
         };
     }
 
+    /// Create the outline that's shown when the host mapped the parameter to a hardware controller.
+    /// Shown on top of the fill and the label using a `ZStack`.
+    fn mapping_indicator_view(cx: &mut Context) {
+        Element::new(cx)
+            .class("mapping-indicator")
+            .border_color(
+                ParamSlider::mapping_color.map(|color| color.unwrap_or(Color::transparent())),
+            )
+            .visibility(ParamSlider::mapping_color.map(Option::is_some))
+            .height(Stretch(1.0))
+            .width(Stretch(1.0))
+            .hoverable(false);
+    }
+
+    /// The color used for the mapping indicator based on the host's indication for the parameter.
+    fn host_mapping_color(indication: &ParamIndication) -> Option<Color> {
+        indication
+            .mapping
+            .as_ref()
+            .and_then(|mapping| mapping.color)
+            .map(|color| Color::rgba(color.red, color.green, color.blue, color.alpha))
+    }
+
     /// Calculate the start position and width of the slider's fill region based on the selected
     /// style, the parameter's current value, and the parameter's step sizes. The resulting tuple
     /// `(start_t, delta)` corresponds to the start and the signed width of the bar. `start_t` is in
@@ -445,6 +480,13 @@ impl View for ParamSlider {
             }
         });
 
+        event.map(|param_event, _| {
+            if let RawParamEvent::ParametersChanged = param_event {
+                self.mapping_color =
+                    Self::host_mapping_color(&self.param_base.param_indication(cx));
+            }
+        });
+
         event.map(|window_event, meta| match window_event {
             // Vizia always captures the third mouse click as a triple click. Treating that triple
             // click as a regular mouse button makes double click followed by another drag work as
@@ -457,8 +499,8 @@ impl View for ParamSlider {
                     self.text_input_active = true;
                     cx.set_active(true);
                 } else if cx.modifiers().command() {
-                    // Ctrl+Click, double click, and right clicks should reset the parameter instead
-                    // of initiating a drag operation
+                    // Ctrl+Click and double click should reset the parameter instead of initiating
+                    // a drag operation
                     self.param_base.begin_set_parameter(cx);
                     self.param_base
                         .set_normalized_value(cx, self.param_base.default_normalized_value());
@@ -492,12 +534,9 @@ impl View for ParamSlider {
 
                 meta.consume();
             }
-            WindowEvent::MouseDoubleClick(MouseButton::Left)
-            | WindowEvent::MouseDown(MouseButton::Right)
-            | WindowEvent::MouseDoubleClick(MouseButton::Right)
-            | WindowEvent::MouseTripleClick(MouseButton::Right) => {
-                // Ctrl+Click, double click, and right clicks should reset the parameter instead of
-                // initiating a drag operation
+            WindowEvent::MouseDoubleClick(MouseButton::Left) => {
+                // Ctrl+Click and double click should reset the parameter instead of initiating a
+                // drag operation
                 self.param_base.begin_set_parameter(cx);
                 self.param_base
                     .set_normalized_value(cx, self.param_base.default_normalized_value());
@@ -505,6 +544,15 @@ impl View for ParamSlider {
 
                 meta.consume();
             }
+            WindowEvent::MouseDown(MouseButton::Right)
+            | WindowEvent::MouseDoubleClick(MouseButton::Right)
+            | WindowEvent::MouseTripleClick(MouseButton::Right) => {
+                // Right clicks show the host's context menu for the parameter. If the host doesn't
+                // support this, then the parameter gets reset instead.
+                self.param_base.show_context_menu(cx);
+
+                meta.consume();
+            }
             WindowEvent::MouseUp(MouseButton::Left) => {
                 if self.drag_active {
                     self.drag_active = false;
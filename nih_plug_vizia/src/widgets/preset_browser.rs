@@ -0,0 +1,201 @@
+//! A browser for the plugin's factory and user presets.
+
+use nih_plug::prelude::GuiContext;
+use nih_plug::preset::{PresetLibrary, PresetNavigator};
+use std::sync::Arc;
+use vizia::prelude::*;
+
+/// A preset browser for the plugin's factory and user presets. This shows the current preset with
+/// buttons to step through the presets, a search field, a row of category filters, a list of the
+/// presets matching the search query and the category, and a text field for saving the current
+/// state as a new user preset. The current preset and the selected category have the `:checked`
+/// pseudoclass.
+#[derive(Lens)]
+pub struct PresetBrowser {
+    context: Arc<dyn GuiContext>,
+    navigator: PresetNavigator,
+
+    // These fields mirror the navigator's state so they can be bound to the view:
+    /// The name of the current preset, or a placeholder if no preset has been loaded.
+    current_name: String,
+    /// The current search query.
+    search: String,
+    /// The selected category, or `None` when showing all categories.
+    category: Option<String>,
+    /// All categories used by the presets.
+    categories: Vec<String>,
+    /// The indices of the presets matching the search query and the selected category.
+    visible_indices: Vec<usize>,
+    /// The names of the presets in `visible_indices`.
+    visible_names: Vec<String>,
+    /// The index of the current preset, if any.
+    current: Option<usize>,
+    /// The initial contents of the save-as text field. This is always empty.
+    save_name: String,
+    /// The last error that occurred while loading or saving a preset, or an empty string.
+    error: String,
+}
+
+enum PresetBrowserEvent {
+    LoadPrevious,
+    LoadNext,
+    Load(usize),
+    SetSearch(String),
+    SetCategory(Option<String>),
+    SaveAs(String),
+}
+
+impl PresetBrowser {
+    /// Create a new preset browser showing the presets from `library`, which is usually
+    /// [`PresetLibrary::new()`]. The [`GuiContext`] passed to the editor's build function is used to
+    /// load and save presets.
+    pub fn new(
+        cx: &mut Context,
+        library: PresetLibrary,
+        context: Arc<dyn GuiContext>,
+    ) -> Handle<Self> {
+        let mut browser = Self {
+            context,
+            navigator: PresetNavigator::new(library),
+
+            current_name: String::new(),
+            search: String::new(),
+            category: None,
+            categories: Vec::new(),
+            visible_indices: Vec::new(),
+            visible_names: Vec::new(),
+            current: None,
+            save_name: String::new(),
+            error: String::new(),
+        };
+        browser.sync();
+
+        // Styling is done in the style sheet
+        browser.build(cx, |cx| {
+            HStack::new(cx, |cx| {
+                Label::new(cx, "<")
+                    .class("preset-browser__button")
+                    .on_press(|cx| cx.emit(PresetBrowserEvent::LoadPrevious));
+                Label::new(cx, ">")
+                    .class("preset-browser__button")
+                    .on_press(|cx| cx.emit(PresetBrowserEvent::LoadNext));
+                Label::new(cx, PresetBrowser::current_name).class("preset-browser__current");
+            })
+            .class("preset-browser__row");
+
+            Textbox::new(cx, PresetBrowser::search)
+                .class("preset-browser__search")
+                .on_edit(|cx, text| cx.emit(PresetBrowserEvent::SetSearch(text)));
+
+            Binding::new(cx, PresetBrowser::categories, |cx, categories| {
+                HStack::new(cx, |cx| {
+                    Self::category_view(cx, None);
+                    for category in categories.get(cx) {
+                        Self::category_view(cx, Some(category));
+                    }
+                })
+                .class("preset-browser__categories");
+            });
+
+            ScrollView::new(cx, 0.0, 0.0, false, true, |cx| {
+                Binding::new(cx, PresetBrowser::visible_indices, |cx, indices| {
+                    for (position, idx) in indices.get(cx).into_iter().enumerate() {
+                        let name = PresetBrowser::visible_names
+                            .map(move |names| names.get(position).cloned().unwrap_or_default());
+
+                        Label::new(cx, name)
+                            .class("preset-browser__preset")
+                            .checked(
+                                PresetBrowser::current.map(move |current| *current == Some(idx)),
+                            )
+                            .on_press(move |cx| cx.emit(PresetBrowserEvent::Load(idx)));
+                    }
+                });
+            })
+            .class("preset-browser__list");
+
+            Textbox::new(cx, PresetBrowser::save_name)
+                .class("preset-browser__save")
+                .on_submit(|cx, text, success| {
+                    if success {
+                        cx.emit(PresetBrowserEvent::SaveAs(text));
+                    }
+                });
+
+            Label::new(cx, PresetBrowser::error).class("preset-browser__error");
+        })
+    }
+
+    /// A toggle for only showing the presets in `category`, or all presets if `category` is
+    /// `None`.
+    fn category_view(cx: &mut Context, category: Option<String>) {
+        let label = category.clone().unwrap_or_else(|| String::from("All"));
+        let selected_category = category.clone();
+
+        Label::new(cx, &label)
+            .class("preset-browser__category")
+            .checked(PresetBrowser::category.map(move |current| *current == selected_category))
+            .on_press(move |cx| cx.emit(PresetBrowserEvent::SetCategory(category.clone())));
+    }
+
+    /// Copy the navigator's state to the fields the views are bound to.
+    fn sync(&mut self) {
+        let presets = self.navigator.library().presets();
+
+        self.current_name = match self.navigator.current_preset() {
+            Some(entry) => entry.metadata.name.clone(),
+            None => String::from("No preset loaded"),
+        };
+        self.search = String::from(self.navigator.search());
+        self.category = self.navigator.category().map(String::from);
+        self.categories = self
+            .navigator
+            .library()
+            .categories()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self.visible_indices = self.navigator.visible().to_vec();
+        self.visible_names = self
+            .visible_indices
+            .iter()
+            .map(|&idx| presets[idx].metadata.name.clone())
+            .collect();
+        self.current = self.navigator.current();
+    }
+}
+
+impl View for PresetBrowser {
+    fn element(&self) -> Option<&'static str> {
+        Some("preset-browser")
+    }
+
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|preset_browser_event, meta| {
+            let context = self.context.as_ref();
+            let result = match preset_browser_event {
+                PresetBrowserEvent::LoadPrevious => self.navigator.load_previous(context),
+                PresetBrowserEvent::LoadNext => self.navigator.load_next(context),
+                PresetBrowserEvent::Load(idx) => self.navigator.load(*idx, context),
+                PresetBrowserEvent::SetSearch(query) => {
+                    self.navigator.set_search(query.as_str());
+                    Ok(())
+                }
+                PresetBrowserEvent::SetCategory(category) => {
+                    self.navigator.set_category(category.clone());
+                    Ok(())
+                }
+                PresetBrowserEvent::SaveAs(name) if name.trim().is_empty() => Ok(()),
+                PresetBrowserEvent::SaveAs(name) => self.navigator.save_as(name, context),
+            };
+
+            self.error = match result {
+                Ok(()) => String::new(),
+                Err(err) => format!("{err:#}"),
+            };
+            self.sync();
+
+            meta.consume();
+        });
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use nih_plug::buffer::Block;
 use nih_plug::prelude::*;
 use std::sync::Arc;
 
@@ -231,117 +232,39 @@ impl Plugin for BuffrGlitch {
         _aux: &mut AuxiliaryBuffers,
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        let num_samples = buffer.samples();
-        let output = buffer.as_slice();
-
-        let mut next_event = context.next_event();
-        let mut block_start: usize = 0;
-        let mut block_end: usize = MAX_BLOCK_SIZE.min(num_samples);
-        while block_start < num_samples {
-            // Keep processing events until all events at or before `block_start` have been
-            // processed
-            'events: loop {
-                match next_event {
-                    // If the event happens now, then we'll keep processing events
-                    Some(event) if (event.timing() as usize) <= block_start => {
-                        match event {
-                            NoteEvent::NoteOn { note, velocity, .. } => {
-                                let new_voice_id = self.new_voice_id();
-                                self.voices[new_voice_id].note_on(&self.params, note, velocity);
-                            }
-                            NoteEvent::NoteOff { note, .. } => {
-                                for voice in &mut self.voices {
-                                    if voice.midi_note_id == Some(note) {
-                                        // Playback still continues until the release is done.
-                                        voice.note_off();
-                                        break;
-                                    }
-                                }
-                            }
-                            NoteEvent::PolyVolume { note, gain, .. } => {
-                                for voice in &mut self.voices {
-                                    if voice.midi_note_id == Some(note) {
-                                        voice
-                                            .gain_expression_gain
-                                            .set_target(self.sample_rate, gain);
-                                        break;
-                                    }
-                                }
+        // Events are yielded before the block starting at their timing, and blocks are cut short
+        // at the next event
+        let mut blocks = buffer.iter_event_blocks(MAX_BLOCK_SIZE);
+        while let Some(item) = blocks.next(context) {
+            match item {
+                EventBlockItem::Event(event) => match event {
+                    NoteEvent::NoteOn { note, velocity, .. } => {
+                        let new_voice_id = self.new_voice_id();
+                        self.voices[new_voice_id].note_on(&self.params, note, velocity);
+                    }
+                    NoteEvent::NoteOff { note, .. } => {
+                        for voice in &mut self.voices {
+                            if voice.midi_note_id == Some(note) {
+                                // Playback still continues until the release is done.
+                                voice.note_off();
+                                break;
                             }
-                            _ => (),
                         }
-
-                        next_event = context.next_event();
                     }
-                    // If the event happens before the end of the block, then the block should be cut
-                    // short so the next block starts at the event
-                    Some(event) if (event.timing() as usize) < block_end => {
-                        block_end = event.timing() as usize;
-                        break 'events;
+                    NoteEvent::PolyVolume { note, gain, .. } => {
+                        for voice in &mut self.voices {
+                            if voice.midi_note_id == Some(note) {
+                                voice
+                                    .gain_expression_gain
+                                    .set_target(self.sample_rate, gain);
+                                break;
+                            }
+                        }
                     }
-                    _ => break 'events,
-                }
-            }
-
-            // The output buffer is filled with the active voices, so we need to read the inptu
-            // first
-            let block_len = block_end - block_start;
-            let mut input = [[0.0; MAX_BLOCK_SIZE]; 2];
-            input[0][..block_len].copy_from_slice(&output[0][block_start..block_end]);
-            input[1][..block_len].copy_from_slice(&output[1][block_start..block_end]);
-
-            // The dry signal is mixed back in depending on th maximum voice amplitude envelope
-            let mut max_voice_amp_envelope = [0.0f32; MAX_BLOCK_SIZE];
-
-            // We'll empty the buffer, and then add the dry signal back in as needed
-            output[0][block_start..block_end].fill(0.0);
-            output[1][block_start..block_end].fill(0.0);
-            for voice in self.voices.iter_mut().filter(|v| v.is_active()) {
-                let mut voice_amp_envelope = [0.0; MAX_BLOCK_SIZE];
-                voice
-                    .amp_envelope
-                    .set_attack_time(self.sample_rate, self.params.attack_ms.value());
-                voice
-                    .amp_envelope
-                    .set_release_time(self.sample_rate, self.params.release_ms.value());
-                voice
-                    .amp_envelope
-                    .next_block(&mut voice_amp_envelope, block_len);
-                let mut voice_gain_expression_gain = [0.0; MAX_BLOCK_SIZE];
-                voice
-                    .gain_expression_gain
-                    .next_block(&mut voice_gain_expression_gain, block_len);
-
-                for (value_idx, sample_idx) in (block_start..block_end).enumerate() {
-                    max_voice_amp_envelope[value_idx] =
-                        max_voice_amp_envelope[value_idx].max(voice_amp_envelope[value_idx]);
-                    let amp = voice.velocity_gain
-                        * voice_gain_expression_gain[value_idx]
-                        * voice_amp_envelope[value_idx];
-
-                    // This will start recording on the first iteration, and then loop the recorded
-                    // buffer afterwards
-                    output[0][sample_idx] += voice.buffer.next_sample(0, input[0][value_idx]) * amp;
-                    output[1][sample_idx] += voice.buffer.next_sample(1, input[1][value_idx]) * amp;
-                }
-            }
-
-            // The dry signal is mixed back in depending on the amplitude of the currently playing
-            // voices
-            let mut dry_level = [0.0; MAX_BLOCK_SIZE];
-            self.params
-                .dry_level
-                .smoothed
-                .next_block(&mut dry_level, block_len);
-            for (value_idx, sample_idx) in (block_start..block_end).enumerate() {
-                let gain = (1.0 - max_voice_amp_envelope[value_idx]) * dry_level[value_idx];
-                output[0][sample_idx] += input[0][value_idx] * gain;
-                output[1][sample_idx] += input[1][value_idx] * gain;
+                    _ => (),
+                },
+                EventBlockItem::Block(_, block) => self.process_block(block),
             }
-
-            // And then just keep processing blocks until we've run out of buffer to fill
-            block_start = block_end;
-            block_end = (block_start + MAX_BLOCK_SIZE).min(num_samples);
         }
 
         ProcessStatus::Normal
@@ -349,6 +272,72 @@ impl Plugin for BuffrGlitch {
 }
 
 impl BuffrGlitch {
+    /// Render the voices for a block of at most `MAX_BLOCK_SIZE` samples and mix in the dry signal.
+    /// All events up to the block's start have already been handled.
+    fn process_block(&mut self, block: Block) {
+        let block_len = block.samples();
+        let mut channels = block.into_iter();
+        let (Some(left), Some(right)) = (channels.next(), channels.next()) else {
+            nih_debug_assert_failure!("Buffr Glitch only supports stereo audio");
+            return;
+        };
+        let output = [left, right];
+
+        // The output buffer is filled with the active voices, so we need to read the input first
+        let mut input = [[0.0; MAX_BLOCK_SIZE]; 2];
+        input[0][..block_len].copy_from_slice(output[0]);
+        input[1][..block_len].copy_from_slice(output[1]);
+
+        // The dry signal is mixed back in depending on th maximum voice amplitude envelope
+        let mut max_voice_amp_envelope = [0.0f32; MAX_BLOCK_SIZE];
+
+        // We'll empty the buffer, and then add the dry signal back in as needed
+        output[0].fill(0.0);
+        output[1].fill(0.0);
+        for voice in self.voices.iter_mut().filter(|v| v.is_active()) {
+            let mut voice_amp_envelope = [0.0; MAX_BLOCK_SIZE];
+            voice
+                .amp_envelope
+                .set_attack_time(self.sample_rate, self.params.attack_ms.value());
+            voice
+                .amp_envelope
+                .set_release_time(self.sample_rate, self.params.release_ms.value());
+            voice
+                .amp_envelope
+                .next_block(&mut voice_amp_envelope, block_len);
+            let mut voice_gain_expression_gain = [0.0; MAX_BLOCK_SIZE];
+            voice
+                .gain_expression_gain
+                .next_block(&mut voice_gain_expression_gain, block_len);
+
+            for sample_idx in 0..block_len {
+                max_voice_amp_envelope[sample_idx] =
+                    max_voice_amp_envelope[sample_idx].max(voice_amp_envelope[sample_idx]);
+                let amp = voice.velocity_gain
+                    * voice_gain_expression_gain[sample_idx]
+                    * voice_amp_envelope[sample_idx];
+
+                // This will start recording on the first iteration, and then loop the recorded
+                // buffer afterwards
+                output[0][sample_idx] += voice.buffer.next_sample(0, input[0][sample_idx]) * amp;
+                output[1][sample_idx] += voice.buffer.next_sample(1, input[1][sample_idx]) * amp;
+            }
+        }
+
+        // The dry signal is mixed back in depending on the amplitude of the currently playing
+        // voices
+        let mut dry_level = [0.0; MAX_BLOCK_SIZE];
+        self.params
+            .dry_level
+            .smoothed
+            .next_block(&mut dry_level, block_len);
+        for sample_idx in 0..block_len {
+            let gain = (1.0 - max_voice_amp_envelope[sample_idx]) * dry_level[sample_idx];
+            output[0][sample_idx] += input[0][sample_idx] * gain;
+            output[1][sample_idx] += input[1][sample_idx] * gain;
+        }
+    }
+
     /// Find the ID of a voice that is either unused or that is quietest if all voices are in use.
     /// This does not do anything to the voice to end it.
     pub fn new_voice_id(&self) -> usize {
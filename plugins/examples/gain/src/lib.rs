@@ -134,6 +134,9 @@ impl Plugin for Gain {
             // are generated as needed. This layout will be called 'Stereo', while the other one is
             // given the name 'Mono' based no the number of input and output channels.
             names: PortNames::const_default(),
+            // Ports with one or two channels are treated as mono and stereo ports. Other layouts
+            // like 5.1 surround or ambisonics can be specified here.
+            channel_layouts: PortChannelLayouts::const_default(),
         },
         AudioIOLayout {
             main_input_channels: NonZeroU32::new(1),
@@ -149,6 +152,10 @@ impl Plugin for Gain {
     // stuff like making sure transport and other timing information stays consistent between the
     // splits.
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
+    // Hosts that support double precision processing will call `process_f64()` instead of
+    // `process()` when this is set. The sample type can be made generic to share the code between
+    // the two.
+    const SUPPORTS_F64: bool = true;
 
     // If the plugin can send or receive SysEx messages, it can define a type to wrap around those
     // messages here. The type implements the `SysExMessage` trait, which allows conversion to and
@@ -174,9 +181,28 @@ impl Plugin for Gain {
         _aux: &mut AuxiliaryBuffers,
         _context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
+        self.process_generic(buffer)
+    }
+
+    fn process_f64(
+        &mut self,
+        buffer: &mut Buffer<f64>,
+        _aux: &mut AuxiliaryBuffers<f64>,
+        _context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        self.process_generic(buffer)
+    }
+
+    // This can be used for cleaning up special resources like socket connections whenever the
+    // plugin is deactivated. Most plugins won't need to do anything here.
+    fn deactivate(&mut self) {}
+}
+
+impl Gain {
+    fn process_generic<S: Sample>(&mut self, buffer: &mut Buffer<S>) -> ProcessStatus {
         for channel_samples in buffer.iter_samples() {
             // Smoothing is optionally built into the parameters themselves
-            let gain = self.params.gain.smoothed.next();
+            let gain = S::from(self.params.gain.smoothed.next());
 
             for sample in channel_samples {
                 *sample *= gain;
@@ -185,10 +211,6 @@ impl Plugin for Gain {
 
         ProcessStatus::Normal
     }
-
-    // This can be used for cleaning up special resources like socket connections whenever the
-    // plugin is deactivated. Most plugins won't need to do anything here.
-    fn deactivate(&mut self) {}
 }
 
 impl ClapPlugin for Gain {
@@ -182,251 +182,236 @@ impl Plugin for PolyModSynth {
         _aux: &mut AuxiliaryBuffers,
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
-        // NIH-plug has a block-splitting adapter for `Buffer`. While this works great for effect
-        // plugins, for polyphonic synths the block size should be `min(MAX_BLOCK_SIZE,
-        // num_remaining_samples, next_event_idx - block_start_idx)`. Because blocks also need to be
-        // split on note events, it's easier to work with raw audio here and to do the splitting by
-        // hand.
-        let num_samples = buffer.samples();
+        // NIH-plug has a block-splitting adapter for `Buffer` that also splits blocks on note
+        // events. For polyphonic synths the block size should be `min(MAX_BLOCK_SIZE,
+        // num_remaining_samples, next_event_idx - block_start_idx)`, and all events happening at a
+        // block's start are yielded before that block so they can be handled first.
         let sample_rate = context.transport().sample_rate;
-        let output = buffer.as_slice();
-
-        let mut next_event = context.next_event();
-        let mut block_start: usize = 0;
-        let mut block_end: usize = MAX_BLOCK_SIZE.min(num_samples);
-        while block_start < num_samples {
-            // First of all, handle all note events that happen at the start of the block, and cut
-            // the block short if another event happens before the end of it. To handle polyphonic
-            // modulation for new notes properly, we'll keep track of the next internal note index
-            // at the block's start. If we receive polyphonic modulation that matches a voice that
-            // has an internal note ID that's great than or equal to this one, then we should start
-            // the note's smoother at the new value instead of fading in from the global value.
-            let this_sample_internal_voice_id_start = self.next_internal_voice_id;
-            'events: loop {
-                match next_event {
-                    // If the event happens now, then we'll keep processing events
-                    Some(event) if (event.timing() as usize) <= block_start => {
-                        // This synth doesn't support any of the polyphonic expression events. A
-                        // real synth plugin however will want to support those.
-                        match event {
-                            NoteEvent::NoteOn {
-                                timing,
-                                voice_id,
-                                channel,
-                                note,
-                                velocity,
-                            } => {
-                                let initial_phase: f32 = self.prng.gen();
-                                // This starts with the attack portion of the amplitude envelope
-                                let amp_envelope = Smoother::new(SmoothingStyle::Exponential(
-                                    self.params.amp_attack_ms.value(),
-                                ));
-                                amp_envelope.reset(0.0);
-                                amp_envelope.set_target(sample_rate, 1.0);
-
-                                let voice =
-                                    self.start_voice(context, timing, voice_id, channel, note);
-                                voice.velocity_sqrt = velocity.sqrt();
-                                voice.phase = initial_phase;
-                                voice.phase_delta = util::midi_note_to_freq(note) / sample_rate;
-                                voice.amp_envelope = amp_envelope;
-                            }
-                            NoteEvent::NoteOff {
-                                timing: _,
-                                voice_id,
-                                channel,
-                                note,
-                                velocity: _,
-                            } => {
-                                self.start_release_for_voices(sample_rate, voice_id, channel, note)
-                            }
-                            NoteEvent::Choke {
-                                timing,
-                                voice_id,
-                                channel,
-                                note,
-                            } => {
-                                self.choke_voices(context, timing, voice_id, channel, note);
-                            }
-                            NoteEvent::PolyModulation {
-                                timing: _,
-                                voice_id,
-                                poly_modulation_id,
-                                normalized_offset,
-                            } => {
-                                // Polyphonic modulation events are matched to voices using the
-                                // voice ID, and to parameters using the poly modulation ID. The
-                                // host will probably send a modulation event every N samples. This
-                                // will happen before the voice is active, and of course also after
-                                // it has been terminated (because the host doesn't know that it
-                                // will be). Because of that, we won't print any assertion failures
-                                // when we can't find the voice index here.
-                                if let Some(voice_idx) = self.get_voice_idx(voice_id) {
-                                    let voice = self.voices[voice_idx].as_mut().unwrap();
-
-                                    match poly_modulation_id {
-                                        GAIN_POLY_MOD_ID => {
-                                            // This should either create a smoother for this
-                                            // modulated parameter or update the existing one.
-                                            // Notice how this uses the parameter's unmodulated
-                                            // normalized value in combination with the normalized
-                                            // offset to create the target plain value
-                                            let target_plain_value = self
-                                                .params
-                                                .gain
-                                                .preview_modulated(normalized_offset);
-                                            let (_, smoother) =
-                                                voice.voice_gain.get_or_insert_with(|| {
-                                                    (
-                                                        normalized_offset,
-                                                        self.params.gain.smoothed.clone(),
-                                                    )
-                                                });
-
-                                            // If this `PolyModulation` events happens on the
-                                            // same sample as a voice's `NoteOn` event, then it
-                                            // should immediately use the modulated value
-                                            // instead of slowly fading in
-                                            if voice.internal_voice_id
-                                                >= this_sample_internal_voice_id_start
-                                            {
-                                                smoother.reset(target_plain_value);
-                                            } else {
-                                                smoother
-                                                    .set_target(sample_rate, target_plain_value);
-                                            }
+
+        // To handle polyphonic modulation for new notes properly, we'll keep track of the next
+        // internal note index at the block's start. If we receive polyphonic modulation that
+        // matches a voice that has an internal note ID that's great than or equal to this one, then
+        // we should start the note's smoother at the new value instead of fading in from the global
+        // value.
+        let mut this_sample_internal_voice_id_start = self.next_internal_voice_id;
+        let mut blocks = buffer.iter_event_blocks(MAX_BLOCK_SIZE);
+        while let Some(item) = blocks.next(context) {
+            match item {
+                EventBlockItem::Event(event) => {
+                    // This synth doesn't support any of the polyphonic expression events. A real
+                    // synth plugin however will want to support those.
+                    match event {
+                        NoteEvent::NoteOn {
+                            timing,
+                            voice_id,
+                            channel,
+                            note,
+                            velocity,
+                        } => {
+                            let initial_phase: f32 = self.prng.gen();
+                            // This starts with the attack portion of the amplitude envelope
+                            let amp_envelope = Smoother::new(SmoothingStyle::Exponential(
+                                self.params.amp_attack_ms.value(),
+                            ));
+                            amp_envelope.reset(0.0);
+                            amp_envelope.set_target(sample_rate, 1.0);
+
+                            let voice = self.start_voice(context, timing, voice_id, channel, note);
+                            voice.velocity_sqrt = velocity.sqrt();
+                            voice.phase = initial_phase;
+                            voice.phase_delta = util::midi_note_to_freq(note) / sample_rate;
+                            voice.amp_envelope = amp_envelope;
+                        }
+                        NoteEvent::NoteOff {
+                            timing: _,
+                            voice_id,
+                            channel,
+                            note,
+                            velocity: _,
+                        } => self.start_release_for_voices(sample_rate, voice_id, channel, note),
+                        NoteEvent::Choke {
+                            timing,
+                            voice_id,
+                            channel,
+                            note,
+                        } => {
+                            self.choke_voices(context, timing, voice_id, channel, note);
+                        }
+                        NoteEvent::PolyModulation {
+                            timing: _,
+                            voice_id,
+                            poly_modulation_id,
+                            normalized_offset,
+                        } => {
+                            // Polyphonic modulation events are matched to voices using the
+                            // voice ID, and to parameters using the poly modulation ID. The
+                            // host will probably send a modulation event every N samples. This
+                            // will happen before the voice is active, and of course also after
+                            // it has been terminated (because the host doesn't know that it
+                            // will be). Because of that, we won't print any assertion failures
+                            // when we can't find the voice index here.
+                            if let Some(voice_idx) = self.get_voice_idx(voice_id) {
+                                let voice = self.voices[voice_idx].as_mut().unwrap();
+
+                                match poly_modulation_id {
+                                    GAIN_POLY_MOD_ID => {
+                                        // This should either create a smoother for this
+                                        // modulated parameter or update the existing one.
+                                        // Notice how this uses the parameter's unmodulated
+                                        // normalized value in combination with the normalized
+                                        // offset to create the target plain value
+                                        let target_plain_value =
+                                            self.params.gain.preview_modulated(normalized_offset);
+                                        let (_, smoother) =
+                                            voice.voice_gain.get_or_insert_with(|| {
+                                                (
+                                                    normalized_offset,
+                                                    self.params.gain.smoothed.clone(),
+                                                )
+                                            });
+
+                                        // If this `PolyModulation` events happens on the
+                                        // same sample as a voice's `NoteOn` event, then it
+                                        // should immediately use the modulated value
+                                        // instead of slowly fading in
+                                        if voice.internal_voice_id
+                                            >= this_sample_internal_voice_id_start
+                                        {
+                                            smoother.reset(target_plain_value);
+                                        } else {
+                                            smoother.set_target(sample_rate, target_plain_value);
                                         }
-                                        n => nih_debug_assert_failure!(
-                                            "Polyphonic modulation sent for unknown poly \
-                                             modulation ID {}",
-                                            n
-                                        ),
                                     }
+                                    n => nih_debug_assert_failure!(
+                                        "Polyphonic modulation sent for unknown poly \
+                                         modulation ID {}",
+                                        n
+                                    ),
                                 }
                             }
-                            NoteEvent::MonoAutomation {
-                                timing: _,
-                                poly_modulation_id,
-                                normalized_value,
-                            } => {
-                                // Modulation always acts as an offset to the parameter's current
-                                // automated value. So if the host sends a new automation value for
-                                // a modulated parameter, the modulated values/smoothing targets
-                                // need to be updated for all polyphonically modulated voices.
-                                for voice in self.voices.iter_mut().filter_map(|v| v.as_mut()) {
-                                    match poly_modulation_id {
-                                        GAIN_POLY_MOD_ID => {
-                                            let (normalized_offset, smoother) =
-                                                match voice.voice_gain.as_mut() {
-                                                    Some((o, s)) => (o, s),
-                                                    // If the voice does not have existing
-                                                    // polyphonic modulation, then there's nothing
-                                                    // to do here. The global automation/monophonic
-                                                    // modulation has already been taken care of by
-                                                    // the framework.
-                                                    None => continue,
-                                                };
-                                            let target_plain_value =
-                                                self.params.gain.preview_plain(
-                                                    normalized_value + *normalized_offset,
-                                                );
-                                            smoother.set_target(sample_rate, target_plain_value);
-                                        }
-                                        n => nih_debug_assert_failure!(
-                                            "Automation event sent for unknown poly modulation ID \
-                                             {}",
-                                            n
-                                        ),
+                        }
+                        NoteEvent::MonoAutomation {
+                            timing: _,
+                            poly_modulation_id,
+                            normalized_value,
+                        } => {
+                            // Modulation always acts as an offset to the parameter's current
+                            // automated value. So if the host sends a new automation value for
+                            // a modulated parameter, the modulated values/smoothing targets
+                            // need to be updated for all polyphonically modulated voices.
+                            for voice in self.voices.iter_mut().filter_map(|v| v.as_mut()) {
+                                match poly_modulation_id {
+                                    GAIN_POLY_MOD_ID => {
+                                        let (normalized_offset, smoother) =
+                                            match voice.voice_gain.as_mut() {
+                                                Some((o, s)) => (o, s),
+                                                // If the voice does not have existing
+                                                // polyphonic modulation, then there's nothing
+                                                // to do here. The global automation/monophonic
+                                                // modulation has already been taken care of by
+                                                // the framework.
+                                                None => continue,
+                                            };
+                                        let target_plain_value = self
+                                            .params
+                                            .gain
+                                            .preview_plain(normalized_value + *normalized_offset);
+                                        smoother.set_target(sample_rate, target_plain_value);
                                     }
+                                    n => nih_debug_assert_failure!(
+                                        "Automation event sent for unknown poly modulation ID \
+                                         {}",
+                                        n
+                                    ),
                                 }
                             }
-                            _ => (),
+                        }
+                        _ => (),
+                    };
+                }
+                EventBlockItem::Block(block_start, block) => {
+                    let block_len = block.samples();
+                    let block_end = block_start + block_len;
+
+                    // We'll start with silence, and then add the output from the active voices
+                    let mut channels = block.into_iter();
+                    let left = channels.next().unwrap();
+                    let right = channels.next().unwrap();
+                    left.fill(0.0);
+                    right.fill(0.0);
+
+                    // These are the smoothed global parameter values. These are used for voices
+                    // that do not have polyphonic modulation applied to them. With a plugin as
+                    // simple as this it would be possible to avoid this completely by simply always
+                    // copying the smoother into the voice's struct, but that may not be realistic
+                    // when the plugin has hundreds of parameters. The `voice_*` arrays are scratch
+                    // arrays that an individual voice can use.
+                    let mut gain = [0.0; MAX_BLOCK_SIZE];
+                    let mut voice_gain = [0.0; MAX_BLOCK_SIZE];
+                    let mut voice_amp_envelope = [0.0; MAX_BLOCK_SIZE];
+                    self.params.gain.smoothed.next_block(&mut gain, block_len);
+
+                    // TODO: Some form of band limiting
+                    // TODO: Filter
+                    for voice in self.voices.iter_mut().filter_map(|v| v.as_mut()) {
+                        // Depending on whether the voice has polyphonic modulation applied to it,
+                        // either the global parameter values are used, or the voice's smoother is
+                        // used to generate unique modulated values for that voice
+                        let gain = match &voice.voice_gain {
+                            Some((_, smoother)) => {
+                                smoother.next_block(&mut voice_gain, block_len);
+                                &voice_gain
+                            }
+                            None => &gain,
                         };
 
-                        next_event = context.next_event();
-                    }
-                    // If the event happens before the end of the block, then the block should be cut
-                    // short so the next block starts at the event
-                    Some(event) if (event.timing() as usize) < block_end => {
-                        block_end = event.timing() as usize;
-                        break 'events;
-                    }
-                    _ => break 'events,
-                }
-            }
+                        // This is an exponential smoother repurposed as an AR envelope with values
+                        // between 0 and 1. When a note off event is received, this envelope will
+                        // start fading out again. When it reaches 0, we will terminate the voice.
+                        voice
+                            .amp_envelope
+                            .next_block(&mut voice_amp_envelope, block_len);
+
+                        for (value_idx, (left_sample, right_sample)) in
+                            left.iter_mut().zip(right.iter_mut()).enumerate()
+                        {
+                            let amp = voice.velocity_sqrt
+                                * gain[value_idx]
+                                * voice_amp_envelope[value_idx];
+                            let sample = (voice.phase * 2.0 - 1.0) * amp;
+
+                            voice.phase += voice.phase_delta;
+                            if voice.phase >= 1.0 {
+                                voice.phase -= 1.0;
+                            }
 
-            // We'll start with silence, and then add the output from the active voices
-            output[0][block_start..block_end].fill(0.0);
-            output[1][block_start..block_end].fill(0.0);
-
-            // These are the smoothed global parameter values. These are used for voices that do not
-            // have polyphonic modulation applied to them. With a plugin as simple as this it would
-            // be possible to avoid this completely by simply always copying the smoother into the
-            // voice's struct, but that may not be realistic when the plugin has hundreds of
-            // parameters. The `voice_*` arrays are scratch arrays that an individual voice can use.
-            let block_len = block_end - block_start;
-            let mut gain = [0.0; MAX_BLOCK_SIZE];
-            let mut voice_gain = [0.0; MAX_BLOCK_SIZE];
-            let mut voice_amp_envelope = [0.0; MAX_BLOCK_SIZE];
-            self.params.gain.smoothed.next_block(&mut gain, block_len);
-
-            // TODO: Some form of band limiting
-            // TODO: Filter
-            for voice in self.voices.iter_mut().filter_map(|v| v.as_mut()) {
-                // Depending on whether the voice has polyphonic modulation applied to it,
-                // either the global parameter values are used, or the voice's smoother is used
-                // to generate unique modulated values for that voice
-                let gain = match &voice.voice_gain {
-                    Some((_, smoother)) => {
-                        smoother.next_block(&mut voice_gain, block_len);
-                        &voice_gain
+                            *left_sample += sample;
+                            *right_sample += sample;
+                        }
                     }
-                    None => &gain,
-                };
 
-                // This is an exponential smoother repurposed as an AR envelope with values between
-                // 0 and 1. When a note off event is received, this envelope will start fading out
-                // again. When it reaches 0, we will terminate the voice.
-                voice
-                    .amp_envelope
-                    .next_block(&mut voice_amp_envelope, block_len);
-
-                for (value_idx, sample_idx) in (block_start..block_end).enumerate() {
-                    let amp = voice.velocity_sqrt * gain[value_idx] * voice_amp_envelope[value_idx];
-                    let sample = (voice.phase * 2.0 - 1.0) * amp;
-
-                    voice.phase += voice.phase_delta;
-                    if voice.phase >= 1.0 {
-                        voice.phase -= 1.0;
+                    // Terminate voices whose release period has fully ended. This could be done as
+                    // part of the previous loop but this is simpler.
+                    for voice in self.voices.iter_mut() {
+                        match voice {
+                            Some(v) if v.releasing && v.amp_envelope.previous_value() == 0.0 => {
+                                // This event is very important, as it allows the host to manage
+                                // its own modulation voices
+                                context.send_event(NoteEvent::VoiceTerminated {
+                                    timing: block_end as u32,
+                                    voice_id: Some(v.voice_id),
+                                    channel: v.channel,
+                                    note: v.note,
+                                });
+                                *voice = None;
+                            }
+                            _ => (),
+                        }
                     }
 
-                    output[0][sample_idx] += sample;
-                    output[1][sample_idx] += sample;
-                }
-            }
-
-            // Terminate voices whose release period has fully ended. This could be done as part of
-            // the previous loop but this is simpler.
-            for voice in self.voices.iter_mut() {
-                match voice {
-                    Some(v) if v.releasing && v.amp_envelope.previous_value() == 0.0 => {
-                        // This event is very important, as it allows the host to manage its own modulation
-                        // voices
-                        context.send_event(NoteEvent::VoiceTerminated {
-                            timing: block_end as u32,
-                            voice_id: Some(v.voice_id),
-                            channel: v.channel,
-                            note: v.note,
-                        });
-                        *voice = None;
-                    }
-                    _ => (),
+                    this_sample_internal_voice_id_start = self.next_internal_voice_id;
                 }
             }
-
-            // And then just keep processing blocks until we've run out of buffer to fill
-            block_start = block_end;
-            block_end = (block_start + MAX_BLOCK_SIZE).min(num_samples);
         }
 
         ProcessStatus::Normal
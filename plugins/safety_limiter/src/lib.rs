@@ -175,6 +175,12 @@ impl Plugin for SafetyLimiter {
         self.params.clone()
     }
 
+    fn accept_audio_io_layout(layout: &AudioIOLayout) -> bool {
+        // Every channel is processed in the same way, so the limiter works with any number of
+        // channels as long as the input and output channel counts match
+        layout.main_input_channels == layout.main_output_channels
+    }
+
     fn initialize(
         &mut self,
         _audio_io_layout: &AudioIOLayout,
@@ -101,6 +101,10 @@ pub struct CompressorBank {
     /// for the current block. The compressor thresholds and knee values are multiplied by these
     /// values to get the effective thresholds.
     sidechain_spectrum_magnitudes: Vec<Vec<f32>>,
+    /// The threshold mode used for processing. This is the mode parameter's value, except that the
+    /// sidechain modes fall back to the internal mode when the host has deactivated the sidechain
+    /// input. Set through [`CompressorBank::set_threshold_mode()`].
+    threshold_mode: ThresholdMode,
     /// The window size this compressor bank was configured for. This is used to compute the
     /// coefficients for the envelope followers in the process function.
     window_size: usize,
@@ -149,7 +153,7 @@ pub struct ThresholdParams {
 }
 
 /// The type of threshold to use.
-#[derive(Enum, Debug, PartialEq, Eq)]
+#[derive(Enum, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ThresholdMode {
     /// Configure the thresholds to offset pink noise. This means that the slope will receive an
     /// additional -3 dB/octave slope.
@@ -295,15 +299,16 @@ impl ThresholdParams {
         }
     }
 
-    /// Build [`CurveParams`] out of this set of parameters.
-    pub fn curve_params(&self) -> CurveParams {
+    /// Build [`CurveParams`] out of this set of parameters for the effective threshold mode. This
+    /// may differ from the mode parameter's value, see [`CompressorBank::set_threshold_mode()`].
+    pub fn curve_params(&self, mode: ThresholdMode) -> CurveParams {
         CurveParams {
             intercept: self.threshold_db.value(),
             center_frequency: self.center_frequency.value(),
             // The cheeky 3 additional dB/octave attenuation is to match pink noise with the
             // default settings. When using sidechaining we explicitly don't want this because
             // the curve should be a flat offset to the sidechain input at the default settings.
-            slope: match self.mode.value() {
+            slope: match mode {
                 ThresholdMode::Internal => self.curve_slope.value() - 3.0,
                 ThresholdMode::SidechainMatch | ThresholdMode::SidechainCompress => {
                     self.curve_slope.value()
@@ -459,6 +464,7 @@ impl CompressorBank {
                 Vec::with_capacity(complex_buffer_len);
                 num_channels
             ],
+            threshold_mode: ThresholdMode::Internal,
             window_size: 0,
             sample_rate: 1.0,
 
@@ -575,6 +581,19 @@ impl CompressorBank {
         // Sidechain data doesn't need to be reset as it will be overwritten immediately before use
     }
 
+    /// Set the threshold mode to use for the next processing cycle. This is usually the mode
+    /// parameter's value, but the plugin falls back to the internal mode when the sidechain input
+    /// is inactive. The thresholds are recomputed when the mode changes.
+    pub fn set_threshold_mode(&mut self, mode: ThresholdMode) {
+        if mode != self.threshold_mode {
+            self.threshold_mode = mode;
+            self.should_update_downwards_thresholds
+                .store(true, Ordering::SeqCst);
+            self.should_update_upwards_thresholds
+                .store(true, Ordering::SeqCst);
+        }
+    }
+
     /// Apply the magnitude compression to a buffer of FFT bins. The compressors are first updated
     /// if needed. The overlap amount is needed to compute the effective sample rate. The
     /// `first_non_dc_bin` argument is used to avoid upwards compression on the DC bins, or the
@@ -607,7 +626,7 @@ impl CompressorBank {
         }
 
         self.update_if_needed(params);
-        match params.threshold.mode.value() {
+        match self.threshold_mode {
             ThresholdMode::Internal => {
                 self.update_envelopes(buffer, channel_idx, params, overlap_times);
                 self.compress(buffer, channel_idx, params, first_non_dc_bin)
@@ -631,7 +650,7 @@ impl CompressorBank {
             let analyzer_input_data = self.analyzer_input_data.input_buffer();
 
             // The editor needs to know about this too so it can draw the spectra correctly
-            analyzer_input_data.curve_params = params.threshold.curve_params();
+            analyzer_input_data.curve_params = params.threshold.curve_params(self.threshold_mode);
             analyzer_input_data.curve_offsets_db = (
                 params.compressors.upwards.threshold_offset_db.value(),
                 params.compressors.downwards.threshold_offset_db.value(),
@@ -1048,7 +1067,7 @@ impl CompressorBank {
     /// are updated in accordance to the atomic flags set on this struct.
     fn update_if_needed(&mut self, params: &SpectralCompressorParams) {
         // The threshold curve is a polynomial in log-log (decibels-octaves) space
-        let curve_params = params.threshold.curve_params();
+        let curve_params = params.threshold.curve_params(self.threshold_mode);
         let curve = Curve::new(&curve_params);
 
         if self
@@ -435,7 +435,20 @@ impl Plugin for SpectralCompressor {
         // This is mixed in later with latency compensation applied
         self.dry_wet_mixer.write_dry(buffer);
 
-        match self.params.threshold.mode.value() {
+        // The sidechain modes can only be used if the host actually routes audio to the sidechain
+        // input. Otherwise the input would be compressed based on silence.
+        let threshold_mode = match self.params.threshold.mode.value() {
+            compressor_bank::ThresholdMode::SidechainMatch
+            | compressor_bank::ThresholdMode::SidechainCompress
+                if !aux.is_input_active(0) =>
+            {
+                compressor_bank::ThresholdMode::Internal
+            }
+            mode => mode,
+        };
+        self.compressor_bank.set_threshold_mode(threshold_mode);
+
+        match threshold_mode {
             compressor_bank::ThresholdMode::Internal => self.stft.process_overlap_add(
                 buffer,
                 overlap_times,
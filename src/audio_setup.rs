@@ -30,6 +30,9 @@ pub struct AudioIOLayout {
     /// Optional names for the audio ports. Defining these can be useful for plugins with multiple
     /// output and input ports.
     pub names: PortNames,
+    /// Optional channel layouts for the audio ports. These tell the host how the channels map to
+    /// speakers or ambisonic components. Mono and stereo ports don't need to set these.
+    pub channel_layouts: PortChannelLayouts,
 }
 
 /// Construct a `NonZeroU32` value at compile time. Equivalent to `NonZeroU32::new(n).unwrap()`.
@@ -40,15 +43,45 @@ pub const fn new_nonzero_u32(n: u32) -> NonZeroU32 {
     }
 }
 
-/// Contains auxiliary (sidechain) input and output buffers for a process call.
-pub struct AuxiliaryBuffers<'a> {
+/// Contains auxiliary (sidechain) input and output buffers for a process call. The sample type `S`
+/// matches that of the main [`Buffer`].
+pub struct AuxiliaryBuffers<'a, S = f32> {
+    /// The full main input if the main input and main output ports have a different number of
+    /// channels, like in a 4-in/2-out downmixer or in an analyzer without a main output. Since
+    /// NIH-plug processes audio in place, only the first main input channels that have a matching
+    /// output channel are copied to the main [`Buffer`]. This buffer contains all of the main
+    /// input's channels and it can safely be overwritten. This is `None` if the channel counts
+    /// match, since the main buffer then already contains the main input.
+    pub main_input: Option<&'a mut Buffer<'a, S>>,
     /// Buffers for all auxiliary (sidechain) inputs defined for this plugin. The data in these
     /// buffers can safely be overwritten. Auxiliary inputs can be defined using the
     /// [`AudioIOLayout::aux_input_ports`] field.
-    pub inputs: &'a mut [Buffer<'a>],
+    pub inputs: &'a mut [Buffer<'a, S>],
     /// Buffers for all auxiliary outputs defined for this plugin. Auxiliary outputs can be defined using the
     /// [`AudioIOLayout::aux_output_ports`] field.
-    pub outputs: &'a mut [Buffer<'a>],
+    pub outputs: &'a mut [Buffer<'a, S>],
+    /// Whether the host has activated each of the auxiliary inputs. This has the same length as
+    /// [`inputs`][Self::inputs]. An inactive input is usually a sidechain input that nothing has
+    /// been routed to, and its buffer will only contain silence. Hosts that don't support
+    /// activating and deactivating ports will always report all ports as active.
+    pub inputs_active: &'a [bool],
+    /// Whether the host has activated each of the auxiliary outputs. This has the same length as
+    /// [`outputs`][Self::outputs]. Inactive outputs will be discarded by the host.
+    pub outputs_active: &'a [bool],
+}
+
+impl<S> AuxiliaryBuffers<'_, S> {
+    /// Whether the auxiliary input with index `idx` is active. Returns `false` if the plugin does
+    /// not have that many auxiliary inputs.
+    pub fn is_input_active(&self, idx: usize) -> bool {
+        self.inputs_active.get(idx).copied().unwrap_or(false)
+    }
+
+    /// Whether the auxiliary output with index `idx` is active. Returns `false` if the plugin does
+    /// not have that many auxiliary outputs.
+    pub fn is_output_active(&self, idx: usize) -> bool {
+        self.outputs_active.get(idx).copied().unwrap_or(false)
+    }
 }
 
 /// Contains names for the ports defined in an `AudioIOLayout`. Setting these is optional, but it
@@ -74,6 +107,114 @@ pub struct PortNames {
     pub aux_outputs: &'static [&'static str],
 }
 
+/// Contains the channel layouts for the ports in an `AudioIOLayout`. Ports without a channel layout
+/// use [`ChannelLayout::Mono`] or [`ChannelLayout::Stereo`] if they have one or two channels, and
+/// they don't have a defined layout otherwise. See [`AudioIOLayout::main_input_layout()`] and the
+/// other getters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PortChannelLayouts {
+    /// The channel layout for the main input port.
+    pub main_input: Option<ChannelLayout>,
+    /// The channel layout for the main output port.
+    pub main_output: Option<ChannelLayout>,
+    /// Channel layouts for the auxiliary (sidechain) input ports. Ports past the end of this slice
+    /// use the default layout for their channel count.
+    pub aux_inputs: &'static [ChannelLayout],
+    /// Channel layouts for the auxiliary output ports. Ports past the end of this slice use the
+    /// default layout for their channel count.
+    pub aux_outputs: &'static [ChannelLayout],
+}
+
+/// Describes how an audio port's channels map to speakers or ambisonic components. The number of
+/// channels implied by the layout must match the port's channel count in the `AudioIOLayout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelLayout {
+    /// A single channel.
+    Mono,
+    /// Left and right channels.
+    Stereo,
+    /// Left, right, and center channels. This is also known as 3.0.
+    Lcr,
+    /// Front left, front right, back left, and back right channels.
+    Quad,
+    /// 5.0 surround with left, right, center, back left, and back right channels.
+    Surround5_0,
+    /// 5.1 surround. The same as [`Surround5_0`][Self::Surround5_0] with an additional LFE channel
+    /// after the center channel.
+    Surround5_1,
+    /// 7.1 surround. The same as [`Surround5_1`][Self::Surround5_1] with additional side left and
+    /// side right channels.
+    Surround7_1,
+    /// 7.1.4 surround. The same as [`Surround7_1`][Self::Surround7_1] with additional top front
+    /// left, top front right, top back left, and top back right channels.
+    Surround7_1_4,
+    /// A custom speaker arrangement for layouts not covered by the other variants, like hexagonal
+    /// layouts. The speakers must be listed in the same order as they are defined in the
+    /// [`Speaker`] enum, and each speaker can only be used once.
+    Speakers(&'static [Speaker]),
+    /// Ambisonic audio of a specific order. The port has `(order + 1)^2` channels.
+    Ambisonic(AmbisonicLayout),
+}
+
+/// A speaker position used in a [`ChannelLayout`]. The order of these variants matches the channel
+/// order used by CLAP, VST3, and WAVE files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Speaker {
+    FrontLeft,
+    FrontRight,
+    FrontCenter,
+    LowFrequency,
+    BackLeft,
+    BackRight,
+    FrontLeftCenter,
+    FrontRightCenter,
+    BackCenter,
+    SideLeft,
+    SideRight,
+    TopCenter,
+    TopFrontLeft,
+    TopFrontCenter,
+    TopFrontRight,
+    TopBackLeft,
+    TopBackCenter,
+    TopBackRight,
+}
+
+/// The configuration for an ambisonic port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AmbisonicLayout {
+    /// The ambisonic order. First order ambisonics uses four channels.
+    pub order: u32,
+    /// The order of the ambisonic components within the port's channels.
+    pub ordering: AmbisonicOrdering,
+    /// The normalization used for the ambisonic components.
+    pub normalization: AmbisonicNormalization,
+}
+
+/// The channel ordering used for ambisonic audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AmbisonicOrdering {
+    /// Ambisonic Channel Number ordering, as used by AmbiX.
+    Acn,
+    /// Furse-Malham ordering. Only defined up to third order.
+    Fuma,
+}
+
+/// The normalization used for ambisonic audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AmbisonicNormalization {
+    /// Maximum normalization, as used with Furse-Malham ordering.
+    MaxN,
+    /// Schmidt semi-normalized 3D, as used by AmbiX.
+    Sn3d,
+    /// Full 3D normalization.
+    N3d,
+    /// Schmidt semi-normalized 2D.
+    Sn2d,
+    /// Full 2D normalization.
+    N2d,
+}
+
 /// Configuration for (the host's) audio buffers.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BufferConfig {
@@ -115,6 +256,7 @@ impl AudioIOLayout {
             aux_input_ports: &[],
             aux_output_ports: &[],
             names: PortNames::const_default(),
+            channel_layouts: PortChannelLayouts::const_default(),
         }
     }
 
@@ -187,6 +329,170 @@ impl AudioIOLayout {
             }
         }
     }
+
+    /// The channel layout for the main input port. Either taken from the `channel_layouts` field or
+    /// based on the number of channels. Returns `None` if the layout does not have a main input or
+    /// if the input does not have a defined channel layout.
+    pub fn main_input_layout(&self) -> Option<ChannelLayout> {
+        port_channel_layout(self.main_input_channels?, self.channel_layouts.main_input)
+    }
+
+    /// The channel layout for the main output port. See
+    /// [`main_input_layout()`][Self::main_input_layout()].
+    pub fn main_output_layout(&self) -> Option<ChannelLayout> {
+        port_channel_layout(self.main_output_channels?, self.channel_layouts.main_output)
+    }
+
+    /// The channel layout for the auxiliary input port with the given index. See
+    /// [`main_input_layout()`][Self::main_input_layout()].
+    pub fn aux_input_layout(&self, idx: usize) -> Option<ChannelLayout> {
+        port_channel_layout(
+            *self.aux_input_ports.get(idx)?,
+            self.channel_layouts.aux_inputs.get(idx).copied(),
+        )
+    }
+
+    /// The channel layout for the auxiliary output port with the given index. See
+    /// [`main_input_layout()`][Self::main_input_layout()].
+    pub fn aux_output_layout(&self, idx: usize) -> Option<ChannelLayout> {
+        port_channel_layout(
+            *self.aux_output_ports.get(idx)?,
+            self.channel_layouts.aux_outputs.get(idx).copied(),
+        )
+    }
+}
+
+/// The channel layout for a port with `num_channels` channels and an optional explicit layout.
+fn port_channel_layout(
+    num_channels: NonZeroU32,
+    layout: Option<ChannelLayout>,
+) -> Option<ChannelLayout> {
+    match layout {
+        Some(layout) => {
+            nih_debug_assert_eq!(
+                layout.num_channels(),
+                num_channels.get(),
+                "The channel layout does not match the port's channel count"
+            );
+            Some(layout)
+        }
+        None => ChannelLayout::default_for_channels(num_channels.get()),
+    }
+}
+
+impl PortChannelLayouts {
+    /// [`PortChannelLayouts::default()`], but as a const function. Used when initializing
+    /// `Plugin::AUDIO_IO_LAYOUTS`. (<https://github.com/rust-lang/rust/issues/67792>)
+    pub const fn const_default() -> Self {
+        Self {
+            main_input: None,
+            main_output: None,
+            aux_inputs: &[],
+            aux_outputs: &[],
+        }
+    }
+}
+
+/// The predefined speaker layouts. Used to convert speaker masks from the host back to a
+/// [`ChannelLayout`].
+const SPEAKER_LAYOUT_PRESETS: [ChannelLayout; 8] = [
+    ChannelLayout::Mono,
+    ChannelLayout::Stereo,
+    ChannelLayout::Lcr,
+    ChannelLayout::Quad,
+    ChannelLayout::Surround5_0,
+    ChannelLayout::Surround5_1,
+    ChannelLayout::Surround7_1,
+    ChannelLayout::Surround7_1_4,
+];
+
+impl ChannelLayout {
+    /// Find the predefined speaker layout that uses exactly the speakers in `mask`. See
+    /// [`speaker_mask()`][Self::speaker_mask()]. Custom [`Speakers`][Self::Speakers] layouts can't
+    /// be reconstructed this way, so this returns `None` for any other mask.
+    pub(crate) fn from_speaker_mask(mask: u64) -> Option<Self> {
+        SPEAKER_LAYOUT_PRESETS
+            .into_iter()
+            .find(|layout| layout.speaker_mask() == Some(mask))
+    }
+
+    /// The channel layout used for ports with `num_channels` channels that don't explicitly
+    /// specify a layout, if there is one.
+    pub fn default_for_channels(num_channels: u32) -> Option<Self> {
+        match num_channels {
+            1 => Some(Self::Mono),
+            2 => Some(Self::Stereo),
+            _ => None,
+        }
+    }
+
+    /// The number of channels in this layout.
+    pub fn num_channels(&self) -> u32 {
+        match self {
+            ChannelLayout::Ambisonic(layout) => (layout.order + 1).pow(2),
+            _ => self.speakers().map_or(0, |speakers| speakers.len() as u32),
+        }
+    }
+
+    /// The speakers for each of this layout's channels, in order. Returns `None` for ambisonic
+    /// layouts.
+    pub fn speakers(&self) -> Option<&'static [Speaker]> {
+        use Speaker::*;
+
+        match self {
+            ChannelLayout::Mono => Some(&[FrontCenter]),
+            ChannelLayout::Stereo => Some(&[FrontLeft, FrontRight]),
+            ChannelLayout::Lcr => Some(&[FrontLeft, FrontRight, FrontCenter]),
+            ChannelLayout::Quad => Some(&[FrontLeft, FrontRight, BackLeft, BackRight]),
+            ChannelLayout::Surround5_0 => {
+                Some(&[FrontLeft, FrontRight, FrontCenter, BackLeft, BackRight])
+            }
+            ChannelLayout::Surround5_1 => Some(&[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackLeft,
+                BackRight,
+            ]),
+            ChannelLayout::Surround7_1 => Some(&[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackLeft,
+                BackRight,
+                SideLeft,
+                SideRight,
+            ]),
+            ChannelLayout::Surround7_1_4 => Some(&[
+                FrontLeft,
+                FrontRight,
+                FrontCenter,
+                LowFrequency,
+                BackLeft,
+                BackRight,
+                SideLeft,
+                SideRight,
+                TopFrontLeft,
+                TopFrontRight,
+                TopBackLeft,
+                TopBackRight,
+            ]),
+            ChannelLayout::Speakers(speakers) => Some(speakers),
+            ChannelLayout::Ambisonic(_) => None,
+        }
+    }
+
+    /// A bit mask containing a bit for every speaker in this layout, where the bit index is the
+    /// speaker's position in the [`Speaker`] enum. Returns `None` for ambisonic layouts.
+    pub fn speaker_mask(&self) -> Option<u64> {
+        self.speakers().map(|speakers| {
+            speakers
+                .iter()
+                .fold(0, |mask, speaker| mask | (1 << *speaker as u64))
+        })
+    }
 }
 
 impl PortNames {
@@ -202,3 +508,75 @@ impl PortNames {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_layout_num_channels() {
+        assert_eq!(ChannelLayout::Mono.num_channels(), 1);
+        assert_eq!(ChannelLayout::Stereo.num_channels(), 2);
+        assert_eq!(ChannelLayout::Lcr.num_channels(), 3);
+        assert_eq!(ChannelLayout::Surround5_1.num_channels(), 6);
+        assert_eq!(ChannelLayout::Surround7_1_4.num_channels(), 12);
+        assert_eq!(
+            ChannelLayout::Ambisonic(AmbisonicLayout {
+                order: 3,
+                ordering: AmbisonicOrdering::Acn,
+                normalization: AmbisonicNormalization::Sn3d,
+            })
+            .num_channels(),
+            16
+        );
+    }
+
+    #[test]
+    fn channel_layout_speakers_are_sorted() {
+        for layout in SPEAKER_LAYOUT_PRESETS {
+            let speakers = layout.speakers().unwrap();
+            assert!(
+                speakers.windows(2).all(|pair| pair[0] < pair[1]),
+                "{layout:?}"
+            );
+            assert_eq!(
+                layout.speaker_mask().unwrap().count_ones(),
+                layout.num_channels()
+            );
+        }
+    }
+
+    #[test]
+    fn channel_layout_from_speaker_mask() {
+        for layout in SPEAKER_LAYOUT_PRESETS {
+            assert_eq!(
+                ChannelLayout::from_speaker_mask(layout.speaker_mask().unwrap()),
+                Some(layout)
+            );
+        }
+
+        // Front left, front right, and LFE isn't one of the presets
+        assert_eq!(ChannelLayout::from_speaker_mask(0b1011), None);
+    }
+
+    #[test]
+    fn port_layouts() {
+        const LAYOUT: AudioIOLayout = AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(6)),
+            main_output_channels: Some(new_nonzero_u32(2)),
+            aux_input_ports: &[new_nonzero_u32(1), new_nonzero_u32(4)],
+            channel_layouts: PortChannelLayouts {
+                main_input: Some(ChannelLayout::Surround5_1),
+                ..PortChannelLayouts::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        };
+
+        assert_eq!(LAYOUT.main_input_layout(), Some(ChannelLayout::Surround5_1));
+        assert_eq!(LAYOUT.main_output_layout(), Some(ChannelLayout::Stereo));
+        assert_eq!(LAYOUT.aux_input_layout(0), Some(ChannelLayout::Mono));
+        assert_eq!(LAYOUT.aux_input_layout(1), None);
+        assert_eq!(LAYOUT.aux_input_layout(2), None);
+        assert_eq!(LAYOUT.aux_output_layout(0), None);
+    }
+}
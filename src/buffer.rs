@@ -1,22 +1,89 @@
 //! Adapters and utilities for working with audio buffers.
 
+use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use crate::prelude::Plugin;
 
 mod blocks;
+mod event_blocks;
 mod samples;
 
 pub use blocks::{Block, BlockChannelsIter, BlocksIter};
+pub use event_blocks::{EventBlockItem, EventBlocksIter};
 pub use samples::{ChannelSamples, ChannelSamplesIter, SamplesIter};
 
+/// A floating point sample type that can be used in a [`Buffer`]. This is implemented for `f32`
+/// and `f64`. Buffers contain `f32` samples unless the plugin opts into double precision processing
+/// using [`Plugin::SUPPORTS_F64`][crate::prelude::Plugin::SUPPORTS_F64]. This trait is sealed and
+/// cannot be implemented for other types.
+pub trait Sample:
+    Copy
+    + Default
+    + PartialEq
+    + PartialOrd
+    + Debug
+    + Send
+    + Sync
+    + 'static
+    + From<f32>
+    + Into<f64>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + sealed::Sealed
+{
+    /// A sample with a value of zero.
+    const ZERO: Self;
+
+    /// Convert a double precision value to this sample type. This may lose precision.
+    fn from_f64(value: f64) -> Self;
+}
+
+impl Sample for f32 {
+    const ZERO: Self = 0.0;
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+}
+
+impl Sample for f64 {
+    const ZERO: Self = 0.0;
+
+    #[inline]
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
 /// The audio buffers used during processing. This contains the output audio output buffers with the
 /// inputs already copied to the outputs. You can either use the iterator adapters to conveniently
 /// and efficiently iterate over the samples, or you can do your own thing using the raw audio
 /// buffers.
 ///
+/// The sample type `S` is `f32` unless the plugin opts into double precision processing, in which
+/// case [`Plugin::process_f64()`][crate::prelude::Plugin::process_f64()] receives a `Buffer<f64>`.
+///
 /// TODO: This lifetime makes zero sense because you're going to need unsafe lifetime casts to use
 ///       this either way. Maybe just get rid of it in favor for raw pointers.
 #[derive(Default)]
-pub struct Buffer<'a> {
+pub struct Buffer<'a, S = f32> {
     /// The number of samples contained within `output_slices`. This needs to be stored separately
     /// to be able to handle 0 channel IO for MIDI-only plugins.
     num_samples: usize,
@@ -28,10 +95,24 @@ pub struct Buffer<'a> {
     /// because this `Buffers` either cannot have the same lifetime as the separately stored output
     /// buffers, and it also cannot be stored in a field next to it because that would mean
     /// containing mutable references to data stored in a mutex.
-    output_slices: Vec<&'a mut [f32]>,
+    output_slices: Vec<&'a mut [S]>,
+
+    /// Bit flags for the channels that contained the same value for every sample when this buffer
+    /// was passed to the plugin. Channel `n` corresponds to bit `n`, so only the first 64 channels
+    /// can be flagged. Set by the wrapper.
+    input_constant_mask: u64,
+    /// The subset of `input_constant_mask` whose constant value was zero.
+    input_silent_mask: u64,
+    /// Bit flags for the channels the plugin has marked as constant after processing. These are
+    /// passed on to the host. Reset whenever the wrapper sets new input flags.
+    output_constant_mask: u64,
 }
 
-impl<'a> Buffer<'a> {
+/// The maximum number of channels that can be flagged as constant or silent. The host APIs store
+/// these flags in 64-bit integers.
+const MAX_FLAGGED_CHANNELS: usize = 64;
+
+impl<'a, S: Sample> Buffer<'a, S> {
     /// Returns the number of samples per channel in this buffer.
     #[inline]
     pub fn samples(&self) -> usize {
@@ -53,20 +134,20 @@ impl<'a> Buffer<'a> {
 
     /// Obtain the raw audio buffers.
     #[inline]
-    pub fn as_slice(&mut self) -> &mut [&'a mut [f32]] {
+    pub fn as_slice(&mut self) -> &mut [&'a mut [S]] {
         &mut self.output_slices
     }
 
     /// The same as [`as_slice()`][Self::as_slice()], but for a non-mutable reference. This is
     /// usually not needed.
     #[inline]
-    pub fn as_slice_immutable(&self) -> &[&'a mut [f32]] {
+    pub fn as_slice_immutable(&self) -> &[&'a mut [S]] {
         &self.output_slices
     }
 
     /// Iterate over the samples, returning a channel iterator for each sample.
     #[inline]
-    pub fn iter_samples<'slice>(&'slice mut self) -> SamplesIter<'slice, 'a> {
+    pub fn iter_samples<'slice>(&'slice mut self) -> SamplesIter<'slice, 'a, S> {
         SamplesIter {
             buffers: self.output_slices.as_mut_slice(),
             current_sample: 0,
@@ -100,7 +181,10 @@ impl<'a> Buffer<'a> {
     /// }
     /// ````
     #[inline]
-    pub fn iter_blocks<'slice>(&'slice mut self, max_block_size: usize) -> BlocksIter<'slice, 'a> {
+    pub fn iter_blocks<'slice>(
+        &'slice mut self,
+        max_block_size: usize,
+    ) -> BlocksIter<'slice, 'a, S> {
         BlocksIter {
             buffers: self.output_slices.as_mut_slice(),
             max_block_size,
@@ -109,6 +193,140 @@ impl<'a> Buffer<'a> {
         }
     }
 
+    /// Iterate over the buffer in blocks of at most `max_block_size` samples, just like
+    /// [`iter_blocks()`][Self::iter_blocks()], but with additional splits at the timings of the
+    /// plugin's incoming note events. Every event is yielded right before the block that starts at
+    /// its timing, so voices can be started and stopped and sample-accurate parameter changes can
+    /// be applied without having to interleave [`ProcessContext::next_event()`] calls with the
+    /// block splitting logic by hand. This takes ownership of the plugin's event stream, so the
+    /// plugin should not call `next_event()` itself while using this iterator. `max_block_size`
+    /// must be at least 1.
+    ///
+    /// Because the plugin still needs the context while processing, this is not a regular
+    /// [`Iterator`] and the context needs to be passed to every
+    /// [`next()`][EventBlocksIter::next()] call instead:
+    ///
+    /// ```ignore
+    /// let mut blocks = buffer.iter_event_blocks(64);
+    /// while let Some(item) = blocks.next(context) {
+    ///     match item {
+    ///         EventBlockItem::Event(event) => self.handle_event(context, event),
+    ///         EventBlockItem::Block(block_start, mut block) => {
+    ///             // Process `block.samples()` samples starting at `block_start`
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`ProcessContext::next_event()`]: crate::prelude::ProcessContext::next_event()
+    #[inline]
+    pub fn iter_event_blocks<'slice, P: Plugin>(
+        &'slice mut self,
+        max_block_size: usize,
+    ) -> EventBlocksIter<'slice, 'a, P, S> {
+        // A block size of 0 would cause the iterator to yield empty blocks forever
+        nih_debug_assert_ne!(max_block_size, 0, "The maximum block size cannot be 0");
+
+        EventBlocksIter {
+            buffers: self.output_slices.as_mut_slice(),
+            num_samples: self.num_samples,
+            max_block_size: max_block_size.max(1),
+            current_block_start: 0,
+            next_event: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Whether all samples in `channel` had the same value when this buffer was passed to the
+    /// plugin. This is reported by the host, and it's also set for channels NIH-plug filled with
+    /// zeroes itself, like main output channels without a matching input channel. Effects and
+    /// analyzers can use this to skip work. Writing to the buffer does not change these flags, and
+    /// only the first 64 channels can be flagged.
+    #[inline]
+    pub fn is_input_constant(&self, channel: usize) -> bool {
+        channel < MAX_FLAGGED_CHANNELS && self.input_constant_mask & (1 << channel) != 0
+    }
+
+    /// Whether `channel` contained only silence when this buffer was passed to the plugin. This is
+    /// the same as [`is_input_constant()`][Self::is_input_constant()], but it also requires the
+    /// constant value to be zero.
+    #[inline]
+    pub fn is_input_silent(&self, channel: usize) -> bool {
+        channel < MAX_FLAGGED_CHANNELS && self.input_silent_mask & (1 << channel) != 0
+    }
+
+    /// The [`is_input_constant()`][Self::is_input_constant()] flags for all channels as a bit
+    /// mask, where bit `n` corresponds to channel `n`.
+    #[inline]
+    pub fn input_constant_mask(&self) -> u64 {
+        self.input_constant_mask
+    }
+
+    /// The [`is_input_silent()`][Self::is_input_silent()] flags for all channels as a bit mask,
+    /// where bit `n` corresponds to channel `n`.
+    #[inline]
+    pub fn input_silent_mask(&self) -> u64 {
+        self.input_silent_mask
+    }
+
+    /// Mark an output channel as containing the same value for every sample, or clear that flag
+    /// again. The host can use this to skip work in the plugins after this one. Setting this flag
+    /// for a channel that's not actually constant will cause the host to misinterpret the audio,
+    /// so only set it after writing the constant value to the channel. All output flags are
+    /// cleared at the start of every process call. Channels past the 64th channel are ignored.
+    #[inline]
+    pub fn set_output_constant(&mut self, channel: usize, constant: bool) {
+        if channel >= MAX_FLAGGED_CHANNELS || channel >= self.output_slices.len() {
+            return;
+        }
+
+        if constant {
+            self.output_constant_mask |= 1 << channel;
+        } else {
+            self.output_constant_mask &= !(1 << channel);
+        }
+    }
+
+    /// Fill an output channel with zeroes and mark it as
+    /// [constant][Self::set_output_constant()].
+    pub fn set_output_silent(&mut self, channel: usize) {
+        if let Some(slice) = self.output_slices.get_mut(channel) {
+            slice.fill(S::ZERO);
+            self.set_output_constant(channel, true);
+        }
+    }
+
+    /// The channels the plugin has marked as constant using
+    /// [`set_output_constant()`][Self::set_output_constant()], as a bit mask where bit `n`
+    /// corresponds to channel `n`.
+    #[inline]
+    pub fn output_constant_mask(&self) -> u64 {
+        self.output_constant_mask
+    }
+
+    /// Set the input constant flags for this buffer and clear the output flags. This should be
+    /// called after the buffer's slices have been set and filled with the input data, as the
+    /// silence flags are derived from the buffer's contents. Bits for channels that don't exist are
+    /// ignored.
+    pub(crate) fn set_input_constant_mask(&mut self, mask: u64) {
+        self.input_constant_mask = mask & channel_mask(self.output_slices.len());
+        self.input_silent_mask = 0;
+        for (channel_idx, slice) in self
+            .output_slices
+            .iter()
+            .enumerate()
+            .take(MAX_FLAGGED_CHANNELS)
+        {
+            if self.input_constant_mask & (1 << channel_idx) != 0
+                && slice.first().map_or(true, |sample| *sample == S::ZERO)
+            {
+                self.input_silent_mask |= 1 << channel_idx;
+            }
+        }
+
+        self.output_constant_mask = 0;
+    }
+
     /// Set the slices in the raw output slice vector. This vector needs to be resized to match the
     /// number of output channels during the plugin's initialization. Then during audio processing,
     /// these slices should be updated to point to the plugin's audio buffers. The `num_samples`
@@ -122,7 +340,7 @@ impl<'a> Buffer<'a> {
     pub unsafe fn set_slices(
         &mut self,
         num_samples: usize,
-        update: impl FnOnce(&mut Vec<&'a mut [f32]>),
+        update: impl FnOnce(&mut Vec<&'a mut [S]>),
     ) {
         self.num_samples = num_samples;
         update(&mut self.output_slices);
@@ -134,6 +352,16 @@ impl<'a> Buffer<'a> {
     }
 }
 
+/// A bit mask with the bits for the first `num_channels` channels set. Used for the constant and
+/// silence flags.
+pub(crate) fn channel_mask(num_channels: usize) -> u64 {
+    if num_channels >= MAX_FLAGGED_CHANNELS {
+        u64::MAX
+    } else {
+        (1 << num_channels) - 1
+    }
+}
+
 #[cfg(any(miri, test))]
 mod miri {
     use super::*;
@@ -199,4 +427,41 @@ mod miri {
             assert_eq!(real_buffers[0][i], 0.0);
         }
     }
+
+    #[test]
+    fn constant_flags() {
+        let mut real_buffers = [vec![0.0; 16], vec![0.5; 16], vec![0.25; 16]];
+        real_buffers[2][8] = 1.0;
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(16, |output_slices| {
+                *output_slices = real_buffers.iter_mut().map(|c| c.as_mut_slice()).collect();
+            })
+        };
+
+        // The bit for the nonexistent fourth channel is ignored
+        buffer.set_input_constant_mask(0b1011);
+        assert!(buffer.is_input_constant(0));
+        assert!(buffer.is_input_silent(0));
+        assert!(buffer.is_input_constant(1));
+        assert!(!buffer.is_input_silent(1));
+        assert!(!buffer.is_input_constant(2));
+        assert!(!buffer.is_input_constant(3));
+        assert!(!buffer.is_input_constant(100));
+        assert_eq!(buffer.input_constant_mask(), 0b011);
+        assert_eq!(buffer.input_silent_mask(), 0b001);
+
+        assert_eq!(buffer.output_constant_mask(), 0);
+        buffer.set_output_silent(2);
+        buffer.set_output_constant(1, true);
+        buffer.set_output_constant(3, true);
+        assert_eq!(buffer.output_constant_mask(), 0b110);
+        buffer.set_output_constant(1, false);
+        assert_eq!(buffer.output_constant_mask(), 0b100);
+
+        // The wrapper resets the output flags for every process call
+        buffer.set_input_constant_mask(0);
+        assert_eq!(buffer.output_constant_mask(), 0);
+        assert!(real_buffers[2].iter().all(|sample| *sample == 0.0));
+    }
 }
@@ -3,45 +3,47 @@
 use std::marker::PhantomData;
 
 #[cfg(feature = "simd")]
-use std::simd::{LaneCount, Simd, SupportedLaneCount};
+use std::simd::{LaneCount, Simd, SimdElement, SupportedLaneCount};
 
+#[cfg(feature = "simd")]
+use super::Sample;
 use super::SamplesIter;
 
 /// An iterator over all samples in the buffer, slicing over the sample-dimension with a maximum
 /// size of `max_block_size`. See [`Buffer::iter_blocks()`][super::Buffer::iter_blocks()]. Yields
 /// both the block and the offset from the start of the buffer.
-pub struct BlocksIter<'slice, 'sample: 'slice> {
+pub struct BlocksIter<'slice, 'sample: 'slice, S = f32> {
     /// The raw output buffers.
-    pub(super) buffers: *mut [&'sample mut [f32]],
+    pub(super) buffers: *mut [&'sample mut [S]],
     pub(super) max_block_size: usize,
     pub(super) current_block_start: usize,
-    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [S]]>,
 }
 
 /// A block yielded by [`BlocksIter`]. Can be iterated over once or multiple times, and also
 /// supports direct access to the block's samples if needed.
-pub struct Block<'slice, 'sample: 'slice> {
+pub struct Block<'slice, 'sample: 'slice, S = f32> {
     /// The raw output buffers.
-    pub(self) buffers: *mut [&'sample mut [f32]],
-    pub(self) current_block_start: usize,
+    pub(super) buffers: *mut [&'sample mut [S]],
+    pub(super) current_block_start: usize,
     /// The index of the last sample in the block plus one.
-    pub(self) current_block_end: usize,
-    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(super) current_block_end: usize,
+    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [S]]>,
 }
 
 /// An iterator over all channels in a block yielded by [`Block`], returning an entire channel slice
 /// at a time.
-pub struct BlockChannelsIter<'slice, 'sample: 'slice> {
+pub struct BlockChannelsIter<'slice, 'sample: 'slice, S = f32> {
     /// The raw output buffers.
-    pub(self) buffers: *mut [&'sample mut [f32]],
+    pub(self) buffers: *mut [&'sample mut [S]],
     pub(self) current_block_start: usize,
     pub(self) current_block_end: usize,
     pub(self) current_channel: usize,
-    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [S]]>,
 }
 
-impl<'slice, 'sample> Iterator for BlocksIter<'slice, 'sample> {
-    type Item = (usize, Block<'slice, 'sample>);
+impl<'slice, 'sample, S> Iterator for BlocksIter<'slice, 'sample, S> {
+    type Item = (usize, Block<'slice, 'sample, S>);
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -74,9 +76,9 @@ impl<'slice, 'sample> Iterator for BlocksIter<'slice, 'sample> {
     }
 }
 
-impl<'slice, 'sample> IntoIterator for Block<'slice, 'sample> {
-    type Item = &'sample mut [f32];
-    type IntoIter = BlockChannelsIter<'slice, 'sample>;
+impl<'slice, 'sample, S> IntoIterator for Block<'slice, 'sample, S> {
+    type Item = &'sample mut [S];
+    type IntoIter = BlockChannelsIter<'slice, 'sample, S>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -90,8 +92,8 @@ impl<'slice, 'sample> IntoIterator for Block<'slice, 'sample> {
     }
 }
 
-impl<'slice, 'sample> Iterator for BlockChannelsIter<'slice, 'sample> {
-    type Item = &'sample mut [f32];
+impl<'slice, 'sample, S> Iterator for BlockChannelsIter<'slice, 'sample, S> {
+    type Item = &'sample mut [S];
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -121,10 +123,10 @@ impl<'slice, 'sample> Iterator for BlockChannelsIter<'slice, 'sample> {
     }
 }
 
-impl ExactSizeIterator for BlocksIter<'_, '_> {}
-impl ExactSizeIterator for BlockChannelsIter<'_, '_> {}
+impl<S> ExactSizeIterator for BlocksIter<'_, '_, S> {}
+impl<S> ExactSizeIterator for BlockChannelsIter<'_, '_, S> {}
 
-impl<'slice, 'sample> Block<'slice, 'sample> {
+impl<'slice, 'sample, S> Block<'slice, 'sample, S> {
     /// Get the number of samples per channel in the block.
     #[inline]
     pub fn samples(&self) -> usize {
@@ -141,7 +143,7 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     /// you don't need to use this function as [`Block`] already implements [`Iterator`]. You can
     /// also use the direct accessor functions on this block instead.
     #[inline]
-    pub fn iter_mut(&mut self) -> BlockChannelsIter<'slice, 'sample> {
+    pub fn iter_mut(&mut self) -> BlockChannelsIter<'slice, 'sample, S> {
         BlockChannelsIter {
             buffers: self.buffers,
             current_block_start: self.current_block_start,
@@ -155,7 +157,7 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     /// [`Buffer::iter_samples()`][super::Buffer::iter_samples()] but for a smaller block instead of
     /// the entire buffer
     #[inline]
-    pub fn iter_samples(&mut self) -> SamplesIter<'slice, 'sample> {
+    pub fn iter_samples(&mut self) -> SamplesIter<'slice, 'sample, S> {
         SamplesIter {
             buffers: self.buffers,
             current_sample: self.current_block_start,
@@ -167,7 +169,7 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     /// Access a channel by index. Useful when you would otherwise iterate over this [`Block`]
     /// multiple times.
     #[inline]
-    pub fn get(&self, channel_index: usize) -> Option<&[f32]> {
+    pub fn get(&self, channel_index: usize) -> Option<&[S]> {
         // SAFETY: The block bound has already been checked
         unsafe {
             Some(
@@ -184,7 +186,7 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     ///
     /// `channel_index` must be in the range `0..Self::len()`.
     #[inline]
-    pub unsafe fn get_unchecked(&self, channel_index: usize) -> &[f32] {
+    pub unsafe fn get_unchecked(&self, channel_index: usize) -> &[S] {
         (&(*self.buffers))
             .get_unchecked(channel_index)
             .get_unchecked(self.current_block_start..self.current_block_end)
@@ -193,7 +195,7 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     /// Access a mutable channel by index. Useful when you would otherwise iterate over this
     /// [`Block`] multiple times.
     #[inline]
-    pub fn get_mut(&mut self, channel_index: usize) -> Option<&mut [f32]> {
+    pub fn get_mut(&mut self, channel_index: usize) -> Option<&mut [S]> {
         // SAFETY: The block bound has already been checked
         unsafe {
             Some(
@@ -210,23 +212,22 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     ///
     /// `channel_index` must be in the range `0..Self::len()`.
     #[inline]
-    pub unsafe fn get_unchecked_mut(&mut self, channel_index: usize) -> &mut [f32] {
+    pub unsafe fn get_unchecked_mut(&mut self, channel_index: usize) -> &mut [S] {
         (&mut (*self.buffers))
             .get_unchecked_mut(channel_index)
             .get_unchecked_mut(self.current_block_start..self.current_block_end)
     }
+}
 
+#[cfg(feature = "simd")]
+impl<S: Sample + SimdElement> Block<'_, '_, S> {
     /// Get a SIMD vector containing the channel data for a specific sample in this block. If `LANES
     /// > channels.len()` then this will be padded with zeroes. If `LANES < channels.len()` then
     /// this won't contain all values.
     ///
     /// Returns a `None` value if `sample_index` is out of bounds.
-    #[cfg(feature = "simd")]
     #[inline]
-    pub fn to_channel_simd<const LANES: usize>(
-        &self,
-        sample_index: usize,
-    ) -> Option<Simd<f32, LANES>>
+    pub fn to_channel_simd<const LANES: usize>(&self, sample_index: usize) -> Option<Simd<S, LANES>>
     where
         LaneCount<LANES>: SupportedLaneCount,
     {
@@ -235,7 +236,7 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
         }
 
         let used_lanes = self.samples().max(LANES);
-        let mut values = [0.0; LANES];
+        let mut values = [S::ZERO; LANES];
         for (channel_idx, value) in values.iter_mut().enumerate().take(used_lanes) {
             *value = unsafe {
                 *(&(*self.buffers))
@@ -253,16 +254,15 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     /// # Safety
     ///
     /// Undefined behavior if `LANES > block.len()` or if `sample_index > block.len()`.
-    #[cfg(feature = "simd")]
     #[inline]
     pub unsafe fn to_channel_simd_unchecked<const LANES: usize>(
         &self,
         sample_index: usize,
-    ) -> Simd<f32, LANES>
+    ) -> Simd<S, LANES>
     where
         LaneCount<LANES>: SupportedLaneCount,
     {
-        let mut values = [0.0; LANES];
+        let mut values = [S::ZERO; LANES];
         for (channel_idx, value) in values.iter_mut().enumerate() {
             *value = *(&(*self.buffers))
                 .get_unchecked(channel_idx)
@@ -277,13 +277,12 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     /// account.
     ///
     /// Returns `false` if `sample_index` is out of bounds.
-    #[cfg(feature = "simd")]
     #[allow(clippy::wrong_self_convention)]
     #[inline]
     pub fn from_channel_simd<const LANES: usize>(
         &mut self,
         sample_index: usize,
-        vector: Simd<f32, LANES>,
+        vector: Simd<S, LANES>,
     ) -> bool
     where
         LaneCount<LANES>: SupportedLaneCount,
@@ -312,13 +311,12 @@ impl<'slice, 'sample> Block<'slice, 'sample> {
     /// # Safety
     ///
     /// Undefined behavior if `LANES > block.len()` or if `sample_index > block.len()`.
-    #[cfg(feature = "simd")]
     #[allow(clippy::wrong_self_convention)]
     #[inline]
     pub unsafe fn from_channel_simd_unchecked<const LANES: usize>(
         &mut self,
         sample_index: usize,
-        vector: Simd<f32, LANES>,
+        vector: Simd<S, LANES>,
     ) where
         LaneCount<LANES>: SupportedLaneCount,
     {
@@ -0,0 +1,302 @@
+//! Block iterators that split a buffer at note event timings.
+
+use std::marker::PhantomData;
+
+use super::Block;
+use crate::prelude::{Plugin, PluginNoteEvent, ProcessContext};
+
+/// Splits a buffer into blocks of at most `max_block_size` samples, with additional splits at the
+/// timings of the plugin's incoming note events. See
+/// [`Buffer::iter_event_blocks()`][super::Buffer::iter_event_blocks()].
+///
+/// This is not a regular [`Iterator`] because the plugin still needs to use its
+/// [`ProcessContext`] while handling events and blocks. Instead, the context is passed to
+/// [`next()`][Self::next()] every time the next item is requested.
+pub struct EventBlocksIter<'slice, 'sample: 'slice, P: Plugin, S = f32> {
+    /// The raw output buffers.
+    pub(super) buffers: *mut [&'sample mut [S]],
+    /// The buffer's length in samples. This is stored separately so MIDI-only plugins without any
+    /// audio channels still get their events.
+    pub(super) num_samples: usize,
+    pub(super) max_block_size: usize,
+    pub(super) current_block_start: usize,
+    /// An event that was already taken from the context but that happens after the current block
+    /// start. This will be yielded before the block starting at its timing.
+    pub(super) next_event: Option<PluginNoteEvent<P>>,
+    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [S]]>,
+}
+
+/// An item yielded by [`EventBlocksIter::next()`]. Events are always yielded before the block they
+/// belong to, so when a block is yielded all events up to and including that block's first sample
+/// have already been handled.
+pub enum EventBlockItem<'slice, 'sample: 'slice, P: Plugin, S = f32> {
+    /// A note event that should be handled before processing the next block. The event's timing
+    /// will always be the next block's start offset, or an earlier timing if the host sent events
+    /// out of order.
+    Event(PluginNoteEvent<P>),
+    /// A block of audio along with its offset from the start of the buffer. The block ends either
+    /// at the next note event or after `max_block_size` samples, whichever comes first.
+    Block(usize, Block<'slice, 'sample, S>),
+}
+
+impl<'slice, 'sample, P: Plugin, S> EventBlocksIter<'slice, 'sample, P, S> {
+    /// Get the next event or block. Returns `None` once the entire buffer has been yielded.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(
+        &mut self,
+        context: &mut impl ProcessContext<P>,
+    ) -> Option<EventBlockItem<'slice, 'sample, P, S>> {
+        if self.current_block_start >= self.num_samples {
+            // The wrappers never send events past the end of the buffer, but if an event somehow
+            // ended up here then it's better to still let the plugin handle it
+            return self.next_event.take().map(EventBlockItem::Event);
+        }
+
+        let current_block_start = self.current_block_start;
+        let mut current_block_end =
+            (current_block_start + self.max_block_size).min(self.num_samples);
+        match self.next_event.take().or_else(|| context.next_event()) {
+            Some(event) if event.timing() as usize <= current_block_start => {
+                return Some(EventBlockItem::Event(event));
+            }
+            Some(event) => {
+                current_block_end = current_block_end.min(event.timing() as usize);
+                self.next_event = Some(event);
+            }
+            None => (),
+        }
+
+        self.current_block_start = current_block_end;
+
+        Some(EventBlockItem::Block(
+            current_block_start,
+            Block {
+                buffers: self.buffers,
+                current_block_start,
+                current_block_end,
+                _marker: self._marker,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::prelude::{
+        AudioIOLayout, AuxiliaryBuffers, Buffer, NoteEvent, ParamPtr, Params, PluginApi,
+        ProcessStatus, Transport,
+    };
+
+    #[derive(Default)]
+    struct TestPlugin;
+
+    struct TestParams;
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            Vec::new()
+        }
+    }
+
+    impl Plugin for TestPlugin {
+        const NAME: &'static str = "Test";
+        const VENDOR: &'static str = "Test";
+        const URL: &'static str = "";
+        const EMAIL: &'static str = "";
+        const VERSION: &'static str = "0.0.0";
+        const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[];
+
+        type SysExMessage = ();
+        type BackgroundTask = ();
+
+        fn params(&self) -> Arc<dyn Params> {
+            Arc::new(TestParams)
+        }
+
+        fn process(
+            &mut self,
+            _buffer: &mut Buffer,
+            _aux: &mut AuxiliaryBuffers,
+            _context: &mut impl ProcessContext<Self>,
+        ) -> ProcessStatus {
+            ProcessStatus::Normal
+        }
+    }
+
+    /// A process context that only yields the events it was created with.
+    struct TestContext {
+        events: VecDeque<PluginNoteEvent<TestPlugin>>,
+        transport: Transport,
+    }
+
+    impl ProcessContext<TestPlugin> for TestContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Clap
+        }
+
+        fn execute_background(&self, _task: ()) {}
+
+        fn execute_gui(&self, _task: ()) {}
+
+        fn transport(&self) -> &Transport {
+            &self.transport
+        }
+
+        fn next_event(&mut self) -> Option<PluginNoteEvent<TestPlugin>> {
+            self.events.pop_front()
+        }
+
+        fn send_event(&mut self, _event: PluginNoteEvent<TestPlugin>) {}
+
+        fn set_latency_samples(&self, _samples: u32) {}
+
+        fn set_current_voice_capacity(&self, _capacity: u32) {}
+    }
+
+    /// A simplified version of [`EventBlockItem`] that can be compared in assertions.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Item {
+        /// An event with the given timing.
+        Event(u32),
+        /// A block with the given start offset and length.
+        Block(usize, usize),
+    }
+
+    fn note_on(timing: u32) -> PluginNoteEvent<TestPlugin> {
+        NoteEvent::NoteOn {
+            timing,
+            voice_id: None,
+            channel: 0,
+            note: 60,
+            velocity: 1.0,
+        }
+    }
+
+    /// Split a buffer with the given number of channels and samples at the events' timings, and
+    /// return the yielded items.
+    fn collect_items(
+        num_channels: usize,
+        num_samples: usize,
+        max_block_size: usize,
+        events: Vec<PluginNoteEvent<TestPlugin>>,
+    ) -> Vec<Item> {
+        let mut real_buffers = vec![vec![0.0f32; num_samples]; num_channels];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(num_samples, |output_slices| {
+                *output_slices = real_buffers
+                    .iter_mut()
+                    .map(|channel| &mut *(channel.as_mut_slice() as *mut [f32]))
+                    .collect();
+            });
+        }
+
+        let mut context = TestContext {
+            events: events.into(),
+            transport: Transport::new(44100.0),
+        };
+        let mut items = Vec::new();
+        let mut blocks = buffer.iter_event_blocks::<TestPlugin>(max_block_size);
+        while let Some(item) = blocks.next(&mut context) {
+            items.push(match item {
+                EventBlockItem::Event(event) => Item::Event(event.timing()),
+                EventBlockItem::Block(block_start, block) => {
+                    Item::Block(block_start, block.samples())
+                }
+            });
+        }
+
+        items
+    }
+
+    #[test]
+    fn no_events() {
+        assert_eq!(
+            collect_items(2, 100, 32, Vec::new()),
+            [
+                Item::Block(0, 32),
+                Item::Block(32, 32),
+                Item::Block(64, 32),
+                Item::Block(96, 4)
+            ]
+        );
+    }
+
+    #[test]
+    fn event_at_start() {
+        assert_eq!(
+            collect_items(2, 64, 32, vec![note_on(0)]),
+            [Item::Event(0), Item::Block(0, 32), Item::Block(32, 32)]
+        );
+    }
+
+    #[test]
+    fn events_at_same_timing() {
+        assert_eq!(
+            collect_items(2, 64, 64, vec![note_on(10), note_on(10), note_on(10)]),
+            [
+                Item::Block(0, 10),
+                Item::Event(10),
+                Item::Event(10),
+                Item::Event(10),
+                Item::Block(10, 54)
+            ]
+        );
+    }
+
+    #[test]
+    fn event_splits_block() {
+        assert_eq!(
+            collect_items(2, 100, 32, vec![note_on(40)]),
+            [
+                Item::Block(0, 32),
+                Item::Block(32, 8),
+                Item::Event(40),
+                Item::Block(40, 32),
+                Item::Block(72, 28)
+            ]
+        );
+    }
+
+    #[test]
+    fn event_past_buffer_end() {
+        assert_eq!(
+            collect_items(2, 48, 32, vec![note_on(150)]),
+            [Item::Block(0, 32), Item::Block(32, 16), Item::Event(150)]
+        );
+    }
+
+    #[test]
+    fn midi_only() {
+        assert_eq!(
+            collect_items(0, 64, 64, vec![note_on(0), note_on(16)]),
+            [
+                Item::Event(0),
+                Item::Block(0, 16),
+                Item::Event(16),
+                Item::Block(16, 48)
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn zero_max_block_size() {
+        collect_items(2, 64, 0, Vec::new());
+    }
+
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn zero_max_block_size() {
+        // Without debug assertions this falls back to single sample blocks
+        assert_eq!(
+            collect_items(2, 2, 0, Vec::new()),
+            [Item::Block(0, 1), Item::Block(1, 1)]
+        );
+    }
+}
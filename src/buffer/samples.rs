@@ -3,43 +3,46 @@
 use std::marker::PhantomData;
 
 #[cfg(feature = "simd")]
-use std::simd::{LaneCount, Simd, SupportedLaneCount};
+use std::simd::{LaneCount, Simd, SimdElement, SupportedLaneCount};
+
+#[cfg(feature = "simd")]
+use super::Sample;
 
 /// An iterator over all samples in a buffer or block, yielding iterators over each channel for
 /// every sample. This iteration order offers good cache locality for per-sample access.
-pub struct SamplesIter<'slice, 'sample: 'slice> {
+pub struct SamplesIter<'slice, 'sample: 'slice, S = f32> {
     /// The raw output buffers.
-    pub(super) buffers: *mut [&'sample mut [f32]],
+    pub(super) buffers: *mut [&'sample mut [S]],
     pub(super) current_sample: usize,
     /// The last sample index to iterate over plus one. Would be equal to `buffers.len()` when
     /// iterating over an entire buffer, but this can also be used to iterate over smaller blocks in
     /// a similar fashion.
     pub(super) samples_end: usize,
-    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(super) _marker: PhantomData<&'slice mut [&'sample mut [S]]>,
 }
 
 /// Can construct iterators over actual iterator over the channel data for a sample, yielded by
 /// [`SamplesIter`]. Can be turned into an iterator, or [`ChannelSamples::iter_mut()`] can be used
 /// to iterate over the channel data multiple times, or more efficiently you can use
 /// [`ChannelSamples::get_unchecked_mut()`] to do the same thing.
-pub struct ChannelSamples<'slice, 'sample: 'slice> {
+pub struct ChannelSamples<'slice, 'sample: 'slice, S = f32> {
     /// The raw output buffers.
-    pub(self) buffers: *mut [&'sample mut [f32]],
+    pub(self) buffers: *mut [&'sample mut [S]],
     pub(self) current_sample: usize,
-    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [S]]>,
 }
 
 /// The actual iterator over the channel data for a sample, yielded by [`ChannelSamples`].
-pub struct ChannelSamplesIter<'slice, 'sample: 'slice> {
+pub struct ChannelSamplesIter<'slice, 'sample: 'slice, S = f32> {
     /// The raw output buffers.
-    pub(self) buffers: *mut [&'sample mut [f32]],
+    pub(self) buffers: *mut [&'sample mut [S]],
     pub(self) current_sample: usize,
     pub(self) current_channel: usize,
-    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [f32]]>,
+    pub(self) _marker: PhantomData<&'slice mut [&'sample mut [S]]>,
 }
 
-impl<'slice, 'sample> Iterator for SamplesIter<'slice, 'sample> {
-    type Item = ChannelSamples<'slice, 'sample>;
+impl<'slice, 'sample, S> Iterator for SamplesIter<'slice, 'sample, S> {
+    type Item = ChannelSamples<'slice, 'sample, S>;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -67,9 +70,9 @@ impl<'slice, 'sample> Iterator for SamplesIter<'slice, 'sample> {
     }
 }
 
-impl<'slice, 'sample> IntoIterator for ChannelSamples<'slice, 'sample> {
-    type Item = &'sample mut f32;
-    type IntoIter = ChannelSamplesIter<'slice, 'sample>;
+impl<'slice, 'sample, S> IntoIterator for ChannelSamples<'slice, 'sample, S> {
+    type Item = &'sample mut S;
+    type IntoIter = ChannelSamplesIter<'slice, 'sample, S>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -82,8 +85,8 @@ impl<'slice, 'sample> IntoIterator for ChannelSamples<'slice, 'sample> {
     }
 }
 
-impl<'slice, 'sample> Iterator for ChannelSamplesIter<'slice, 'sample> {
-    type Item = &'sample mut f32;
+impl<'slice, 'sample, S> Iterator for ChannelSamplesIter<'slice, 'sample, S> {
+    type Item = &'sample mut S;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -113,10 +116,10 @@ impl<'slice, 'sample> Iterator for ChannelSamplesIter<'slice, 'sample> {
     }
 }
 
-impl ExactSizeIterator for SamplesIter<'_, '_> {}
-impl ExactSizeIterator for ChannelSamplesIter<'_, '_> {}
+impl<S> ExactSizeIterator for SamplesIter<'_, '_, S> {}
+impl<S> ExactSizeIterator for ChannelSamplesIter<'_, '_, S> {}
 
-impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
+impl<'slice, 'sample, S> ChannelSamples<'slice, 'sample, S> {
     /// Get the number of channels.
     #[allow(clippy::len_without_is_empty)]
     #[inline]
@@ -128,7 +131,7 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// you don't need to use this function as [`ChannelSamples`] already implements
     /// [`IntoIterator`].
     #[inline]
-    pub fn iter_mut(&mut self) -> ChannelSamplesIter<'slice, 'sample> {
+    pub fn iter_mut(&mut self) -> ChannelSamplesIter<'slice, 'sample, S> {
         ChannelSamplesIter {
             buffers: self.buffers,
             current_sample: self.current_sample,
@@ -140,7 +143,7 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// Access a sample by index. Useful when you would otherwise iterate over this 'Channels'
     /// iterator multiple times.
     #[inline]
-    pub fn get_mut(&mut self, channel_index: usize) -> Option<&mut f32> {
+    pub fn get_mut(&mut self, channel_index: usize) -> Option<&mut S> {
         // SAFETY: The sample bound has already been checked
         unsafe {
             Some(
@@ -157,23 +160,25 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     ///
     /// `channel_index` must be in the range `0..Self::len()`.
     #[inline]
-    pub unsafe fn get_unchecked_mut(&mut self, channel_index: usize) -> &mut f32 {
+    pub unsafe fn get_unchecked_mut(&mut self, channel_index: usize) -> &mut S {
         (&mut (*self.buffers))
             .get_unchecked_mut(channel_index)
             .get_unchecked_mut(self.current_sample)
     }
+}
 
+#[cfg(feature = "simd")]
+impl<S: Sample + SimdElement> ChannelSamples<'_, '_, S> {
     /// Get a SIMD vector containing the channel data for this buffer. If `LANES > channels.len()`
     /// then this will be padded with zeroes. If `LANES < channels.len()` then this won't contain
     /// all values.
-    #[cfg(feature = "simd")]
     #[inline]
-    pub fn to_simd<const LANES: usize>(&self) -> Simd<f32, LANES>
+    pub fn to_simd<const LANES: usize>(&self) -> Simd<S, LANES>
     where
         LaneCount<LANES>: SupportedLaneCount,
     {
         let used_lanes = self.len().max(LANES);
-        let mut values = [0.0; LANES];
+        let mut values = [S::ZERO; LANES];
         for (channel_idx, value) in values.iter_mut().enumerate().take(used_lanes) {
             *value = unsafe {
                 *(&(*self.buffers))
@@ -191,13 +196,12 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// # Safety
     ///
     /// Undefined behavior if `LANES > channels.len()`.
-    #[cfg(feature = "simd")]
     #[inline]
-    pub unsafe fn to_simd_unchecked<const LANES: usize>(&self) -> Simd<f32, LANES>
+    pub unsafe fn to_simd_unchecked<const LANES: usize>(&self) -> Simd<S, LANES>
     where
         LaneCount<LANES>: SupportedLaneCount,
     {
-        let mut values = [0.0; LANES];
+        let mut values = [S::ZERO; LANES];
         for (channel_idx, value) in values.iter_mut().enumerate() {
             *value = *(&(*self.buffers))
                 .get_unchecked(channel_idx)
@@ -209,10 +213,9 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
 
     /// Write data from a SIMD vector to this sample's channel data. This takes the padding added by
     /// [`to_simd()`][Self::to_simd()] into account.
-    #[cfg(feature = "simd")]
     #[allow(clippy::wrong_self_convention)]
     #[inline]
-    pub fn from_simd<const LANES: usize>(&mut self, vector: Simd<f32, LANES>)
+    pub fn from_simd<const LANES: usize>(&mut self, vector: Simd<S, LANES>)
     where
         LaneCount<LANES>: SupportedLaneCount,
     {
@@ -233,10 +236,9 @@ impl<'slice, 'sample> ChannelSamples<'slice, 'sample> {
     /// # Safety
     ///
     /// Undefined behavior if `LANES > channels.len()`.
-    #[cfg(feature = "simd")]
     #[allow(clippy::wrong_self_convention)]
     #[inline]
-    pub unsafe fn from_simd_unchecked<const LANES: usize>(&mut self, vector: Simd<f32, LANES>)
+    pub unsafe fn from_simd_unchecked<const LANES: usize>(&mut self, vector: Simd<S, LANES>)
     where
         LaneCount<LANES>: SupportedLaneCount,
     {
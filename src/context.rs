@@ -27,3 +27,44 @@ impl Display for PluginApi {
         }
     }
 }
+
+/// Information about the track the plugin is inserted on, as reported by the host. This can be
+/// queried using [`InitContext::track_info()`][init::InitContext::track_info()] and
+/// [`GuiContext::track_info()`][gui::GuiContext::track_info()], and the editor is notified about
+/// changes through
+/// [`Editor::track_info_changed()`][crate::prelude::Editor::track_info_changed()]. Depending on the
+/// host and the plugin API not all fields may be available.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackInfo {
+    /// The track's name.
+    pub name: Option<String>,
+    /// The track's color.
+    pub color: Option<HostColor>,
+    /// The number of audio channels on the track.
+    pub channel_count: Option<u32>,
+    /// The kind of track the plugin is inserted on. This is not reported by VST3 hosts.
+    pub kind: Option<TrackKind>,
+}
+
+/// A color sent by the host, like a track's color or the color of a parameter's controller mapping.
+/// These are 8-bit RGBA values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HostColor {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+/// The kind of track a plugin is inserted on. See [`TrackInfo::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrackKind {
+    /// A regular audio or instrument track.
+    Regular,
+    /// A bus or group track that other tracks are routed to.
+    Bus,
+    /// A return or FX track that receives signals through sends.
+    Return,
+    /// The master track.
+    Master,
+}
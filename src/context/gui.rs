@@ -1,9 +1,11 @@
 //! A context passed to a plugin's editor.
 
+use std::path::Path;
 use std::sync::Arc;
 
-use super::PluginApi;
+use super::{HostColor, PluginApi, TrackInfo};
 use crate::prelude::{Param, ParamPtr, Plugin, PluginState};
+use crate::preset::PresetMetadata;
 
 /// Callbacks the plugin can make when the user interacts with its GUI such as updating parameter
 /// values. This is passed to the plugin during [`Editor::spawn()`][crate::prelude::Editor::spawn()]. All of
@@ -55,14 +57,157 @@ pub trait GuiContext: Send + Sync + 'static {
     unsafe fn raw_end_set_parameter(&self, param: ParamPtr);
 
     /// Serialize the plugin's current state to a serde-serializable object. Useful for implementing
-    /// preset handling within a plugin's GUI.
+    /// preset handling within a plugin's GUI. The state is saved using
+    /// [`StateContext::Duplicate`][crate::prelude::StateContext::Duplicate], so it also includes
+    /// the persistent fields that are skipped for presets.
     fn get_state(&self) -> PluginState;
 
     /// Restore the state from a previously serialized state object. This will block the GUI thread
     /// until the state has been restored and a parameter value rescan has been requested from the
     /// host. If the plugin is currently processing audio, then the parameter values will be
-    /// restored at the end of the current processing cycle.
+    /// restored at the end of the current processing cycle. Like with
+    /// [`get_state()`][Self::get_state()], the state is loaded using
+    /// [`StateContext::Duplicate`][crate::prelude::StateContext::Duplicate].
     fn set_state(&self, state: PluginState);
+
+    /// Capture the plugin's current parameter values and persistent fields as a named in-memory
+    /// snapshot. An existing snapshot with the same name is overwritten. Snapshots only live as
+    /// long as the plugin instance and they are not saved as part of the plugin's state.
+    fn capture_snapshot(&self, name: &str);
+
+    /// Recall a snapshot previously captured with [`capture_snapshot()`][Self::capture_snapshot()].
+    /// Every parameter that changes as a result is reported to the host using a full automation
+    /// gesture. If the snapshot's persistent fields differ from the current ones, then the state
+    /// is restored as if [`set_state()`][Self::set_state()] was called. Returns `false` if there is
+    /// no snapshot with this name.
+    fn recall_snapshot(&self, name: &str) -> bool;
+
+    /// Remove a previously captured snapshot. Returns `false` if there is no snapshot with this
+    /// name.
+    fn remove_snapshot(&self, name: &str) -> bool;
+
+    /// The names of all captured snapshots, in alphabetical order.
+    fn snapshot_names(&self) -> Vec<String>;
+
+    /// The currently active slot for A/B comparisons. This is [`AbSlot::A`] until
+    /// [`select_ab_slot()`][Self::select_ab_slot()] is called.
+    fn active_ab_slot(&self) -> AbSlot;
+
+    /// Switch between the two A/B comparison states. The current state is stored in the slot that
+    /// is currently active, after which the selected slot's state is recalled like in
+    /// [`recall_snapshot()`][Self::recall_snapshot()]. The first time a slot is selected it starts
+    /// out as a copy of the current state. The slots are stored as snapshots called `"A"` and
+    /// `"B"`.
+    fn select_ab_slot(&self, slot: AbSlot);
+
+    /// Save the plugin's current state to a preset file at `path`, overwriting any existing file.
+    /// See the [`preset`][crate::preset] module for more information on the file format.
+    fn save_preset(&self, path: &Path, metadata: PresetMetadata) -> anyhow::Result<()>;
+
+    /// Load a preset file saved with [`save_preset()`][Self::save_preset()] and restore its state
+    /// like [`set_state()`][Self::set_state()] would. The state is passed through
+    /// [`Plugin::filter_state()`] first, so presets from older plugin versions can be migrated.
+    /// Returns the preset's metadata, or an error if the file could not be read or if it belongs to
+    /// another plugin.
+    fn load_preset(&self, path: &Path) -> anyhow::Result<PresetMetadata>;
+
+    /// The progress of the asynchronous state load started with
+    /// [`InitContext::begin_state_load()`][crate::prelude::InitContext::begin_state_load()],
+    /// between 0 and 1. Returns `None` if the plugin is not currently loading its state in the
+    /// background. This can be polled by the editor to display a loading indicator.
+    fn state_load_progress(&self) -> Option<f32>;
+
+    /// Whether the wrapper calls [`Editor::tick()`][crate::prelude::Editor::tick()] from the host's
+    /// event loop for editors that return a tick rate. This is currently only supported for CLAP
+    /// plugins in hosts that implement the `timer-support` extension. Editors that need ticks
+    /// should set up their own timer when this returns false.
+    fn provides_ticks(&self) -> bool;
+
+    /// Register a POSIX file descriptor with the host's event loop.
+    /// [`Editor::on_fd_ready()`][crate::prelude::Editor::on_fd_ready()] will then be called from
+    /// the host's GUI thread whenever `fd` becomes readable. This is only supported for CLAP
+    /// plugins in hosts that implement the `posix-fd-support` extension. Returns false if the file
+    /// descriptor could not be registered. Any file descriptors that are still registered when the
+    /// editor is closed are unregistered automatically.
+    fn register_fd(&self, fd: i32) -> bool;
+
+    /// Unregister a file descriptor previously registered with
+    /// [`register_fd()`][Self::register_fd()].
+    fn unregister_fd(&self, fd: i32);
+
+    /// Information about the track the plugin is inserted on, if the host provides this
+    /// information. [`Editor::track_info_changed()`][crate::prelude::Editor::track_info_changed()]
+    /// is called when this changes while the editor is open. See
+    /// [`InitContext::track_info()`][crate::prelude::InitContext::track_info()] for more
+    /// information.
+    fn track_info(&self) -> Option<TrackInfo>;
+
+    /// Get the host's indication for a parameter, like whether the parameter is mapped to a hardware
+    /// controller or whether it's automated.
+    /// [`Editor::param_indication_changed()`][crate::prelude::Editor::param_indication_changed()]
+    /// is called when this changes while the editor is open. This is currently only supported for
+    /// CLAP plugins in hosts that implement the `param-indication` extension. Otherwise this always
+    /// returns the default indication. This doesn't allocate, so widgets can call it while
+    /// drawing to for instance query the mapping's color. Consider using
+    /// [`ParamSetter::param_indication()`] instead.
+    fn raw_param_indication(&self, param: ParamPtr) -> ParamIndication;
+
+    /// Ask the host to show its context menu for a parameter. `x` and `y` are the position in
+    /// physical pixels relative to the editor's window. This is currently only supported for CLAP
+    /// plugins in hosts that implement the `context-menu` extension. Returns false if the host
+    /// could not show the menu. Consider using [`ParamSetter::show_context_menu()`] instead.
+    fn raw_show_param_context_menu(&self, param: ParamPtr, x: i32, y: i32) -> bool;
+}
+
+/// One of the two slots used for A/B comparisons. See [`GuiContext::select_ab_slot()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbSlot {
+    #[default]
+    A,
+    B,
+}
+
+/// How the host is currently using a parameter. See [`ParamSetter::param_indication()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParamIndication {
+    /// Information about the parameter's mapping if the host has mapped the parameter to a hardware
+    /// controller or to another host feature.
+    pub mapping: Option<ParamMapping>,
+    /// The parameter's automation state.
+    pub automation: ParamAutomationState,
+    /// The color the host uses to display the parameter's automation, if any.
+    pub automation_color: Option<HostColor>,
+}
+
+/// Describes how the host mapped a parameter to a hardware controller or another host feature. See
+/// [`ParamIndication::mapping`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParamMapping {
+    /// The color the host uses for the mapping, if any. Editors can use this to highlight the
+    /// parameter.
+    pub color: Option<HostColor>,
+    /// A short label for the mapping, like the name of the controller. This is reference counted
+    /// so indications can be queried while drawing without allocating.
+    pub label: Option<Arc<str>>,
+    /// A longer description of the mapping.
+    pub description: Option<Arc<str>>,
+}
+
+/// The automation state of a parameter. See [`ParamIndication::automation`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParamAutomationState {
+    /// The host has no automation for this parameter.
+    #[default]
+    None,
+    /// The host has automation for this parameter, but it isn't playing.
+    Present,
+    /// The host is playing back automation for this parameter.
+    Playing,
+    /// The host is recording automation for this parameter.
+    Recording,
+    /// The host should play back automation for this parameter, but the user has started to
+    /// adjust the parameter and is overriding the automation.
+    Overriding,
 }
 
 /// An way to run background tasks from the plugin's GUI, equivalent to the
@@ -102,6 +247,24 @@ pub struct ParamSetter<'a> {
     pub raw_context: &'a dyn GuiContext,
 }
 
+impl AbSlot {
+    /// The name of the snapshot this slot's state is stored in.
+    pub fn snapshot_name(&self) -> &'static str {
+        match self {
+            AbSlot::A => "A",
+            AbSlot::B => "B",
+        }
+    }
+
+    /// The other slot.
+    pub fn other(&self) -> AbSlot {
+        match self {
+            AbSlot::A => AbSlot::B,
+            AbSlot::B => AbSlot::A,
+        }
+    }
+}
+
 impl<P: Plugin> AsyncExecutor<P> {
     /// Execute a task on a background thread using `[Plugin::task_executor]`. This allows you to
     /// defer expensive tasks for later without blocking either the process function or the GUI
@@ -180,4 +343,20 @@ impl<'a> ParamSetter<'a> {
     pub fn end_set_parameter<P: Param>(&self, param: &P) {
         unsafe { self.raw_context.raw_end_set_parameter(param.as_ptr()) };
     }
+
+    /// Get the host's indication for a parameter, like whether the parameter is mapped to a hardware
+    /// controller or whether it's automated. See
+    /// [`GuiContext::raw_param_indication()`] for more information.
+    pub fn param_indication<P: Param>(&self, param: &P) -> ParamIndication {
+        self.raw_context.raw_param_indication(param.as_ptr())
+    }
+
+    /// Ask the host to show its context menu for a parameter, for instance when the user right
+    /// clicks on the parameter's widget. `x` and `y` are the position in physical pixels relative
+    /// to the editor's window. Returns false if the host could not show the menu. See
+    /// [`GuiContext::raw_show_param_context_menu()`] for more information.
+    pub fn show_context_menu<P: Param>(&self, param: &P, x: i32, y: i32) -> bool {
+        self.raw_context
+            .raw_show_param_context_menu(param.as_ptr(), x, y)
+    }
 }
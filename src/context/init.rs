@@ -1,7 +1,7 @@
 //! A context passed during plugin initialization.
 
-use super::PluginApi;
-use crate::prelude::Plugin;
+use super::{PluginApi, TrackInfo};
+use crate::prelude::{Plugin, StateContext, StateLoadBehavior, StateLoadHandle};
 
 /// Callbacks the plugin can make while it is being initialized. This is passed to the plugin during
 /// [`Plugin::initialize()`][crate::plugin::Plugin::initialize()].
@@ -23,8 +23,38 @@ pub trait InitContext<P: Plugin> {
     /// behavior when doing offline rendering.
     fn execute(&self, task: P::BackgroundTask);
 
-    /// Update the current latency of the plugin. If the plugin is currently processing audio, then
-    /// this may cause audio playback to be restarted.
+    /// Run a task on a background thread. Unlike [`execute()`][Self::execute()] this returns
+    /// immediately. This is mostly useful together with
+    /// [`begin_state_load()`][Self::begin_state_load()] to finish loading the plugin's state in the
+    /// background.
+    fn execute_background(&self, task: P::BackgroundTask);
+
+    /// Signal that the plugin will continue loading its state in the background, for instance
+    /// because it needs to load large sample files referenced by its persistent fields. The
+    /// returned handle can be moved into a background task started with
+    /// [`execute_background()`][Self::execute_background()], and it can be used to report the
+    /// load's progress to the editor through
+    /// [`GuiContext::state_load_progress()`][crate::prelude::GuiContext::state_load_progress()].
+    /// The load is considered finished once the handle is dropped. Starting a new load supersedes
+    /// the previous one.
+    ///
+    /// While the load is in progress the wrapper either outputs silence or keeps calling the
+    /// plugin's process function, depending on `behavior`. When rendering offline the wrapper
+    /// instead blocks the audio thread until the load has finished so the rendered audio is
+    /// always produced using the loaded state.
+    ///
+    /// # Note
+    ///
+    /// The handle must not be kept alive after the load has finished, as that would keep the
+    /// plugin silenced.
+    fn begin_state_load(&self, behavior: StateLoadBehavior) -> StateLoadHandle;
+
+    /// Update the current latency of the plugin. When this is called while the host is activating
+    /// the plugin, then the new latency is reported to the host as part of that activation. If the
+    /// plugin is reinitialized while it's already active, for instance after loading a preset, then
+    /// NIH-plug asks the host to restart the plugin so the new latency can be applied. Dry signal
+    /// paths can be kept in sync with the new latency using
+    /// [`LatencyCompensator`][crate::util::LatencyCompensator].
     fn set_latency_samples(&self, samples: u32);
 
     /// Set the current voice **capacity** for this plugin (so not the number of currently active
@@ -34,4 +64,16 @@ pub trait InitContext<P: Plugin> {
     /// runtime allows the host to better optimize polyphonic modulation, or to switch to strictly
     /// monophonic modulation when dropping the capacity down to 1.
     fn set_current_voice_capacity(&self, capacity: u32);
+
+    /// If the plugin's state was restored since the last time the plugin was initialized, then
+    /// this returns the context that state was loaded in. This is `None` if the plugin is being
+    /// initialized for another reason, like the host changing the sample rate. This can be used to
+    /// for instance regenerate per-instance data when the plugin was duplicated.
+    fn state_context(&self) -> Option<StateContext>;
+
+    /// Information about the track the plugin is inserted on, if the host provides this
+    /// information. This is only supported by CLAP hosts that implement the `track-info` extension
+    /// and by VST3 hosts that support `IInfoListener`. Note that VST3 hosts may only send this
+    /// information after the plugin has been initialized.
+    fn track_info(&self) -> Option<TrackInfo>;
 }
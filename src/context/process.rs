@@ -37,6 +37,21 @@ pub trait ProcessContext<P: Plugin> {
     /// your task executor.
     fn execute_gui(&self, task: P::BackgroundTask);
 
+    /// Call `task` once for every task index in `0..num_tasks`, and wait for all of those calls to
+    /// finish. When the plugin is running as a CLAP plugin and the host supports the `thread-pool`
+    /// extension, the tasks are executed in parallel on the host's thread pool. Otherwise they are
+    /// run one after the other on the audio thread. This can be used to split up expensive
+    /// processing, like processing independent channels or frequency bands.
+    ///
+    /// # Note
+    ///
+    /// `task` may be called from several threads at the same time, so any state it modifies needs
+    /// to either be split up by task index or be guarded by atomics. Tasks run on the host's
+    /// realtime threads, so they need to be realtime-safe in the same way the process function is.
+    fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync)) {
+        (0..num_tasks).for_each(task);
+    }
+
     /// Get information about the current transport position and status.
     fn transport(&self) -> &Transport;
 
@@ -80,8 +95,15 @@ pub trait ProcessContext<P: Plugin> {
     /// otherwise.
     fn send_event(&mut self, event: PluginNoteEvent<P>);
 
-    /// Update the current latency of the plugin. If the plugin is currently processing audio, then
-    /// this may cause audio playback to be restarted.
+    /// Update the current latency of the plugin. This is safe to call from the audio thread. Hosts
+    /// only accept latency changes while the plugin is deactivated, so if the plugin is currently
+    /// active then NIH-plug asks the host to restart the plugin and the new latency is reported
+    /// once the host has reactivated it. This may cause audio playback to be briefly interrupted.
+    /// Dry signal paths can be kept in sync with the new latency using
+    /// [`LatencyCompensator`][crate::util::LatencyCompensator].
+    ///
+    /// Standalone builds of the plugin can be started with the `--check-latency` option to verify
+    /// that the reported latency matches the plugin's actual latency.
     fn set_latency_samples(&self, samples: u32);
 
     /// Set the current voice **capacity** for this plugin (so not the number of currently active
@@ -69,10 +69,41 @@ pub trait Editor: Send {
     /// loaded.
     fn param_values_changed(&self);
 
-    // TODO: Reconsider adding a tick function here for the Linux `IRunLoop`. To keep this platform
-    //       and API agnostic, add a way to ask the GuiContext if the wrapper already provides a
-    //       tick function. If it does not, then the Editor implementation must handle this by
-    //       itself. This would also need an associated `PREFERRED_FRAME_RATE` constant.
+    /// Called when the host's indication for a parameter changed while the editor is open. The new
+    /// indication can be queried using
+    /// [`ParamSetter::param_indication()`][crate::prelude::ParamSetter::param_indication()]. This
+    /// can be used to redraw the parameter's widget with the host's mapping color.
+    fn param_indication_changed(&self, _id: &str) {}
+
+    /// Called when the information about the plugin's track changed while the editor is open. The
+    /// new information can be queried using
+    /// [`GuiContext::track_info()`][crate::prelude::GuiContext::track_info()]. This can be used to
+    /// for instance update the editor's colors to match the track's color.
+    fn track_info_changed(&self) {}
+
+    /// The rate in Hertz at which [`tick()`][Self::tick()] should be called while the editor is
+    /// open. Returning `None`, the default, means that the editor does not need to be ticked.
+    ///
+    /// The egui, iced, and VIZIA adapters don't use this since baseview drives their windows with
+    /// its own frame timer. This is meant for custom editors that don't come with an event loop.
+    fn tick_rate(&self) -> Option<f32> {
+        None
+    }
+
+    /// Called periodically from the host's GUI thread at the rate returned by
+    /// [`tick_rate()`][Self::tick_rate()] while the editor is open. This lets the editor redraw
+    /// or poll for events from within the host's event loop instead of using its own timer. The
+    /// wrapper can only do this when the host supports it, which can be checked using
+    /// [`GuiContext::provides_ticks()`][crate::prelude::GuiContext::provides_ticks()] in
+    /// [`spawn()`][Self::spawn()]. If that returns false, then the editor needs to set up its own
+    /// timer.
+    fn tick(&self) {}
+
+    /// Called from the host's GUI thread when a file descriptor registered with
+    /// [`GuiContext::register_fd()`][crate::prelude::GuiContext::register_fd()] becomes readable
+    /// or when an error occurs on it.
+    fn on_fd_ready(&self, _fd: i32) {}
+
     // TODO: Host->Plugin resizing
 }
 
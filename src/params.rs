@@ -9,6 +9,8 @@ use std::fmt::{Debug, Display};
 use std::sync::Arc;
 
 use self::internals::ParamPtr;
+use self::modulation::ModulationBuffer;
+use crate::wrapper::state::StateContext;
 
 // The proc-macro for deriving `Params`
 pub use nih_plug_derive::Params;
@@ -20,6 +22,8 @@ mod float;
 mod integer;
 
 pub mod internals;
+pub mod modulation;
+pub mod morph;
 pub mod persist;
 pub mod range;
 pub mod smoothing;
@@ -37,7 +41,8 @@ bitflags::bitflags! {
         /// When applied to a [`BoolParam`], this will cause the parameter to be linked to the
         /// host's bypass control. Only a single parameter can be marked as a bypass parameter. If
         /// you don't have a bypass parameter, then NIH-plug will add one for you. You will need to
-        /// implement this yourself if your plugin introduces latency.
+        /// implement the bypass yourself unless the plugin sets
+        /// [`Plugin::MANAGED_BYPASS`][crate::prelude::Plugin::MANAGED_BYPASS].
         const BYPASS = 1 << 0;
         /// The parameter cannot be changed from an automation lane. The parameter can however still
         /// be manually changed by the user from either the plugin's own GUI or from the host's
@@ -50,6 +55,11 @@ bitflags::bitflags! {
         /// Don't show this parameter when generating a generic UI for the plugin using one of
         /// NIH-plug's generic UI widgets.
         const HIDE_IN_GENERIC_UI = 1 << 3;
+        /// Exclude this parameter from randomization and morphing through
+        /// [`ParamMorph`][morph::ParamMorph]. Randomizing or morphing the plugin's state leaves
+        /// these parameters at their current values. Parameters with the `BYPASS`,
+        /// `NON_AUTOMATABLE`, or `HIDDEN` flags are always excluded.
+        const NON_RANDOMIZABLE = 1 << 4;
     }
 }
 
@@ -230,6 +240,12 @@ pub(crate) trait ParamMut: Param {
     /// restoring a plugin so everything is in sync. In that case the smoother should completely
     /// reset to the current value.
     fn update_smoother(&self, sample_rate: f32, reset: bool);
+
+    /// The parameter's per-sample modulation buffer, if it has one. The wrappers fill this buffer
+    /// from timestamped modulation events during the process call.
+    fn modulation_buffer(&self) -> Option<&ModulationBuffer> {
+        None
+    }
 }
 
 /// Describes a struct containing parameters and other persistent fields.
@@ -249,6 +265,21 @@ pub(crate) trait ParamMut: Param {
 /// with the `#[persist = "key"]` attribute containing types that can be serialized and deserialized
 /// with [Serde](https://serde.rs/).
 ///
+/// ## `#[persist(key = "key", skip_presets)]`
+///
+/// Fields that should only be stored in the host's project, like per-instance random seeds or
+/// sample caches, can use this form of the attribute instead. These fields are not written to or
+/// restored from presets. See [`StateContext`] for more information. This also works for
+/// `#[persist_blob(key = "key", skip_presets)]`.
+///
+/// ## `#[persist_blob = "key"]`
+///
+/// Large binary data like samples or wavetables can be persisted using
+/// [`PersistentBlob`][persist::PersistentBlob] fields annotated with the `#[persist_blob = "key"]`
+/// attribute. These bytes are stored as is in the plugin's state and in preset files instead of
+/// being encoded as JSON. Blob keys are separate from the `#[persist]` keys, and they're prefixed
+/// and suffixed the same way when using nested parameter structs.
+///
 /// ## `#[nested]`, `#[nested(group_name = "group name")]`
 ///
 /// Finally, the `Params` object may include parameters from other objects. Setting a group name is
@@ -299,8 +330,11 @@ pub unsafe trait Params: 'static + Send + Sync {
 
     /// Serialize all fields marked with `#[persist = "stable_name"]` into a hash map containing
     /// JSON-representations of those fields so they can be written to the plugin's state and
-    /// recalled later. This uses [`persist::serialize_field()`] under the hood.
-    fn serialize_fields(&self) -> BTreeMap<String, String> {
+    /// recalled later. This uses [`persist::serialize_field()`] under the hood. `context` describes
+    /// why the state is being saved. Fields marked with `skip_presets` are left out when saving a
+    /// preset.
+    #[allow(unused_variables)]
+    fn serialize_fields(&self, context: StateContext) -> BTreeMap<String, String> {
         BTreeMap::new()
     }
 
@@ -308,9 +342,27 @@ pub unsafe trait Params: 'static + Send + Sync {
     /// [`serialize_fields()`][Self::serialize_fields()]. All of these fields should be wrapped in a
     /// [`persist::PersistentField`] with thread safe interior mutability, like an `RwLock` or a
     /// `Mutex`. This gets called when the plugin's state is being restored. This uses
-    /// [`persist::deserialize_field()`] under the hood.
+    /// [`persist::deserialize_field()`] under the hood. Fields marked with `skip_presets` are not
+    /// restored when `context` is [`StateContext::Preset`].
+    #[allow(unused_variables)]
+    fn deserialize_fields(&self, serialized: &BTreeMap<String, String>, context: StateContext) {}
+
+    /// Collect the contents of all fields marked with `#[persist_blob = "stable_name"]` so they can
+    /// be written to the plugin's state as raw bytes. `context` is used the same way as in
+    /// [`serialize_fields()`][Self::serialize_fields()].
     #[allow(unused_variables)]
-    fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {}
+    fn serialize_blobs(&self, context: StateContext) -> BTreeMap<String, Vec<u8>> {
+        BTreeMap::new()
+    }
+
+    /// Restore all fields marked with `#[persist_blob = "stable_name"]` from a map created by
+    /// [`serialize_blobs()`][Self::serialize_blobs()]. All of these fields should be
+    /// [`persist::PersistentBlob`]s. This gets called when the plugin's state is being restored.
+    /// Blobs are removed from `serialized` as they are restored so they don't need to be copied.
+    /// Any blobs left in the map afterwards are unknown to the plugin.
+    #[allow(unused_variables)]
+    fn deserialize_blobs(&self, serialized: &mut BTreeMap<String, Vec<u8>>, context: StateContext) {
+    }
 }
 
 /// This may be useful when building generic UIs using nested `Params` objects.
@@ -319,11 +371,19 @@ unsafe impl<P: Params> Params for Arc<P> {
         self.as_ref().param_map()
     }
 
-    fn serialize_fields(&self) -> BTreeMap<String, String> {
-        self.as_ref().serialize_fields()
+    fn serialize_fields(&self, context: StateContext) -> BTreeMap<String, String> {
+        self.as_ref().serialize_fields(context)
+    }
+
+    fn deserialize_fields(&self, serialized: &BTreeMap<String, String>, context: StateContext) {
+        self.as_ref().deserialize_fields(serialized, context)
+    }
+
+    fn serialize_blobs(&self, context: StateContext) -> BTreeMap<String, Vec<u8>> {
+        self.as_ref().serialize_blobs(context)
     }
 
-    fn deserialize_fields(&self, serialized: &BTreeMap<String, String>) {
-        self.as_ref().deserialize_fields(serialized)
+    fn deserialize_blobs(&self, serialized: &mut BTreeMap<String, Vec<u8>>, context: StateContext) {
+        self.as_ref().deserialize_blobs(serialized, context)
     }
 }
@@ -303,8 +303,9 @@ impl BoolParam {
 
     /// Mark this parameter as a bypass parameter. Plugin hosts can integrate this parameter into
     /// their UI. Only a single [`BoolParam`] can be a bypass parameter, and NIH-plug will add one
-    /// if you don't create one yourself. You will need to implement this yourself if your plugin
-    /// introduces latency.
+    /// if you don't create one yourself. You will either need to implement the bypass yourself, or
+    /// you can set [`Plugin::MANAGED_BYPASS`][crate::prelude::Plugin::MANAGED_BYPASS] to let
+    /// NIH-plug crossfade to a latency compensated dry signal when this parameter is enabled.
     pub fn make_bypass(mut self) -> Self {
         self.flags.insert(ParamFlags::BYPASS);
         self
@@ -332,4 +333,12 @@ impl BoolParam {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Exclude the parameter from randomization and morphing. See
+    /// [`ParamFlags::NON_RANDOMIZABLE`]. Useful for switches like a channel link toggle that
+    /// changes how the plugin is used rather than how it sounds.
+    pub fn non_randomizable(mut self) -> Self {
+        self.flags.insert(ParamFlags::NON_RANDOMIZABLE);
+        self
+    }
 }
@@ -413,6 +413,14 @@ impl<T: Enum + PartialEq + 'static> EnumParam<T> {
         self.inner.inner = self.inner.inner.hide_in_generic_ui();
         self
     }
+
+    /// Exclude the parameter from randomization and morphing. See
+    /// [`ParamFlags::NON_RANDOMIZABLE`]. Useful for options like an oversampling mode where a
+    /// random value could drastically increase the plugin's CPU usage.
+    pub fn non_randomizable(mut self) -> Self {
+        self.inner.inner = self.inner.inner.non_randomizable();
+        self
+    }
 }
 
 impl EnumParamInner {
@@ -435,10 +443,7 @@ impl EnumParamInner {
     /// Set the parameter based on a serialized stable string identifier. Return whether the ID was
     /// known and the parameter was set.
     pub fn set_from_id(&self, id: &str) -> bool {
-        match self
-            .ids
-            .and_then(|ids| ids.iter().position(|candidate| *candidate == id))
-        {
+        match self.index_from_id(id) {
             Some(index) => {
                 self.set_plain_value(index as i32);
                 true
@@ -446,4 +451,11 @@ impl EnumParamInner {
             None => false,
         }
     }
+
+    /// Get the variant index for a serialized stable string identifier. Returns `None` if this
+    /// enum parameter doesn't have any stable IDs or if the ID is not known.
+    pub(crate) fn index_from_id(&self, id: &str) -> Option<usize> {
+        self.ids
+            .and_then(|ids| ids.iter().position(|candidate| *candidate == id))
+    }
 }
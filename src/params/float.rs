@@ -6,6 +6,7 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use super::internals::ParamPtr;
+use super::modulation::ModulationBuffer;
 use super::range::FloatRange;
 use super::smoothing::{Smoother, SmoothingStyle};
 use super::{Param, ParamFlags, ParamMut};
@@ -32,6 +33,9 @@ pub struct FloatParam {
     /// An optional smoother that will automatically interpolate between the new automation values
     /// set by the host.
     pub smoothed: Smoother<f32>,
+    /// An optional buffer containing per-sample modulation offsets. See
+    /// [`with_modulation_buffer()`][Self::with_modulation_buffer()].
+    modulation_buffer: Option<ModulationBuffer>,
 
     /// Flags to control the parameter's behavior. See [`ParamFlags`].
     flags: ParamFlags,
@@ -256,13 +260,24 @@ impl ParamMut for FloatParam {
     }
 
     fn update_smoother(&self, sample_rate: f32, reset: bool) {
+        // When the parameter has a modulation buffer, the modulation is applied per-sample on top
+        // of the smoothed unmodulated value instead
+        let value = if self.modulation_buffer.is_some() {
+            self.unmodulated_plain_value()
+        } else {
+            self.modulated_plain_value()
+        };
+
         if reset {
-            self.smoothed.reset(self.modulated_plain_value());
+            self.smoothed.reset(value);
         } else {
-            self.smoothed
-                .set_target(sample_rate, self.modulated_plain_value());
+            self.smoothed.set_target(sample_rate, value);
         }
     }
+
+    fn modulation_buffer(&self) -> Option<&ModulationBuffer> {
+        self.modulation_buffer.as_ref()
+    }
 }
 
 impl FloatParam {
@@ -279,6 +294,7 @@ impl FloatParam {
             modulation_offset: AtomicF32::new(0.0),
             default,
             smoothed: Smoother::none(),
+            modulation_buffer: None,
 
             flags: ParamFlags::default(),
             value_changed: None,
@@ -306,6 +322,40 @@ impl FloatParam {
         self.range
     }
 
+    /// The parameter's per-sample modulation buffer, if it was enabled with
+    /// [`with_modulation_buffer()`][Self::with_modulation_buffer()].
+    #[inline]
+    pub fn modulation(&self) -> Option<&ModulationBuffer> {
+        self.modulation_buffer.as_ref()
+    }
+
+    /// Produce smoothed values for an entire block of audio with the per-sample modulation from the
+    /// parameter's [modulation buffer][Self::with_modulation_buffer()] applied on top of them.
+    /// `block_values[..block_len]` will be filled with the modulated values. If the parameter does
+    /// not have a modulation buffer, then this is the same as calling
+    /// [`Smoother::next_block()`] on [`smoothed`][Self::smoothed].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_len > block_values.len()`, or if `block_len` is larger than the block
+    /// passed to the plugin's process function.
+    pub fn next_block_modulated(&self, block_values: &mut [f32], block_len: usize) {
+        self.smoothed.next_block(block_values, block_len);
+
+        if let Some(modulation_buffer) = &self.modulation_buffer {
+            let offsets = modulation_buffer.offsets();
+            for (value, offset) in block_values[..block_len]
+                .iter_mut()
+                .zip(&offsets[..block_len])
+            {
+                if *offset != 0.0 {
+                    let normalized_value = (self.range.normalize(*value) + offset).clamp(0.0, 1.0);
+                    *value = self.range.unnormalize(normalized_value);
+                }
+            }
+        }
+    }
+
     /// Enable polyphonic modulation for this parameter. The ID is used to uniquely identify this
     /// parameter in [`NoteEvent::PolyModulation`][crate::prelude::NoteEvent::PolyModulation]
     /// events, and must thus be unique between _all_ polyphonically modulatable parameters. See the
@@ -323,6 +373,19 @@ impl FloatParam {
         self
     }
 
+    /// Enable per-sample modulation for this parameter. The wrappers will fill the parameter's
+    /// [`ModulationBuffer`] from timestamped modulation events (for instance CLAP's
+    /// `CLAP_EVENT_PARAM_MOD`), and the host's block won't be split on these events when using
+    /// sample accurate automation. Modulation events for this parameter are then no longer
+    /// included in the [smoother][Self::smoothed], which will only smooth the unmodulated value.
+    /// Use [`next_block_modulated()`][Self::next_block_modulated()] or
+    /// [`modulation()`][Self::modulation()] to apply the modulation in your DSP code. This avoids
+    /// zipper noise when a parameter is modulated at audio rate.
+    pub fn with_modulation_buffer(mut self) -> Self {
+        self.modulation_buffer = Some(ModulationBuffer::default());
+        self
+    }
+
     /// Set up a smoother that can gradually interpolate changes made to this parameter, preventing
     /// clicks and zipper noises.
     pub fn with_smoother(mut self, style: SmoothingStyle) -> Self {
@@ -418,6 +481,14 @@ impl FloatParam {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Exclude the parameter from randomization and morphing. See
+    /// [`ParamFlags::NON_RANDOMIZABLE`]. Useful for parameters like output gain that should never
+    /// jump to a random value.
+    pub fn non_randomizable(mut self) -> Self {
+        self.flags.insert(ParamFlags::NON_RANDOMIZABLE);
+        self
+    }
 }
 
 /// Calculate how many decimals to round to when displaying a floating point value with a specific
@@ -387,4 +387,12 @@ impl IntParam {
         self.flags.insert(ParamFlags::HIDE_IN_GENERIC_UI);
         self
     }
+
+    /// Exclude the parameter from randomization and morphing. See
+    /// [`ParamFlags::NON_RANDOMIZABLE`]. Useful for parameters like a voice count or an FFT window
+    /// size that affect the plugin's latency or CPU usage.
+    pub fn non_randomizable(mut self) -> Self {
+        self.flags.insert(ParamFlags::NON_RANDOMIZABLE);
+        self
+    }
 }
@@ -1,5 +1,6 @@
 //! Implementation details for the parameter management.
 
+use super::modulation::ModulationBuffer;
 use super::{Param, ParamFlags, ParamMut};
 
 /// Internal pointers to parameters. This is an implementation detail used by the wrappers for type
@@ -77,6 +78,7 @@ impl ParamPtr {
     param_ptr_forward!(pub(crate) unsafe fn set_normalized_value(&self, normalized: f32) -> bool);
     param_ptr_forward!(pub(crate) unsafe fn modulate_value(&self, modulation_offset: f32) -> bool);
     param_ptr_forward!(pub(crate) unsafe fn update_smoother(&self, sample_rate: f32, reset: bool));
+    param_ptr_forward!(pub(crate) unsafe fn modulation_buffer(&self) -> Option<&ModulationBuffer>);
 
     // These functions involve casts since the plugin formats only do floating point types, so we
     // can't generate them with the macro:
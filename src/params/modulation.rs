@@ -0,0 +1,136 @@
+//! Per-sample modulation buffers for parameters. See [`FloatParam::with_modulation_buffer()`] for
+//! more information.
+//!
+//! [`FloatParam::with_modulation_buffer()`]: super::FloatParam::with_modulation_buffer()
+
+use atomic_float::AtomicF32;
+use atomic_refcell::{AtomicRef, AtomicRefCell};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A buffer containing the parameter's normalized monophonic modulation offset for every sample in
+/// the current block. The wrappers fill this buffer from timestamped modulation events before the
+/// plugin's process function is called. Modulation offsets are held until the next modulation
+/// event, so the last offset from the previous block carries over to the next block.
+///
+/// This buffer should only be accessed from the audio thread.
+#[derive(Debug, Default)]
+pub struct ModulationBuffer {
+    /// The normalized modulation offsets for the entire block the host passed to the plugin. When
+    /// the wrapper splits this block into smaller sub-blocks, only the part of the buffer
+    /// corresponding to the current sub-block is exposed through [`offsets()`][Self::offsets()].
+    offsets: AtomicRefCell<Vec<f32>>,
+    /// The length of the block the host passed to the plugin, within `offsets`.
+    total_len: AtomicUsize,
+    /// The start of the current sub-block within `offsets`.
+    block_start: AtomicUsize,
+    /// The length of the current sub-block.
+    block_len: AtomicUsize,
+    /// The modulation offset set by the last modulation event. This is used to fill the buffer at
+    /// the start of the next block.
+    current_offset: AtomicF32,
+}
+
+impl ModulationBuffer {
+    /// The normalized modulation offsets for every sample in the current block. These offsets are
+    /// relative to the parameter's unmodulated normalized value. The slice always has the same
+    /// length as the [`Buffer`][crate::prelude::Buffer] passed to the plugin's process function.
+    pub fn offsets(&self) -> AtomicRef<'_, [f32]> {
+        let block_start = self.block_start.load(Ordering::Relaxed);
+        let block_end = block_start + self.block_len.load(Ordering::Relaxed);
+
+        AtomicRef::map(self.offsets.borrow(), |offsets| {
+            &offsets[block_start..block_end]
+        })
+    }
+
+    /// Whether any of the samples in the current block are modulated. Can be used to skip
+    /// per-sample computations when no modulation is applied.
+    pub fn is_modulated(&self) -> bool {
+        self.offsets().iter().any(|offset| *offset != 0.0)
+    }
+
+    /// Make sure the buffer has enough capacity to hold offsets for blocks up to `max_block_size`
+    /// samples. Called by the wrappers when the plugin gets activated.
+    pub(crate) fn resize(&self, max_block_size: usize) {
+        let mut offsets = self.offsets.borrow_mut();
+        offsets.resize(max_block_size, 0.0);
+
+        self.total_len.store(0, Ordering::Relaxed);
+        self.block_start.store(0, Ordering::Relaxed);
+        self.block_len.store(0, Ordering::Relaxed);
+    }
+
+    /// Start a new block of `total_len` samples. This fills the buffer with the offset from the
+    /// last modulation event and exposes the entire block to the plugin.
+    pub(crate) fn start_block(&self, total_len: usize) {
+        let mut offsets = self.offsets.borrow_mut();
+        nih_debug_assert!(total_len <= offsets.len());
+        let total_len = total_len.min(offsets.len());
+
+        offsets[..total_len].fill(self.current_offset.load(Ordering::Relaxed));
+
+        self.total_len.store(total_len, Ordering::Relaxed);
+        self.block_start.store(0, Ordering::Relaxed);
+        self.block_len.store(total_len, Ordering::Relaxed);
+    }
+
+    /// Apply a modulation event at `timing` samples into the block started with
+    /// [`start_block()`][Self::start_block()]. The offset is held until the end of the block.
+    /// Events need to be added in chronological order.
+    pub(crate) fn add_event(&self, timing: usize, normalized_offset: f32) {
+        let total_len = self.total_len.load(Ordering::Relaxed);
+        let mut offsets = self.offsets.borrow_mut();
+
+        offsets[timing.min(total_len)..total_len].fill(normalized_offset);
+        self.current_offset
+            .store(normalized_offset, Ordering::Relaxed);
+    }
+
+    /// Only expose the region of `block_len` samples starting at `block_start` to the plugin. Used
+    /// when the wrapper splits the host's block into smaller sub-blocks.
+    pub(crate) fn set_block(&self, block_start: usize, block_len: usize) {
+        let total_len = self.total_len.load(Ordering::Relaxed);
+        nih_debug_assert!(block_start + block_len <= total_len);
+
+        let block_start = block_start.min(total_len);
+        self.block_start.store(block_start, Ordering::Relaxed);
+        self.block_len
+            .store(block_len.min(total_len - block_start), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_are_held_between_blocks() {
+        let buffer = ModulationBuffer::default();
+        buffer.resize(8);
+
+        buffer.start_block(8);
+        assert!(!buffer.is_modulated());
+        buffer.add_event(3, 0.25);
+        buffer.add_event(6, -0.5);
+        assert_eq!(
+            *buffer.offsets(),
+            [0.0, 0.0, 0.0, 0.25, 0.25, 0.25, -0.5, -0.5]
+        );
+
+        buffer.start_block(4);
+        assert_eq!(*buffer.offsets(), [-0.5; 4]);
+    }
+
+    #[test]
+    fn sub_blocks() {
+        let buffer = ModulationBuffer::default();
+        buffer.resize(8);
+
+        buffer.start_block(6);
+        buffer.add_event(2, 1.0);
+        buffer.set_block(1, 3);
+        assert_eq!(*buffer.offsets(), [0.0, 1.0, 1.0]);
+        buffer.set_block(4, 2);
+        assert_eq!(*buffer.offsets(), [1.0, 1.0]);
+    }
+}
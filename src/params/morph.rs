@@ -0,0 +1,314 @@
+//! Randomizing parameters and morphing between plugin states. See [`ParamMorph`] for more
+//! information.
+
+use super::internals::ParamPtr;
+use super::{ParamFlags, Params};
+use crate::prelude::ParamSetter;
+use crate::wrapper::state::{self, PluginState};
+
+/// A set of parameter changes that can be applied gradually. This can be used to randomize a
+/// plugin's parameters, to morph between two [`PluginState`]s, or to do both at the same time by
+/// morphing towards a randomized state. A position of 0.0 corresponds to the start state and a
+/// position of 1.0 to the end state. A morph can be applied in one go using
+/// [`apply()`][Self::apply()], or it can be controlled continuously from something like an XY pad
+/// using [`begin_gesture()`][Self::begin_gesture()], [`set_position()`][Self::set_position()], and
+/// [`end_gesture()`][Self::end_gesture()]. All changes go through the
+/// [`ParamSetter`]'s [`GuiContext`][crate::prelude::GuiContext] so the host can record them.
+///
+/// Parameters with the `BYPASS`, `NON_AUTOMATABLE`, `HIDDEN`, or `NON_RANDOMIZABLE`
+/// [`ParamFlags`] are never changed. Integer, enum, and boolean parameters always snap to one of
+/// their steps, and float parameters snap to their step size if they have one. Persistent fields
+/// are not affected.
+///
+/// This object contains pointers to the parameters it was created for, so it should not outlive
+/// the [`Params`] object that was passed to its constructor.
+#[derive(Debug, Clone)]
+pub struct ParamMorph {
+    targets: Vec<MorphTarget>,
+}
+
+/// A single parameter that gets changed by a [`ParamMorph`].
+#[derive(Debug, Clone, Copy)]
+struct MorphTarget {
+    param: ParamPtr,
+    /// The normalized value at position 0.0.
+    start: f32,
+    /// The normalized value at position 1.0.
+    end: f32,
+}
+
+impl ParamMorph {
+    /// Morph between two plugin states. Parameters that are missing from one of the states use the
+    /// parameter's current value in its place.
+    pub fn between_states(params: &dyn Params, start: &PluginState, end: &PluginState) -> Self {
+        Self::new(params, |param_id, param| unsafe {
+            let current = param.unmodulated_normalized_value();
+            let start = normalized_state_value(start, param_id, param).unwrap_or(current);
+            let end = normalized_state_value(end, param_id, param).unwrap_or(current);
+
+            Some((start, end))
+        })
+    }
+
+    /// Morph from the parameters' current values to another plugin state. Parameters that are
+    /// missing from `end` are left unchanged.
+    pub fn to_state(params: &dyn Params, end: &PluginState) -> Self {
+        Self::new(params, |param_id, param| unsafe {
+            let start = param.unmodulated_normalized_value();
+            normalized_state_value(end, param_id, param).map(|end| (start, end))
+        })
+    }
+
+    /// Morph from the parameters' current values to a randomized state. `amount` determines how far
+    /// the randomized values can deviate from the current values, with 1.0 choosing new values
+    /// uniformly over the parameter's entire range and 0.0 leaving all parameters unchanged.
+    ///
+    /// `rng` should return uniformly distributed random numbers in the range `[0, 1]`, for
+    /// instance using `move || rng.gen::<f32>()` with the `rand` crate. It's called once per
+    /// parameter.
+    pub fn randomize(params: &dyn Params, amount: f32, mut rng: impl FnMut() -> f32) -> Self {
+        let amount = amount.clamp(0.0, 1.0);
+        Self::new(params, |_, param| unsafe {
+            let start = param.unmodulated_normalized_value();
+            let end = start + (rng().clamp(0.0, 1.0) - start) * amount;
+
+            Some((start, end))
+        })
+    }
+
+    /// Build the morph from a function that returns the normalized start and end values for a
+    /// parameter, if the parameter should be changed. Parameters that are excluded through their
+    /// flags are skipped, and so are parameters whose start and end values are the same after
+    /// snapping.
+    fn new(
+        params: &dyn Params,
+        mut start_end: impl FnMut(&str, ParamPtr) -> Option<(f32, f32)>,
+    ) -> Self {
+        let targets = params
+            .param_map()
+            .into_iter()
+            .filter(|(_, param, _)| is_morphable(unsafe { param.flags() }))
+            .filter_map(|(param_id, param, _)| {
+                let (start, end) = start_end(&param_id, param)?;
+                let start = unsafe { snap_normalized(param, start) };
+                let end = unsafe { snap_normalized(param, end) };
+
+                (start != end).then_some(MorphTarget { param, start, end })
+            })
+            .collect();
+
+        Self { targets }
+    }
+
+    /// Returns `true` if this morph does not change any parameters.
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    /// Set all affected parameters to their values at `position` using a single automation gesture
+    /// per parameter. Parameters that already have the correct value are skipped.
+    pub fn apply(&self, setter: &ParamSetter, position: f32) {
+        for target in &self.targets {
+            let value = target.value_at(position);
+            unsafe {
+                if value != target.param.unmodulated_normalized_value() {
+                    setter.raw_context.raw_begin_set_parameter(target.param);
+                    setter
+                        .raw_context
+                        .raw_set_parameter_normalized(target.param, value);
+                    setter.raw_context.raw_end_set_parameter(target.param);
+                }
+            }
+        }
+    }
+
+    /// Begin an automation gesture for all affected parameters. This needs to be called before
+    /// calling [`set_position()`][Self::set_position()], for instance when the user starts dragging
+    /// an XY pad.
+    pub fn begin_gesture(&self, setter: &ParamSetter) {
+        for target in &self.targets {
+            unsafe { setter.raw_context.raw_begin_set_parameter(target.param) };
+        }
+    }
+
+    /// Set all affected parameters to their values at `position`, where 0.0 is the start state and
+    /// 1.0 is the end state. This needs to be surrounded by matching calls to
+    /// [`begin_gesture()`][Self::begin_gesture()] and [`end_gesture()`][Self::end_gesture()], and it
+    /// can be called multiple times in between.
+    pub fn set_position(&self, setter: &ParamSetter, position: f32) {
+        for target in &self.targets {
+            let value = target.value_at(position);
+            unsafe {
+                if value != target.param.unmodulated_normalized_value() {
+                    setter
+                        .raw_context
+                        .raw_set_parameter_normalized(target.param, value);
+                }
+            }
+        }
+    }
+
+    /// End the automation gestures started in [`begin_gesture()`][Self::begin_gesture()].
+    pub fn end_gesture(&self, setter: &ParamSetter) {
+        for target in &self.targets {
+            unsafe { setter.raw_context.raw_end_set_parameter(target.param) };
+        }
+    }
+}
+
+impl MorphTarget {
+    /// The snapped normalized value at `position` between the start and the end value.
+    fn value_at(&self, position: f32) -> f32 {
+        let position = position.clamp(0.0, 1.0);
+        unsafe { snap_normalized(self.param, self.start + (self.end - self.start) * position) }
+    }
+}
+
+/// Whether a parameter with these flags can be randomized and morphed.
+fn is_morphable(flags: ParamFlags) -> bool {
+    !flags.intersects(
+        ParamFlags::BYPASS
+            | ParamFlags::NON_AUTOMATABLE
+            | ParamFlags::HIDDEN
+            | ParamFlags::NON_RANDOMIZABLE,
+    )
+}
+
+/// Snap a normalized value to the parameter's steps. Discrete parameters snap to their step count,
+/// and continuous parameters snap to their step size if they have one.
+///
+/// # Safety
+///
+/// `param` needs to point to a parameter that's still alive.
+unsafe fn snap_normalized(param: ParamPtr, normalized: f32) -> f32 {
+    let normalized = normalized.clamp(0.0, 1.0);
+    match param.step_count() {
+        Some(step_count) => (normalized * step_count as f32).round() / step_count as f32,
+        None => param.preview_normalized(param.preview_plain(normalized)),
+    }
+}
+
+/// Get a parameter's normalized value from a plugin state, if the state contains a valid value for
+/// that parameter.
+///
+/// # Safety
+///
+/// `param` needs to point to a parameter that's still alive.
+unsafe fn normalized_state_value(
+    plugin_state: &PluginState,
+    param_id: &str,
+    param: ParamPtr,
+) -> Option<f32> {
+    let value = plugin_state.params.get(param_id)?;
+    let normalized = state::param_value_to_normalized(param, value);
+    nih_debug_assert!(
+        normalized.is_some(),
+        "Invalid value {:?} for parameter \"{}\" in plugin state",
+        value,
+        param_id
+    );
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::prelude::{BoolParam, FloatParam, FloatRange, IntParam, IntRange, Param};
+    use crate::wrapper::state::ParamValue;
+
+    struct TestParams {
+        gain: FloatParam,
+        steps: IntParam,
+        toggle: BoolParam,
+        output: FloatParam,
+        hidden: FloatParam,
+    }
+
+    unsafe impl Params for TestParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            vec![
+                (String::from("gain"), self.gain.as_ptr(), String::new()),
+                (String::from("steps"), self.steps.as_ptr(), String::new()),
+                (String::from("toggle"), self.toggle.as_ptr(), String::new()),
+                (String::from("output"), self.output.as_ptr(), String::new()),
+                (String::from("hidden"), self.hidden.as_ptr(), String::new()),
+            ]
+        }
+    }
+
+    fn make_params() -> TestParams {
+        TestParams {
+            gain: FloatParam::new("Gain", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 }),
+            steps: IntParam::new("Steps", 0, IntRange::Linear { min: 0, max: 4 }),
+            toggle: BoolParam::new("Toggle", false),
+            output: FloatParam::new("Output", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .non_randomizable(),
+            hidden: FloatParam::new("Hidden", 0.0, FloatRange::Linear { min: 0.0, max: 1.0 })
+                .hide(),
+        }
+    }
+
+    fn make_state(params: &[(&str, ParamValue)]) -> PluginState {
+        PluginState {
+            version: String::new(),
+            params: params
+                .iter()
+                .map(|(id, value)| (id.to_string(), value.clone()))
+                .collect(),
+            fields: BTreeMap::new(),
+            blobs: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn excluded_params_are_skipped() {
+        let params = make_params();
+        let morph = ParamMorph::randomize(&params, 1.0, || 1.0);
+
+        let changed: Vec<ParamPtr> = morph.targets.iter().map(|t| t.param).collect();
+        assert_eq!(
+            changed,
+            [
+                params.gain.as_ptr(),
+                params.steps.as_ptr(),
+                params.toggle.as_ptr()
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_amount_is_empty() {
+        let params = make_params();
+        assert!(ParamMorph::randomize(&params, 0.0, || 1.0).is_empty());
+    }
+
+    #[test]
+    fn morph_snaps_to_steps() {
+        let params = make_params();
+        let start = make_state(&[
+            ("gain", ParamValue::F32(0.0)),
+            ("steps", ParamValue::I32(0)),
+            ("toggle", ParamValue::Bool(false)),
+        ]);
+        let end = make_state(&[
+            ("gain", ParamValue::F32(1.0)),
+            ("steps", ParamValue::I32(4)),
+            ("toggle", ParamValue::Bool(true)),
+            ("output", ParamValue::F32(1.0)),
+        ]);
+        let morph = ParamMorph::between_states(&params, &start, &end);
+        assert_eq!(morph.targets.len(), 3);
+
+        let values: Vec<f32> = morph.targets.iter().map(|t| t.value_at(0.3)).collect();
+        assert!((values[0] - 0.3).abs() < 1e-6);
+        assert_eq!(values[1], 0.25);
+        assert_eq!(values[2], 0.0);
+
+        let values: Vec<f32> = morph.targets.iter().map(|t| t.value_at(0.6)).collect();
+        assert_eq!(values[1], 0.5);
+        assert_eq!(values[2], 1.0);
+    }
+}
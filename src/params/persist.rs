@@ -217,6 +217,59 @@ where
 impl_persistent_arc!(crossbeam::atomic::AtomicCell<T>,
                      T: serde::Serialize + serde::Deserialize<'a> + Copy + Send);
 
+/// Handles the functionality needed for persisting raw binary data in a plugin's state. These types
+/// can be used with [`Params`][super::Params]' `#[persist_blob = "..."]` attributes. Unlike
+/// [`PersistentField`]s, these bytes are not serialized to JSON. They're stored as is alongside the
+/// rest of the plugin's state, which makes this a better fit for large amounts of data like
+/// samples or wavetables.
+///
+/// This is implemented for `Vec<u8>`s wrapped in the same interior mutability types as
+/// [`PersistentField`].
+pub trait PersistentBlob: Send + Sync {
+    /// Replace the stored bytes using interior mutability.
+    fn set_bytes(&self, new_bytes: Vec<u8>);
+
+    /// Get a reference to the stored bytes, and apply a function to it. This is used to write the
+    /// bytes to the plugin's state.
+    fn map_bytes<F, R>(&self, f: F) -> R
+    where
+        F: Fn(&[u8]) -> R;
+}
+
+macro_rules! impl_persistent_blob {
+    ($ty:ty, $read:ident, $write:ident $(, $poisoned:literal)?) => {
+        impl PersistentBlob for $ty {
+            fn set_bytes(&self, new_bytes: Vec<u8>) {
+                *self.$write()$(.expect($poisoned))? = new_bytes;
+            }
+            fn map_bytes<F, R>(&self, f: F) -> R
+            where
+                F: Fn(&[u8]) -> R,
+            {
+                f(&self.$read()$(.expect($poisoned))?)
+            }
+        }
+
+        impl PersistentBlob for Arc<$ty> {
+            fn set_bytes(&self, new_bytes: Vec<u8>) {
+                self.as_ref().set_bytes(new_bytes);
+            }
+            fn map_bytes<F, R>(&self, f: F) -> R
+            where
+                F: Fn(&[u8]) -> R,
+            {
+                self.as_ref().map_bytes(f)
+            }
+        }
+    };
+}
+
+impl_persistent_blob!(std::sync::RwLock<Vec<u8>>, read, write, "Poisoned RwLock");
+impl_persistent_blob!(parking_lot::RwLock<Vec<u8>>, read, write);
+impl_persistent_blob!(std::sync::Mutex<Vec<u8>>, lock, lock, "Poisoned Mutex");
+impl_persistent_blob!(parking_lot::Mutex<Vec<u8>>, lock, lock);
+impl_persistent_blob!(atomic_refcell::AtomicRefCell<Vec<u8>>, borrow, borrow_mut);
+
 /// Can be used with the `#[serde(with = "nih_plug::params::internals::serialize_atomic_cell")]`
 /// attribute to serialize `AtomicCell<T>`s.
 pub mod serialize_atomic_cell {
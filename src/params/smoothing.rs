@@ -1,5 +1,7 @@
 //! Utilities to handle smoothing parameter changes over time.
 
+#[cfg(feature = "simd")]
+use std::simd::{LaneCount, Simd, SupportedLaneCount};
 use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Arc;
 
@@ -73,6 +75,29 @@ pub struct SmootherIter<'a, T: Smoothable> {
     smoother: &'a Smoother<T>,
 }
 
+/// A non-atomic view into a [`Smoother`], obtained through [`Smoother::view()`]. The smoother's
+/// state is loaded once when the view is created and it's written back to the smoother when the
+/// view is dropped. In between the view can be used to generate smoothed values without any
+/// atomic loads or stores, which cuts down on the per-sample overhead when smoothing many
+/// parameters in a single block. With the `simd` feature enabled, the view can also generate
+/// smoothed values directly as `std::simd` vectors.
+///
+/// The view should only ever be used from the audio thread, and there should only be a single
+/// view for a smoother at a time. Any changes made to the smoother while the view exists will be
+/// overwritten when the view is dropped.
+pub struct SmootherView<'a, T: Smoothable> {
+    smoother: &'a Smoother<T>,
+
+    /// The number of steps of smoothing left to take. See [`Smoother`].
+    steps_left: i32,
+    /// The step size or coefficient used for the smoothing. See [`Smoother`].
+    step_size: f32,
+    /// The value for the current sample.
+    current: f32,
+    /// The value we're smoothing towards.
+    target: T,
+}
+
 impl SmoothingStyle {
     /// Compute the number of steps to reach the target value based on the sample rate and this
     /// smoothing style's duration.
@@ -345,6 +370,21 @@ impl<T: Smoothable> Smoother<T> {
         }
     }
 
+    /// Create a non-atomic [`SmootherView`] for this smoother. This is useful when generating a lot
+    /// of smoothed values at once, as the view only needs to touch the atomics when it's created
+    /// and dropped. The view should only be used on the audio thread.
+    #[inline]
+    pub fn view(&self) -> SmootherView<'_, T> {
+        SmootherView {
+            smoother: self,
+
+            steps_left: self.steps_left.load(Ordering::Relaxed),
+            step_size: self.step_size.load(Ordering::Relaxed),
+            current: self.current.load(Ordering::Relaxed),
+            target: T::atomic_load(&self.target),
+        }
+    }
+
     /// Get previous value returned by this smoother. This may be useful to save some boilerplate
     /// when [`is_smoothing()`][Self::is_smoothing()] is used to determine whether an expensive
     /// calculation should take place, and [`next()`][Self::next()] gets called as part of that
@@ -485,6 +525,149 @@ impl<T: Smoothable> Smoother<T> {
     }
 }
 
+impl Smoother<f32> {
+    /// Produce smoothed values for an entire block of audio as SIMD vectors, where each vector
+    /// contains `LANES` consecutive smoothed values. `block_values[..num_vectors]` will be filled
+    /// with the smoothed values, for a total of `num_vectors * LANES` samples. This is a
+    /// convenience function for [`SmootherView::next_block_simd()`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_vectors > block_values.len()`.
+    #[cfg(feature = "simd")]
+    pub fn next_block_simd<const LANES: usize>(
+        &self,
+        block_values: &mut [Simd<f32, LANES>],
+        num_vectors: usize,
+    ) where
+        LaneCount<LANES>: SupportedLaneCount,
+    {
+        self.view().next_block_simd(block_values, num_vectors)
+    }
+}
+
+impl<T: Smoothable> Drop for SmootherView<'_, T> {
+    fn drop(&mut self) {
+        self.smoother
+            .steps_left
+            .store(self.steps_left, Ordering::Relaxed);
+        self.smoother.current.store(self.current, Ordering::Relaxed);
+    }
+}
+
+impl<T: Smoothable> SmootherView<'_, T> {
+    /// The number of steps left until calling [`next()`][Self::next()] will stop yielding new
+    /// values.
+    #[inline]
+    pub fn steps_left(&self) -> i32 {
+        self.steps_left
+    }
+
+    /// Whether calling [`next()`][Self::next()] will yield a new value or an old value.
+    #[inline]
+    pub fn is_smoothing(&self) -> bool {
+        self.steps_left > 0
+    }
+
+    /// Get the next value from this smoother. This behaves exactly the same as
+    /// [`Smoother::next()`], but without touching any atomics.
+    #[allow(clippy::should_implement_trait)]
+    #[inline]
+    pub fn next(&mut self) -> T {
+        T::from_f32(self.next_f32())
+    }
+
+    /// Produce smoothed values for an entire block of audio. This behaves exactly the same as
+    /// [`Smoother::next_block_exact()`].
+    pub fn next_block_exact(&mut self, block_values: &mut [T]) {
+        let num_smoothed_values = block_values.len().min(self.steps_left.max(0) as usize);
+        block_values[..num_smoothed_values].fill_with(|| self.next());
+        block_values[num_smoothed_values..].fill(self.target);
+    }
+
+    /// The same as [`next()`][Self::next()], but returning the floating point representation of
+    /// the smoothed value.
+    #[inline]
+    fn next_f32(&mut self) -> f32 {
+        let target_f32 = self.target.to_f32();
+        if self.steps_left > 1 {
+            self.current = self
+                .smoother
+                .style
+                .next(self.current, target_f32, self.step_size);
+            self.steps_left -= 1;
+        } else if self.steps_left == 1 {
+            // Just like in `Smoother::next()`, we'll snap to the target value on the last step
+            self.current = target_f32;
+            self.steps_left = 0;
+        } else {
+            return target_f32;
+        }
+
+        self.current
+    }
+}
+
+impl SmootherView<'_, f32> {
+    /// Get the next `LANES` smoothed values as a SIMD vector. This is equivalent to calling
+    /// [`next()`][Self::next()] `LANES` times, but the values are computed in parallel when the
+    /// smoother is either not smoothing or when it's using linear smoothing.
+    #[cfg(feature = "simd")]
+    #[inline]
+    pub fn next_simd<const LANES: usize>(&mut self) -> Simd<f32, LANES>
+    where
+        LaneCount<LANES>: SupportedLaneCount,
+    {
+        if self.steps_left <= 0 {
+            return Simd::splat(self.target);
+        }
+
+        // The last step snaps to the target value, so that step needs to be computed separately
+        if self.steps_left as usize > LANES {
+            if let SmoothingStyle::Linear(_) = self.smoother.style {
+                let mut offsets = [0.0; LANES];
+                for (idx, offset) in offsets.iter_mut().enumerate() {
+                    *offset = (idx + 1) as f32;
+                }
+
+                let values = Simd::splat(self.current)
+                    + (Simd::splat(self.step_size) * Simd::from_array(offsets));
+                self.current = values[LANES - 1];
+                self.steps_left -= LANES as i32;
+
+                return values;
+            }
+        }
+
+        let mut values = [0.0; LANES];
+        values.fill_with(|| self.next_f32());
+
+        Simd::from_array(values)
+    }
+
+    /// Produce smoothed values for an entire block of audio as SIMD vectors, where each vector
+    /// contains `LANES` consecutive smoothed values. `block_values[..num_vectors]` will be filled
+    /// with the smoothed values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_vectors > block_values.len()`.
+    #[cfg(feature = "simd")]
+    pub fn next_block_simd<const LANES: usize>(
+        &mut self,
+        block_values: &mut [Simd<f32, LANES>],
+        num_vectors: usize,
+    ) where
+        LaneCount<LANES>: SupportedLaneCount,
+    {
+        // Once the smoother is done smoothing every following vector can just be the target value
+        let num_smoothed_vectors =
+            num_vectors.min((self.steps_left.max(0) as usize).div_ceil(LANES));
+        block_values[..num_smoothed_vectors].fill_with(|| self.next_simd());
+        block_values[num_smoothed_vectors..num_vectors].fill(Simd::splat(self.target));
+    }
+}
+
 impl Smoothable for f32 {
     type Atomic = AtomicF32;
 
@@ -807,4 +990,55 @@ mod tests {
         // After all steps complete, should no longer be smoothing
         assert!(!smoother.is_smoothing());
     }
+
+    /// The non-atomic view should produce the exact same values as the smoother itself.
+    #[test]
+    fn view_next_equivalence() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Logarithmic(100.0));
+        smoother.reset(10.0);
+        smoother.set_target(100.0, 20.0);
+        let reference = smoother.clone();
+
+        let mut view = smoother.view();
+        for _ in 0..12 {
+            assert_eq!(view.next(), reference.next());
+        }
+    }
+
+    /// The view's state should be written back to the smoother when it gets dropped.
+    #[test]
+    fn view_block_writeback() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.reset(10.0);
+        smoother.set_target(100.0, 20.0);
+
+        let mut values = [0.0; 8];
+        smoother.view().next_block_exact(&mut values);
+        assert_eq!(smoother.steps_left(), 2);
+        assert_eq!(smoother.previous_value(), values[7]);
+
+        smoother.view().next_block_exact(&mut values);
+        assert_eq!(values[1], 20.0);
+        assert_eq!(values[7], 20.0);
+        assert!(!smoother.is_smoothing());
+    }
+
+    /// The SIMD block smoothing should match the scalar smoothing, including the final snap to the
+    /// target value.
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_block_equivalence() {
+        let smoother: Smoother<f32> = Smoother::new(SmoothingStyle::Linear(100.0));
+        smoother.reset(10.0);
+        smoother.set_target(100.0, 20.0);
+        let reference = smoother.clone();
+
+        let mut vectors = [Simd::<f32, 4>::splat(0.0); 4];
+        smoother.next_block_simd(&mut vectors, 4);
+        for value in vectors.iter().flat_map(|vector| vector.to_array()) {
+            approx::assert_relative_eq!(value, reference.next(), epsilon = 1e-5);
+        }
+        assert_eq!(vectors[3], Simd::splat(20.0));
+        assert!(!smoother.is_smoothing());
+    }
 }
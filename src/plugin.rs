@@ -4,11 +4,12 @@
 use std::sync::Arc;
 
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, Editor, InitContext,
-    MidiConfig, Params, PluginState, ProcessContext, SysExMessage,
+    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, Editor, FactoryPreset,
+    InitContext, MidiConfig, Params, PluginState, ProcessContext, StateCodec, SysExMessage,
 };
 
 pub mod clap;
+pub mod migration;
 #[cfg(feature = "vst3")]
 pub mod vst3;
 
@@ -77,6 +78,12 @@ pub trait Plugin: Default + Send + 'static {
     /// auxiliary input and output ports, if the plugin has any. If the slice is empty, then the
     /// plugin will not have any audio IO.
     ///
+    /// Ports with more than two channels, like surround or ambisonic ports, should also set an
+    /// explicit [`ChannelLayout`][crate::prelude::ChannelLayout] through the layout's
+    /// `channel_layouts` field so the host knows how the channels map to speakers. Use
+    /// [`AudioIOLayout::main_output_layout()`] and the related functions to query a port's layout
+    /// in [`initialize()`][Self::initialize()].
+    ///
     /// Both [`AudioIOLayout`] and [`PortNames`][crate::prelude::PortNames] have `.const_default()`
     /// functions for compile-time equivalents to `Default::default()`:
     ///
@@ -112,11 +119,40 @@ pub trait Plugin: Default + Send + 'static {
     /// every 64 samples.
     const SAMPLE_ACCURATE_AUTOMATION: bool = false;
 
+    /// Whether the plugin supports double precision processing. If this is set, then hosts that
+    /// offer 64-bit audio will call [`process_f64()`][Self::process_f64()] instead of
+    /// [`process()`][Self::process()], and the plugin must implement that function. Hosts may still
+    /// decide to use single precision processing, so `process()` needs to be implemented either
+    /// way. The standalone target always uses single precision processing.
+    const SUPPORTS_F64: bool = false;
+
+    /// If this is set to true, then NIH-plug implements the plugin's bypass. While the parameter
+    /// marked with [`BoolParam::make_bypass()`][crate::prelude::BoolParam::make_bypass()] is
+    /// enabled, the wrapper crossfades the plugin's main output to the unprocessed main input,
+    /// delayed by the plugin's reported latency so both signals stay aligned. The plugin's
+    /// `process()` function is still called while bypassed, so the plugin's state stays up to date
+    /// and disabling the bypass is just as seamless. The plugin needs to have a bypass parameter
+    /// when this is enabled.
+    const MANAGED_BYPASS: bool = false;
+
     /// If this is set to true, then the plugin will report itself as having a hard realtime
     /// processing requirement when the host asks for it. Supported hosts will never ask the plugin
     /// to do offline processing.
     const HARD_REALTIME_ONLY: bool = false;
 
+    /// The encoding used when the host saves the plugin's state. [`StateCodec::Binary`] results
+    /// in much smaller states for plugins that persist large amounts of data through `#[persist]`
+    /// fields. States are decoded regardless of which codec was used to save them, so this can be
+    /// changed in a later version of the plugin without breaking existing projects.
+    const STATE_CODEC: StateCodec = StateCodec::Json;
+
+    /// Presets that are compiled into the plugin, usually using
+    /// `FactoryPreset::new(include_bytes!("../presets/Init.nihpreset"))`. These are listed next to
+    /// the user's own presets by [`PresetLibrary`][crate::preset::PresetLibrary] and the preset
+    /// browser widgets in the GUI adapter crates. See the [`preset`][crate::preset] module for more
+    /// information on the file format.
+    const FACTORY_PRESETS: &'static [FactoryPreset] = &[];
+
     /// The plugin's SysEx message type if it supports sending or receiving MIDI SysEx messages, or
     /// `()` if it does not. This type can be a struct or enum wrapping around one or more message
     /// types, and the [`SysExMessage`] trait is then used to convert between this type and basic
@@ -173,11 +209,32 @@ pub trait Plugin: Default + Send + 'static {
     /// with default values that would otherwise change the sound of a preset. Keep in mind that
     /// automation may still be broken in the first two use cases.
     ///
+    /// The most common migrations can be declared using
+    /// [`StateMigrations`][migration::StateMigrations], which takes care of comparing versions.
+    ///
     /// # Note
     ///
     /// This is an advanced feature that the vast majority of plugins won't need to implement.
     fn filter_state(state: &mut PluginState) {}
 
+    /// Called when the host requests an audio IO layout that's not part of
+    /// [`AUDIO_IO_LAYOUTS`][Self::AUDIO_IO_LAYOUTS]. Channel-agnostic plugins, like effects that
+    /// process every channel in the same way, can use this to run on any number of channels without
+    /// having to list every possible layout. Return `true` to accept the layout. An accepted layout
+    /// is passed to [`initialize()`][Self::initialize()] like any other layout.
+    ///
+    /// The requested layout is the currently active layout with the main input and output ports'
+    /// channel counts and [`ChannelLayout`][crate::prelude::ChannelLayout]s replaced by the host's
+    /// request. The number of ports and the auxiliary ports' channel counts cannot be changed this
+    /// way. This is supported through CLAP's `configurable-audio-ports` extension and VST3's speaker
+    /// arrangement negotiation.
+    ///
+    /// This function is called before the plugin is initialized, and it does not have access to
+    /// the plugin instance.
+    fn accept_audio_io_layout(layout: &AudioIOLayout) -> bool {
+        false
+    }
+
     //
     // The following functions follow the lifetime of the plugin.
     //
@@ -195,6 +252,10 @@ pub trait Plugin: Default + Send + 'static {
     ///   values to your plugin instance's object.
     /// - If the plugin is being restored from an old state,
     ///   then that state will have already been restored at this point.
+    /// - If restoring that state requires expensive work like loading sample files, then that work
+    ///   can be moved to a background thread using
+    ///   [`InitContext::begin_state_load()`][crate::prelude::InitContext::begin_state_load()] and
+    ///   [`InitContext::execute_background()`][crate::prelude::InitContext::execute_background()].
     /// - If based on those parameters (or for any reason whatsoever) the plugin needs to introduce
     ///   latency, then you can do so here using the process context.
     /// - Depending on how the host restores plugin state, this function may be called multiple
@@ -232,10 +293,9 @@ pub trait Plugin: Default + Send + 'static {
     ///
     /// The `context` object contains context information as well as callbacks for working with note
     /// events. The [`AuxiliaryBuffers`] contain the plugin's sidechain input buffers and
-    /// auxiliary output buffers if it has any.
-    ///
-    /// TODO: Provide a way to access auxiliary input channels if the IO configuration is
-    ///       asymmetric
+    /// auxiliary output buffers if it has any. If the main input and output ports have different
+    /// channel counts, then the full main input is also available through
+    /// [`AuxiliaryBuffers::main_input`].
     fn process(
         &mut self,
         buffer: &mut Buffer,
@@ -243,6 +303,34 @@ pub trait Plugin: Default + Send + 'static {
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus;
 
+    /// The same as [`process()`][Self::process()], but for double precision audio. This is only
+    /// called if [`SUPPORTS_F64`][Self::SUPPORTS_F64] is set and the host decided to process audio
+    /// in double precision. The sample type can be made generic using the
+    /// [`Sample`][crate::prelude::Sample] trait to share the implementation with `process()`:
+    ///
+    /// ```ignore
+    /// fn process_f64(
+    ///     &mut self,
+    ///     buffer: &mut Buffer<f64>,
+    ///     aux: &mut AuxiliaryBuffers<f64>,
+    ///     context: &mut impl ProcessContext<Self>,
+    /// ) -> ProcessStatus {
+    ///     self.process_generic(buffer, aux, context)
+    /// }
+    /// ```
+    fn process_f64(
+        &mut self,
+        buffer: &mut Buffer<f64>,
+        aux: &mut AuxiliaryBuffers<f64>,
+        context: &mut impl ProcessContext<Self>,
+    ) -> ProcessStatus {
+        nih_debug_assert_failure!(
+            "'Plugin::SUPPORTS_F64' is set but 'Plugin::process_f64()' is not implemented"
+        );
+
+        ProcessStatus::Error("Double precision processing is not implemented")
+    }
+
     /// Called when the plugin is deactivated. The host will call
     /// [`initialize()`][Self::initialize()] again before the plugin resumes processing audio. These
     /// two functions will not be called when the host only temporarily stops processing audio. You
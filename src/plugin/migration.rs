@@ -0,0 +1,463 @@
+//! Declarative migrations for old plugin state. See [`StateMigrations`] for more information.
+
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+use crate::params::persist::{deserialize_field, serialize_field};
+use crate::prelude::PluginState;
+use crate::wrapper::state::ParamValue;
+
+/// A list of versioned migrations for a plugin's [`PluginState`]. This is meant to be used from
+/// [`Plugin::filter_state()`][crate::prelude::Plugin::filter_state()] so a plugin's `Params` can
+/// change between releases without breaking old presets and projects:
+///
+/// ```
+/// # use nih_plug::prelude::*;
+/// # use nih_plug::plugin::migration::StateMigrations;
+/// fn filter_state(state: &mut PluginState) {
+///     StateMigrations::new()
+///         // Version 1.1.0 renamed the gain parameter and swapped the filter mode enum's variants
+///         .migration("1.1.0", |m| {
+///             m.rename_param("gain", "input_gain")
+///                 .remap_enum_index("mode", [(0, 1), (1, 0)])
+///         })
+///         // Version 2.0.0 changed the cutoff parameter's range and the curve's representation
+///         .migration("2.0.0", |m| {
+///             m.rescale_param("cutoff", 0.0..=1.0, 20.0..=20_000.0)
+///                 .convert_field("curve", |points| {
+///                     serde_json::json!({ "points": points })
+///                 })
+///         })
+///         .apply(state);
+/// }
+/// ```
+///
+/// A migration is applied when the state's [`version`][PluginState::version] is lower than the
+/// migration's version. Versions are compared using semantic versioning rules, and states without
+/// a version are treated as version `0.0.0`. Migrations are applied in version order, and after
+/// each migration the state's version is set to the migration's version. This means that applying
+/// the same migrations to a state multiple times is safe.
+#[derive(Default)]
+pub struct StateMigrations {
+    migrations: Vec<Migration>,
+}
+
+/// A single migration consisting of one or more steps. Created through
+/// [`StateMigrations::migration()`].
+pub struct Migration {
+    version: Version,
+    steps: Vec<MigrationStep>,
+}
+
+/// A single change applied to a [`PluginState`] as part of a [`Migration`].
+enum MigrationStep {
+    RenameParam {
+        from: String,
+        to: String,
+    },
+    RemapEnumIndex {
+        param: String,
+        mapping: Vec<(i32, i32)>,
+    },
+    RescaleParam {
+        param: String,
+        from: RangeInclusive<f32>,
+        to: RangeInclusive<f32>,
+    },
+    ConvertField {
+        field: String,
+        convert: Box<dyn Fn(serde_json::Value) -> serde_json::Value>,
+    },
+}
+
+/// A parsed semantic version, used to compare the version stored in a [`PluginState`] with a
+/// migration's version. Versions consist of a major, minor, and patch version number, an optional
+/// pre-release suffix, and optional build metadata. Pre-release versions are ordered before the
+/// release they belong to, and build metadata is ignored during comparisons.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Version {
+    /// The major version number. Incremented for incompatible changes.
+    pub major: u64,
+    /// The minor version number. Incremented for backwards compatible additions.
+    pub minor: u64,
+    /// The patch version number. Incremented for backwards compatible bug fixes.
+    pub patch: u64,
+    /// The pre-release suffix, without the leading hyphen. Empty for regular releases.
+    pub pre: String,
+}
+
+impl StateMigrations {
+    /// Create an empty set of migrations. Add migrations using
+    /// [`migration()`][Self::migration()].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a migration for states saved with versions lower than `version`. The closure can add
+    /// steps to the migration using the methods on [`Migration`]. The migration's steps are applied
+    /// in the order they're added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` is not a valid semantic version.
+    pub fn migration(mut self, version: &str, build: impl FnOnce(Migration) -> Migration) -> Self {
+        let version = Version::parse(version)
+            .unwrap_or_else(|| panic!("'{version}' is not a valid semantic version"));
+        self.migrations.push(build(Migration {
+            version,
+            steps: Vec::new(),
+        }));
+
+        self
+    }
+
+    /// Apply all migrations whose version is higher than the state's version. Returns `true` if
+    /// any migrations were applied. If the state's version cannot be parsed, then the state is left
+    /// unchanged.
+    pub fn apply(&self, state: &mut PluginState) -> bool {
+        let mut state_version = if state.version.is_empty() {
+            Version::default()
+        } else {
+            match Version::parse(&state.version) {
+                Some(version) => version,
+                None => {
+                    nih_debug_assert_failure!(
+                        "Cannot migrate state with unknown version '{}'",
+                        state.version
+                    );
+                    return false;
+                }
+            }
+        };
+
+        let mut migrations: Vec<&Migration> = self.migrations.iter().collect();
+        migrations.sort_by(|a, b| a.version.cmp(&b.version));
+
+        let mut migrated = false;
+        for migration in migrations {
+            if state_version >= migration.version {
+                continue;
+            }
+
+            nih_trace!(
+                "Migrating state from version {state_version} to {}",
+                migration.version
+            );
+            for step in &migration.steps {
+                step.apply(state);
+            }
+
+            state_version = migration.version.clone();
+            state.version = state_version.to_string();
+            migrated = true;
+        }
+
+        migrated
+    }
+}
+
+impl Migration {
+    /// Rename a parameter from `from` to `to`. This replaces any existing value for `to`.
+    pub fn rename_param(mut self, from: &str, to: &str) -> Self {
+        self.steps.push(MigrationStep::RenameParam {
+            from: from.to_owned(),
+            to: to.to_owned(),
+        });
+        self
+    }
+
+    /// Change the variant indices of an enum parameter that was stored by index. `mapping`
+    /// contains `(old_index, new_index)` pairs, and indices that don't appear in the mapping are
+    /// left unchanged. Enum parameters that are stored using stable `#[id = "..."]` string IDs are
+    /// not affected.
+    pub fn remap_enum_index(
+        mut self,
+        param: &str,
+        mapping: impl IntoIterator<Item = (i32, i32)>,
+    ) -> Self {
+        self.steps.push(MigrationStep::RemapEnumIndex {
+            param: param.to_owned(),
+            mapping: mapping.into_iter().collect(),
+        });
+        self
+    }
+
+    /// Linearly map a float or integer parameter's plain value from the `from` range to the `to`
+    /// range. Integer values are rounded to the nearest integer after rescaling.
+    pub fn rescale_param(
+        mut self,
+        param: &str,
+        from: RangeInclusive<f32>,
+        to: RangeInclusive<f32>,
+    ) -> Self {
+        nih_debug_assert!(
+            from.start() != from.end(),
+            "The source range for '{}' is empty",
+            param
+        );
+
+        self.steps.push(MigrationStep::RescaleParam {
+            param: param.to_owned(),
+            from,
+            to,
+        });
+        self
+    }
+
+    /// Convert the JSON value of a `#[persist = "..."]` field. The closure receives the field's
+    /// deserialized JSON value and should return the new value. This is useful when the field's
+    /// type has changed in a way serde can't deserialize automatically.
+    pub fn convert_field(
+        mut self,
+        field: &str,
+        convert: impl Fn(serde_json::Value) -> serde_json::Value + 'static,
+    ) -> Self {
+        self.steps.push(MigrationStep::ConvertField {
+            field: field.to_owned(),
+            convert: Box::new(convert),
+        });
+        self
+    }
+}
+
+impl MigrationStep {
+    fn apply(&self, state: &mut PluginState) {
+        match self {
+            MigrationStep::RenameParam { from, to } => {
+                if let Some(value) = state.params.remove(from) {
+                    state.params.insert(to.clone(), value);
+                }
+            }
+            MigrationStep::RemapEnumIndex { param, mapping } => {
+                if let Some(ParamValue::I32(index)) = state.params.get_mut(param) {
+                    if let Some((_, new_index)) = mapping.iter().find(|(old, _)| old == index) {
+                        *index = *new_index;
+                    }
+                }
+            }
+            MigrationStep::RescaleParam { param, from, to } => {
+                let rescale = |value: f32| {
+                    let t = (value - from.start()) / (from.end() - from.start());
+                    to.start() + t * (to.end() - to.start())
+                };
+
+                match state.params.get_mut(param) {
+                    Some(ParamValue::F32(value)) => *value = rescale(*value),
+                    Some(ParamValue::I32(value)) => *value = rescale(*value as f32).round() as i32,
+                    Some(value) => nih_debug_assert_failure!(
+                        "Cannot rescale parameter '{}' with value {:?}",
+                        param,
+                        value
+                    ),
+                    None => (),
+                }
+            }
+            MigrationStep::ConvertField { field, convert } => {
+                if let Some(serialized) = state.fields.get_mut(field) {
+                    match deserialize_field(serialized) {
+                        Ok(value) => match serialize_field(&convert(value)) {
+                            Ok(converted) => *serialized = converted,
+                            Err(err) => nih_debug_assert_failure!(
+                                "Could not serialize converted field '{}': {}",
+                                field,
+                                err
+                            ),
+                        },
+                        Err(err) => nih_debug_assert_failure!(
+                            "Could not parse field '{}' as JSON: {}",
+                            field,
+                            err
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Version {
+    /// Parse a version string like `1.2.3`, `1.2.3-beta.1`, or `1.2.3+build`. A leading `v` is
+    /// allowed, and the minor and patch versions may be omitted. Returns `None` if the string is
+    /// not a valid version.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.strip_prefix('v').unwrap_or(version);
+        // Build metadata does not affect the version's precedence
+        let version = version
+            .split_once('+')
+            .map_or(version, |(version, _)| version);
+        let (numbers, pre) = version.split_once('-').unwrap_or((version, ""));
+
+        let mut numbers = numbers.split('.').map(|number| number.parse::<u64>().ok());
+        let major = numbers.next()??;
+        let minor = numbers.next().unwrap_or(Some(0))?;
+        let patch = numbers.next().unwrap_or(Some(0))?;
+        if numbers.next().is_some() {
+            return None;
+        }
+
+        Some(Self {
+            major,
+            minor,
+            patch,
+            pre: pre.to_owned(),
+        })
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                // Pre-releases come before the actual release
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => compare_pre_release(&self.pre, &other.pre),
+            })
+    }
+}
+
+/// Compare two pre-release suffixes using semver's precedence rules. Numeric identifiers are
+/// compared numerically and have a lower precedence than alphanumeric identifiers.
+fn compare_pre_release(a: &str, b: &str) -> Ordering {
+    let mut a_identifiers = a.split('.');
+    let mut b_identifiers = b.split('.');
+    loop {
+        match (a_identifiers.next(), b_identifiers.next()) {
+            (Some(a), Some(b)) => {
+                let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    (Ok(_), Err(_)) => Ordering::Less,
+                    (Err(_), Ok(_)) => Ordering::Greater,
+                    (Err(_), Err(_)) => a.cmp(b),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    fn make_migrations() -> StateMigrations {
+        // These are intentionally added out of order
+        StateMigrations::new()
+            .migration("2.0.0", |m| {
+                m.rescale_param("cutoff", 0.0..=1.0, 20.0..=20_000.0)
+                    .rescale_param("voices", 0.0..=4.0, 0.0..=8.0)
+                    .convert_field("curve", |points| serde_json::json!({ "points": points }))
+            })
+            .migration("1.1.0", |m| {
+                m.rename_param("gain", "input_gain")
+                    .remap_enum_index("mode", [(0, 1), (1, 0)])
+            })
+    }
+
+    fn make_old_state() -> PluginState {
+        PluginState {
+            version: String::from("1.0.2"),
+            params: BTreeMap::from([
+                (String::from("gain"), ParamValue::F32(0.5)),
+                (String::from("mode"), ParamValue::I32(0)),
+                (String::from("cutoff"), ParamValue::F32(0.5)),
+                (String::from("voices"), ParamValue::I32(3)),
+            ]),
+            fields: BTreeMap::from([(String::from("curve"), String::from("[1,2,3]"))]),
+            blobs: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn version_ordering() {
+        assert!(version("1.0.0") < version("1.0.1"));
+        assert!(version("1.9.0") < version("1.10.0"));
+        assert!(version("1.0.0-alpha") < version("1.0.0"));
+        assert!(version("1.0.0-alpha") < version("1.0.0-alpha.1"));
+        assert!(version("1.0.0-alpha.2") < version("1.0.0-alpha.10"));
+        assert!(version("1.0.0-alpha.1") < version("1.0.0-beta"));
+        assert_eq!(version("v1.2"), version("1.2.0+build.5"));
+        assert_eq!(version("1.2.3-rc.1").to_string(), "1.2.3-rc.1");
+        assert!(Version::parse("1.2.3.4").is_none());
+        assert!(Version::parse("one").is_none());
+    }
+
+    #[test]
+    fn old_state_is_migrated() {
+        let mut state = make_old_state();
+        assert!(make_migrations().apply(&mut state));
+
+        assert_eq!(state.version, "2.0.0");
+        assert!(!state.params.contains_key("gain"));
+        assert!(matches!(state.params["input_gain"], ParamValue::F32(v) if v == 0.5));
+        assert!(matches!(state.params["mode"], ParamValue::I32(1)));
+        assert!(matches!(state.params["cutoff"], ParamValue::F32(v) if v == 10_010.0));
+        assert!(matches!(state.params["voices"], ParamValue::I32(6)));
+        assert_eq!(state.fields["curve"], r#"{"points":[1,2,3]}"#);
+    }
+
+    #[test]
+    fn partial_migration() {
+        let mut state = make_old_state();
+        state.version = String::from("1.5.0");
+        assert!(make_migrations().apply(&mut state));
+
+        // Only the 2.0.0 migration should have been applied
+        assert!(state.params.contains_key("gain"));
+        assert!(matches!(state.params["mode"], ParamValue::I32(0)));
+        assert!(matches!(state.params["voices"], ParamValue::I32(6)));
+    }
+
+    #[test]
+    fn migrated_state_round_trips() {
+        let migrations = make_migrations();
+        let mut state = make_old_state();
+        migrations.apply(&mut state);
+
+        // Saving and loading the migrated state again should not cause it to be migrated again
+        let json = serde_json::to_string(&state).unwrap();
+        let mut reloaded: PluginState = serde_json::from_str(&json).unwrap();
+        assert!(!migrations.apply(&mut reloaded));
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), json);
+    }
+
+    #[test]
+    fn unversioned_state() {
+        let mut state = make_old_state();
+        state.version = String::new();
+        assert!(make_migrations().apply(&mut state));
+        assert_eq!(state.version, "2.0.0");
+    }
+}
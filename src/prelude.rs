@@ -14,31 +14,40 @@ pub use crate::formatters;
 pub use crate::util;
 
 pub use crate::audio_setup::{
-    new_nonzero_u32, AudioIOLayout, AuxiliaryBuffers, BufferConfig, PortNames, ProcessMode,
+    new_nonzero_u32, AmbisonicLayout, AmbisonicNormalization, AmbisonicOrdering, AudioIOLayout,
+    AuxiliaryBuffers, BufferConfig, ChannelLayout, PortChannelLayouts, PortNames, ProcessMode,
+    Speaker,
+};
+pub use crate::buffer::{Buffer, EventBlockItem, Sample};
+pub use crate::context::gui::{
+    AbSlot, AsyncExecutor, GuiContext, ParamAutomationState, ParamIndication, ParamMapping,
+    ParamSetter,
 };
-pub use crate::buffer::Buffer;
-pub use crate::context::gui::{AsyncExecutor, GuiContext, ParamSetter};
 pub use crate::context::init::InitContext;
 pub use crate::context::process::{ProcessContext, Transport};
 pub use crate::context::remote_controls::{
     RemoteControlsContext, RemoteControlsPage, RemoteControlsSection,
 };
-pub use crate::context::PluginApi;
+pub use crate::context::{HostColor, PluginApi, TrackInfo, TrackKind};
 // This also includes the derive macro
 pub use crate::editor::{Editor, ParentWindowHandle};
 pub use crate::midi::sysex::SysExMessage;
 pub use crate::midi::{control_change, MidiConfig, NoteEvent, PluginNoteEvent};
 pub use crate::params::enums::{Enum, EnumParam};
 pub use crate::params::internals::ParamPtr;
+pub use crate::params::morph::ParamMorph;
 pub use crate::params::range::{FloatRange, IntRange};
-pub use crate::params::smoothing::{AtomicF32, Smoothable, Smoother, SmoothingStyle};
+pub use crate::params::smoothing::{AtomicF32, Smoothable, Smoother, SmootherView, SmoothingStyle};
 pub use crate::params::Params;
 pub use crate::params::{BoolParam, FloatParam, IntParam, Param, ParamFlags};
 pub use crate::plugin::clap::{ClapPlugin, PolyModulationConfig};
 #[cfg(feature = "vst3")]
 pub use crate::plugin::vst3::Vst3Plugin;
 pub use crate::plugin::{Plugin, ProcessStatus, TaskExecutor};
+pub use crate::preset::{FactoryPreset, PresetFile, PresetMetadata};
 pub use crate::wrapper::clap::features::ClapFeature;
-pub use crate::wrapper::state::PluginState;
+pub use crate::wrapper::state::{
+    PluginState, StateCodec, StateContext, StateLoadBehavior, StateLoadHandle,
+};
 #[cfg(feature = "vst3")]
 pub use crate::wrapper::vst3::subcategories::Vst3SubCategory;
@@ -0,0 +1,282 @@
+//! A versioned on-disk format for plugin presets. Preset files contain a [`PluginState`] together
+//! with some metadata about the preset and the plugin that created it, and they can be saved and
+//! loaded from the plugin's editor through [`GuiContext::save_preset()`] and
+//! [`GuiContext::load_preset()`]. The standalone wrapper can load a preset on startup using the
+//! `--preset` command line option.
+//!
+//! # Format
+//!
+//! Preset files are UTF-8 encoded JSON files with the [`PRESET_FILE_EXTENSION`] file extension.
+//! The top level object contains the following fields:
+//!
+//! - `format_version`: The version of the preset file format, currently [`PRESET_FORMAT_VERSION`].
+//!   Files with a newer format version are rejected.
+//! - `plugin_id`: The ID of the plugin the preset belongs to, see [`plugin_id()`]. Presets for
+//!   other plugins are rejected.
+//! - `plugin_version`: The version of the plugin that saved the preset.
+//! - `name`, `author`, `category`, and `tags`: The preset's [`PresetMetadata`]. Everything but the
+//!   name may be omitted.
+//! - `state`: The preset's [`PluginState`], in the same format NIH-plug uses for CLAP and VST3
+//!   state.
+//!
+//! If the plugin has `#[persist_blob]` fields, then the preset's JSON data is followed by the raw
+//! bytes of those blobs. These files start with a short binary header instead of the JSON object.
+//!
+//! When a preset gets loaded, the state goes through [`Plugin::filter_state()`] so presets saved
+//! with older versions of the plugin can be migrated the same way as the plugin's regular state.
+//!
+//! # Preset libraries
+//!
+//! A [`PresetLibrary`] lists both the plugin's factory presets, which are compiled into the plugin
+//! through [`Plugin::FACTORY_PRESETS`], and the user's presets stored in the plugin's
+//! [`user_preset_dir()`]. The [`PresetNavigator`] builds on top of that to implement searching,
+//! filtering by category, and stepping through presets. The GUI adapter crates use these to
+//! implement their preset browser widgets.
+//!
+//! [`GuiContext::save_preset()`]: crate::prelude::GuiContext::save_preset()
+//! [`GuiContext::load_preset()`]: crate::prelude::GuiContext::load_preset()
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::prelude::{Plugin, PluginState};
+use crate::wrapper::state::blobs;
+
+mod library;
+
+pub use library::{
+    user_preset_dir, FactoryPreset, PresetEntry, PresetLibrary, PresetNavigator, PresetSource,
+};
+
+/// The current version of the preset file format. This is incremented whenever the format changes
+/// in a way older versions of NIH-plug can't read.
+pub const PRESET_FORMAT_VERSION: u32 = 1;
+
+/// The file extension used for preset files, without the leading period.
+pub const PRESET_FILE_EXTENSION: &str = "nihpreset";
+
+/// Information about a preset that's stored alongside the preset's state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PresetMetadata {
+    /// The preset's name.
+    pub name: String,
+    /// The preset's author. May be empty.
+    #[serde(default)]
+    pub author: String,
+    /// The category the preset is listed under in preset browsers, like `"Bass"` or `"Pads"`. May
+    /// be empty.
+    #[serde(default)]
+    pub category: String,
+    /// Free-form tags that can be used to categorize and search presets.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// The contents of a preset file. See the [module documentation][self] for a description of the
+/// format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetFile {
+    /// The version of the preset file format. This is [`PRESET_FORMAT_VERSION`] for newly created
+    /// presets.
+    pub format_version: u32,
+    /// The ID of the plugin this preset belongs to. See [`plugin_id()`].
+    pub plugin_id: String,
+    /// The version of the plugin that created this preset.
+    pub plugin_version: String,
+    /// The preset's name, author, and tags.
+    #[serde(flatten)]
+    pub metadata: PresetMetadata,
+    /// The plugin's state.
+    pub state: PluginState,
+}
+
+/// The ID stored in preset files to check whether a preset belongs to a plugin. This is derived
+/// from the plugin's vendor and name, so it's the same for all plugin formats.
+pub fn plugin_id<P: Plugin>() -> String {
+    format!("{}/{}", P::VENDOR, P::NAME)
+}
+
+impl PresetFile {
+    /// Create a preset for plugin `P` from a state object, for instance one obtained through
+    /// [`GuiContext::get_state()`][crate::prelude::GuiContext::get_state()].
+    pub fn new<P: Plugin>(metadata: PresetMetadata, state: PluginState) -> Self {
+        Self {
+            format_version: PRESET_FORMAT_VERSION,
+            plugin_id: plugin_id::<P>(),
+            plugin_version: String::from(P::VERSION),
+            metadata,
+            state,
+        }
+    }
+
+    /// Serialize the preset to the on-disk format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let json =
+            serde_json::to_vec_pretty(self).context("Could not format the preset as JSON")?;
+        if self.state.blobs.is_empty() {
+            Ok(json)
+        } else {
+            Ok(blobs::wrap(&json, &self.state.blobs))
+        }
+    }
+
+    /// Parse a preset from the on-disk format. This does not check whether the preset belongs to a
+    /// specific plugin, use [`check_plugin()`][Self::check_plugin()] for that.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let (json, blobs) = if blobs::is_container(bytes) {
+            blobs::unwrap(bytes).context("Could not read the preset's blobs")?
+        } else {
+            (bytes, Default::default())
+        };
+
+        let mut preset: Self =
+            serde_json::from_slice(json).context("Could not parse the preset")?;
+        preset.state.blobs = blobs;
+        if preset.format_version > PRESET_FORMAT_VERSION {
+            anyhow::bail!(
+                "The preset uses format version {}, but only versions up to {} are supported",
+                preset.format_version,
+                PRESET_FORMAT_VERSION
+            );
+        }
+
+        Ok(preset)
+    }
+
+    /// Write the preset to a file. Existing files are overwritten.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_bytes()?)
+            .with_context(|| format!("Could not write the preset to '{}'", path.display()))
+    }
+
+    /// Read a preset from a file. See [`from_bytes()`][Self::from_bytes()].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .with_context(|| format!("Could not read the preset from '{}'", path.display()))?;
+
+        Self::from_bytes(&bytes).with_context(|| format!("Could not load '{}'", path.display()))
+    }
+
+    /// Check whether this preset belongs to plugin `P`.
+    pub fn check_plugin<P: Plugin>(&self) -> Result<()> {
+        let expected_plugin_id = plugin_id::<P>();
+        if self.plugin_id != expected_plugin_id {
+            anyhow::bail!(
+                "The preset belongs to '{}' instead of '{}'",
+                self.plugin_id,
+                expected_plugin_id
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Save `state` for plugin `P` as a preset file. Used to implement
+/// [`GuiContext::save_preset()`][crate::prelude::GuiContext::save_preset()].
+pub(crate) fn save_preset<P: Plugin>(
+    path: &Path,
+    metadata: PresetMetadata,
+    state: PluginState,
+) -> Result<()> {
+    PresetFile::new::<P>(metadata, state).save(path)
+}
+
+/// Load a preset file for plugin `P`. The returned state still needs to be passed to
+/// [`deserialize_object()`][crate::wrapper::state::deserialize_object()], which also calls
+/// [`Plugin::filter_state()`]. Used to implement
+/// [`GuiContext::load_preset()`][crate::prelude::GuiContext::load_preset()].
+pub(crate) fn load_preset<P: Plugin>(path: &Path) -> Result<(PresetMetadata, PluginState)> {
+    let preset = PresetFile::load(path)?;
+    preset.check_plugin::<P>()?;
+
+    Ok((preset.metadata, preset.state))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::wrapper::state::ParamValue;
+
+    fn make_preset() -> PresetFile {
+        PresetFile {
+            format_version: PRESET_FORMAT_VERSION,
+            plugin_id: String::from("Vendor/Plugin"),
+            plugin_version: String::from("1.2.3"),
+            metadata: PresetMetadata {
+                name: String::from("Init"),
+                author: String::from("Someone"),
+                category: String::from("Bass"),
+                tags: vec![String::from("bass")],
+            },
+            state: PluginState {
+                version: String::from("1.2.3"),
+                params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.5))]),
+                fields: BTreeMap::from([(String::from("field"), String::from("[1,2]"))]),
+                blobs: BTreeMap::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let preset = make_preset();
+        let bytes = preset.to_bytes().unwrap();
+        let decoded = PresetFile::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.plugin_id, preset.plugin_id);
+        assert_eq!(decoded.plugin_version, preset.plugin_version);
+        assert_eq!(decoded.metadata, preset.metadata);
+        assert!(matches!(
+            decoded.state.params.get("gain"),
+            Some(ParamValue::F32(v)) if *v == 0.5
+        ));
+        assert_eq!(decoded.state.fields, preset.state.fields);
+    }
+
+    #[test]
+    fn round_trip_blobs() {
+        let mut preset = make_preset();
+        preset
+            .state
+            .blobs
+            .insert(String::from("samples"), vec![0, 255, 128]);
+        let bytes = preset.to_bytes().unwrap();
+        let decoded = PresetFile::from_bytes(&bytes).unwrap();
+
+        assert_eq!(decoded.metadata, preset.metadata);
+        assert_eq!(decoded.state.blobs, preset.state.blobs);
+    }
+
+    #[test]
+    fn optional_metadata() {
+        let json = br#"{
+            "format_version": 1,
+            "plugin_id": "Vendor/Plugin",
+            "plugin_version": "1.0.0",
+            "name": "Init",
+            "state": { "params": {}, "fields": {} }
+        }"#;
+        let preset = PresetFile::from_bytes(json).unwrap();
+
+        assert_eq!(preset.metadata.name, "Init");
+        assert!(preset.metadata.author.is_empty());
+        assert!(preset.metadata.category.is_empty());
+        assert!(preset.metadata.tags.is_empty());
+    }
+
+    #[test]
+    fn newer_format_is_rejected() {
+        let mut preset = make_preset();
+        preset.format_version = PRESET_FORMAT_VERSION + 1;
+        let bytes = preset.to_bytes().unwrap();
+
+        assert!(PresetFile::from_bytes(&bytes).is_err());
+    }
+}
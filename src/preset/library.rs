@@ -0,0 +1,717 @@
+//! Listing, searching, and saving the presets available to a plugin. See the
+//! [module documentation][super] for an overview.
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{plugin_id, PresetFile, PresetMetadata, PRESET_FILE_EXTENSION, PRESET_FORMAT_VERSION};
+use crate::prelude::{GuiContext, Plugin, PluginState};
+
+/// A preset file that's compiled into the plugin. See
+/// [`Plugin::FACTORY_PRESETS`][crate::prelude::Plugin::FACTORY_PRESETS].
+#[derive(Debug, Clone, Copy)]
+pub struct FactoryPreset {
+    data: &'static [u8],
+}
+
+/// Where a [`PresetEntry`] was loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PresetSource {
+    /// A preset compiled into the plugin. This is the preset's index in
+    /// [`Plugin::FACTORY_PRESETS`][crate::prelude::Plugin::FACTORY_PRESETS].
+    Factory(usize),
+    /// A preset file in the user's preset directory.
+    User(PathBuf),
+}
+
+/// A single preset listed by a [`PresetLibrary`].
+#[derive(Debug, Clone)]
+pub struct PresetEntry {
+    /// The preset's name, author, category, and tags.
+    pub metadata: PresetMetadata,
+    /// Where the preset was loaded from.
+    pub source: PresetSource,
+}
+
+/// The factory presets and user presets available to a plugin. The presets are sorted by category
+/// and then by name. Only the presets' metadata is kept in memory, the state is read from the
+/// preset file again when loading a preset.
+#[derive(Debug, Clone)]
+pub struct PresetLibrary {
+    plugin_id: String,
+    plugin_version: String,
+    user_dir: Option<PathBuf>,
+    factory_presets: &'static [FactoryPreset],
+
+    presets: Vec<PresetEntry>,
+}
+
+/// Keeps track of the currently selected preset, the search query, and the selected category for a
+/// preset browser. This contains all of the logic needed for a preset browser so the preset browser
+/// widgets in the GUI adapter crates only need to draw it.
+#[derive(Debug, Clone)]
+pub struct PresetNavigator {
+    library: PresetLibrary,
+
+    search: String,
+    category: Option<String>,
+    /// The index of the last loaded or saved preset in `library.presets`.
+    current: Option<usize>,
+    /// Indices into `library.presets` for the presets matching `search` and `category`.
+    visible: Vec<usize>,
+}
+
+/// The directory user presets for plugin `P` are stored in. This is
+/// `<data directory>/<vendor>/<plugin name>/Presets`, where the data directory is `%APPDATA%` on
+/// Windows, `~/Library/Application Support` on macOS, and `$XDG_DATA_HOME` or `~/.local/share` on
+/// Linux. Returns `None` if that directory could not be determined.
+pub fn user_preset_dir<P: Plugin>() -> Option<PathBuf> {
+    let data_dir = if cfg!(target_os = "windows") {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| Path::new(&home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+    }?;
+
+    Some(
+        data_dir
+            .join(sanitize_file_name(P::VENDOR))
+            .join(sanitize_file_name(P::NAME))
+            .join("Presets"),
+    )
+}
+
+impl FactoryPreset {
+    /// Embed a preset file's contents in the plugin. This is usually used together with
+    /// [`include_bytes!()`].
+    pub const fn new(data: &'static [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl PresetEntry {
+    /// Whether this is one of the plugin's factory presets. Factory presets cannot be overwritten.
+    pub fn is_factory(&self) -> bool {
+        matches!(self.source, PresetSource::Factory(_))
+    }
+
+    /// Whether the preset's name, author, category, or tags contain `query`, ignoring case.
+    /// `query` must already be lowercase.
+    fn matches(&self, query: &str) -> bool {
+        let metadata = &self.metadata;
+        query.is_empty()
+            || metadata.name.to_lowercase().contains(query)
+            || metadata.author.to_lowercase().contains(query)
+            || metadata.category.to_lowercase().contains(query)
+            || metadata
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(query))
+    }
+}
+
+impl PresetLibrary {
+    /// List `P`'s factory presets and the user presets in [`user_preset_dir()`].
+    pub fn new<P: Plugin>() -> Self {
+        Self::with_user_dir::<P>(user_preset_dir::<P>())
+    }
+
+    /// The same as [`new()`][Self::new()], but with a custom user preset directory. If the
+    /// directory is `None`, then only the factory presets are listed and user presets cannot be
+    /// saved.
+    pub fn with_user_dir<P: Plugin>(user_dir: Option<PathBuf>) -> Self {
+        Self::from_parts(
+            plugin_id::<P>(),
+            String::from(P::VERSION),
+            user_dir,
+            P::FACTORY_PRESETS,
+        )
+    }
+
+    fn from_parts(
+        plugin_id: String,
+        plugin_version: String,
+        user_dir: Option<PathBuf>,
+        factory_presets: &'static [FactoryPreset],
+    ) -> Self {
+        let mut library = Self {
+            plugin_id,
+            plugin_version,
+            user_dir,
+            factory_presets,
+
+            presets: Vec::new(),
+        };
+        library.rescan();
+
+        library
+    }
+
+    /// Scan the factory presets and the user preset directory again. Files that aren't valid
+    /// presets for this plugin are skipped.
+    pub fn rescan(&mut self) {
+        self.presets.clear();
+
+        for (idx, factory_preset) in self.factory_presets.iter().enumerate() {
+            match PresetFile::from_bytes(factory_preset.data) {
+                Ok(preset) if preset.plugin_id == self.plugin_id => {
+                    self.presets.push(PresetEntry {
+                        metadata: preset.metadata,
+                        source: PresetSource::Factory(idx),
+                    })
+                }
+                Ok(preset) => nih_debug_assert_failure!(
+                    "Factory preset {} belongs to '{}' instead of '{}'",
+                    idx,
+                    preset.plugin_id,
+                    self.plugin_id
+                ),
+                Err(err) => nih_debug_assert_failure!("Invalid factory preset {}: {:#}", idx, err),
+            }
+        }
+
+        if let Some(user_dir) = self.user_dir.clone() {
+            self.scan_dir(&user_dir);
+        }
+
+        self.presets.sort_by_cached_key(|entry| {
+            (
+                entry.metadata.category.to_lowercase(),
+                entry.metadata.name.to_lowercase(),
+                !entry.is_factory(),
+            )
+        });
+    }
+
+    /// Add the presets from `dir` and its subdirectories to the list.
+    fn scan_dir(&mut self, dir: &Path) {
+        // The user preset directory not existing yet is perfectly fine
+        let Ok(dir_entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in dir_entries.filter_map(Result::ok) {
+            // `DirEntry::file_type()` doesn't follow symlinks, so a symlink loop in the user's
+            // preset directory cannot cause us to recurse forever
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            let path = entry.path();
+            if file_type.is_dir() {
+                self.scan_dir(&path);
+            } else if path.extension().and_then(|ext| ext.to_str()) == Some(PRESET_FILE_EXTENSION) {
+                match PresetFile::load(&path) {
+                    Ok(preset) if preset.plugin_id == self.plugin_id => {
+                        self.presets.push(PresetEntry {
+                            metadata: preset.metadata,
+                            source: PresetSource::User(path),
+                        })
+                    }
+                    // Other plugins from the same vendor may share the directory structure
+                    Ok(_) => (),
+                    Err(err) => nih_warn!("Skipping invalid preset: {:#}", err),
+                }
+            }
+        }
+    }
+
+    /// All presets, sorted by category and then by name.
+    pub fn presets(&self) -> &[PresetEntry] {
+        &self.presets
+    }
+
+    /// The directory user presets are stored in, if any.
+    pub fn user_dir(&self) -> Option<&Path> {
+        self.user_dir.as_deref()
+    }
+
+    /// The non-empty categories used by the presets, in alphabetical order ignoring case.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories: Vec<&str> = self
+            .presets
+            .iter()
+            .map(|entry| entry.metadata.category.as_str())
+            .filter(|category| !category.is_empty())
+            .collect();
+        categories.sort_by_cached_key(|category| (category.to_lowercase(), *category));
+        categories.dedup();
+
+        categories
+    }
+
+    /// The indices of the presets whose name, author, category, or tags contain `query` (ignoring
+    /// case) and that are in `category`, if set.
+    pub fn filter(&self, query: &str, category: Option<&str>) -> Vec<usize> {
+        let query = query.trim().to_lowercase();
+        self.presets
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| {
+                category.map_or(true, |category| entry.metadata.category == category)
+                    && entry.matches(&query)
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Read the state of the preset at `index`. The state can be restored using
+    /// [`GuiContext::set_state()`], which also runs it through
+    /// [`Plugin::filter_state()`][crate::prelude::Plugin::filter_state()].
+    pub fn load(&self, index: usize) -> Result<PluginState> {
+        let entry = self
+            .presets
+            .get(index)
+            .with_context(|| format!("There is no preset with index {index}"))?;
+        let preset = match &entry.source {
+            PresetSource::Factory(idx) => PresetFile::from_bytes(self.factory_presets[*idx].data)?,
+            PresetSource::User(path) => PresetFile::load(path)?,
+        };
+        if preset.plugin_id != self.plugin_id {
+            anyhow::bail!(
+                "The preset belongs to '{}' instead of '{}'",
+                preset.plugin_id,
+                self.plugin_id
+            );
+        }
+
+        Ok(preset.state)
+    }
+
+    /// Save `state` as a user preset. The file name is derived from the preset's name. An existing
+    /// user preset with the exact same name is overwritten. If the file name is already taken by
+    /// anything else, for instance a preset whose name only differs in characters that can't be
+    /// used in file names, then a number is appended to the file name instead. Returns the new
+    /// preset's index.
+    pub fn save_user_preset(
+        &mut self,
+        metadata: PresetMetadata,
+        state: PluginState,
+    ) -> Result<usize> {
+        let user_dir = self
+            .user_dir
+            .clone()
+            .context("There is no user preset directory")?;
+        if metadata.name.trim().is_empty() {
+            anyhow::bail!("Presets need a name");
+        }
+
+        fs::create_dir_all(&user_dir)
+            .with_context(|| format!("Could not create '{}'", user_dir.display()))?;
+        let path = self.user_preset_path(&user_dir, &metadata.name);
+        PresetFile {
+            format_version: PRESET_FORMAT_VERSION,
+            plugin_id: self.plugin_id.clone(),
+            plugin_version: self.plugin_version.clone(),
+            metadata,
+            state,
+        }
+        .save(&path)?;
+
+        self.rescan();
+        self.index_of(&PresetSource::User(path))
+            .context("The saved preset could not be read back")
+    }
+
+    /// The path a user preset called `name` should be saved to. See
+    /// [`save_user_preset()`][Self::save_user_preset()].
+    fn user_preset_path(&self, user_dir: &Path, name: &str) -> PathBuf {
+        let file_name = sanitize_file_name(name);
+        (1..)
+            .map(|n| match n {
+                1 => user_dir.join(format!("{file_name}.{PRESET_FILE_EXTENSION}")),
+                n => user_dir.join(format!("{file_name} ({n}).{PRESET_FILE_EXTENSION}")),
+            })
+            .find(|path| {
+                !path.exists()
+                    || self.presets.iter().any(|entry| {
+                        entry.metadata.name == name
+                            && entry.source == PresetSource::User(path.clone())
+                    })
+            })
+            .expect("Ran out of preset file names")
+    }
+
+    /// The index of the preset loaded from `source`, if it's still part of the library.
+    pub fn index_of(&self, source: &PresetSource) -> Option<usize> {
+        self.presets
+            .iter()
+            .position(|entry| &entry.source == source)
+    }
+}
+
+impl PresetNavigator {
+    /// Create a navigator showing all of the library's presets, without any preset being selected.
+    pub fn new(library: PresetLibrary) -> Self {
+        let mut navigator = Self {
+            library,
+
+            search: String::new(),
+            category: None,
+            current: None,
+            visible: Vec::new(),
+        };
+        navigator.update_visible();
+
+        navigator
+    }
+
+    /// The underlying preset library.
+    pub fn library(&self) -> &PresetLibrary {
+        &self.library
+    }
+
+    /// Rescan the library's presets, keeping the current preset selected if it still exists.
+    pub fn rescan(&mut self) {
+        let current_source = self.current_preset().map(|entry| entry.source.clone());
+        self.library.rescan();
+        self.current = current_source.and_then(|source| self.library.index_of(&source));
+        self.update_visible();
+    }
+
+    /// The current search query.
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    /// Only show presets whose name, author, category, or tags contain `query`.
+    pub fn set_search(&mut self, query: impl Into<String>) {
+        self.search = query.into();
+        self.update_visible();
+    }
+
+    /// The selected category, or `None` if presets from all categories are shown.
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    /// Only show presets from `category`, or from all categories if this is `None`.
+    pub fn set_category(&mut self, category: Option<String>) {
+        self.category = category;
+        self.update_visible();
+    }
+
+    /// The indices of the presets matching the search query and the selected category, in the
+    /// order they should be displayed in. These indices can be used with
+    /// [`PresetLibrary::presets()`].
+    pub fn visible(&self) -> &[usize] {
+        &self.visible
+    }
+
+    /// The index of the last loaded or saved preset, if any.
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// The last loaded or saved preset, if any.
+    pub fn current_preset(&self) -> Option<&PresetEntry> {
+        self.current.map(|idx| &self.library.presets()[idx])
+    }
+
+    /// Load the preset at `index` and restore its state using [`GuiContext::set_state()`].
+    pub fn load(&mut self, index: usize, context: &dyn GuiContext) -> Result<()> {
+        let state = self.library.load(index)?;
+        context.set_state(state);
+        self.current = Some(index);
+
+        Ok(())
+    }
+
+    /// Load the visible preset after the current one, wrapping around at the end of the list.
+    /// Does nothing if no presets are visible.
+    pub fn load_next(&mut self, context: &dyn GuiContext) -> Result<()> {
+        match self.step(true) {
+            Some(index) => self.load(index, context),
+            None => Ok(()),
+        }
+    }
+
+    /// Load the visible preset before the current one, wrapping around at the start of the list.
+    /// Does nothing if no presets are visible.
+    pub fn load_previous(&mut self, context: &dyn GuiContext) -> Result<()> {
+        match self.step(false) {
+            Some(index) => self.load(index, context),
+            None => Ok(()),
+        }
+    }
+
+    /// Save the plugin's current state as a user preset called `name` in the selected category and
+    /// select the new preset. See [`PresetLibrary::save_user_preset()`].
+    pub fn save_as(&mut self, name: &str, context: &dyn GuiContext) -> Result<()> {
+        let metadata = PresetMetadata {
+            name: String::from(name.trim()),
+            category: self.category.clone().unwrap_or_default(),
+            ..Default::default()
+        };
+        let index = self
+            .library
+            .save_user_preset(metadata, context.get_state())?;
+        self.current = Some(index);
+        self.update_visible();
+
+        Ok(())
+    }
+
+    /// The index of the visible preset after or before the current one.
+    fn step(&self, forward: bool) -> Option<usize> {
+        let num_visible = self.visible.len();
+        if num_visible == 0 {
+            return None;
+        }
+
+        let position = self
+            .current
+            .and_then(|current| self.visible.iter().position(|&idx| idx == current));
+        let new_position = match (position, forward) {
+            (Some(position), true) => (position + 1) % num_visible,
+            (Some(position), false) => (position + num_visible - 1) % num_visible,
+            (None, true) => 0,
+            (None, false) => num_visible - 1,
+        };
+
+        Some(self.visible[new_position])
+    }
+
+    fn update_visible(&mut self) {
+        self.visible = self.library.filter(&self.search, self.category.as_deref());
+    }
+}
+
+/// Replace characters that can't be used in file names on common file systems.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim_matches('.');
+
+    if sanitized.is_empty() {
+        String::from("Preset")
+    } else {
+        String::from(sanitized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const PLUGIN_ID: &str = "Vendor/Plugin";
+
+    static FACTORY_PRESETS: &[FactoryPreset] = &[
+        FactoryPreset::new(
+            br#"{"format_version":1,"plugin_id":"Vendor/Plugin","plugin_version":"1.0.0",
+                "name":"Init","state":{"params":{},"fields":{}}}"#,
+        ),
+        FactoryPreset::new(
+            br#"{"format_version":1,"plugin_id":"Vendor/Plugin","plugin_version":"1.0.0",
+                "name":"Wobble","category":"Bass","tags":["dubstep"],
+                "state":{"params":{},"fields":{}}}"#,
+        ),
+    ];
+
+    /// A unique, initially empty user preset directory for a test.
+    fn temp_user_dir() -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = env::temp_dir().join(format!(
+            "nih-plug-preset-library-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+
+        dir
+    }
+
+    fn make_library(user_dir: Option<PathBuf>) -> PresetLibrary {
+        PresetLibrary::from_parts(
+            String::from(PLUGIN_ID),
+            String::from("1.0.0"),
+            user_dir,
+            FACTORY_PRESETS,
+        )
+    }
+
+    fn make_state() -> PluginState {
+        PluginState {
+            version: String::from("1.0.0"),
+            params: BTreeMap::new(),
+            fields: BTreeMap::from([(String::from("field"), String::from("1"))]),
+            blobs: BTreeMap::new(),
+        }
+    }
+
+    fn names(library: &PresetLibrary, indices: &[usize]) -> Vec<String> {
+        indices
+            .iter()
+            .map(|&idx| library.presets()[idx].metadata.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn factory_presets() {
+        let library = make_library(None);
+        let all: Vec<usize> = (0..library.presets().len()).collect();
+
+        assert_eq!(names(&library, &all), ["Init", "Wobble"]);
+        assert!(library.presets().iter().all(PresetEntry::is_factory));
+        assert_eq!(library.categories(), ["Bass"]);
+        assert!(library.load(1).is_ok());
+    }
+
+    #[test]
+    fn filter() {
+        let library = make_library(None);
+
+        assert_eq!(names(&library, &library.filter("WOB", None)), ["Wobble"]);
+        assert_eq!(
+            names(&library, &library.filter("dubstep", None)),
+            ["Wobble"]
+        );
+        assert_eq!(
+            names(&library, &library.filter("", Some("Bass"))),
+            ["Wobble"]
+        );
+        assert!(library.filter("init", Some("Bass")).is_empty());
+    }
+
+    #[test]
+    fn save_user_preset() {
+        let user_dir = temp_user_dir();
+        let mut library = make_library(Some(user_dir.clone()));
+
+        let metadata = PresetMetadata {
+            name: String::from("My: Preset"),
+            category: String::from("Bass"),
+            ..Default::default()
+        };
+        let index = library.save_user_preset(metadata, make_state()).unwrap();
+        let entry = &library.presets()[index];
+        assert_eq!(entry.metadata.name, "My: Preset");
+        assert_eq!(
+            entry.source,
+            PresetSource::User(user_dir.join("My_ Preset.nihpreset"))
+        );
+        assert_eq!(library.load(index).unwrap().fields, make_state().fields);
+
+        // The user preset is picked up again by a fresh scan
+        let library = make_library(Some(user_dir.clone()));
+        assert_eq!(library.presets().len(), 3);
+        assert_eq!(library.categories(), ["Bass"]);
+
+        fs::remove_dir_all(user_dir).unwrap();
+    }
+
+    #[test]
+    fn save_user_preset_file_name_collision() {
+        let user_dir = temp_user_dir();
+        let mut library = make_library(Some(user_dir.clone()));
+
+        let save = |library: &mut PresetLibrary, name: &str| {
+            let metadata = PresetMetadata {
+                name: String::from(name),
+                ..Default::default()
+            };
+            let index = library.save_user_preset(metadata, make_state()).unwrap();
+            library.presets()[index].source.clone()
+        };
+
+        let first = save(&mut library, "A/B");
+        assert_eq!(first, PresetSource::User(user_dir.join("A_B.nihpreset")));
+        let second = save(&mut library, "A:B");
+        assert_eq!(
+            second,
+            PresetSource::User(user_dir.join("A_B (2).nihpreset"))
+        );
+        assert_eq!(library.presets().len(), 4);
+
+        // Saving a preset with the exact same name replaces it
+        assert_eq!(save(&mut library, "A:B"), second);
+        assert_eq!(library.presets().len(), 4);
+
+        fs::remove_dir_all(user_dir).unwrap();
+    }
+
+    #[test]
+    fn categories_ignore_case() {
+        let user_dir = temp_user_dir();
+        let mut library = make_library(Some(user_dir.clone()));
+        for (name, category) in [("1", "keys"), ("2", "Pads"), ("3", "bass")] {
+            let metadata = PresetMetadata {
+                name: String::from(name),
+                category: String::from(category),
+                ..Default::default()
+            };
+            library.save_user_preset(metadata, make_state()).unwrap();
+        }
+
+        assert_eq!(library.categories(), ["Bass", "bass", "keys", "Pads"]);
+
+        fs::remove_dir_all(user_dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlink_loop() {
+        let user_dir = temp_user_dir();
+        let mut library = make_library(Some(user_dir.clone()));
+        let metadata = PresetMetadata {
+            name: String::from("Preset"),
+            ..Default::default()
+        };
+        library.save_user_preset(metadata, make_state()).unwrap();
+        std::os::unix::fs::symlink(".", user_dir.join("self")).unwrap();
+
+        // This would overflow the stack if the symlink was followed
+        let library = make_library(Some(user_dir.clone()));
+        assert_eq!(library.presets().len(), 3);
+
+        fs::remove_dir_all(user_dir).unwrap();
+    }
+
+    #[test]
+    fn save_without_user_dir() {
+        let mut library = make_library(None);
+        let metadata = PresetMetadata {
+            name: String::from("Preset"),
+            ..Default::default()
+        };
+
+        assert!(library.save_user_preset(metadata, make_state()).is_err());
+    }
+
+    #[test]
+    fn step_wraps_around() {
+        let mut navigator = PresetNavigator::new(make_library(None));
+        assert_eq!(navigator.step(true), Some(0));
+        assert_eq!(navigator.step(false), Some(1));
+
+        navigator.current = Some(1);
+        assert_eq!(navigator.step(true), Some(0));
+        assert_eq!(navigator.step(false), Some(0));
+
+        navigator.set_search("nothing matches this");
+        assert_eq!(navigator.step(true), None);
+    }
+
+    #[test]
+    fn sanitize() {
+        assert_eq!(sanitize_file_name(" a/b\\c? "), "a_b_c_");
+        assert_eq!(sanitize_file_name(".."), "Preset");
+    }
+}
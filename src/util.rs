@@ -1,8 +1,10 @@
 //! General conversion functions and utilities.
 
+mod latency;
 mod stft;
 pub mod window;
 
+pub use latency::{LatencyCheckResult, LatencyChecker, LatencyCompensator};
 pub use stft::StftHelper;
 
 pub const MINUS_INFINITY_DB: f32 = -100.0;
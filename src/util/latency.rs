@@ -0,0 +1,325 @@
+//! Utilities for compensating for and verifying a plugin's latency.
+
+use crate::buffer::{Buffer, Sample};
+
+use super::MINUS_INFINITY_GAIN;
+
+/// Delays a signal by a configurable number of samples to keep it time aligned with a plugin's
+/// processed signal. This is useful for dry signal paths, like bypassing or dry/wet mixing, in
+/// plugins that report latency through
+/// [`ProcessContext::set_latency_samples()`][crate::prelude::ProcessContext::set_latency_samples()].
+/// The latency can be changed from the audio thread without allocating, as long as it stays below
+/// the maximum latency the compensator was created with.
+pub struct LatencyCompensator<S: Sample = f32> {
+    /// One ring buffer for every channel. The length of these buffers is the current latency.
+    ring_buffers: Vec<Vec<S>>,
+    /// The current read and write position in the ring buffers.
+    current_pos: usize,
+    latency: usize,
+    max_latency: usize,
+}
+
+/// The result of a latency measurement produced by [`LatencyChecker::check_output()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LatencyCheckResult {
+    /// The plugin's output peaked exactly at the reported latency.
+    Matches { latency: u32 },
+    /// The plugin's output peaked at a different position than the reported latency.
+    Mismatch { reported: u32, measured: u32 },
+    /// The plugin did not produce any audible output in response to the impulse.
+    NoOutput,
+}
+
+/// Measures a plugin's actual latency so it can be compared to the reported latency. This works
+/// by replacing the plugin's main input with an impulse at the start of every period, and then
+/// looking for the loudest output sample within that period. The measurement is only meaningful
+/// for plugins that preserve an impulse's peak position, and the latency needs to be shorter than
+/// the period. This is used by the standalone target's `--check-latency` option, but it can also be
+/// used in a plugin's own tests.
+pub struct LatencyChecker {
+    /// The number of samples between two impulses.
+    period: usize,
+    /// The position within the current period. The impulse is written at position 0.
+    current_pos: usize,
+    /// The position and magnitude of the loudest output sample in the current period, if there was
+    /// any output at all.
+    peak: Option<(usize, f32)>,
+    /// The last result returned from `check_output()`. Used to only report changes.
+    last_result: Option<LatencyCheckResult>,
+}
+
+impl<S: Sample> LatencyCompensator<S> {
+    /// Create a latency compensator for `num_channels` channels that can delay the signal by up to
+    /// `max_latency` samples. The latency starts out at zero, call
+    /// [`set_latency()`][Self::set_latency()] to change it.
+    pub fn new(num_channels: usize, max_latency: usize) -> Self {
+        Self {
+            ring_buffers: (0..num_channels)
+                .map(|_| Vec::with_capacity(max_latency))
+                .collect(),
+            current_pos: 0,
+            latency: 0,
+            max_latency,
+        }
+    }
+
+    /// Change the latency in samples. If the latency changes, then the delay line is cleared, so
+    /// the next `latency` samples of output will be silent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `latency > max_latency`.
+    pub fn set_latency(&mut self, latency: u32) {
+        let latency = latency as usize;
+        assert!(latency <= self.max_latency);
+        if latency == self.latency {
+            return;
+        }
+
+        for ring_buffer in &mut self.ring_buffers {
+            ring_buffer.clear();
+            ring_buffer.resize(latency, S::ZERO);
+        }
+        self.current_pos = 0;
+        self.latency = latency;
+    }
+
+    /// The current latency in samples.
+    pub fn latency(&self) -> u32 {
+        self.latency as u32
+    }
+
+    /// The maximum latency supported by this instance.
+    pub fn max_latency(&self) -> usize {
+        self.max_latency
+    }
+
+    /// The number of channels this `LatencyCompensator` was configured for.
+    pub fn num_channels(&self) -> usize {
+        self.ring_buffers.len()
+    }
+
+    /// Clear the delay line without changing the latency.
+    pub fn reset(&mut self) {
+        for ring_buffer in &mut self.ring_buffers {
+            ring_buffer.fill(S::ZERO);
+        }
+        self.current_pos = 0;
+    }
+
+    /// Delay the audio in `buffer` in place by the current latency.
+    pub fn process(&mut self, buffer: &mut Buffer<S>) {
+        self.process_slices(buffer.as_slice());
+    }
+
+    /// The same as [`process()`][Self::process()], but for raw channel slices or vectors. All
+    /// channels need to have the same length. Channels past `num_channels` are left untouched.
+    pub fn process_slices<C: AsMut<[S]>>(&mut self, channels: &mut [C]) {
+        nih_debug_assert!(channels.len() <= self.ring_buffers.len());
+        if self.latency == 0 {
+            return;
+        }
+
+        let num_samples = channels
+            .first_mut()
+            .map(|channel| channel.as_mut().len())
+            .unwrap_or(0);
+        for (channel, ring_buffer) in channels.iter_mut().zip(self.ring_buffers.iter_mut()) {
+            let channel = channel.as_mut();
+            nih_debug_assert_eq!(channel.len(), num_samples);
+
+            let mut pos = self.current_pos;
+            for sample in channel.iter_mut() {
+                std::mem::swap(sample, &mut ring_buffer[pos]);
+
+                pos += 1;
+                if pos == self.latency {
+                    pos = 0;
+                }
+            }
+        }
+
+        self.current_pos = (self.current_pos + num_samples) % self.latency;
+    }
+}
+
+impl LatencyChecker {
+    /// Create a latency checker that sends an impulse every `period` samples. Latencies of `period`
+    /// samples or longer cannot be measured. A period of one second is a good default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period == 0`.
+    pub fn new(period: usize) -> Self {
+        assert_ne!(period, 0);
+
+        Self {
+            period,
+            current_pos: 0,
+            peak: None,
+            last_result: None,
+        }
+    }
+
+    /// Replace the plugin's main input with the next block of the impulse signal. This needs to be
+    /// called before the plugin processes `channels`, followed by a call to
+    /// [`check_output()`][Self::check_output()] with the same number of samples.
+    pub fn prepare_input(&self, channels: &mut [&mut [f32]]) {
+        let first_impulse_idx = (self.period - self.current_pos) % self.period;
+        for channel in channels.iter_mut() {
+            channel.fill(0.0);
+            for sample_idx in (first_impulse_idx..channel.len()).step_by(self.period) {
+                channel[sample_idx] = 1.0;
+            }
+        }
+    }
+
+    /// Analyze the plugin's output after it has processed the input written by
+    /// [`prepare_input()`][Self::prepare_input()]. Whenever a period ends this compares the
+    /// measured latency to `reported_latency`. The result is only returned when it differs from
+    /// the previous result, so this can be logged directly.
+    pub fn check_output(
+        &mut self,
+        channels: &[&mut [f32]],
+        reported_latency: u32,
+    ) -> Option<LatencyCheckResult> {
+        let num_samples = channels.first().map(|channel| channel.len()).unwrap_or(0);
+
+        let mut new_result = None;
+        for sample_idx in 0..num_samples {
+            let magnitude = channels
+                .iter()
+                .map(|channel| channel[sample_idx].abs())
+                .fold(0.0f32, f32::max);
+            if magnitude > MINUS_INFINITY_GAIN
+                && self.peak.map_or(true, |(_, peak)| magnitude > peak)
+            {
+                self.peak = Some((self.current_pos, magnitude));
+            }
+
+            self.current_pos += 1;
+            if self.current_pos == self.period {
+                let result = match self.peak.take() {
+                    Some((measured, _)) if measured as u32 == reported_latency => {
+                        LatencyCheckResult::Matches {
+                            latency: reported_latency,
+                        }
+                    }
+                    Some((measured, _)) => LatencyCheckResult::Mismatch {
+                        reported: reported_latency,
+                        measured: measured as u32,
+                    },
+                    None => LatencyCheckResult::NoOutput,
+                };
+                if self.last_result != Some(result) {
+                    self.last_result = Some(result);
+                    new_result = Some(result);
+                }
+
+                self.current_pos = 0;
+            }
+        }
+
+        new_result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compensator_delays_across_blocks() {
+        let mut compensator = LatencyCompensator::new(2, 8);
+        compensator.set_latency(3);
+
+        let mut left = [1.0f32, 2.0];
+        let mut right = [-1.0f32, -2.0];
+        compensator.process_slices(&mut [&mut left, &mut right]);
+        assert_eq!(left, [0.0, 0.0]);
+        assert_eq!(right, [0.0, 0.0]);
+
+        let mut left = [3.0f32, 4.0, 5.0, 6.0];
+        let mut right = [-3.0f32, -4.0, -5.0, -6.0];
+        compensator.process_slices(&mut [&mut left, &mut right]);
+        assert_eq!(left, [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(right, [0.0, -1.0, -2.0, -3.0]);
+    }
+
+    #[test]
+    fn compensator_zero_latency_passes_through() {
+        let mut compensator = LatencyCompensator::new(1, 8);
+
+        let mut samples = [1.0f32, 2.0, 3.0];
+        compensator.process_slices(&mut [&mut samples]);
+        assert_eq!(samples, [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn compensator_latency_change_clears() {
+        let mut compensator = LatencyCompensator::new(1, 8);
+        compensator.set_latency(2);
+
+        let mut samples = [1.0f32, 2.0, 3.0];
+        compensator.process_slices(&mut [&mut samples]);
+        compensator.set_latency(1);
+
+        let mut samples = [4.0f32, 5.0];
+        compensator.process_slices(&mut [&mut samples]);
+        assert_eq!(samples, [0.0, 4.0]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn compensator_latency_too_large() {
+        let mut compensator = LatencyCompensator::<f32>::new(1, 8);
+        compensator.set_latency(9);
+    }
+
+    #[test]
+    fn checker_measures_delay() {
+        let mut checker = LatencyChecker::new(16);
+        let mut compensator = LatencyCompensator::new(1, 16);
+        compensator.set_latency(5);
+
+        let mut results = Vec::new();
+        for _ in 0..8 {
+            // Uneven block sizes make sure the impulses line up across blocks
+            let mut samples = [0.0f32; 7];
+            checker.prepare_input(&mut [&mut samples]);
+            compensator.process_slices(&mut [&mut samples]);
+            results.extend(checker.check_output(&[&mut samples], 5));
+        }
+        assert_eq!(results, [LatencyCheckResult::Matches { latency: 5 }]);
+
+        let mut results = Vec::new();
+        for _ in 0..8 {
+            let mut samples = [0.0f32; 7];
+            checker.prepare_input(&mut [&mut samples]);
+            compensator.process_slices(&mut [&mut samples]);
+            results.extend(checker.check_output(&[&mut samples], 3));
+        }
+        assert_eq!(
+            results,
+            [LatencyCheckResult::Mismatch {
+                reported: 3,
+                measured: 5
+            }]
+        );
+    }
+
+    #[test]
+    fn checker_no_output() {
+        let mut checker = LatencyChecker::new(4);
+
+        let mut samples = [0.0f32; 4];
+        checker.prepare_input(&mut [&mut samples]);
+        assert_eq!(samples, [1.0, 0.0, 0.0, 0.0]);
+
+        samples.fill(0.0);
+        assert_eq!(
+            checker.check_output(&[&mut samples], 0),
+            Some(LatencyCheckResult::NoOutput)
+        );
+    }
+}
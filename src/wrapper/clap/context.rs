@@ -6,14 +6,18 @@ use clap_sys::id::{clap_id, CLAP_INVALID_ID};
 use clap_sys::string_sizes::CLAP_NAME_SIZE;
 use std::cell::Cell;
 use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::sync::Arc;
 
 use super::wrapper::{OutputParamEvent, Task, Wrapper};
 use crate::event_loop::EventLoop;
 use crate::prelude::{
-    ClapPlugin, GuiContext, InitContext, ParamPtr, PluginApi, PluginNoteEvent, ProcessContext,
-    RemoteControlsContext, RemoteControlsPage, RemoteControlsSection, Transport,
+    AbSlot, ClapPlugin, GuiContext, InitContext, ParamIndication, ParamPtr, PluginApi,
+    PluginNoteEvent, ProcessContext, RemoteControlsContext, RemoteControlsPage,
+    RemoteControlsSection, StateContext, StateLoadBehavior, StateLoadHandle, TrackInfo, Transport,
 };
+use crate::preset::{self, PresetMetadata};
+use crate::wrapper::util::snapshots;
 use crate::wrapper::util::strlcpy;
 
 /// An [`InitContext`] implementation for the wrapper.
@@ -25,6 +29,8 @@ use crate::wrapper::util::strlcpy;
 pub(crate) struct WrapperInitContext<'a, P: ClapPlugin> {
     pub(super) wrapper: &'a Wrapper<P>,
     pub(super) pending_requests: PendingInitContextRequests,
+    /// The context of the state that was loaded before this initialization, if any.
+    pub(super) state_context: Option<StateContext>,
 }
 
 /// Any requests that should be sent out when the [`WrapperInitContext`] is dropped. See that
@@ -82,6 +88,15 @@ impl<P: ClapPlugin> InitContext<P> for WrapperInitContext<'_, P> {
         (self.wrapper.task_executor.lock())(task);
     }
 
+    fn execute_background(&self, task: P::BackgroundTask) {
+        let task_posted = self.wrapper.schedule_background(Task::PluginTask(task));
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
+    fn begin_state_load(&self, behavior: StateLoadBehavior) -> StateLoadHandle {
+        self.wrapper.state_load_tracker.begin(behavior)
+    }
+
     fn set_latency_samples(&self, samples: u32) {
         // See this struct's docstring
         self.pending_requests.latency_changed.set(Some(samples));
@@ -90,6 +105,14 @@ impl<P: ClapPlugin> InitContext<P> for WrapperInitContext<'_, P> {
     fn set_current_voice_capacity(&self, capacity: u32) {
         self.wrapper.set_current_voice_capacity(capacity)
     }
+
+    fn state_context(&self) -> Option<StateContext> {
+        self.state_context
+    }
+
+    fn track_info(&self) -> Option<TrackInfo> {
+        self.wrapper.track_info.read().clone()
+    }
 }
 
 impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
@@ -107,6 +130,10 @@ impl<P: ClapPlugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
         nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
     }
 
+    fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync)) {
+        self.wrapper.execute_parallel(num_tasks, task)
+    }
+
     #[inline]
     fn transport(&self) -> &Transport {
         &self.transport
@@ -234,11 +261,100 @@ impl<P: ClapPlugin> GuiContext for WrapperGuiContext<P> {
     }
 
     fn get_state(&self) -> crate::wrapper::state::PluginState {
-        self.wrapper.get_state_object()
+        self.wrapper.get_state_object(StateContext::Duplicate)
     }
 
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
-        self.wrapper.set_state_object_from_gui(state)
+        self.wrapper
+            .set_state_object_from_gui(state, StateContext::Duplicate)
+    }
+
+    fn capture_snapshot(&self, name: &str) {
+        self.wrapper
+            .snapshots
+            .capture(name, self.wrapper.get_state_object(StateContext::Duplicate))
+    }
+
+    fn recall_snapshot(&self, name: &str) -> bool {
+        match self.wrapper.snapshots.get(name) {
+            Some(snapshot) => {
+                unsafe {
+                    snapshots::recall_snapshot(
+                        self,
+                        |param_id| self.wrapper.param_ptr_from_id(param_id),
+                        snapshot,
+                    )
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove_snapshot(&self, name: &str) -> bool {
+        self.wrapper.snapshots.remove(name)
+    }
+
+    fn snapshot_names(&self) -> Vec<String> {
+        self.wrapper.snapshots.names()
+    }
+
+    fn active_ab_slot(&self) -> AbSlot {
+        self.wrapper.snapshots.active_ab_slot()
+    }
+
+    fn select_ab_slot(&self, slot: AbSlot) {
+        unsafe {
+            self.wrapper.snapshots.select_ab_slot(
+                self,
+                |param_id| self.wrapper.param_ptr_from_id(param_id),
+                slot,
+            )
+        }
+    }
+
+    fn save_preset(&self, path: &Path, metadata: PresetMetadata) -> anyhow::Result<()> {
+        preset::save_preset::<P>(
+            path,
+            metadata,
+            self.wrapper.get_state_object(StateContext::Preset),
+        )
+    }
+
+    fn load_preset(&self, path: &Path) -> anyhow::Result<PresetMetadata> {
+        let (metadata, state) = preset::load_preset::<P>(path)?;
+        self.wrapper
+            .set_state_object_from_gui(state, StateContext::Preset);
+
+        Ok(metadata)
+    }
+
+    fn state_load_progress(&self) -> Option<f32> {
+        self.wrapper.state_load_tracker.progress()
+    }
+
+    fn provides_ticks(&self) -> bool {
+        self.wrapper.provides_editor_ticks()
+    }
+
+    fn register_fd(&self, fd: i32) -> bool {
+        self.wrapper.register_editor_fd(fd)
+    }
+
+    fn unregister_fd(&self, fd: i32) {
+        self.wrapper.unregister_editor_fd(fd)
+    }
+
+    fn track_info(&self) -> Option<TrackInfo> {
+        self.wrapper.track_info.read().clone()
+    }
+
+    fn raw_param_indication(&self, param: ParamPtr) -> ParamIndication {
+        self.wrapper.param_indication(param)
+    }
+
+    fn raw_show_param_context_menu(&self, param: ParamPtr, x: i32, y: i32) -> bool {
+        self.wrapper.show_param_context_menu(param, x, y)
     }
 }
 
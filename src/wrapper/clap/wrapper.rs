@@ -1,5 +1,7 @@
 use atomic_float::AtomicF32;
 use atomic_refcell::{AtomicRefCell, AtomicRefMut};
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::color::clap_color;
 use clap_sys::events::{
     clap_event_header, clap_event_midi, clap_event_midi_sysex, clap_event_note,
     clap_event_note_expression, clap_event_param_gesture, clap_event_param_mod,
@@ -15,13 +17,34 @@ use clap_sys::events::{
     CLAP_TRANSPORT_HAS_TIME_SIGNATURE, CLAP_TRANSPORT_IS_LOOP_ACTIVE, CLAP_TRANSPORT_IS_PLAYING,
     CLAP_TRANSPORT_IS_RECORDING, CLAP_TRANSPORT_IS_WITHIN_PRE_ROLL,
 };
+use clap_sys::ext::ambisonic::{
+    clap_ambisonic_config, clap_plugin_ambisonic, CLAP_AMBISONIC_NORMALIZATION_MAXN,
+    CLAP_AMBISONIC_NORMALIZATION_N2D, CLAP_AMBISONIC_NORMALIZATION_N3D,
+    CLAP_AMBISONIC_NORMALIZATION_SN2D, CLAP_AMBISONIC_NORMALIZATION_SN3D,
+    CLAP_AMBISONIC_ORDERING_ACN, CLAP_AMBISONIC_ORDERING_FUMA, CLAP_EXT_AMBISONIC,
+    CLAP_EXT_AMBISONIC_COMPAT, CLAP_PORT_AMBISONIC,
+};
 use clap_sys::ext::audio_ports::{
-    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN, CLAP_EXT_AUDIO_PORTS,
-    CLAP_PORT_MONO, CLAP_PORT_STEREO,
+    clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN,
+    CLAP_AUDIO_PORT_REQUIRES_COMMON_SAMPLE_SIZE, CLAP_AUDIO_PORT_SUPPORTS_64BITS,
+    CLAP_EXT_AUDIO_PORTS, CLAP_PORT_MONO, CLAP_PORT_STEREO,
+};
+use clap_sys::ext::audio_ports_activation::{
+    clap_plugin_audio_ports_activation, CLAP_EXT_AUDIO_PORTS_ACTIVATION,
+    CLAP_EXT_AUDIO_PORTS_ACTIVATION_COMPAT,
 };
 use clap_sys::ext::audio_ports_config::{
     clap_audio_ports_config, clap_plugin_audio_ports_config, CLAP_EXT_AUDIO_PORTS_CONFIG,
 };
+use clap_sys::ext::configurable_audio_ports::{
+    clap_audio_port_configuration_request, clap_plugin_configurable_audio_ports,
+    CLAP_EXT_CONFIGURABLE_AUDIO_PORTS, CLAP_EXT_CONFIGURABLE_AUDIO_PORTS_COMPAT,
+};
+use clap_sys::ext::context_menu::{
+    clap_context_menu_builder, clap_context_menu_entry, clap_context_menu_target,
+    clap_host_context_menu, clap_plugin_context_menu, CLAP_CONTEXT_MENU_ITEM_ENTRY,
+    CLAP_CONTEXT_MENU_TARGET_KIND_PARAM, CLAP_EXT_CONTEXT_MENU, CLAP_EXT_CONTEXT_MENU_COMPAT,
+};
 use clap_sys::ext::remote_controls::{
     clap_plugin_remote_controls, clap_remote_controls_page, CLAP_EXT_REMOTE_CONTROLS,
 };
@@ -34,19 +57,49 @@ use clap_sys::ext::note_ports::{
     clap_note_port_info, clap_plugin_note_ports, CLAP_EXT_NOTE_PORTS, CLAP_NOTE_DIALECT_CLAP,
     CLAP_NOTE_DIALECT_MIDI,
 };
+use clap_sys::ext::param_indication::{
+    clap_plugin_param_indication, CLAP_EXT_PARAM_INDICATION, CLAP_EXT_PARAM_INDICATION_COMPAT,
+    CLAP_PARAM_INDICATION_AUTOMATION_NONE, CLAP_PARAM_INDICATION_AUTOMATION_OVERRIDING,
+    CLAP_PARAM_INDICATION_AUTOMATION_PLAYING, CLAP_PARAM_INDICATION_AUTOMATION_PRESENT,
+    CLAP_PARAM_INDICATION_AUTOMATION_RECORDING,
+};
 use clap_sys::ext::params::{
     clap_host_params, clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS,
     CLAP_PARAM_IS_AUTOMATABLE, CLAP_PARAM_IS_BYPASS, CLAP_PARAM_IS_HIDDEN,
     CLAP_PARAM_IS_MODULATABLE, CLAP_PARAM_IS_MODULATABLE_PER_NOTE_ID, CLAP_PARAM_IS_READONLY,
     CLAP_PARAM_IS_STEPPED, CLAP_PARAM_RESCAN_VALUES,
 };
+use clap_sys::ext::posix_fd_support::{
+    clap_host_posix_fd_support, clap_plugin_posix_fd_support, clap_posix_fd_flags,
+    CLAP_EXT_POSIX_FD_SUPPORT, CLAP_POSIX_FD_ERROR, CLAP_POSIX_FD_READ,
+};
 use clap_sys::ext::render::{
     clap_plugin_render, clap_plugin_render_mode, CLAP_EXT_RENDER, CLAP_RENDER_OFFLINE,
     CLAP_RENDER_REALTIME,
 };
 use clap_sys::ext::state::{clap_plugin_state, CLAP_EXT_STATE};
+use clap_sys::ext::state_context::{
+    clap_plugin_state_context, clap_plugin_state_context_type, CLAP_EXT_STATE_CONTEXT,
+    CLAP_STATE_CONTEXT_FOR_DUPLICATE, CLAP_STATE_CONTEXT_FOR_PRESET,
+    CLAP_STATE_CONTEXT_FOR_PROJECT,
+};
+use clap_sys::ext::surround::{
+    clap_plugin_surround, CLAP_EXT_SURROUND, CLAP_EXT_SURROUND_COMPAT, CLAP_PORT_SURROUND,
+};
 use clap_sys::ext::tail::{clap_plugin_tail, CLAP_EXT_TAIL};
 use clap_sys::ext::thread_check::{clap_host_thread_check, CLAP_EXT_THREAD_CHECK};
+use clap_sys::ext::thread_pool::{
+    clap_host_thread_pool, clap_plugin_thread_pool, CLAP_EXT_THREAD_POOL,
+};
+use clap_sys::ext::timer_support::{
+    clap_host_timer_support, clap_plugin_timer_support, CLAP_EXT_TIMER_SUPPORT,
+};
+use clap_sys::ext::track_info::{
+    clap_host_track_info, clap_plugin_track_info, clap_track_info, CLAP_EXT_TRACK_INFO,
+    CLAP_EXT_TRACK_INFO_COMPAT, CLAP_TRACK_INFO_HAS_AUDIO_CHANNEL, CLAP_TRACK_INFO_HAS_TRACK_COLOR,
+    CLAP_TRACK_INFO_HAS_TRACK_NAME, CLAP_TRACK_INFO_IS_FOR_BUS, CLAP_TRACK_INFO_IS_FOR_MASTER,
+    CLAP_TRACK_INFO_IS_FOR_RETURN_TRACK,
+};
 use clap_sys::ext::voice_info::{
     clap_host_voice_info, clap_plugin_voice_info, clap_voice_info, CLAP_EXT_VOICE_INFO,
     CLAP_VOICE_INFO_SUPPORTS_OVERLAPPING_NOTES,
@@ -60,10 +113,11 @@ use clap_sys::process::{
     CLAP_PROCESS_ERROR,
 };
 use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::string_sizes::CLAP_NAME_SIZE;
 use crossbeam::atomic::AtomicCell;
 use crossbeam::channel::{self, SendTimeoutError};
 use crossbeam::queue::ArrayQueue;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use std::any::Any;
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet, VecDeque};
@@ -83,23 +137,32 @@ use super::util::ClapPtr;
 use crate::event_loop::{BackgroundThread, EventLoop, MainThreadExecutor, TASK_QUEUE_CAPACITY};
 use crate::midi::MidiResult;
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, AuxiliaryBuffers, BufferConfig, ClapPlugin, Editor, MidiConfig,
-    NoteEvent, ParamFlags, ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent,
-    ProcessMode, ProcessStatus, SysExMessage, TaskExecutor, Transport,
+    AmbisonicLayout, AmbisonicNormalization, AmbisonicOrdering, AsyncExecutor, AudioIOLayout,
+    AuxiliaryBuffers, Buffer, BufferConfig, ChannelLayout, ClapPlugin, Editor, HostColor,
+    MidiConfig, NoteEvent, ParamAutomationState, ParamFlags, ParamIndication, ParamMapping,
+    ParamPtr, Params, ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus,
+    Sample, SysExMessage, TaskExecutor, TrackInfo, TrackKind, Transport,
 };
 use crate::util::permit_alloc;
 use crate::wrapper::clap::context::RemoteControlPages;
 use crate::wrapper::clap::util::{read_stream, write_stream};
-use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
+use crate::wrapper::state::loading::{self, StateLoadTracker};
+use crate::wrapper::state::{self, PluginState, StateContext};
+use crate::wrapper::util::buffer_management::{AuxPortActivation, BufferManager, ChannelPointers};
+use crate::wrapper::util::bypass::ManagedBypass;
+use crate::wrapper::util::snapshots::Snapshots;
 use crate::wrapper::util::{
-    clamp_input_event_timing, clamp_output_event_timing, hash_param_id, process_wrapper, strlcpy,
+    check_param_map, clamp_input_event_timing, clamp_output_event_timing, hash_param_id,
+    process_wrapper, strlcpy, supported_audio_io_layout,
 };
 
 /// How many output parameter changes we can store in our output parameter change queue. Storing
 /// more than this many parameters at a time will cause changes to get lost.
 const OUTPUT_EVENT_QUEUE_CAPACITY: usize = 2048;
 
+/// The action ID for the 'Reset to Default' entry we add to the host's parameter context menus.
+const CONTEXT_MENU_ACTION_RESET_PARAM: clap_id = 0;
+
 pub struct Wrapper<P: ClapPlugin> {
     /// A reference to this object, upgraded to an `Arc<Self>` for the GUI context.
     this: AtomicRefCell<Weak<Self>>,
@@ -125,6 +188,9 @@ pub struct Wrapper<P: ClapPlugin> {
     /// APIs only deal in logical pixels.
     editor_scaling_factor: AtomicF32,
 
+    /// Whether the plugin is currently activated. In other words, whether `clap_plugin::activate()`
+    /// has been called without a matching `clap_plugin::deactivate()`.
+    is_active: AtomicBool,
     is_processing: AtomicBool,
     /// The current IO configuration, modified through the `clap_plugin_audio_ports_config`
     /// extension. Initialized to the plugin's first audio IO configuration.
@@ -145,12 +211,30 @@ pub struct Wrapper<P: ClapPlugin> {
     output_events: AtomicRefCell<VecDeque<PluginNoteEvent<P>>>,
     /// The last process status returned by the plugin. This is used for tail handling.
     last_process_status: AtomicCell<ProcessStatus>,
-    /// The current latency in samples, as set by the plugin through the [`ProcessContext`]. Uses
-    /// the latency extension.
+    /// The latency in samples reported to the host through the latency extension. CLAP only allows
+    /// the latency to change while the plugin is being activated, so this is only updated from
+    /// `requested_latency` in `clap_plugin::activate()`.
     pub current_latency: AtomicU32,
+    /// The latency in samples most recently set by the plugin through the
+    /// [`InitContext`][crate::prelude::InitContext] or the [`ProcessContext`]. If this differs from
+    /// `current_latency` while the plugin is activated, then the host is asked to restart the
+    /// plugin so the new latency can be applied.
+    requested_latency: AtomicU32,
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     buffer_manager: AtomicRefCell<BufferManager>,
+    /// The same as `buffer_manager`, but for double precision processing. This is only allocated
+    /// when the plugin sets [`Plugin::SUPPORTS_F64`].
+    buffer_manager_f64: AtomicRefCell<BufferManager<f64>>,
+    /// Crossfades to the latency compensated dry signal when the plugin sets
+    /// [`Plugin::MANAGED_BYPASS`] and its bypass parameter is enabled. Set up in
+    /// `clap_plugin::activate()`.
+    managed_bypass: AtomicRefCell<ManagedBypass>,
+    /// The same as `managed_bypass`, but for double precision processing.
+    managed_bypass_f64: AtomicRefCell<ManagedBypass<f64>>,
+    /// Which auxiliary ports the host has activated through the `audio-ports-activation`
+    /// extension. This is copied to the [`AuxiliaryBuffers`] for every process call.
+    aux_port_activation: AuxPortActivation,
     /// The plugin is able to restore state through a method on the `GuiContext`. To avoid changing
     /// parameters mid-processing and running into garbled data if the host also tries to load state
     /// at the same time the restoring happens at the end of each processing call. If this zero
@@ -159,9 +243,17 @@ pub struct Wrapper<P: ClapPlugin> {
     /// In other words, the GUI thread acts as a sender and then as a receiver, while the audio
     /// thread acts as a receiver and then as a sender. That way deallocation can happen on the GUI
     /// thread. All of this happens without any blocking on the audio thread.
-    updated_state_sender: channel::Sender<PluginState>,
+    updated_state_sender: channel::Sender<(PluginState, StateContext)>,
     /// The receiver belonging to [`new_state_sender`][Self::new_state_sender].
-    updated_state_receiver: channel::Receiver<PluginState>,
+    updated_state_receiver: channel::Receiver<(PluginState, StateContext)>,
+    /// The context of the last state that was loaded, if the plugin has not been (re)initialized
+    /// since then. This is exposed to the plugin through
+    /// [`InitContext::state_context()`][crate::prelude::InitContext::state_context()].
+    pending_state_context: AtomicCell<Option<StateContext>>,
+    /// Keeps track of state loads the plugin continues in the background. While one of these is in
+    /// progress the audio thread may output silence instead of calling the plugin's process
+    /// function.
+    pub state_load_tracker: Arc<StateLoadTracker>,
 
     // We'll query all of the host's extensions upfront
     host_callback: ClapPtr<clap_host>,
@@ -175,8 +267,17 @@ pub struct Wrapper<P: ClapPlugin> {
     /// this.
     _plugin_descriptor: Box<PluginDescriptor>,
 
+    clap_plugin_ambisonic: clap_plugin_ambisonic,
+
     clap_plugin_audio_ports: clap_plugin_audio_ports,
 
+    clap_plugin_audio_ports_activation: clap_plugin_audio_ports_activation,
+
+    clap_plugin_configurable_audio_ports: clap_plugin_configurable_audio_ports,
+
+    clap_plugin_context_menu: clap_plugin_context_menu,
+    host_context_menu: AtomicRefCell<Option<ClapPtr<clap_host_context_menu>>>,
+
     clap_plugin_gui: clap_plugin_gui,
     host_gui: AtomicRefCell<Option<ClapPtr<clap_host_gui>>>,
 
@@ -185,6 +286,11 @@ pub struct Wrapper<P: ClapPlugin> {
 
     clap_plugin_note_ports: clap_plugin_note_ports,
 
+    clap_plugin_param_indication: clap_plugin_param_indication,
+    /// The host's indications for the plugin's parameters, indexed by the parameter's hash.
+    /// Parameters without an entry use the default indication.
+    param_indications: Mutex<HashMap<u32, ParamIndication>>,
+
     clap_plugin_params: clap_plugin_params,
     host_params: AtomicRefCell<Option<ClapPtr<clap_host_params>>>,
     // These fields are exactly the same as their VST3 wrapper counterparts.
@@ -215,6 +321,10 @@ pub struct Wrapper<P: ClapPlugin> {
     /// the parameter's poly modulation ID. These IDs are then passed to the plugin, so it can
     /// quickly refer to parameter by matching on constant IDs.
     poly_mod_ids_by_hash: HashMap<u32, u32>,
+    /// Pointers to all parameters with per-sample modulation buffers, indexed by the parameter's
+    /// hash. These buffers are filled from the `CLAP_EVENT_PARAM_MOD` events at the start of each
+    /// process call.
+    modulation_buffer_params_by_hash: HashMap<u32, ParamPtr>,
     /// A queue of parameter changes and gestures that should be output in either the next process
     /// call or in the next parameter flush.
     ///
@@ -222,9 +332,19 @@ pub struct Wrapper<P: ClapPlugin> {
     ///      even if it does then that should still not be a problem because the host also reads it
     ///      in the same order, right?
     output_parameter_events: ArrayQueue<OutputParamEvent>,
+    /// In-memory state snapshots and the A/B comparison state, managed through the
+    /// [`GuiContext`][crate::prelude::GuiContext].
+    pub snapshots: Snapshots,
 
     host_thread_check: AtomicRefCell<Option<ClapPtr<clap_host_thread_check>>>,
 
+    clap_plugin_posix_fd_support: clap_plugin_posix_fd_support,
+    host_posix_fd_support: AtomicRefCell<Option<ClapPtr<clap_host_posix_fd_support>>>,
+    /// The file descriptors the editor registered with the host through
+    /// [`GuiContext::register_fd()`][crate::prelude::GuiContext::register_fd()]. Any remaining
+    /// file descriptors are unregistered when the editor is closed.
+    editor_fds: Mutex<Vec<i32>>,
+
     clap_plugin_remote_controls: clap_plugin_remote_controls,
     /// The plugin's remote control pages, if it defines any. Filled when initializing the plugin.
     remote_control_pages: Vec<clap_remote_controls_page>,
@@ -233,8 +353,30 @@ pub struct Wrapper<P: ClapPlugin> {
 
     clap_plugin_state: clap_plugin_state,
 
+    clap_plugin_state_context: clap_plugin_state_context,
+
+    clap_plugin_surround: clap_plugin_surround,
+
     clap_plugin_tail: clap_plugin_tail,
 
+    clap_plugin_thread_pool: clap_plugin_thread_pool,
+    host_thread_pool: AtomicRefCell<Option<ClapPtr<clap_host_thread_pool>>>,
+    /// The task passed to [`execute_parallel()`][Self::execute_parallel()]. This is only set while
+    /// the host is executing that task on its thread pool.
+    current_thread_pool_task: AtomicRefCell<Option<ThreadPoolTask>>,
+
+    clap_plugin_timer_support: clap_plugin_timer_support,
+    host_timer_support: AtomicRefCell<Option<ClapPtr<clap_host_timer_support>>>,
+    /// The ID of the timer used to call [`Editor::tick()`] while the editor is open, or
+    /// `CLAP_INVALID_ID` if there is no such timer.
+    editor_timer_id: AtomicU32,
+
+    clap_plugin_track_info: clap_plugin_track_info,
+    host_track_info: AtomicRefCell<Option<ClapPtr<clap_host_track_info>>>,
+    /// Information about the plugin's track, fetched from the host during initialization and
+    /// whenever the host indicates that it has changed.
+    pub track_info: RwLock<Option<TrackInfo>>,
+
     clap_plugin_voice_info: clap_plugin_voice_info,
     host_voice_info: AtomicRefCell<Option<ClapPtr<clap_host_voice_info>>>,
     /// If `P::CLAP_POLY_MODULATION_CONFIG` is set, then the plugin can configure the current number
@@ -281,6 +423,15 @@ pub enum Task<P: Plugin> {
     RescanParamValues,
 }
 
+/// A pointer to the task passed to [`Wrapper::execute_parallel()`] with its lifetime erased, so it
+/// can be called from the host's thread pool. The task is `Sync`, and the pointer is only
+/// dereferenced while `execute_parallel()` is waiting for the host to finish running it.
+#[derive(Clone, Copy)]
+struct ThreadPoolTask(*const (dyn Fn(u32) + Sync));
+
+unsafe impl Send for ThreadPoolTask {}
+unsafe impl Sync for ThreadPoolTask {}
+
 /// The types of CLAP parameter updates for events.
 pub enum ClapParamUpdate {
     /// Set the parameter to this plain value. In our wrapper the plain values are the normalized
@@ -389,22 +540,18 @@ impl<P: ClapPlugin> MainThreadExecutor<Task<P>> for Wrapper<P> {
                     }
                 }
             }
-            Task::LatencyChanged => match &*self.host_latency.borrow() {
-                Some(host_latency) => {
-                    nih_debug_assert!(is_gui_thread);
-
-                    // XXX: The CLAP docs mention that you should request a restart if this happens
-                    //      while the plugin is activated (which is not entirely the same thing as
-                    //      is processing, but we'll treat it as the same thing). In practice just
-                    //      calling the latency changed function also seems to work just fine.
-                    if self.is_processing.load(Ordering::SeqCst) {
-                        unsafe_clap_call! { &*self.host_callback=>request_restart(&*self.host_callback) };
-                    } else {
-                        unsafe_clap_call! { host_latency=>changed(&*self.host_callback) };
-                    }
+            Task::LatencyChanged => {
+                nih_debug_assert!(is_gui_thread);
+
+                // The new latency is applied the next time the plugin gets activated. If that
+                // already happened in the meantime, then there's nothing left to do here.
+                if self.is_active.load(Ordering::SeqCst)
+                    && self.requested_latency.load(Ordering::SeqCst)
+                        != self.current_latency.load(Ordering::SeqCst)
+                {
+                    unsafe_clap_call! { &*self.host_callback=>request_restart(&*self.host_callback) };
                 }
-                None => nih_debug_assert_failure!("Host does not support the latency extension"),
-            },
+            }
             Task::VoiceInfoChanged => match &*self.host_voice_info.borrow() {
                 Some(host_voice_info) => {
                     nih_debug_assert!(is_gui_thread);
@@ -487,19 +634,14 @@ impl<P: ClapPlugin> Wrapper<P> {
                 ptr.poly_modulation_id().map(|id| (*hash, id))
             })
             .collect();
+        let modulation_buffer_params_by_hash = param_id_hashes_ptrs_groups
+            .iter()
+            .filter(|(_, _, ptr, _)| unsafe { ptr.modulation_buffer() }.is_some())
+            .map(|(_, hash, ptr, _)| (*hash, *ptr))
+            .collect();
 
         if cfg!(debug_assertions) {
-            let param_map = params.param_map();
-            let param_ids: HashSet<_> = param_id_hashes_ptrs_groups
-                .iter()
-                .map(|(id, _, _, _)| id.clone())
-                .collect();
-            nih_debug_assert_eq!(
-                param_map.len(),
-                param_ids.len(),
-                "The plugin has duplicate parameter IDs, weird things may happen. Consider using \
-                 6 character parameter IDs to avoid collisions."
-            );
+            check_param_map(&params.param_map());
 
             let poly_mod_ids: HashSet<u32> = poly_mod_ids_by_hash.values().copied().collect();
             nih_debug_assert_eq!(
@@ -508,20 +650,6 @@ impl<P: ClapPlugin> Wrapper<P> {
                 "The plugin has duplicate poly modulation IDs. Polyphonic modulation will not be \
                  routed to the correct parameter."
             );
-
-            let mut bypass_param_exists = false;
-            for (_, _, ptr, _) in &param_id_hashes_ptrs_groups {
-                let flags = unsafe { ptr.flags() };
-                let is_bypass = flags.contains(ParamFlags::BYPASS);
-
-                if is_bypass && bypass_param_exists {
-                    nih_debug_assert_failure!(
-                        "Duplicate bypass parameters found, the host will only use the first one"
-                    );
-                }
-
-                bypass_param_exists |= is_bypass;
-            }
         }
 
         // Support for the remote controls extension
@@ -543,6 +671,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             editor_handle: Mutex::new(None),
             editor_scaling_factor: AtomicF32::new(1.0),
 
+            is_active: AtomicBool::new(false),
             is_processing: AtomicBool::new(false),
             current_audio_io_layout: AtomicCell::new(
                 P::AUDIO_IO_LAYOUTS.first().copied().unwrap_or_default(),
@@ -553,14 +682,24 @@ impl<P: ClapPlugin> Wrapper<P> {
             output_events: AtomicRefCell::new(VecDeque::with_capacity(512)),
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
             current_latency: AtomicU32::new(0),
+            requested_latency: AtomicU32::new(0),
             // This is initialized just before calling `Plugin::initialize()` so that during the
             // process call buffers can be initialized without any allocations
             buffer_manager: AtomicRefCell::new(BufferManager::for_audio_io_layout(
                 0,
                 AudioIOLayout::default(),
             )),
+            buffer_manager_f64: AtomicRefCell::new(BufferManager::for_audio_io_layout(
+                0,
+                AudioIOLayout::default(),
+            )),
+            managed_bypass: AtomicRefCell::new(ManagedBypass::default()),
+            managed_bypass_f64: AtomicRefCell::new(ManagedBypass::default()),
+            aux_port_activation: AuxPortActivation::for_audio_io_layouts(P::AUDIO_IO_LAYOUTS),
             updated_state_sender,
             updated_state_receiver,
+            pending_state_context: AtomicCell::new(None),
+            state_load_tracker: Arc::default(),
 
             host_callback,
 
@@ -591,11 +730,36 @@ impl<P: ClapPlugin> Wrapper<P> {
                 select: Some(Self::ext_audio_ports_config_select),
             },
 
+            clap_plugin_ambisonic: clap_plugin_ambisonic {
+                is_config_supported: Some(Self::ext_ambisonic_is_config_supported),
+                get_config: Some(Self::ext_ambisonic_get_config),
+            },
+
             clap_plugin_audio_ports: clap_plugin_audio_ports {
                 count: Some(Self::ext_audio_ports_count),
                 get: Some(Self::ext_audio_ports_get),
             },
 
+            clap_plugin_audio_ports_activation: clap_plugin_audio_ports_activation {
+                can_activate_while_processing: Some(
+                    Self::ext_audio_ports_activation_can_activate_while_processing,
+                ),
+                set_active: Some(Self::ext_audio_ports_activation_set_active),
+            },
+
+            clap_plugin_configurable_audio_ports: clap_plugin_configurable_audio_ports {
+                can_apply_configuration: Some(
+                    Self::ext_configurable_audio_ports_can_apply_configuration,
+                ),
+                apply_configuration: Some(Self::ext_configurable_audio_ports_apply_configuration),
+            },
+
+            clap_plugin_context_menu: clap_plugin_context_menu {
+                populate: Some(Self::ext_context_menu_populate),
+                perform: Some(Self::ext_context_menu_perform),
+            },
+            host_context_menu: AtomicRefCell::new(None),
+
             clap_plugin_gui: clap_plugin_gui {
                 is_api_supported: Some(Self::ext_gui_is_api_supported),
                 get_preferred_api: Some(Self::ext_gui_get_preferred_api),
@@ -625,6 +789,12 @@ impl<P: ClapPlugin> Wrapper<P> {
                 get: Some(Self::ext_note_ports_get),
             },
 
+            clap_plugin_param_indication: clap_plugin_param_indication {
+                set_mapping: Some(Self::ext_param_indication_set_mapping),
+                set_automation: Some(Self::ext_param_indication_set_automation),
+            },
+            param_indications: Mutex::new(HashMap::new()),
+
             clap_plugin_params: clap_plugin_params {
                 count: Some(Self::ext_params_count),
                 get_info: Some(Self::ext_params_get_info),
@@ -641,10 +811,18 @@ impl<P: ClapPlugin> Wrapper<P> {
             param_id_to_hash,
             param_ptr_to_hash,
             poly_mod_ids_by_hash,
+            modulation_buffer_params_by_hash,
             output_parameter_events: ArrayQueue::new(OUTPUT_EVENT_QUEUE_CAPACITY),
+            snapshots: Snapshots::default(),
 
             host_thread_check: AtomicRefCell::new(None),
 
+            clap_plugin_posix_fd_support: clap_plugin_posix_fd_support {
+                on_fd: Some(Self::ext_posix_fd_support_on_fd),
+            },
+            host_posix_fd_support: AtomicRefCell::new(None),
+            editor_fds: Mutex::new(Vec::new()),
+
             clap_plugin_remote_controls: clap_plugin_remote_controls {
                 count: Some(Self::ext_remote_controls_count),
                 get: Some(Self::ext_remote_controls_get),
@@ -661,10 +839,38 @@ impl<P: ClapPlugin> Wrapper<P> {
                 load: Some(Self::ext_state_load),
             },
 
+            clap_plugin_state_context: clap_plugin_state_context {
+                save: Some(Self::ext_state_context_save),
+                load: Some(Self::ext_state_context_load),
+            },
+
+            clap_plugin_surround: clap_plugin_surround {
+                is_channel_mask_supported: Some(Self::ext_surround_is_channel_mask_supported),
+                get_channel_map: Some(Self::ext_surround_get_channel_map),
+            },
+
             clap_plugin_tail: clap_plugin_tail {
                 get: Some(Self::ext_tail_get),
             },
 
+            clap_plugin_thread_pool: clap_plugin_thread_pool {
+                exec: Some(Self::ext_thread_pool_exec),
+            },
+            host_thread_pool: AtomicRefCell::new(None),
+            current_thread_pool_task: AtomicRefCell::new(None),
+
+            clap_plugin_timer_support: clap_plugin_timer_support {
+                on_timer: Some(Self::ext_timer_support_on_timer),
+            },
+            host_timer_support: AtomicRefCell::new(None),
+            editor_timer_id: AtomicU32::new(CLAP_INVALID_ID),
+
+            clap_plugin_track_info: clap_plugin_track_info {
+                changed: Some(Self::ext_track_info_changed),
+            },
+            host_track_info: AtomicRefCell::new(None),
+            track_info: RwLock::new(None),
+
             clap_plugin_voice_info: clap_plugin_voice_info {
                 get: Some(Self::ext_voice_info_get),
             },
@@ -743,6 +949,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         WrapperInitContext {
             wrapper: self,
             pending_requests: Default::default(),
+            state_context: self.pending_state_context.take(),
         }
     }
 
@@ -765,6 +972,11 @@ impl<P: ClapPlugin> Wrapper<P> {
             .map(|s| s.as_str())
     }
 
+    /// Get a parameter's pointer based on its string ID. Used when recalling state snapshots.
+    pub fn param_ptr_from_id(&self, param_id: &str) -> Option<ParamPtr> {
+        state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash)(param_id)
+    }
+
     /// Queue a parameter output event to be sent to the host at the end of the audio processing
     /// cycle, and request a parameter flush from the host if the plugin is not currently processing
     /// audio. The parameter's actual value will only be updated at that point so the value won't
@@ -810,6 +1022,173 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    /// Fetch the current track information from the host, if it supports the track info
+    /// extension. Needs to be called from the main thread.
+    fn update_track_info(&self) {
+        let host_track_info = self.host_track_info.borrow();
+        let host_track_info = match &*host_track_info {
+            Some(host_track_info) => host_track_info,
+            None => return,
+        };
+
+        let mut info: clap_track_info = unsafe { mem::zeroed() };
+        let success = unsafe_clap_call! {
+            host_track_info=>get(&*self.host_callback, &mut info)
+        };
+        if !success {
+            *self.track_info.write() = None;
+            return;
+        }
+
+        // Just in case the host didn't null terminate the name
+        info.name[CLAP_NAME_SIZE - 1] = 0;
+        let name = if info.flags & CLAP_TRACK_INFO_HAS_TRACK_NAME != 0 {
+            Some(
+                unsafe { CStr::from_ptr(info.name.as_ptr()) }
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+        } else {
+            None
+        };
+        let color = if info.flags & CLAP_TRACK_INFO_HAS_TRACK_COLOR != 0 {
+            Some(host_color_from_clap(&info.color))
+        } else {
+            None
+        };
+        let channel_count = if info.flags & CLAP_TRACK_INFO_HAS_AUDIO_CHANNEL != 0 {
+            Some(info.audio_channel_count.max(0) as u32)
+        } else {
+            None
+        };
+        let kind = if info.flags & CLAP_TRACK_INFO_IS_FOR_MASTER != 0 {
+            TrackKind::Master
+        } else if info.flags & CLAP_TRACK_INFO_IS_FOR_RETURN_TRACK != 0 {
+            TrackKind::Return
+        } else if info.flags & CLAP_TRACK_INFO_IS_FOR_BUS != 0 {
+            TrackKind::Bus
+        } else {
+            TrackKind::Regular
+        };
+
+        *self.track_info.write() = Some(TrackInfo {
+            name,
+            color,
+            channel_count,
+            kind: Some(kind),
+        });
+    }
+
+    /// Whether the editor's [`Editor::tick()`] function can be driven by the host's timers. See
+    /// [`GuiContext::provides_ticks()`][crate::prelude::GuiContext::provides_ticks()].
+    pub fn provides_editor_ticks(&self) -> bool {
+        self.host_timer_support.borrow().is_some()
+    }
+
+    /// Register a file descriptor for the editor with the host's event loop. Needs to be called
+    /// from the main thread. See
+    /// [`GuiContext::register_fd()`][crate::prelude::GuiContext::register_fd()].
+    pub fn register_editor_fd(&self, fd: i32) -> bool {
+        match &*self.host_posix_fd_support.borrow() {
+            Some(host_posix_fd_support) => {
+                if self.editor_fds.lock().contains(&fd) {
+                    nih_debug_assert_failure!("File descriptor {} was already registered", fd);
+                    return true;
+                }
+
+                // The lock is not held while calling into the host, since the host may call
+                // `on_fd()` before this function returns
+                let success = unsafe_clap_call! {
+                    host_posix_fd_support=>register_fd(
+                        &*self.host_callback,
+                        fd,
+                        CLAP_POSIX_FD_READ | CLAP_POSIX_FD_ERROR,
+                    )
+                };
+                if success {
+                    self.editor_fds.lock().push(fd);
+                }
+
+                success
+            }
+            None => false,
+        }
+    }
+
+    /// Unregister a file descriptor registered with
+    /// [`register_editor_fd()`][Self::register_editor_fd()]. Needs to be called from the main
+    /// thread.
+    pub fn unregister_editor_fd(&self, fd: i32) {
+        // The lock is released before calling into the host, just like in `register_editor_fd()`
+        let was_registered = {
+            let mut editor_fds = self.editor_fds.lock();
+            match editor_fds.iter().position(|&other_fd| other_fd == fd) {
+                Some(idx) => {
+                    editor_fds.swap_remove(idx);
+                    true
+                }
+                None => false,
+            }
+        };
+        if !was_registered {
+            nih_debug_assert_failure!("File descriptor {} was not registered", fd);
+            return;
+        }
+
+        if let Some(host_posix_fd_support) = &*self.host_posix_fd_support.borrow() {
+            unsafe_clap_call! {
+                host_posix_fd_support=>unregister_fd(&*self.host_callback, fd)
+            };
+        }
+    }
+
+    /// Get the host's last sent indication for a parameter. See
+    /// [`GuiContext::raw_param_indication()`][crate::prelude::GuiContext::raw_param_indication()].
+    pub fn param_indication(&self, param: ParamPtr) -> ParamIndication {
+        match self.param_ptr_to_hash.get(&param) {
+            Some(hash) => self
+                .param_indications
+                .lock()
+                .get(hash)
+                .cloned()
+                .unwrap_or_default(),
+            None => {
+                nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+                ParamIndication::default()
+            }
+        }
+    }
+
+    /// Ask the host to show its context menu for a parameter at the specified position relative to
+    /// the editor's window. Needs to be called from the main thread. See
+    /// [`GuiContext::raw_show_param_context_menu()`][crate::prelude::GuiContext::raw_show_param_context_menu()].
+    pub fn show_param_context_menu(&self, param: ParamPtr, x: i32, y: i32) -> bool {
+        let param_hash = match self.param_ptr_to_hash.get(&param) {
+            Some(hash) => *hash,
+            None => {
+                nih_debug_assert_failure!("Unknown parameter: {:?}", param);
+                return false;
+            }
+        };
+
+        match &*self.host_context_menu.borrow() {
+            Some(host_context_menu) => {
+                if !unsafe_clap_call! { host_context_menu=>can_popup(&*self.host_callback) } {
+                    return false;
+                }
+
+                let target = clap_context_menu_target {
+                    kind: CLAP_CONTEXT_MENU_TARGET_KIND_PARAM,
+                    id: param_hash,
+                };
+                unsafe_clap_call! {
+                    host_context_menu=>popup(&*self.host_callback, &target, 0, x, y)
+                }
+            }
+            None => false,
+        }
+    }
+
     /// Convenience function for setting a value for a parameter as triggered by a VST3 parameter
     /// update. The same rate is for updating parameter smoothing.
     ///
@@ -972,6 +1351,256 @@ impl<P: ClapPlugin> Wrapper<P> {
         None
     }
 
+    /// Fill the per-sample modulation buffers for all parameters that have one using the monophonic
+    /// `CLAP_EVENT_PARAM_MOD` events in `in_`. This is done once at the start of the process call
+    /// for the entire buffer. The events themselves are still handled as usual when the wrapper
+    /// goes through the event queue.
+    ///
+    /// # Safety
+    ///
+    /// `in_` must either be a null pointer or a valid event queue.
+    unsafe fn fill_modulation_buffers(
+        &self,
+        in_: *const clap_input_events,
+        total_buffer_len: usize,
+    ) {
+        for param in self.modulation_buffer_params_by_hash.values() {
+            if let Some(modulation_buffer) = param.modulation_buffer() {
+                modulation_buffer.start_block(total_buffer_len);
+            }
+        }
+
+        if in_.is_null() {
+            return;
+        }
+
+        let in_ = &*in_;
+        let num_events = clap_call! { in_=>size(in_) };
+        for event_idx in 0..num_events {
+            let event = clap_call! { in_=>get(in_, event_idx) };
+            if !matches!(
+                ((*event).space_id, (*event).type_),
+                (CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_MOD)
+            ) {
+                continue;
+            }
+
+            // Polyphonic modulation is sent to the plugin as note events instead
+            let event = &*(event as *const clap_event_param_mod);
+            if event.note_id != -1 && self.poly_mod_ids_by_hash.contains_key(&event.param_id) {
+                continue;
+            }
+
+            if let Some(param_ptr) = self.modulation_buffer_params_by_hash.get(&event.param_id) {
+                // This uses the same normalization as `update_plain_value_by_hash()`
+                let normalized_offset =
+                    event.amount as f32 / param_ptr.step_count().unwrap_or(1) as f32;
+                if let Some(modulation_buffer) = param_ptr.modulation_buffer() {
+                    modulation_buffer.add_event(event.header.time as usize, normalized_offset);
+                }
+            }
+        }
+    }
+
+    /// Create the buffers for a single block of audio from the host's `data32` or `data64` channel
+    /// pointers, and then let the plugin process them. Used with a `BufferManager<f32>` or a
+    /// `BufferManager<f64>` depending on the sample size the host uses for this process call.
+    /// `channel_data` selects the host's channel pointers for that sample size, and
+    /// `process_plugin` calls the corresponding process function on the plugin.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn process_block<S: Sample>(
+        &self,
+        buffer_manager: &AtomicRefCell<BufferManager<S>>,
+        managed_bypass: &AtomicRefCell<ManagedBypass<S>>,
+        process: &clap_process,
+        block_start: usize,
+        block_len: usize,
+        transport: Transport,
+        channel_data: impl Fn(&clap_audio_buffer) -> *mut *mut S,
+        process_plugin: impl FnOnce(
+            &mut P,
+            &mut Buffer<S>,
+            &mut AuxiliaryBuffers<S>,
+            &mut WrapperProcessContext<'_, P>,
+        ) -> ProcessStatus,
+    ) -> ProcessStatus {
+        let current_audio_io_layout = self.current_audio_io_layout.load();
+        let has_main_input = current_audio_io_layout.main_input_channels.is_some();
+        let has_main_output = current_audio_io_layout.main_output_channels.is_some();
+        let aux_input_start_idx = if has_main_input { 1 } else { 0 };
+        let aux_output_start_idx = if has_main_output { 1 } else { 0 };
+
+        // The buffer manager preallocated buffer slices for all the IO and storage for any
+        // axuiliary inputs.
+        // TODO: The audio buffers have a latency field, should we use those?
+        let mut buffer_manager = buffer_manager.borrow_mut();
+        let buffers = buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
+            // Explicitly take plugins with no main output that does have auxiliary
+            // outputs into account. Shouldn't happen, but if we just start copying
+            // audio here then that would result in unsoundness.
+            if process.audio_outputs_count > 0
+                && !process.audio_outputs.is_null()
+                && !channel_data(&*process.audio_outputs).is_null()
+                && has_main_output
+            {
+                let audio_output = &*process.audio_outputs;
+                let ptrs = NonNull::new(channel_data(audio_output)).unwrap();
+                let num_channels = audio_output.channel_count as usize;
+
+                *buffer_source.main_output_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+            }
+
+            if process.audio_inputs_count > 0
+                && !process.audio_inputs.is_null()
+                && !channel_data(&*process.audio_inputs).is_null()
+                && has_main_input
+            {
+                let audio_input = &*process.audio_inputs;
+                let ptrs = NonNull::new(channel_data(audio_input)).unwrap();
+                let num_channels = audio_input.channel_count as usize;
+
+                *buffer_source.main_input_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+                *buffer_source.main_input_constant_mask = audio_input.constant_mask;
+            }
+
+            if !process.audio_inputs.is_null() {
+                for (aux_input_no, (aux_input_channel_pointers, aux_input_constant_mask)) in
+                    buffer_source
+                        .aux_input_channel_pointers
+                        .iter_mut()
+                        .zip(buffer_source.aux_input_constant_masks.iter_mut())
+                        .enumerate()
+                {
+                    let aux_input_idx = aux_input_no + aux_input_start_idx;
+                    if aux_input_idx > process.audio_inputs_count as usize {
+                        break;
+                    }
+
+                    let audio_input = &*process.audio_inputs.add(aux_input_idx);
+                    match NonNull::new(channel_data(audio_input)) {
+                        Some(ptrs) => {
+                            let num_channels = audio_input.channel_count as usize;
+
+                            *aux_input_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                            *aux_input_constant_mask = audio_input.constant_mask;
+                        }
+                        None => continue,
+                    }
+                }
+            }
+
+            if !process.audio_outputs.is_null() {
+                for (aux_output_no, aux_output_channel_pointers) in buffer_source
+                    .aux_output_channel_pointers
+                    .iter_mut()
+                    .enumerate()
+                {
+                    let aux_output_idx = aux_output_no + aux_output_start_idx;
+                    if aux_output_idx > process.audio_outputs_count as usize {
+                        break;
+                    }
+
+                    let audio_output = &*process.audio_outputs.add(aux_output_idx);
+                    match NonNull::new(channel_data(audio_output)) {
+                        Some(ptrs) => {
+                            let num_channels = audio_output.channel_count as usize;
+
+                            *aux_output_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                        }
+                        None => continue,
+                    }
+                }
+            }
+
+            self.aux_port_activation.copy_to(buffer_source);
+        });
+
+        // If the host does not provide outputs or if it does not provide the required
+        // number of channels (should not happen, but Ableton Live does this for bypassed
+        // VST3 plugins) then we'll skip audio processing. In that case
+        // `buffer_manager.create_buffers` will have set one or more of the output buffers
+        // to empty slices since there is no storage to point them to. The auxiliary input
+        // buffers always point to valid storage.
+        let mut buffer_is_valid = true;
+        for output_buffer_slice in buffers.main_buffer.as_slice_immutable().iter().chain(
+            buffers
+                .aux_outputs
+                .iter()
+                .flat_map(|buffer| buffer.as_slice_immutable().iter()),
+        ) {
+            if output_buffer_slice.is_empty() {
+                buffer_is_valid = false;
+                break;
+            }
+        }
+
+        nih_debug_assert!(buffer_is_valid);
+
+        let result = if buffer_is_valid {
+            // When rendering offline we can't output silence, so we'll wait for
+            // asynchronous state loads to finish instead. Parking the thread may allocate.
+            if self.current_process_mode.load() == ProcessMode::Offline {
+                permit_alloc(|| self.state_load_tracker.wait());
+            }
+
+            // SAFETY: Shortening these borrows is safe as even if the plugin overwrites the
+            //         slices (which it cannot do without using unsafe code), then they
+            //         would still be reset on the next iteration
+            let mut aux = AuxiliaryBuffers {
+                main_input: buffers.main_input,
+                inputs: buffers.aux_inputs,
+                outputs: buffers.aux_outputs,
+                inputs_active: buffers.aux_inputs_active,
+                outputs_active: buffers.aux_outputs_active,
+            };
+            if self.state_load_tracker.should_silence() {
+                loading::silence_outputs(buffers.main_buffer, &mut aux);
+                ProcessStatus::Normal
+            } else {
+                let mut plugin = self.plugin.lock();
+                let mut context = self.make_process_context(transport);
+                let result = managed_bypass.borrow_mut().process(
+                    buffers.main_buffer,
+                    self.requested_latency.load(Ordering::SeqCst),
+                    |buffer| process_plugin(&mut plugin, buffer, &mut aux, &mut context),
+                );
+                self.last_process_status.store(result);
+                result
+            }
+        } else {
+            ProcessStatus::Normal
+        };
+        buffer_manager.accumulate_output_constant_masks();
+
+        result
+    }
+
+    /// Copy the output constant flags accumulated by a buffer manager during the last process call
+    /// to the host's output buffers.
+    unsafe fn write_output_constant_masks<S: Sample>(
+        buffer_manager: &BufferManager<S>,
+        process: &clap_process,
+        aux_output_start_idx: usize,
+    ) {
+        if process.audio_outputs.is_null() {
+            return;
+        }
+
+        for output_idx in 0..process.audio_outputs_count as usize {
+            let constant_mask = match output_idx.checked_sub(aux_output_start_idx) {
+                None => buffer_manager.main_output_constant_mask(false),
+                Some(aux_output_idx) => {
+                    buffer_manager.aux_output_constant_mask(aux_output_idx, false)
+                }
+            };
+            (*process.audio_outputs.add(output_idx)).constant_mask = constant_mask;
+        }
+    }
+
     /// Write the unflushed parameter changes to the host's output event queue. The sample index is
     /// used as part of splitting up the input buffer for sample accurate automation changes. This
     /// will also modify the actual parameter values, since we should only do that while the wrapped
@@ -1678,20 +2307,24 @@ impl<P: ClapPlugin> Wrapper<P> {
 
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
-    /// the JSON in the relevant plugin API methods instead.
-    pub fn get_state_object(&self) -> PluginState {
+    /// the JSON in the relevant plugin API methods instead. Presets are saved using
+    /// [`StateContext::Preset`], while in-memory copies like snapshots use
+    /// [`StateContext::Duplicate`] so they also include the fields that are skipped for presets.
+    pub fn get_state_object(&self, context: StateContext) -> PluginState {
         unsafe {
             state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+                context,
             )
         }
     }
 
     /// Update the plugin's internal state, called by the plugin itself from the GUI thread. To
     /// prevent corrupting data and changing parameters during processing the actual state is only
-    /// updated at the end of the audio processing cycle.
-    pub fn set_state_object_from_gui(&self, mut state: PluginState) {
+    /// updated at the end of the audio processing cycle. `context` should match the context the
+    /// state was saved with.
+    pub fn set_state_object_from_gui(&self, mut state: PluginState, context: StateContext) {
         // Use a loop and timeouts to handle the super rare edge case when this function gets called
         // between a process call and the host disabling the plugin
         loop {
@@ -1703,7 +2336,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 // deallocated without blocking the audio thread.
                 match self
                     .updated_state_sender
-                    .send_timeout(state, Duration::from_secs(1))
+                    .send_timeout((state, context), Duration::from_secs(1))
                 {
                     Ok(_) => {
                         // As mentioned above, the state object will be passed back to this thread
@@ -1712,7 +2345,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                         drop(state);
                         break;
                     }
-                    Err(SendTimeoutError::Timeout(value)) => {
+                    Err(SendTimeoutError::Timeout((value, _))) => {
                         state = value;
                         continue;
                     }
@@ -1724,7 +2357,7 @@ impl<P: ClapPlugin> Wrapper<P> {
             } else {
                 // Otherwise we'll set the state right here and now, since this function should be
                 // called from a GUI thread
-                self.set_state_inner(&mut state);
+                self.set_state_inner(&mut state, context);
                 break;
             }
         }
@@ -1735,16 +2368,52 @@ impl<P: ClapPlugin> Wrapper<P> {
     }
 
     pub fn set_latency_samples(&self, samples: u32) {
-        // Only make a callback if it's actually needed
-        // XXX: For CLAP we could move this handling to the Plugin struct, but it may be worthwhile
-        //      to keep doing it this way to stay consistent with VST3.
-        let old_latency = self.current_latency.swap(samples, Ordering::SeqCst);
+        // Only make a callback if it's actually needed. The new latency will be reported to the
+        // host the next time the plugin gets activated.
+        let old_latency = self.requested_latency.swap(samples, Ordering::SeqCst);
         if old_latency != samples {
             let task_posted = self.schedule_gui(Task::LatencyChanged);
             nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
         }
     }
 
+    /// Run `task` for every task index in `0..num_tasks` on the host's thread pool, falling back to
+    /// running the tasks serially if the host does not support the thread pool extension or if it
+    /// refused the request. This may only be called from the audio thread during processing.
+    pub fn execute_parallel(&self, num_tasks: u32, task: &(dyn Fn(u32) + Sync)) {
+        if num_tasks == 0 {
+            return;
+        }
+
+        if let Some(host_thread_pool) = &*self.host_thread_pool.borrow() {
+            // SAFETY: The pointer is cleared again before this function returns, and the host only
+            //         calls `ext_thread_pool_exec()` from within `request_exec()`
+            let task_ptr = unsafe {
+                std::mem::transmute::<
+                    *const (dyn Fn(u32) + Sync + '_),
+                    *const (dyn Fn(u32) + Sync + 'static),
+                >(task)
+            };
+            *self.current_thread_pool_task.borrow_mut() = Some(ThreadPoolTask(task_ptr));
+            let success = unsafe_clap_call! {
+                host_thread_pool=>request_exec(&*self.host_callback, num_tasks)
+            };
+            *self.current_thread_pool_task.borrow_mut() = None;
+
+            if success {
+                return;
+            }
+        }
+
+        (0..num_tasks).for_each(task);
+    }
+
+    /// Clear the managed bypass' dry signal delay lines when the plugin gets reset.
+    fn reset_managed_bypass(&self) {
+        self.managed_bypass.borrow_mut().reset();
+        self.managed_bypass_f64.borrow_mut().reset();
+    }
+
     pub fn set_current_voice_capacity(&self, capacity: u32) {
         match P::CLAP_POLY_MODULATION_CONFIG {
             Some(config) => {
@@ -1779,7 +2448,7 @@ impl<P: ClapPlugin> Wrapper<P> {
     /// # Notes
     ///
     /// `self.plugin` must _not_ be locked while calling this function or it will deadlock.
-    pub fn set_state_inner(&self, state: &mut PluginState) -> bool {
+    pub fn set_state_inner(&self, state: &mut PluginState, context: StateContext) -> bool {
         let audio_io_layout = self.current_audio_io_layout.load();
         let buffer_config = self.current_buffer_config.load();
 
@@ -1795,6 +2464,7 @@ impl<P: ClapPlugin> Wrapper<P> {
                 self.params.clone(),
                 state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
                 self.current_buffer_config.load().as_ref(),
+                context,
             )
         });
         if !success {
@@ -1802,6 +2472,9 @@ impl<P: ClapPlugin> Wrapper<P> {
             return false;
         }
 
+        // The plugin can query this context when it gets (re)initialized
+        self.pending_state_context.store(Some(context));
+
         // If the plugin was already initialized then it needs to be reinitialized
         if let Some(buffer_config) = buffer_config {
             // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
@@ -1841,12 +2514,45 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         // We weren't allowed to query these in the constructor, so we need to do it now instead.
+        *wrapper.host_context_menu.borrow_mut() = query_host_extension::<clap_host_context_menu>(
+            &wrapper.host_callback,
+            CLAP_EXT_CONTEXT_MENU,
+        )
+        .or_else(|| {
+            query_host_extension::<clap_host_context_menu>(
+                &wrapper.host_callback,
+                CLAP_EXT_CONTEXT_MENU_COMPAT,
+            )
+        });
         *wrapper.host_gui.borrow_mut() =
             query_host_extension::<clap_host_gui>(&wrapper.host_callback, CLAP_EXT_GUI);
         *wrapper.host_latency.borrow_mut() =
             query_host_extension::<clap_host_latency>(&wrapper.host_callback, CLAP_EXT_LATENCY);
         *wrapper.host_params.borrow_mut() =
             query_host_extension::<clap_host_params>(&wrapper.host_callback, CLAP_EXT_PARAMS);
+        *wrapper.host_posix_fd_support.borrow_mut() =
+            query_host_extension::<clap_host_posix_fd_support>(
+                &wrapper.host_callback,
+                CLAP_EXT_POSIX_FD_SUPPORT,
+            );
+        *wrapper.host_thread_pool.borrow_mut() = query_host_extension::<clap_host_thread_pool>(
+            &wrapper.host_callback,
+            CLAP_EXT_THREAD_POOL,
+        );
+        *wrapper.host_timer_support.borrow_mut() = query_host_extension::<clap_host_timer_support>(
+            &wrapper.host_callback,
+            CLAP_EXT_TIMER_SUPPORT,
+        );
+        *wrapper.host_track_info.borrow_mut() = query_host_extension::<clap_host_track_info>(
+            &wrapper.host_callback,
+            CLAP_EXT_TRACK_INFO,
+        )
+        .or_else(|| {
+            query_host_extension::<clap_host_track_info>(
+                &wrapper.host_callback,
+                CLAP_EXT_TRACK_INFO_COMPAT,
+            )
+        });
         *wrapper.host_voice_info.borrow_mut() = query_host_extension::<clap_host_voice_info>(
             &wrapper.host_callback,
             CLAP_EXT_VOICE_INFO,
@@ -1856,6 +2562,9 @@ impl<P: ClapPlugin> Wrapper<P> {
             CLAP_EXT_THREAD_CHECK,
         );
 
+        // The plugin can query this from its `initialize()` function
+        wrapper.update_track_info();
+
         true
     }
 
@@ -1888,6 +2597,11 @@ impl<P: ClapPlugin> Wrapper<P> {
         for param in wrapper.param_by_hash.values() {
             param.update_smoother(buffer_config.sample_rate, true);
         }
+        for param in wrapper.modulation_buffer_params_by_hash.values() {
+            if let Some(modulation_buffer) = param.modulation_buffer() {
+                modulation_buffer.resize(max_frames_count as usize);
+            }
+        }
 
         // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
         let mut init_context = wrapper.make_init_context();
@@ -1900,13 +2614,60 @@ impl<P: ClapPlugin> Wrapper<P> {
             // pointers into a set of `Buffer` objects for the plugin's main and auxiliary IO
             *wrapper.buffer_manager.borrow_mut() =
                 BufferManager::for_audio_io_layout(max_frames_count as usize, audio_io_layout);
+            if P::SUPPORTS_F64 {
+                *wrapper.buffer_manager_f64.borrow_mut() =
+                    BufferManager::for_audio_io_layout(max_frames_count as usize, audio_io_layout);
+            }
 
             // Also store this for later, so we can reinitialize the plugin after restoring state
             wrapper.current_buffer_config.store(Some(buffer_config));
 
-            true
-        } else {
-            false
+            // Latency changes requested while the plugin was activated, or by the plugin's
+            // `initialize()` function, are applied here since CLAP only allows the latency to
+            // change during activation
+            let requested_latency = wrapper.requested_latency.load(Ordering::SeqCst);
+            let old_latency = wrapper
+                .current_latency
+                .swap(requested_latency, Ordering::SeqCst);
+            if old_latency != requested_latency {
+                match &*wrapper.host_latency.borrow() {
+                    Some(host_latency) => {
+                        unsafe_clap_call! { host_latency=>changed(&*wrapper.host_callback) };
+                    }
+                    None => {
+                        nih_debug_assert_failure!("Host does not support the latency extension")
+                    }
+                }
+            }
+
+            // The dry signal for the managed bypass is delayed by the latency that was just
+            // reported to the host
+            let num_output_channels = audio_io_layout
+                .main_output_channels
+                .map(NonZeroU32::get)
+                .unwrap_or_default() as usize;
+            *wrapper.managed_bypass.borrow_mut() = ManagedBypass::for_plugin::<P>(
+                wrapper.param_by_hash.values(),
+                num_output_channels,
+                max_frames_count as usize,
+                buffer_config.sample_rate,
+                requested_latency,
+            );
+            if P::SUPPORTS_F64 {
+                *wrapper.managed_bypass_f64.borrow_mut() = ManagedBypass::for_plugin::<P>(
+                    wrapper.param_by_hash.values(),
+                    num_output_channels,
+                    max_frames_count as usize,
+                    buffer_config.sample_rate,
+                    requested_latency,
+                );
+            }
+
+            wrapper.is_active.store(true, Ordering::SeqCst);
+
+            true
+        } else {
+            false
         }
     }
 
@@ -1914,6 +2675,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!((), plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
+        wrapper.is_active.store(false, Ordering::SeqCst);
         wrapper.plugin.lock().deactivate();
     }
 
@@ -1930,6 +2692,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         // To be consistent with the VST3 wrapper, we'll also reset the buffers here in addition to
         // the dedicated `reset()` function.
         process_wrapper(|| wrapper.plugin.lock().reset());
+        wrapper.reset_managed_bypass();
 
         true
     }
@@ -1946,6 +2709,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
         process_wrapper(|| wrapper.plugin.lock().reset());
+        wrapper.reset_managed_bypass();
     }
 
     unsafe extern "C" fn process(
@@ -1965,11 +2729,27 @@ impl<P: ClapPlugin> Wrapper<P> {
             let total_buffer_len = process.frames_count as usize;
 
             let current_audio_io_layout = wrapper.current_audio_io_layout.load();
-            let has_main_input = current_audio_io_layout.main_input_channels.is_some();
             let has_main_output = current_audio_io_layout.main_output_channels.is_some();
-            let aux_input_start_idx = if has_main_input { 1 } else { 0 };
             let aux_output_start_idx = if has_main_output { 1 } else { 0 };
 
+            // The host decides on the sample size for every process call, and it indicates double
+            // precision processing by providing `data64` pointers instead of `data32` pointers.
+            // Since the ports require a common sample size, checking the first port is enough.
+            let use_f64 = P::SUPPORTS_F64 && {
+                let first_audio_buffer =
+                    if process.audio_outputs_count > 0 && !process.audio_outputs.is_null() {
+                        Some(&*process.audio_outputs)
+                    } else if process.audio_inputs_count > 0 && !process.audio_inputs.is_null() {
+                        Some(&*process.audio_inputs)
+                    } else {
+                        None
+                    };
+
+                first_audio_buffer.is_some_and(|audio_buffer| {
+                    audio_buffer.data32.is_null() && !audio_buffer.data64.is_null()
+                })
+            };
+
             // If `P::SAMPLE_ACCURATE_AUTOMATION` is set, then we'll split up the audio buffer into
             // chunks whenever a parameter change occurs
             let mut block_start = 0;
@@ -1980,6 +2760,12 @@ impl<P: ClapPlugin> Wrapper<P> {
             // split the buffer.
             let mut transport_info = process.transport;
 
+            // Parameters with modulation buffers get all of their modulation for this process call
+            // up front, so the buffer doesn't need to be split for those modulation events
+            if !wrapper.modulation_buffer_params_by_hash.is_empty() {
+                wrapper.fill_modulation_buffers(process.in_events, total_buffer_len);
+            }
+
             let result = loop {
                 if !process.in_events.is_null() {
                     let split_result = wrapper.handle_in_events_until(
@@ -2002,11 +2788,15 @@ impl<P: ClapPlugin> Wrapper<P> {
                                             &*(next_event as *const clap_event_param_mod);
 
                                         // The buffer should not be split on polyphonic modulation
-                                        // as those events will be converted to note events
-                                        !(next_event.note_id != -1
+                                        // as those events will be converted to note events, or on
+                                        // modulation for parameters with modulation buffers
+                                        !((next_event.note_id != -1
                                             && wrapper
                                                 .poly_mod_ids_by_hash
                                                 .contains_key(&next_event.param_id))
+                                            || wrapper
+                                                .modulation_buffer_params_by_hash
+                                                .contains_key(&next_event.param_id))
                                     }
                                     _ => false,
                                 }
@@ -2036,113 +2826,6 @@ impl<P: ClapPlugin> Wrapper<P> {
                 // we can start preparing audio processing
                 let block_len = block_end - block_start;
 
-                // The buffer manager preallocated buffer slices for all the IO and storage for any
-                // axuiliary inputs.
-                // TODO: The audio buffers have a latency field, should we use those?
-                // TODO: Like with VST3, should we expose some way to access or set the silence/constant
-                //       flags?
-                let mut buffer_manager = wrapper.buffer_manager.borrow_mut();
-                let buffers =
-                    buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
-                        // Explicitly take plugins with no main output that does have auxiliary
-                        // outputs into account. Shouldn't happen, but if we just start copying
-                        // audio here then that would result in unsoundness.
-                        if process.audio_outputs_count > 0
-                            && !process.audio_outputs.is_null()
-                            && !(*process.audio_outputs).data32.is_null()
-                            && has_main_output
-                        {
-                            let audio_output = &*process.audio_outputs;
-                            let ptrs = NonNull::new(audio_output.data32 as *mut *mut f32).unwrap();
-                            let num_channels = audio_output.channel_count as usize;
-
-                            *buffer_source.main_output_channel_pointers =
-                                Some(ChannelPointers { ptrs, num_channels });
-                        }
-
-                        if process.audio_inputs_count > 0
-                            && !process.audio_inputs.is_null()
-                            && !(*process.audio_inputs).data32.is_null()
-                            && has_main_input
-                        {
-                            let audio_input = &*process.audio_inputs;
-                            let ptrs = NonNull::new(audio_input.data32 as *mut *mut f32).unwrap();
-                            let num_channels = audio_input.channel_count as usize;
-
-                            *buffer_source.main_input_channel_pointers =
-                                Some(ChannelPointers { ptrs, num_channels });
-                        }
-
-                        if !process.audio_inputs.is_null() {
-                            for (aux_input_no, aux_input_channel_pointers) in buffer_source
-                                .aux_input_channel_pointers
-                                .iter_mut()
-                                .enumerate()
-                            {
-                                let aux_input_idx = aux_input_no + aux_input_start_idx;
-                                if aux_input_idx > process.audio_inputs_count as usize {
-                                    break;
-                                }
-
-                                let audio_input = &*process.audio_inputs.add(aux_input_idx);
-                                match NonNull::new(audio_input.data32 as *mut *mut f32) {
-                                    Some(ptrs) => {
-                                        let num_channels = audio_input.channel_count as usize;
-
-                                        *aux_input_channel_pointers =
-                                            Some(ChannelPointers { ptrs, num_channels });
-                                    }
-                                    None => continue,
-                                }
-                            }
-                        }
-
-                        if !process.audio_outputs.is_null() {
-                            for (aux_output_no, aux_output_channel_pointers) in buffer_source
-                                .aux_output_channel_pointers
-                                .iter_mut()
-                                .enumerate()
-                            {
-                                let aux_output_idx = aux_output_no + aux_output_start_idx;
-                                if aux_output_idx > process.audio_outputs_count as usize {
-                                    break;
-                                }
-
-                                let audio_output = &*process.audio_outputs.add(aux_output_idx);
-                                match NonNull::new(audio_output.data32 as *mut *mut f32) {
-                                    Some(ptrs) => {
-                                        let num_channels = audio_output.channel_count as usize;
-
-                                        *aux_output_channel_pointers =
-                                            Some(ChannelPointers { ptrs, num_channels });
-                                    }
-                                    None => continue,
-                                }
-                            }
-                        }
-                    });
-
-                // If the host does not provide outputs or if it does not provide the required
-                // number of channels (should not happen, but Ableton Live does this for bypassed
-                // VST3 plugins) then we'll skip audio processing. In that case
-                // `buffer_manager.create_buffers` will have set one or more of the output buffers
-                // to empty slices since there is no storage to point them to. The auxiliary input
-                // buffers always point to valid storage.
-                let mut buffer_is_valid = true;
-                for output_buffer_slice in buffers.main_buffer.as_slice_immutable().iter().chain(
-                    buffers
-                        .aux_outputs
-                        .iter()
-                        .flat_map(|buffer| buffer.as_slice_immutable().iter()),
-                ) {
-                    if output_buffer_slice.is_empty() {
-                        buffer_is_valid = false;
-                        break;
-                    }
-                }
-
-                nih_debug_assert!(buffer_is_valid);
-
                 // Some of the fields are left empty because CLAP does not provide this information,
                 // but the methods on [`Transport`] can reconstruct these values from the other
                 // fields
@@ -2235,21 +2918,34 @@ impl<P: ClapPlugin> Wrapper<P> {
                     }
                 }
 
-                let result = if buffer_is_valid {
-                    let mut plugin = wrapper.plugin.lock();
-                    // SAFETY: Shortening these borrows is safe as even if the plugin overwrites the
-                    //         slices (which it cannot do without using unsafe code), then they
-                    //         would still be reset on the next iteration
-                    let mut aux = AuxiliaryBuffers {
-                        inputs: buffers.aux_inputs,
-                        outputs: buffers.aux_outputs,
-                    };
-                    let mut context = wrapper.make_process_context(transport);
-                    let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
-                    wrapper.last_process_status.store(result);
-                    result
+                for param in wrapper.modulation_buffer_params_by_hash.values() {
+                    if let Some(modulation_buffer) = param.modulation_buffer() {
+                        modulation_buffer.set_block(block_start, block_len);
+                    }
+                }
+
+                let result = if use_f64 {
+                    wrapper.process_block(
+                        &wrapper.buffer_manager_f64,
+                        &wrapper.managed_bypass_f64,
+                        process,
+                        block_start,
+                        block_len,
+                        transport,
+                        |audio_buffer| audio_buffer.data64,
+                        |plugin, buffer, aux, context| plugin.process_f64(buffer, aux, context),
+                    )
                 } else {
-                    ProcessStatus::Normal
+                    wrapper.process_block(
+                        &wrapper.buffer_manager,
+                        &wrapper.managed_bypass,
+                        process,
+                        block_start,
+                        block_len,
+                        transport,
+                        |audio_buffer| audio_buffer.data32,
+                        |plugin, buffer, aux, context| plugin.process(buffer, aux, context),
+                    )
                 };
 
                 let clap_result = match result {
@@ -2279,6 +2975,22 @@ impl<P: ClapPlugin> Wrapper<P> {
                 }
             };
 
+            // The plugin can mark its outputs as constant, so the host can skip work in the
+            // following plugins
+            if use_f64 {
+                Self::write_output_constant_masks(
+                    &wrapper.buffer_manager_f64.borrow(),
+                    process,
+                    aux_output_start_idx,
+                );
+            } else {
+                Self::write_output_constant_masks(
+                    &wrapper.buffer_manager.borrow(),
+                    process,
+                    aux_output_start_idx,
+                );
+            }
+
             // After processing audio, we'll check if the editor has sent us updated plugin state.
             // We'll restore that here on the audio thread to prevent changing the values during the
             // process call and also to prevent inconsistent state when the host also wants to load
@@ -2286,12 +2998,12 @@ impl<P: ClapPlugin> Wrapper<P> {
             // FIXME: Zero capacity channels allocate on receiving, find a better alternative that
             //        doesn't do that
             let updated_state = permit_alloc(|| wrapper.updated_state_receiver.try_recv());
-            if let Ok(mut state) = updated_state {
-                wrapper.set_state_inner(&mut state);
+            if let Ok((mut state, context)) = updated_state {
+                wrapper.set_state_inner(&mut state, context);
 
                 // We'll pass the state object back to the GUI thread so deallocation can happen
                 // there without potentially blocking the audio thread
-                if let Err(err) = wrapper.updated_state_sender.send(state) {
+                if let Err(err) = wrapper.updated_state_sender.send((state, context)) {
                     nih_debug_assert_failure!(
                         "Failed to send state object back to GUI thread: {}",
                         err
@@ -2314,8 +3026,20 @@ impl<P: ClapPlugin> Wrapper<P> {
 
         if id == CLAP_EXT_AUDIO_PORTS_CONFIG {
             &wrapper.clap_plugin_audio_ports_config as *const _ as *const c_void
+        } else if id == CLAP_EXT_AMBISONIC || id == CLAP_EXT_AMBISONIC_COMPAT {
+            &wrapper.clap_plugin_ambisonic as *const _ as *const c_void
         } else if id == CLAP_EXT_AUDIO_PORTS {
             &wrapper.clap_plugin_audio_ports as *const _ as *const c_void
+        } else if id == CLAP_EXT_AUDIO_PORTS_ACTIVATION
+            || id == CLAP_EXT_AUDIO_PORTS_ACTIVATION_COMPAT
+        {
+            &wrapper.clap_plugin_audio_ports_activation as *const _ as *const c_void
+        } else if id == CLAP_EXT_CONFIGURABLE_AUDIO_PORTS
+            || id == CLAP_EXT_CONFIGURABLE_AUDIO_PORTS_COMPAT
+        {
+            &wrapper.clap_plugin_configurable_audio_ports as *const _ as *const c_void
+        } else if id == CLAP_EXT_CONTEXT_MENU || id == CLAP_EXT_CONTEXT_MENU_COMPAT {
+            &wrapper.clap_plugin_context_menu as *const _ as *const c_void
         } else if id == CLAP_EXT_GUI && wrapper.editor.borrow().is_some() {
             // Only report that we support this extension if the plugin has an editor
             &wrapper.clap_plugin_gui as *const _ as *const c_void
@@ -2325,16 +3049,30 @@ impl<P: ClapPlugin> Wrapper<P> {
             && (P::MIDI_INPUT >= MidiConfig::Basic || P::MIDI_OUTPUT >= MidiConfig::Basic)
         {
             &wrapper.clap_plugin_note_ports as *const _ as *const c_void
+        } else if id == CLAP_EXT_PARAM_INDICATION || id == CLAP_EXT_PARAM_INDICATION_COMPAT {
+            &wrapper.clap_plugin_param_indication as *const _ as *const c_void
         } else if id == CLAP_EXT_PARAMS {
             &wrapper.clap_plugin_params as *const _ as *const c_void
+        } else if id == CLAP_EXT_POSIX_FD_SUPPORT {
+            &wrapper.clap_plugin_posix_fd_support as *const _ as *const c_void
         } else if id == CLAP_EXT_REMOTE_CONTROLS {
             &wrapper.clap_plugin_remote_controls as *const _ as *const c_void
         } else if id == CLAP_EXT_RENDER {
             &wrapper.clap_plugin_render as *const _ as *const c_void
         } else if id == CLAP_EXT_STATE {
             &wrapper.clap_plugin_state as *const _ as *const c_void
+        } else if id == CLAP_EXT_STATE_CONTEXT {
+            &wrapper.clap_plugin_state_context as *const _ as *const c_void
+        } else if id == CLAP_EXT_SURROUND || id == CLAP_EXT_SURROUND_COMPAT {
+            &wrapper.clap_plugin_surround as *const _ as *const c_void
         } else if id == CLAP_EXT_TAIL {
             &wrapper.clap_plugin_tail as *const _ as *const c_void
+        } else if id == CLAP_EXT_THREAD_POOL {
+            &wrapper.clap_plugin_thread_pool as *const _ as *const c_void
+        } else if id == CLAP_EXT_TIMER_SUPPORT {
+            &wrapper.clap_plugin_timer_support as *const _ as *const c_void
+        } else if id == CLAP_EXT_TRACK_INFO || id == CLAP_EXT_TRACK_INFO_COMPAT {
+            &wrapper.clap_plugin_track_info as *const _ as *const c_void
         } else if id == CLAP_EXT_VOICE_INFO && P::CLAP_POLY_MODULATION_CONFIG.is_some() {
             &wrapper.clap_plugin_voice_info as *const _ as *const c_void
         } else {
@@ -2354,6 +3092,47 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_ambisonic_is_config_supported(
+        plugin: *const clap_plugin,
+        config: *const clap_ambisonic_config,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, config);
+
+        // The configuration is supported if any port in any of the audio IO layouts uses it
+        let config = &*config;
+        P::AUDIO_IO_LAYOUTS
+            .iter()
+            .flat_map(all_port_channel_layouts)
+            .any(|layout| match layout {
+                ChannelLayout::Ambisonic(layout) => {
+                    let supported_config = clap_ambisonic_config_from_layout(layout);
+                    supported_config.ordering == config.ordering
+                        && supported_config.normalization == config.normalization
+                }
+                _ => false,
+            })
+    }
+
+    unsafe extern "C" fn ext_ambisonic_get_config(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        config: *mut clap_ambisonic_config,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, config);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let current_audio_io_layout = wrapper.current_audio_io_layout.load();
+        match Self::port_channel_layout(&current_audio_io_layout, is_input, port_index) {
+            Some(ChannelLayout::Ambisonic(layout)) => {
+                *config = clap_ambisonic_config_from_layout(layout);
+
+                true
+            }
+            _ => false,
+        }
+    }
+
     unsafe extern "C" fn ext_audio_ports_config_count(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
 
@@ -2376,16 +3155,8 @@ impl<P: ClapPlugin> Wrapper<P> {
                 let main_input_channels = audio_io_layout.main_input_channels.map(NonZeroU32::get);
                 let main_output_channels =
                     audio_io_layout.main_output_channels.map(NonZeroU32::get);
-                let input_port_type = match main_input_channels {
-                    Some(1) => CLAP_PORT_MONO.as_ptr(),
-                    Some(2) => CLAP_PORT_STEREO.as_ptr(),
-                    _ => std::ptr::null(),
-                };
-                let output_port_type = match main_output_channels {
-                    Some(1) => CLAP_PORT_MONO.as_ptr(),
-                    Some(2) => CLAP_PORT_STEREO.as_ptr(),
-                    _ => std::ptr::null(),
-                };
+                let input_port_type = clap_port_type(audio_io_layout.main_input_layout());
+                let output_port_type = clap_port_type(audio_io_layout.main_output_layout());
 
                 *config = std::mem::zeroed();
 
@@ -2429,6 +3200,8 @@ impl<P: ClapPlugin> Wrapper<P> {
         match P::AUDIO_IO_LAYOUTS.get(config_id as usize) {
             Some(audio_io_layout) => {
                 wrapper.current_audio_io_layout.store(*audio_io_layout);
+                // Selecting a new configuration resets the ports' activation state
+                wrapper.aux_port_activation.reset();
 
                 true
             }
@@ -2505,11 +3278,15 @@ impl<P: ClapPlugin> Wrapper<P> {
         } else {
             index + num_input_ports
         };
+        // In-place processing is only possible if the main input and output have the same number of
+        // channels
+        let has_symmetric_main_io = current_audio_io_layout.main_input_channels
+            == current_audio_io_layout.main_output_channels;
         let pair_stable_id = match (is_input, is_main_port) {
             // Ports are named linearly with inputs coming before outputs, so this is the index of
             // the first output port
-            (true, true) if has_main_output => num_input_ports,
-            (false, true) if has_main_input => 0,
+            (true, true) if has_main_output && has_symmetric_main_io => num_input_ports,
+            (false, true) if has_main_input && has_symmetric_main_io => 0,
             _ => CLAP_INVALID_ID,
         };
 
@@ -2531,11 +3308,11 @@ impl<P: ClapPlugin> Wrapper<P> {
             (n, false) => current_audio_io_layout.aux_output_ports[n as usize].get(),
         };
 
-        let port_type = match channel_count {
-            1 => CLAP_PORT_MONO.as_ptr(),
-            2 => CLAP_PORT_STEREO.as_ptr(),
-            _ => std::ptr::null(),
-        };
+        let port_type = clap_port_type(Self::port_channel_layout(
+            &current_audio_io_layout,
+            is_input,
+            index,
+        ));
 
         *info = std::mem::zeroed();
 
@@ -2568,6 +3345,11 @@ impl<P: ClapPlugin> Wrapper<P> {
         } else {
             0
         };
+        if P::SUPPORTS_F64 {
+            // The process function only looks at the first port to determine the sample size
+            info.flags |=
+                CLAP_AUDIO_PORT_SUPPORTS_64BITS | CLAP_AUDIO_PORT_REQUIRES_COMMON_SAMPLE_SIZE;
+        }
         info.channel_count = channel_count;
         info.port_type = port_type;
         info.in_place_pair = pair_stable_id;
@@ -2575,6 +3357,242 @@ impl<P: ClapPlugin> Wrapper<P> {
         true
     }
 
+    /// Get the channel layout for the audio port with the given CLAP port index. The main port, if
+    /// the layout has one, always comes first. Returns `None` if the port index is out of bounds
+    /// or if the port doesn't have a defined channel layout.
+    fn port_channel_layout(
+        audio_io_layout: &AudioIOLayout,
+        is_input: bool,
+        index: u32,
+    ) -> Option<ChannelLayout> {
+        let index = index as usize;
+        match is_input {
+            true if audio_io_layout.main_input_channels.is_some() => match index {
+                0 => audio_io_layout.main_input_layout(),
+                n => audio_io_layout.aux_input_layout(n - 1),
+            },
+            false if audio_io_layout.main_output_channels.is_some() => match index {
+                0 => audio_io_layout.main_output_layout(),
+                n => audio_io_layout.aux_output_layout(n - 1),
+            },
+            true => audio_io_layout.aux_input_layout(index),
+            false => audio_io_layout.aux_output_layout(index),
+        }
+    }
+
+    unsafe extern "C" fn ext_audio_ports_activation_can_activate_while_processing(
+        _plugin: *const clap_plugin,
+    ) -> bool {
+        // The activation state is stored in atomics and read at the start of every process call
+        true
+    }
+
+    unsafe extern "C" fn ext_audio_ports_activation_set_active(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        is_active: bool,
+        _sample_size: u32,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let num_ports = Self::ext_audio_ports_count(plugin, is_input);
+        if port_index >= num_ports {
+            nih_debug_assert_failure!(
+                "Host tried to activate out of bounds audio port {} (input: {})",
+                port_index,
+                is_input
+            );
+
+            return false;
+        }
+
+        // Only the activation state of the auxiliary ports is exposed to the plugin. The main
+        // ports are always the first port if they exist.
+        let current_audio_io_layout = wrapper.current_audio_io_layout.load();
+        let has_main_port = if is_input {
+            current_audio_io_layout.main_input_channels.is_some()
+        } else {
+            current_audio_io_layout.main_output_channels.is_some()
+        };
+        match (port_index, has_main_port) {
+            (0, true) => true,
+            (n, true) => {
+                wrapper
+                    .aux_port_activation
+                    .set_active(is_input, n as usize - 1, is_active)
+            }
+            (n, false) => wrapper
+                .aux_port_activation
+                .set_active(is_input, n as usize, is_active),
+        }
+    }
+
+    unsafe extern "C" fn ext_configurable_audio_ports_can_apply_configuration(
+        plugin: *const clap_plugin,
+        requests: *const clap_audio_port_configuration_request,
+        request_count: u32,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        wrapper
+            .configured_audio_io_layout(requests, request_count)
+            .is_some()
+    }
+
+    unsafe extern "C" fn ext_configurable_audio_ports_apply_configuration(
+        plugin: *const clap_plugin,
+        requests: *const clap_audio_port_configuration_request,
+        request_count: u32,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        match wrapper.configured_audio_io_layout(requests, request_count) {
+            Some(audio_io_layout) => {
+                wrapper.current_audio_io_layout.store(audio_io_layout);
+                // Reconfiguring the ports resets their activation state, just like when selecting
+                // a different audio ports config
+                wrapper.aux_port_activation.reset();
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Apply the host's `configurable-audio-ports` requests to the current audio IO layout. Only
+    /// the main ports' channel counts and layouts can be changed, so requests for auxiliary ports
+    /// need to match the current layout. Returns `None` if the request is invalid or if the plugin
+    /// doesn't support the resulting layout.
+    unsafe fn configured_audio_io_layout(
+        &self,
+        requests: *const clap_audio_port_configuration_request,
+        request_count: u32,
+    ) -> Option<AudioIOLayout> {
+        let requests = if request_count == 0 {
+            &[]
+        } else if requests.is_null() {
+            nih_debug_assert_failure!("Null pointer passed to function");
+            return None;
+        } else {
+            std::slice::from_raw_parts(requests, request_count as usize)
+        };
+
+        let mut audio_io_layout = self.current_audio_io_layout.load();
+        for request in requests {
+            let num_channels = NonZeroU32::new(request.channel_count)?;
+            // Without a port type the port doesn't have a specific channel layout
+            let channel_layout = if request.port_type.is_null() {
+                None
+            } else {
+                Some(channel_layout_from_clap_request(request)?)
+            };
+
+            let has_main_port = if request.is_input {
+                audio_io_layout.main_input_channels.is_some()
+            } else {
+                audio_io_layout.main_output_channels.is_some()
+            };
+            match (request.is_input, request.port_index == 0 && has_main_port) {
+                (true, true) => {
+                    audio_io_layout.main_input_channels = Some(num_channels);
+                    audio_io_layout.channel_layouts.main_input = channel_layout;
+                }
+                (false, true) => {
+                    audio_io_layout.main_output_channels = Some(num_channels);
+                    audio_io_layout.channel_layouts.main_output = channel_layout;
+                }
+                (is_input, false) => {
+                    let aux_port_idx =
+                        request.port_index as usize - if has_main_port { 1 } else { 0 };
+                    let aux_ports = if is_input {
+                        audio_io_layout.aux_input_ports
+                    } else {
+                        audio_io_layout.aux_output_ports
+                    };
+                    let current_channel_layout =
+                        Self::port_channel_layout(&audio_io_layout, is_input, request.port_index);
+                    if *aux_ports.get(aux_port_idx)? != num_channels
+                        || (channel_layout.is_some() && channel_layout != current_channel_layout)
+                    {
+                        return None;
+                    }
+                }
+            }
+        }
+
+        supported_audio_io_layout::<P>(&audio_io_layout)
+    }
+
+    unsafe extern "C" fn ext_context_menu_populate(
+        plugin: *const clap_plugin,
+        target: *const clap_context_menu_target,
+        builder: *const clap_context_menu_builder,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, builder);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        // We only add our own entries to parameter context menus. A null target means the global
+        // context menu, which we don't add anything to.
+        if target.is_null() || (*target).kind != CLAP_CONTEXT_MENU_TARGET_KIND_PARAM {
+            return true;
+        }
+        if !wrapper.param_by_hash.contains_key(&(*target).id) {
+            nih_debug_assert_failure!("Unknown parameter hash: {}", (*target).id);
+            return false;
+        }
+
+        let builder = &*builder;
+        let entry = clap_context_menu_entry {
+            label: c"Reset to Default".as_ptr(),
+            is_enabled: true,
+            action_id: CONTEXT_MENU_ACTION_RESET_PARAM,
+        };
+        unsafe_clap_call! {
+            builder=>add_item(builder, CLAP_CONTEXT_MENU_ITEM_ENTRY, &entry as *const _ as *const c_void)
+        }
+    }
+
+    unsafe extern "C" fn ext_context_menu_perform(
+        plugin: *const clap_plugin,
+        target: *const clap_context_menu_target,
+        action_id: clap_id,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, target);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        if (*target).kind != CLAP_CONTEXT_MENU_TARGET_KIND_PARAM
+            || action_id != CONTEXT_MENU_ACTION_RESET_PARAM
+        {
+            nih_debug_assert_failure!("Unknown context menu action: {}", action_id);
+            return false;
+        }
+
+        let param_hash = (*target).id;
+        match wrapper.param_by_hash.get(&param_hash) {
+            Some(param_ptr) => {
+                // This is sent to the host as a regular automation gesture. The parameter's value
+                // is updated when these output events are flushed, just like with changes made
+                // from the editor.
+                let clap_plain_value = param_ptr.default_normalized_value() as f64
+                    * param_ptr.step_count().unwrap_or(1) as f64;
+                wrapper.queue_parameter_event(OutputParamEvent::BeginGesture { param_hash })
+                    && wrapper.queue_parameter_event(OutputParamEvent::SetValue {
+                        param_hash,
+                        clap_plain_value,
+                    })
+                    && wrapper.queue_parameter_event(OutputParamEvent::EndGesture { param_hash })
+            }
+            None => {
+                nih_debug_assert_failure!("Unknown parameter hash: {}", param_hash);
+                false
+            }
+        }
+    }
+
     unsafe extern "C" fn ext_gui_is_api_supported(
         _plugin: *const clap_plugin,
         api: *const c_char,
@@ -2659,6 +3677,27 @@ impl<P: ClapPlugin> Wrapper<P> {
         let mut editor_handle = wrapper.editor_handle.lock();
         if editor_handle.is_some() {
             *editor_handle = None;
+
+            // The editor is no longer around to receive these callbacks
+            let timer_id = wrapper
+                .editor_timer_id
+                .swap(CLAP_INVALID_ID, Ordering::SeqCst);
+            if timer_id != CLAP_INVALID_ID {
+                if let Some(host_timer_support) = &*wrapper.host_timer_support.borrow() {
+                    unsafe_clap_call! {
+                        host_timer_support=>unregister_timer(&*wrapper.host_callback, timer_id)
+                    };
+                }
+            }
+            // The lock needs to be released before calling into the host
+            let editor_fds = std::mem::take(&mut *wrapper.editor_fds.lock());
+            for fd in editor_fds {
+                if let Some(host_posix_fd_support) = &*wrapper.host_posix_fd_support.borrow() {
+                    unsafe_clap_call! {
+                        host_posix_fd_support=>unregister_fd(&*wrapper.host_callback, fd)
+                    };
+                }
+            }
         } else {
             nih_debug_assert_failure!("Tried destroying editor while the editor was not active");
         }
@@ -2790,6 +3829,26 @@ impl<P: ClapPlugin> Wrapper<P> {
                         .spawn(parent_handle, wrapper.clone().make_gui_context()),
                 );
 
+                // If the editor wants to be ticked, then the host's timers are used to drive it
+                let tick_rate = wrapper.editor.borrow().as_ref().unwrap().lock().tick_rate();
+                if let (Some(tick_rate), Some(host_timer_support)) =
+                    (tick_rate, &*wrapper.host_timer_support.borrow())
+                {
+                    let period_ms = (1000.0 / tick_rate).round().max(1.0) as u32;
+                    let mut timer_id = CLAP_INVALID_ID;
+                    let success = unsafe_clap_call! {
+                        host_timer_support=>register_timer(
+                            &*wrapper.host_callback,
+                            period_ms,
+                            &mut timer_id,
+                        )
+                    };
+                    nih_debug_assert!(success, "The host refused to register the editor's timer");
+                    if success {
+                        wrapper.editor_timer_id.store(timer_id, Ordering::SeqCst);
+                    }
+                }
+
                 true
             } else {
                 nih_debug_assert_failure!(
@@ -2882,6 +3941,97 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_param_indication_set_mapping(
+        plugin: *const clap_plugin,
+        param_id: clap_id,
+        has_mapping: bool,
+        color: *const clap_color,
+        label: *const c_char,
+        description: *const c_char,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let param_id_str = match wrapper.param_id_by_hash.get(&param_id) {
+            Some(param_id_str) => param_id_str,
+            None => {
+                nih_debug_assert_failure!("Unknown parameter hash: {}", param_id);
+                return;
+            }
+        };
+
+        let optional_string = |ptr: *const c_char| {
+            if ptr.is_null() {
+                None
+            } else {
+                Some(Arc::from(CStr::from_ptr(ptr).to_string_lossy()))
+            }
+        };
+        let mapping = if has_mapping {
+            Some(ParamMapping {
+                color: color.as_ref().map(host_color_from_clap),
+                label: optional_string(label),
+                description: optional_string(description),
+            })
+        } else {
+            None
+        };
+
+        wrapper
+            .param_indications
+            .lock()
+            .entry(param_id)
+            .or_default()
+            .mapping = mapping;
+        if wrapper.editor_handle.lock().is_some() {
+            if let Some(editor) = wrapper.editor.borrow().as_ref() {
+                editor.lock().param_indication_changed(param_id_str);
+            }
+        }
+    }
+
+    unsafe extern "C" fn ext_param_indication_set_automation(
+        plugin: *const clap_plugin,
+        param_id: clap_id,
+        automation_state: u32,
+        color: *const clap_color,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let param_id_str = match wrapper.param_id_by_hash.get(&param_id) {
+            Some(param_id_str) => param_id_str,
+            None => {
+                nih_debug_assert_failure!("Unknown parameter hash: {}", param_id);
+                return;
+            }
+        };
+
+        let automation = match automation_state {
+            CLAP_PARAM_INDICATION_AUTOMATION_NONE => ParamAutomationState::None,
+            CLAP_PARAM_INDICATION_AUTOMATION_PRESENT => ParamAutomationState::Present,
+            CLAP_PARAM_INDICATION_AUTOMATION_PLAYING => ParamAutomationState::Playing,
+            CLAP_PARAM_INDICATION_AUTOMATION_RECORDING => ParamAutomationState::Recording,
+            CLAP_PARAM_INDICATION_AUTOMATION_OVERRIDING => ParamAutomationState::Overriding,
+            n => {
+                nih_debug_assert_failure!("Unknown automation state: {}", n);
+                return;
+            }
+        };
+
+        {
+            let mut param_indications = wrapper.param_indications.lock();
+            let indication = param_indications.entry(param_id).or_default();
+            indication.automation = automation;
+            indication.automation_color = color.as_ref().map(host_color_from_clap);
+        }
+        if wrapper.editor_handle.lock().is_some() {
+            if let Some(editor) = wrapper.editor.borrow().as_ref() {
+                editor.lock().param_indication_changed(param_id_str);
+            }
+        }
+    }
+
     unsafe extern "C" fn ext_params_count(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
@@ -3043,6 +4193,24 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_posix_fd_support_on_fd(
+        plugin: *const clap_plugin,
+        fd: i32,
+        _flags: clap_posix_fd_flags,
+    ) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        if !wrapper.editor_fds.lock().contains(&fd) {
+            nih_debug_assert_failure!("The host called 'on_fd()' for an unknown file descriptor");
+            return;
+        }
+
+        if let Some(editor) = wrapper.editor.borrow().as_ref() {
+            editor.lock().on_fd_ready(fd);
+        }
+    }
+
     unsafe extern "C" fn ext_remote_controls_count(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
@@ -3102,29 +4270,74 @@ impl<P: ClapPlugin> Wrapper<P> {
         check_null_ptr!(false, plugin, (*plugin).plugin_data, stream);
         let wrapper = &*((*plugin).plugin_data as *const Self);
 
-        let serialized = state::serialize_json::<P>(
-            wrapper.params.clone(),
-            state::make_params_iter(&wrapper.param_by_hash, &wrapper.param_id_to_hash),
+        wrapper.save_state(&*stream, StateContext::Project)
+    }
+
+    unsafe extern "C" fn ext_state_load(
+        plugin: *const clap_plugin,
+        stream: *const clap_istream,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, stream);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        wrapper.load_state(&*stream, StateContext::Project)
+    }
+
+    unsafe extern "C" fn ext_state_context_save(
+        plugin: *const clap_plugin,
+        stream: *const clap_ostream,
+        context_type: clap_plugin_state_context_type,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, stream);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        match state_context_from_clap(context_type) {
+            Some(context) => wrapper.save_state(&*stream, context),
+            None => false,
+        }
+    }
+
+    unsafe extern "C" fn ext_state_context_load(
+        plugin: *const clap_plugin,
+        stream: *const clap_istream,
+        context_type: clap_plugin_state_context_type,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data, stream);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        match state_context_from_clap(context_type) {
+            Some(context) => wrapper.load_state(&*stream, context),
+            None => false,
+        }
+    }
+
+    /// Write the plugin's state to a CLAP stream. Used for both the `state` and the `state-context`
+    /// extensions.
+    unsafe fn save_state(&self, stream: &clap_ostream, context: StateContext) -> bool {
+        let serialized = state::serialize_state::<P>(
+            self.params.clone(),
+            state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+            context,
         );
         match serialized {
             Ok(serialized) => {
                 // CLAP does not provide a way to tell how much data there is left in a stream, so
                 // we need to prepend it to our actual state data.
                 let length_bytes = (serialized.len() as u64).to_le_bytes();
-                if !write_stream(&*stream, &length_bytes) {
+                if !write_stream(stream, &length_bytes) {
                     nih_debug_assert_failure!(
                         "Error or end of stream while writing the state length to the stream."
                     );
                     return false;
                 }
-                if !write_stream(&*stream, &serialized) {
+                if !write_stream(stream, &serialized) {
                     nih_debug_assert_failure!(
                         "Error or end of stream while writing the state buffer to the stream."
                     );
                     return false;
                 }
 
-                nih_trace!("Saved state ({} bytes)", serialized.len());
+                nih_trace!("Saved state ({} bytes, {:?})", serialized.len(), context);
 
                 true
             }
@@ -3135,17 +4348,13 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
-    unsafe extern "C" fn ext_state_load(
-        plugin: *const clap_plugin,
-        stream: *const clap_istream,
-    ) -> bool {
-        check_null_ptr!(false, plugin, (*plugin).plugin_data, stream);
-        let wrapper = &*((*plugin).plugin_data as *const Self);
-
+    /// Restore the plugin's state from a CLAP stream. Used for both the `state` and the
+    /// `state-context` extensions.
+    unsafe fn load_state(&self, stream: &clap_istream, context: StateContext) -> bool {
         // CLAP does not have a way to tell how much data there is left in a stream, so we've
         // prepended the size in front of our JSON state
         let mut length_bytes = [0u8; 8];
-        if !read_stream(&*stream, length_bytes.as_mut_slice()) {
+        if !read_stream(stream, length_bytes.as_mut_slice()) {
             nih_debug_assert_failure!(
                 "Error or end of stream while reading the state length from the stream."
             );
@@ -3154,7 +4363,7 @@ impl<P: ClapPlugin> Wrapper<P> {
         let length = u64::from_le_bytes(length_bytes);
 
         let mut read_buffer: Vec<u8> = Vec::with_capacity(length as usize);
-        if !read_stream(&*stream, read_buffer.spare_capacity_mut()) {
+        if !read_stream(stream, read_buffer.spare_capacity_mut()) {
             nih_debug_assert_failure!(
                 "Error or end of stream while reading the state buffer from the stream."
             );
@@ -3162,11 +4371,11 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
         read_buffer.set_len(length as usize);
 
-        match state::deserialize_json(&read_buffer) {
+        match state::deserialize_state(&read_buffer) {
             Some(mut state) => {
-                let success = wrapper.set_state_inner(&mut state);
+                let success = self.set_state_inner(&mut state, context);
                 if success {
-                    nih_trace!("Loaded state ({} bytes)", read_buffer.len());
+                    nih_trace!("Loaded state ({} bytes, {:?})", read_buffer.len(), context);
                 }
 
                 success
@@ -3175,6 +4384,47 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_surround_is_channel_mask_supported(
+        plugin: *const clap_plugin,
+        channel_mask: u64,
+    ) -> bool {
+        check_null_ptr!(false, plugin, (*plugin).plugin_data);
+
+        P::AUDIO_IO_LAYOUTS
+            .iter()
+            .flat_map(all_port_channel_layouts)
+            .any(|layout| layout.speaker_mask() == Some(channel_mask))
+    }
+
+    unsafe extern "C" fn ext_surround_get_channel_map(
+        plugin: *const clap_plugin,
+        is_input: bool,
+        port_index: u32,
+        channel_map: *mut u8,
+        channel_map_capacity: u32,
+    ) -> u32 {
+        check_null_ptr!(0, plugin, (*plugin).plugin_data, channel_map);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        let current_audio_io_layout = wrapper.current_audio_io_layout.load();
+        let speakers =
+            match Self::port_channel_layout(&current_audio_io_layout, is_input, port_index)
+                .and_then(|layout| layout.speakers())
+            {
+                Some(speakers) => speakers,
+                None => return 0,
+            };
+
+        // The `Speaker` enum uses the same order as CLAP's surround channel identifiers
+        let channel_map =
+            std::slice::from_raw_parts_mut(channel_map, channel_map_capacity as usize);
+        for (channel, speaker) in channel_map.iter_mut().zip(speakers) {
+            *channel = *speaker as u8;
+        }
+
+        speakers.len().min(channel_map_capacity as usize) as u32
+    }
+
     unsafe extern "C" fn ext_tail_get(plugin: *const clap_plugin) -> u32 {
         check_null_ptr!(0, plugin, (*plugin).plugin_data);
         let wrapper = &*((*plugin).plugin_data as *const Self);
@@ -3186,6 +4436,46 @@ impl<P: ClapPlugin> Wrapper<P> {
         }
     }
 
+    unsafe extern "C" fn ext_thread_pool_exec(plugin: *const clap_plugin, task_index: u32) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        match *wrapper.current_thread_pool_task.borrow() {
+            Some(ThreadPoolTask(task)) => (*task)(task_index),
+            None => nih_debug_assert_failure!(
+                "The host tried to execute a thread pool task outside of \
+                 'clap_host_thread_pool::request_exec()'"
+            ),
+        }
+    }
+
+    unsafe extern "C" fn ext_timer_support_on_timer(plugin: *const clap_plugin, timer_id: clap_id) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        if timer_id == CLAP_INVALID_ID || timer_id != wrapper.editor_timer_id.load(Ordering::SeqCst)
+        {
+            nih_debug_assert_failure!("The host called 'on_timer()' for an unknown timer");
+            return;
+        }
+
+        if let Some(editor) = wrapper.editor.borrow().as_ref() {
+            editor.lock().tick();
+        }
+    }
+
+    unsafe extern "C" fn ext_track_info_changed(plugin: *const clap_plugin) {
+        check_null_ptr!((), plugin, (*plugin).plugin_data);
+        let wrapper = &*((*plugin).plugin_data as *const Self);
+
+        wrapper.update_track_info();
+        if wrapper.editor_handle.lock().is_some() {
+            if let Some(editor) = wrapper.editor.borrow().as_ref() {
+                editor.lock().track_info_changed();
+            }
+        }
+    }
+
     unsafe extern "C" fn ext_voice_info_get(
         plugin: *const clap_plugin,
         info: *mut clap_voice_info,
@@ -3212,6 +4502,16 @@ impl<P: ClapPlugin> Wrapper<P> {
     }
 }
 
+/// Convert a CLAP color to a [`HostColor`].
+fn host_color_from_clap(color: &clap_color) -> HostColor {
+    HostColor {
+        red: color.red,
+        green: color.green,
+        blue: color.blue,
+        alpha: color.alpha,
+    }
+}
+
 /// Convenience function to query an extension from the host.
 ///
 /// # Safety
@@ -3229,3 +4529,126 @@ unsafe fn query_host_extension<T>(
         None
     }
 }
+
+/// Convert a CLAP `state-context` type to a [`StateContext`]. Returns `None` and logs an error for
+/// unknown context types.
+fn state_context_from_clap(context_type: clap_plugin_state_context_type) -> Option<StateContext> {
+    match context_type {
+        CLAP_STATE_CONTEXT_FOR_PROJECT => Some(StateContext::Project),
+        CLAP_STATE_CONTEXT_FOR_PRESET => Some(StateContext::Preset),
+        CLAP_STATE_CONTEXT_FOR_DUPLICATE => Some(StateContext::Duplicate),
+        n => {
+            nih_debug_assert_failure!("Unknown CLAP state context type: {}", n);
+            None
+        }
+    }
+}
+
+/// All of the defined channel layouts in an audio IO layout, for both inputs and outputs.
+fn all_port_channel_layouts(audio_io_layout: &AudioIOLayout) -> Vec<ChannelLayout> {
+    let aux_input_layouts =
+        (0..audio_io_layout.aux_input_ports.len()).map(|idx| audio_io_layout.aux_input_layout(idx));
+    let aux_output_layouts = (0..audio_io_layout.aux_output_ports.len())
+        .map(|idx| audio_io_layout.aux_output_layout(idx));
+
+    [
+        audio_io_layout.main_input_layout(),
+        audio_io_layout.main_output_layout(),
+    ]
+    .into_iter()
+    .chain(aux_input_layouts)
+    .chain(aux_output_layouts)
+    .flatten()
+    .collect()
+}
+
+/// The CLAP port type for a port with the given channel layout. Ports without a defined layout
+/// don't have a port type.
+fn clap_port_type(layout: Option<ChannelLayout>) -> *const c_char {
+    match layout {
+        Some(ChannelLayout::Mono) => CLAP_PORT_MONO.as_ptr(),
+        Some(ChannelLayout::Stereo) => CLAP_PORT_STEREO.as_ptr(),
+        Some(ChannelLayout::Ambisonic(_)) => CLAP_PORT_AMBISONIC.as_ptr(),
+        Some(_) => CLAP_PORT_SURROUND.as_ptr(),
+        None => std::ptr::null(),
+    }
+}
+
+/// Convert a `configurable-audio-ports` request with a non-null port type to a [`ChannelLayout`].
+/// Returns `None` if the port type is unknown, or if the channel layout can't be represented as a
+/// [`ChannelLayout`].
+unsafe fn channel_layout_from_clap_request(
+    request: &clap_audio_port_configuration_request,
+) -> Option<ChannelLayout> {
+    let port_type = CStr::from_ptr(request.port_type);
+    let channel_layout = if port_type == CLAP_PORT_MONO {
+        ChannelLayout::Mono
+    } else if port_type == CLAP_PORT_STEREO {
+        ChannelLayout::Stereo
+    } else if port_type == CLAP_PORT_SURROUND && !request.port_details.is_null() {
+        // The channel map needs to use the same speaker order as the matching layout
+        let channel_map = std::slice::from_raw_parts(
+            request.port_details as *const u8,
+            request.channel_count as usize,
+        );
+        let speaker_mask = channel_map.iter().try_fold(0u64, |mask, &channel| {
+            Some(mask | 1u64.checked_shl(channel as u32)?)
+        })?;
+        let channel_layout = ChannelLayout::from_speaker_mask(speaker_mask)?;
+        if !channel_layout
+            .speakers()?
+            .iter()
+            .map(|speaker| *speaker as u8)
+            .eq(channel_map.iter().copied())
+        {
+            return None;
+        }
+
+        channel_layout
+    } else if port_type == CLAP_PORT_AMBISONIC && !request.port_details.is_null() {
+        let config = &*(request.port_details as *const clap_ambisonic_config);
+        let order = ((request.channel_count as f64).sqrt() as u32).saturating_sub(1);
+        ChannelLayout::Ambisonic(AmbisonicLayout {
+            order,
+            ordering: match config.ordering {
+                CLAP_AMBISONIC_ORDERING_FUMA => AmbisonicOrdering::Fuma,
+                CLAP_AMBISONIC_ORDERING_ACN => AmbisonicOrdering::Acn,
+                _ => return None,
+            },
+            normalization: match config.normalization {
+                CLAP_AMBISONIC_NORMALIZATION_MAXN => AmbisonicNormalization::MaxN,
+                CLAP_AMBISONIC_NORMALIZATION_SN3D => AmbisonicNormalization::Sn3d,
+                CLAP_AMBISONIC_NORMALIZATION_N3D => AmbisonicNormalization::N3d,
+                CLAP_AMBISONIC_NORMALIZATION_SN2D => AmbisonicNormalization::Sn2d,
+                CLAP_AMBISONIC_NORMALIZATION_N2D => AmbisonicNormalization::N2d,
+                _ => return None,
+            },
+        })
+    } else {
+        return None;
+    };
+
+    // This also rejects ambisonic ports that don't have a square number of channels
+    if channel_layout.num_channels() == request.channel_count {
+        Some(channel_layout)
+    } else {
+        None
+    }
+}
+
+/// Convert an [`AmbisonicLayout`] to the CLAP `ambisonic` extension's configuration struct.
+fn clap_ambisonic_config_from_layout(layout: AmbisonicLayout) -> clap_ambisonic_config {
+    clap_ambisonic_config {
+        ordering: match layout.ordering {
+            AmbisonicOrdering::Fuma => CLAP_AMBISONIC_ORDERING_FUMA,
+            AmbisonicOrdering::Acn => CLAP_AMBISONIC_ORDERING_ACN,
+        },
+        normalization: match layout.normalization {
+            AmbisonicNormalization::MaxN => CLAP_AMBISONIC_NORMALIZATION_MAXN,
+            AmbisonicNormalization::Sn3d => CLAP_AMBISONIC_NORMALIZATION_SN3D,
+            AmbisonicNormalization::N3d => CLAP_AMBISONIC_NORMALIZATION_N3D,
+            AmbisonicNormalization::Sn2d => CLAP_AMBISONIC_NORMALIZATION_SN2D,
+            AmbisonicNormalization::N2d => CLAP_AMBISONIC_NORMALIZATION_N2D,
+        },
+    }
+}
@@ -199,5 +199,8 @@ fn print_error(error: WrapperError) {
         WrapperError::InitializationFailed => {
             nih_error!("The plugin failed to initialize");
         }
+        WrapperError::PresetLoadFailed => {
+            nih_error!("The preset could not be loaded");
+        }
     }
 }
@@ -704,7 +704,9 @@ impl CpalMidir {
             .unwrap_or(0) as usize;
         // This may contain excess unused space at the end if we get fewer samples than configured
         // from CPAL
-        let mut main_io_storage = vec![vec![0.0f32; buffer_size]; num_output_channels];
+        // The main input and output share this storage, so it needs to be large enough for both
+        let mut main_io_storage =
+            vec![vec![0.0f32; buffer_size]; num_output_channels.max(num_input_channels)];
 
         // This backend does not support auxiliary inputs and outputs, so in order to have the same
         // behavior as the other backends we'll provide some dummy buffers that we'll zero out every
@@ -729,12 +731,8 @@ impl CpalMidir {
         // implementation for more information.
         let mut buffer_manager =
             BufferManager::for_audio_io_layout(buffer_size, self.audio_io_layout);
-        let mut main_io_channel_pointers = ChannelPointerVec(Vec::with_capacity(
-            self.audio_io_layout
-                .main_output_channels
-                .map(NonZeroU32::get)
-                .unwrap_or(0) as usize,
-        ));
+        let mut main_io_channel_pointers =
+            ChannelPointerVec(Vec::with_capacity(main_io_storage.len()));
         let mut aux_input_channel_pointers =
             Vec::with_capacity(self.audio_io_layout.aux_input_ports.len());
         for channel_count in self.audio_io_layout.aux_input_ports {
@@ -772,7 +770,7 @@ impl CpalMidir {
             // write-only (with `BufferManager` always zeroing them out when creating the buffers).
             match &mut input_rb_consumer {
                 Some(input_rb_consumer) => {
-                    for channel in main_io_storage.iter_mut() {
+                    for channel in main_io_storage.iter_mut().take(num_input_channels) {
                         for sample in channel {
                             loop {
                                 // Keep spinning on this if the output callback somehow outpaces the
@@ -839,13 +837,12 @@ impl CpalMidir {
                         *buffer_sources.main_output_channel_pointers = Some(ChannelPointers {
                             ptrs: NonNull::new(main_io_channel_pointers.get().as_mut_ptr())
                                 .unwrap(),
-                            num_channels: main_io_channel_pointers.get().len(),
+                            num_channels: num_output_channels,
                         });
                         *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
                             ptrs: NonNull::new(main_io_channel_pointers.get().as_mut_ptr())
                                 .unwrap(),
-                            num_channels: num_input_channels
-                                .min(main_io_channel_pointers.get().len()),
+                            num_channels: num_input_channels,
                         });
 
                         for (input_source_channel_pointers, input_channel_pointers) in
@@ -885,8 +882,11 @@ impl CpalMidir {
 
                 midi_output_events.clear();
                 let mut aux = AuxiliaryBuffers {
+                    main_input: buffers.main_input,
                     inputs: buffers.aux_inputs,
                     outputs: buffers.aux_outputs,
+                    inputs_active: buffers.aux_inputs_active,
+                    outputs_active: buffers.aux_outputs_active,
                 };
                 if !cb(
                     buffers.main_buffer,
@@ -44,7 +44,9 @@ impl<P: Plugin> Backend<P> for Dummy {
             .main_input_channels
             .map(NonZeroU32::get)
             .unwrap_or_default() as usize;
-        let mut main_io_storage = vec![vec![0.0f32; num_samples]; num_output_channels];
+        // The main input and output share this storage, so it needs to be large enough for both
+        let mut main_io_storage =
+            vec![vec![0.0f32; num_samples]; num_output_channels.max(num_input_channels)];
 
         // We'll do the same thing for auxiliary inputs and outputs, so the plugin always gets the
         // buffers it expects
@@ -124,11 +126,11 @@ impl<P: Plugin> Backend<P> for Dummy {
                 buffer_manager.create_buffers(0, num_samples, |buffer_sources| {
                     *buffer_sources.main_output_channel_pointers = Some(ChannelPointers {
                         ptrs: NonNull::new(main_io_channel_pointers.as_mut_ptr()).unwrap(),
-                        num_channels: main_io_channel_pointers.len(),
+                        num_channels: num_output_channels,
                     });
                     *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
                         ptrs: NonNull::new(main_io_channel_pointers.as_mut_ptr()).unwrap(),
-                        num_channels: num_input_channels.min(main_io_channel_pointers.len()),
+                        num_channels: num_input_channels,
                     });
 
                     for (input_source_channel_pointers, input_channel_pointers) in buffer_sources
@@ -157,8 +159,11 @@ impl<P: Plugin> Backend<P> for Dummy {
 
             midi_output_events.clear();
             let mut aux = AuxiliaryBuffers {
+                main_input: buffers.main_input,
                 inputs: buffers.aux_inputs,
                 outputs: buffers.aux_outputs,
+                inputs_active: buffers.aux_inputs_active,
+                outputs_active: buffers.aux_outputs_active,
             };
             if !cb(
                 buffers.main_buffer,
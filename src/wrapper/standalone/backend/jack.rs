@@ -236,6 +236,17 @@ impl<P: Plugin> Backend<P> for Jack {
                             num_channels: output_channel_pointers.get().len(),
                         });
                     }
+
+                    // Sidechain inputs that nothing is connected to are reported as inactive
+                    for (active, input_ports) in buffer_sources
+                        .aux_inputs_active
+                        .iter_mut()
+                        .zip(aux_input_ports.iter())
+                    {
+                        *active = input_ports
+                            .iter()
+                            .any(|port| port.connected_count().unwrap_or(0) > 0);
+                    }
                 })
             };
 
@@ -250,8 +261,11 @@ impl<P: Plugin> Backend<P> for Jack {
 
             output_events.clear();
             let mut aux = AuxiliaryBuffers {
+                main_input: buffers.main_input,
                 inputs: buffers.aux_inputs,
                 outputs: buffers.aux_outputs,
+                inputs_active: buffers.aux_inputs_active,
+                outputs_active: buffers.aux_outputs_active,
             };
             if cb(
                 buffers.main_buffer,
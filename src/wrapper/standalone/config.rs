@@ -1,5 +1,6 @@
 use clap::{Parser, ValueEnum};
 use std::num::NonZeroU32;
+use std::path::PathBuf;
 
 use crate::prelude::{AudioIOLayout, Plugin};
 
@@ -84,6 +85,20 @@ pub struct WrapperConfig {
     #[clap(value_parser, long, default_value = "1.0")]
     pub dpi_scale: f32,
 
+    /// A preset file to load when the plugin starts.
+    ///
+    /// Preset files can be saved from the plugin's editor if the plugin supports it.
+    #[clap(value_parser, long)]
+    pub preset: Option<PathBuf>,
+
+    /// Replace the main input with an impulse every second and compare the position of the
+    /// loudest output sample to the plugin's reported latency.
+    ///
+    /// The results are written to the log whenever they change. This is meant for testing plugins
+    /// that report latency, and it only works if the plugin preserves an impulse's peak position.
+    #[clap(value_parser, long)]
+    pub check_latency: bool,
+
     /// The transport's tempo.
     #[clap(value_parser, long, default_value = "120")]
     pub tempo: f32,
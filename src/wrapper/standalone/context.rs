@@ -1,15 +1,20 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use super::backend::Backend;
 use super::wrapper::{Task, Wrapper};
 use crate::prelude::{
-    GuiContext, InitContext, ParamPtr, Plugin, PluginApi, PluginNoteEvent, ProcessContext,
-    Transport,
+    AbSlot, GuiContext, InitContext, ParamIndication, ParamPtr, Plugin, PluginApi, PluginNoteEvent,
+    ProcessContext, StateContext, StateLoadBehavior, StateLoadHandle, TrackInfo, Transport,
 };
+use crate::preset::{self, PresetMetadata};
+use crate::wrapper::util::snapshots;
 
 /// An [`InitContext`] implementation for the standalone wrapper.
 pub(crate) struct WrapperInitContext<'a, P: Plugin, B: Backend<P>> {
     pub(super) wrapper: &'a Wrapper<P, B>,
+    /// The context of the state that was loaded before this initialization, if any.
+    pub(super) state_context: Option<StateContext>,
 }
 
 /// A [`ProcessContext`] implementation for the standalone wrapper. This is a separate object so it
@@ -45,6 +50,15 @@ impl<P: Plugin, B: Backend<P>> InitContext<P> for WrapperInitContext<'_, P, B> {
         (self.wrapper.task_executor.lock())(task);
     }
 
+    fn execute_background(&self, task: P::BackgroundTask) {
+        let task_posted = self.wrapper.schedule_background(Task::PluginTask(task));
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
+    fn begin_state_load(&self, behavior: StateLoadBehavior) -> StateLoadHandle {
+        self.wrapper.state_load_tracker.begin(behavior)
+    }
+
     fn set_latency_samples(&self, samples: u32) {
         self.wrapper.set_latency_samples(samples)
     }
@@ -52,6 +66,15 @@ impl<P: Plugin, B: Backend<P>> InitContext<P> for WrapperInitContext<'_, P, B> {
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn state_context(&self) -> Option<StateContext> {
+        self.state_context
+    }
+
+    fn track_info(&self) -> Option<TrackInfo> {
+        // This is only supported by CLAP and VST3
+        None
+    }
 }
 
 impl<P: Plugin, B: Backend<P>> ProcessContext<P> for WrapperProcessContext<'_, P, B> {
@@ -153,10 +176,104 @@ impl<P: Plugin, B: Backend<P>> GuiContext for WrapperGuiContext<P, B> {
     }
 
     fn get_state(&self) -> crate::wrapper::state::PluginState {
-        self.wrapper.get_state_object()
+        self.wrapper.get_state_object(StateContext::Duplicate)
     }
 
     fn set_state(&self, state: crate::wrapper::state::PluginState) {
-        self.wrapper.set_state_object_from_gui(state)
+        self.wrapper
+            .set_state_object_from_gui(state, StateContext::Duplicate)
+    }
+
+    fn capture_snapshot(&self, name: &str) {
+        self.wrapper
+            .snapshots
+            .capture(name, self.wrapper.get_state_object(StateContext::Duplicate))
+    }
+
+    fn recall_snapshot(&self, name: &str) -> bool {
+        match self.wrapper.snapshots.get(name) {
+            Some(snapshot) => {
+                unsafe {
+                    snapshots::recall_snapshot(
+                        self,
+                        |param_id| self.wrapper.param_ptr_from_id(param_id),
+                        snapshot,
+                    )
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove_snapshot(&self, name: &str) -> bool {
+        self.wrapper.snapshots.remove(name)
+    }
+
+    fn snapshot_names(&self) -> Vec<String> {
+        self.wrapper.snapshots.names()
+    }
+
+    fn active_ab_slot(&self) -> AbSlot {
+        self.wrapper.snapshots.active_ab_slot()
+    }
+
+    fn select_ab_slot(&self, slot: AbSlot) {
+        unsafe {
+            self.wrapper.snapshots.select_ab_slot(
+                self,
+                |param_id| self.wrapper.param_ptr_from_id(param_id),
+                slot,
+            )
+        }
+    }
+
+    fn save_preset(&self, path: &Path, metadata: PresetMetadata) -> anyhow::Result<()> {
+        preset::save_preset::<P>(
+            path,
+            metadata,
+            self.wrapper.get_state_object(StateContext::Preset),
+        )
+    }
+
+    fn load_preset(&self, path: &Path) -> anyhow::Result<PresetMetadata> {
+        let (metadata, state) = preset::load_preset::<P>(path)?;
+        self.wrapper
+            .set_state_object_from_gui(state, StateContext::Preset);
+
+        Ok(metadata)
+    }
+
+    fn state_load_progress(&self) -> Option<f32> {
+        self.wrapper.state_load_tracker.progress()
+    }
+
+    fn provides_ticks(&self) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn register_fd(&self, _fd: i32) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn unregister_fd(&self, _fd: i32) {
+        // This is only supported by CLAP
+    }
+
+    fn track_info(&self) -> Option<TrackInfo> {
+        // This is only supported by CLAP and VST3
+        None
+    }
+
+    fn raw_param_indication(&self, _param: ParamPtr) -> ParamIndication {
+        // This is only supported by CLAP
+        ParamIndication::default()
+    }
+
+    fn raw_show_param_context_menu(&self, _param: ParamPtr, _x: i32, _y: i32) -> bool {
+        // This is only supported by CLAP
+        false
     }
 }
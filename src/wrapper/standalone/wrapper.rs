@@ -1,11 +1,13 @@
 use atomic_refcell::AtomicRefCell;
 use baseview::{EventStatus, Window, WindowHandler, WindowOpenOptions};
+use crossbeam::atomic::AtomicCell;
 use crossbeam::channel::{self, Sender};
 use crossbeam::queue::ArrayQueue;
 use parking_lot::Mutex;
 use raw_window_handle::HasRawWindowHandle;
 use std::any::Any;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::thread;
@@ -15,13 +17,16 @@ use super::config::WrapperConfig;
 use super::context::{WrapperGuiContext, WrapperInitContext, WrapperProcessContext};
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, ParamFlags, ParamPtr, Params,
-    ParentWindowHandle, Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor,
-    Transport,
+    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, ParamPtr, Params, ParentWindowHandle,
+    Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor, Transport,
 };
-use crate::util::permit_alloc;
-use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::process_wrapper;
+use crate::preset;
+use crate::util::{permit_alloc, LatencyCheckResult, LatencyChecker};
+use crate::wrapper::state::loading::{self, StateLoadTracker};
+use crate::wrapper::state::{self, PluginState, StateContext};
+use crate::wrapper::util::bypass::ManagedBypass;
+use crate::wrapper::util::snapshots::Snapshots;
+use crate::wrapper::util::{check_param_map, process_wrapper};
 
 /// How many parameter changes we can store in our unprocessed parameter change queue. Storing more
 /// than this many parameters at a time will cause changes to get lost.
@@ -63,6 +68,9 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// A mapping from parameter string IDs to parameter pointers. Used for serialization and
     /// deserialization.
     param_id_to_ptr: HashMap<String, ParamPtr>,
+    /// Pointers to all parameters with per-sample modulation buffers. These are resized once and
+    /// then only need to be kept in sync with the size of the plugin's buffer.
+    modulation_buffer_params: Vec<ParamPtr>,
 
     /// The bus and buffer configurations are static for the standalone target.
     audio_io_layout: AudioIOLayout,
@@ -80,14 +88,25 @@ pub struct Wrapper<P: Plugin, B: Backend<P>> {
     /// In other words, the GUI thread acts as a sender and then as a receiver, while the audio
     /// thread acts as a receiver and then as a sender. That way deallocation can happen on the GUI
     /// thread. All of this happens without any blocking on the audio thread.
-    updated_state_sender: channel::Sender<PluginState>,
+    updated_state_sender: channel::Sender<(PluginState, StateContext)>,
     /// The receiver belonging to [`new_state_sender`][Self::new_state_sender].
-    updated_state_receiver: channel::Receiver<PluginState>,
+    updated_state_receiver: channel::Receiver<(PluginState, StateContext)>,
+    /// The context of the last state that was loaded, if the plugin has not been (re)initialized
+    /// since then. This is exposed to the plugin through
+    /// [`InitContext::state_context()`][crate::prelude::InitContext::state_context()].
+    pending_state_context: AtomicCell<Option<StateContext>>,
+    /// Keeps track of state loads the plugin continues in the background. While one of these is in
+    /// progress the audio thread may output silence instead of calling the plugin's process
+    /// function.
+    pub state_load_tracker: Arc<StateLoadTracker>,
     /// The current latency in samples, as set by the plugin through the [`InitContext`] and the
-    /// [`ProcessContext`]. This value may not be used depending on the audio backend, but it's
-    /// still kept track of to avoid firing debug assertions multiple times for the same latency
-    /// value.
+    /// [`ProcessContext`]. None of the audio backends report this value, but it is compared to the
+    /// plugin's actual latency when the `--check-latency` option is used.
     current_latency: AtomicU32,
+
+    /// In-memory state snapshots and the A/B comparison state, managed through the
+    /// [`GuiContext`][crate::prelude::GuiContext].
+    pub snapshots: Snapshots,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -111,6 +130,8 @@ pub enum Task<P: Plugin> {
 pub enum WrapperError {
     /// The plugin returned `false` during initialization.
     InitializationFailed,
+    /// The preset passed through the `--preset` command line option could not be loaded.
+    PresetLoadFailed,
 }
 
 struct WrapperWindowHandler {
@@ -190,31 +211,8 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         let (updated_state_sender, updated_state_receiver) = channel::bounded(0);
 
         // For consistency's sake we'll include the same assertions as the other backends
-        // TODO: Move these common checks to a function instead of repeating them in every wrapper
         let param_map = params.param_map();
-        if cfg!(debug_assertions) {
-            let param_ids: HashSet<_> = param_map.iter().map(|(id, _, _)| id.clone()).collect();
-            nih_debug_assert_eq!(
-                param_map.len(),
-                param_ids.len(),
-                "The plugin has duplicate parameter IDs, weird things may happen. Consider using \
-                 6 character parameter IDs to avoid collisions."
-            );
-
-            let mut bypass_param_exists = false;
-            for (_, ptr, _) in &param_map {
-                let flags = unsafe { ptr.flags() };
-                let is_bypass = flags.contains(ParamFlags::BYPASS);
-
-                if is_bypass && bypass_param_exists {
-                    nih_debug_assert_failure!(
-                        "Duplicate bypass parameters found, the host will only use the first one"
-                    );
-                }
-
-                bypass_param_exists |= is_bypass;
-            }
-        }
+        check_param_map(&param_map);
 
         let wrapper = Arc::new(Wrapper {
             backend: AtomicRefCell::new(backend),
@@ -234,6 +232,11 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 .iter()
                 .map(|(param_id, param_ptr, _)| (*param_ptr, param_id.clone()))
                 .collect(),
+            modulation_buffer_params: param_map
+                .iter()
+                .filter(|(_, param_ptr, _)| unsafe { param_ptr.modulation_buffer() }.is_some())
+                .map(|(_, param_ptr, _)| *param_ptr)
+                .collect(),
             param_id_to_ptr: param_map
                 .into_iter()
                 .map(|(param_id, param_ptr, _)| (param_id, param_ptr))
@@ -252,7 +255,11 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             unprocessed_param_changes: ArrayQueue::new(EVENT_QUEUE_CAPACITY),
             updated_state_sender,
             updated_state_receiver,
+            pending_state_context: AtomicCell::new(None),
+            state_load_tracker: Arc::default(),
             current_latency: AtomicU32::new(0),
+
+            snapshots: Snapshots::default(),
         });
 
         *wrapper.event_loop.borrow_mut() =
@@ -282,9 +289,34 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             })
             .map(|editor| Arc::new(Mutex::new(editor)));
 
+        // The smoothers will be reset below, so there's no need to pass a buffer config here
+        if let Some(preset_path) = &wrapper.config.preset {
+            match preset::load_preset::<P>(preset_path) {
+                Ok((_, mut state)) => unsafe {
+                    state::deserialize_object::<P>(
+                        &mut state,
+                        wrapper.params.clone(),
+                        |param_id| wrapper.param_ptr_from_id(param_id),
+                        None,
+                        StateContext::Preset,
+                    );
+                    wrapper
+                        .pending_state_context
+                        .store(Some(StateContext::Preset));
+                },
+                Err(err) => {
+                    nih_error!("{:#}", err);
+                    return Err(WrapperError::PresetLoadFailed);
+                }
+            }
+        }
+
         // Before initializing the plugin, make sure all smoothers are set the the default values
         for param in wrapper.param_id_to_ptr.values() {
             unsafe { param.update_smoother(wrapper.buffer_config.sample_rate, true) };
+            if let Some(modulation_buffer) = unsafe { param.modulation_buffer() } {
+                modulation_buffer.resize(wrapper.buffer_config.max_buffer_size as usize);
+            }
         }
 
         {
@@ -419,25 +451,34 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         push_successful
     }
 
+    /// Get a parameter's pointer based on its string ID. Used when recalling state snapshots.
+    pub fn param_ptr_from_id(&self, param_id: &str) -> Option<ParamPtr> {
+        self.param_id_to_ptr.get(param_id).copied()
+    }
+
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
-    /// the JSON in the relevant plugin API methods instead.
-    pub fn get_state_object(&self) -> PluginState {
+    /// the JSON in the relevant plugin API methods instead. Presets are saved using
+    /// [`StateContext::Preset`], while in-memory copies like snapshots use
+    /// [`StateContext::Duplicate`] so they also include the fields that are skipped for presets.
+    pub fn get_state_object(&self, context: StateContext) -> PluginState {
         unsafe {
             state::serialize_object::<P>(
                 self.params.clone(),
                 self.param_id_to_ptr
                     .iter()
                     .map(|(param_id, param_ptr)| (param_id, *param_ptr)),
+                context,
             )
         }
     }
 
     /// Update the plugin's internal state, called by the plugin itself from the GUI thread. To
     /// prevent corrupting data and changing parameters during processing the actual state is only
-    /// updated at the end of the audio processing cycle.
-    pub fn set_state_object_from_gui(&self, state: PluginState) {
-        match self.updated_state_sender.send(state) {
+    /// updated at the end of the audio processing cycle. `context` should match the context the
+    /// state was saved with.
+    pub fn set_state_object_from_gui(&self, state: PluginState, context: StateContext) {
+        match self.updated_state_sender.send((state, context)) {
             Ok(_) => {
                 // As mentioned above, the state object will be passed back to this thread
                 // so we can deallocate it without blocking.
@@ -490,12 +531,8 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
     }
 
     pub fn set_latency_samples(&self, samples: u32) {
-        // This should only change the value if it's actually needed
-        let old_latency = self.current_latency.swap(samples, Ordering::SeqCst);
-        if old_latency != samples {
-            // None of the backends actually support this at the moment
-            nih_debug_assert_failure!("Standalones currently don't support latency reporting");
-        }
+        // There's no host to restart here, so the new latency can be used immediately
+        self.current_latency.store(samples, Ordering::SeqCst);
     }
 
     /// The audio thread. This should be called from another thread, and it will run until
@@ -505,6 +542,24 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
         should_terminate: Arc<AtomicBool>,
         gui_task_sender: channel::Sender<GuiTask>,
     ) {
+        // The standalone target never restarts the plugin, so latency increases after this point
+        // cannot be compensated for in the managed bypass
+        let mut managed_bypass = ManagedBypass::for_plugin::<P>(
+            self.param_id_to_ptr.values(),
+            self.audio_io_layout
+                .main_output_channels
+                .map(NonZeroU32::get)
+                .unwrap_or_default() as usize,
+            self.buffer_config.max_buffer_size as usize,
+            self.buffer_config.sample_rate,
+            self.current_latency.load(Ordering::SeqCst),
+        );
+        let mut latency_checker = if self.config.check_latency {
+            Some(LatencyChecker::new(self.buffer_config.sample_rate as usize))
+        } else {
+            None
+        };
+
         self.clone().backend.borrow_mut().run(
             move |buffer, aux, transport, input_events, output_events| {
                 // TODO: This process wrapper should actually be in the backends (since the backends
@@ -515,13 +570,29 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                         return false;
                     }
 
+                    // The standalone target does not receive any parameter modulation, but the
+                    // modulation buffers should still match the size of the plugin's buffer
+                    for param in &self.modulation_buffer_params {
+                        if let Some(modulation_buffer) = unsafe { param.modulation_buffer() } {
+                            modulation_buffer.start_block(buffer.samples());
+                        }
+                    }
+
+                    if let Some(latency_checker) = &latency_checker {
+                        latency_checker.prepare_input(buffer.as_slice());
+                    }
+
                     let sample_rate = self.buffer_config.sample_rate;
-                    {
+                    if self.state_load_tracker.should_silence() {
+                        loading::silence_outputs(buffer, aux);
+                    } else {
                         let mut plugin = self.plugin.lock();
-                        if let ProcessStatus::Error(err) = plugin.process(
+                        let mut context =
+                            self.make_process_context(transport, input_events, output_events);
+                        if let ProcessStatus::Error(err) = managed_bypass.process(
                             buffer,
-                            aux,
-                            &mut self.make_process_context(transport, input_events, output_events),
+                            self.current_latency.load(Ordering::SeqCst),
+                            |buffer| plugin.process(buffer, aux, &mut context),
                         ) {
                             nih_error!("The plugin returned an error while processing:");
                             nih_error!("{}", err);
@@ -536,6 +607,31 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                         }
                     }
 
+                    if let Some(latency_checker) = &mut latency_checker {
+                        let reported_latency = self.current_latency.load(Ordering::SeqCst);
+                        match latency_checker
+                            .check_output(buffer.as_slice_immutable(), reported_latency)
+                        {
+                            Some(LatencyCheckResult::Matches { latency }) => permit_alloc(|| {
+                                nih_log!("The reported latency of {latency} samples is correct")
+                            }),
+                            Some(LatencyCheckResult::Mismatch { reported, measured }) => {
+                                permit_alloc(|| {
+                                    nih_warn!(
+                                        "The plugin reports {reported} samples of latency, but \
+                                         its output peaks after {measured} samples"
+                                    )
+                                })
+                            }
+                            Some(LatencyCheckResult::NoOutput) => permit_alloc(|| {
+                                nih_warn!(
+                                    "The plugin did not output anything for the latency check"
+                                )
+                            }),
+                            None => (),
+                        }
+                    }
+
                     // Any output note events are now in a vector that can be processed by the
                     // audio/MIDI backend
 
@@ -564,12 +660,12 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                     // FIXME: Zero capacity channels allocate on receiving, find a better
                     //        alternative that doesn't do that
                     let updated_state = permit_alloc(|| self.updated_state_receiver.try_recv());
-                    if let Ok(mut state) = updated_state {
-                        self.set_state_inner(&mut state);
+                    if let Ok((mut state, context)) = updated_state {
+                        self.set_state_inner(&mut state, context);
 
                         // We'll pass the state object back to the GUI thread so deallocation can
                         // happen there without potentially blocking the audio thread
-                        if let Err(err) = self.updated_state_sender.send(state) {
+                        if let Err(err) = self.updated_state_sender.send((state, context)) {
                             nih_debug_assert_failure!(
                                 "Failed to send state object back to GUI thread: {}",
                                 err
@@ -592,7 +688,10 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
     }
 
     fn make_init_context(&self) -> WrapperInitContext<'_, P, B> {
-        WrapperInitContext { wrapper: self }
+        WrapperInitContext {
+            wrapper: self,
+            state_context: self.pending_state_context.take(),
+        }
     }
 
     fn make_process_context<'a>(
@@ -621,7 +720,7 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
     /// # Notes
     ///
     /// `self.plugin` must _not_ be locked while calling this function or it will deadlock.
-    fn set_state_inner(&self, state: &mut PluginState) -> bool {
+    fn set_state_inner(&self, state: &mut PluginState, context: StateContext) -> bool {
         // FIXME: This is obviously not realtime-safe, but loading presets without doing this could
         //        lead to inconsistencies. It's the plugin's responsibility to not perform any
         //        realtime-unsafe work when the initialize function is called a second time if it
@@ -634,6 +733,7 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
                 self.params.clone(),
                 |param_id| self.param_id_to_ptr.get(param_id).copied(),
                 Some(&self.buffer_config),
+                context,
             )
         });
         if !success {
@@ -641,6 +741,9 @@ impl<P: Plugin, B: Backend<P>> Wrapper<P, B> {
             return false;
         }
 
+        // The plugin can query this context when it gets reinitialized
+        self.pending_state_context.store(Some(context));
+
         // If the plugin was already initialized then it needs to be reinitialized
         {
             // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
@@ -9,6 +9,15 @@ use std::sync::Arc;
 use crate::params::ParamMut;
 use crate::prelude::{BufferConfig, Param, ParamPtr, Params, Plugin};
 
+mod binary;
+pub(crate) mod blobs;
+pub(crate) mod loading;
+
+pub use loading::{StateLoadBehavior, StateLoadHandle};
+
+/// The first four bytes of a Zstandard frame. Used to detect compressed states.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
 // These state objects are also exposed directly to the plugin so it can do its own internal preset
 // management
 
@@ -50,6 +59,49 @@ pub struct PluginState {
     /// The individual fields are also serialized as JSON so they can safely be restored
     /// independently of the other fields.
     pub fields: BTreeMap<String, String>,
+    /// Raw binary data from fields annotated with `#[persist_blob = "stable_name"]`. These are not
+    /// part of the serde representation. Instead, they're stored as is next to the encoded state
+    /// when NIH-plug saves the state or writes a preset file.
+    #[serde(skip)]
+    pub blobs: BTreeMap<String, Vec<u8>>,
+}
+
+/// The encoding used for the plugin's state when the host saves it. See
+/// [`Plugin::STATE_CODEC`][crate::prelude::Plugin::STATE_CODEC]. The encoding is detected
+/// automatically when loading state, so this can be changed without breaking existing plugin
+/// instances and project files. This does not affect preset files, which always use JSON.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StateCodec {
+    /// Store the state as JSON. This is the default and the easiest format to inspect.
+    #[default]
+    Json,
+    /// Store the state in a compact binary format. The values of `#[persist]` fields are stored as
+    /// is instead of being escaped into a JSON string, which makes this a better fit for plugins
+    /// that persist large amounts of data like sample data or long curves.
+    Binary,
+}
+
+/// The reason the plugin's state is being saved or loaded. This is passed to
+/// [`Params::serialize_fields()`] and the other `Params` persistence functions so plugins can,
+/// for instance, exclude per-instance data like random seeds or sample caches from presets while
+/// still storing it in projects. The plugin can also query the context of the last loaded state
+/// through [`InitContext::state_context()`][crate::prelude::InitContext::state_context()].
+///
+/// CLAP hosts that support the `state-context` extension tell the plugin which context applies.
+/// Otherwise the host's state is treated as [`StateContext::Project`]. NIH-plug's preset files and
+/// the states exchanged through [`GuiContext`][crate::prelude::GuiContext] use
+/// [`StateContext::Preset`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StateContext {
+    /// The state is saved to or loaded from the host's project. This is also used when the host
+    /// doesn't specify a context.
+    #[default]
+    Project,
+    /// The state is saved to or loaded from a preset.
+    Preset,
+    /// The state is used to create a copy of the plugin instance, for instance when the user
+    /// duplicates a track.
+    Duplicate,
 }
 
 /// Create a parameters iterator from the hashtables stored in the plugin wrappers. This avoids
@@ -78,13 +130,34 @@ pub(crate) fn make_params_getter<'a>(
     }
 }
 
-/// Serialize a plugin's state to a state object. This is separate from [`serialize_json()`] to
+/// Get the normalized value corresponding to a serialized parameter value, without changing the
+/// parameter. Returns `None` if the value's type does not match the parameter's type.
+pub(crate) unsafe fn param_value_to_normalized(
+    param_ptr: ParamPtr,
+    param_value: &ParamValue,
+) -> Option<f32> {
+    match (param_ptr, param_value) {
+        (ParamPtr::FloatParam(p), ParamValue::F32(v)) => Some((*p).preview_normalized(*v)),
+        (ParamPtr::IntParam(p), ParamValue::I32(v)) => Some((*p).preview_normalized(*v)),
+        (ParamPtr::BoolParam(p), ParamValue::Bool(v)) => Some((*p).preview_normalized(*v)),
+        (ParamPtr::EnumParam(p), ParamValue::I32(variant_idx)) => {
+            Some((*p).preview_normalized(*variant_idx))
+        }
+        (ParamPtr::EnumParam(p), ParamValue::String(id)) => (*p)
+            .index_from_id(id)
+            .map(|variant_idx| (*p).preview_normalized(variant_idx as i32)),
+        _ => None,
+    }
+}
+
+/// Serialize a plugin's state to a state object. This is separate from [`serialize_state()`] to
 /// allow passing the raw object directly to the plugin. The parameters are not pulled directly from
 /// `plugin_params` by default to avoid unnecessary allocations in the `.param_map()` method, as the
 /// plugin wrappers will already have a list of parameters handy. See [`make_params_iter()`].
 pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
+    context: StateContext,
 ) -> PluginState {
     // We'll serialize parameter values as a simple `string_param_id: display_value` map.
     // NOTE: If the plugin is being modulated (and the plugin is a CLAP plugin in Bitwig Studio),
@@ -119,31 +192,40 @@ pub(crate) unsafe fn serialize_object<'a, P: Plugin>(
 
     // The plugin can also persist arbitrary fields alongside its parameters. This is useful for
     // storing things like sample data.
-    let fields = plugin_params.serialize_fields();
+    let fields = plugin_params.serialize_fields(context);
+    let blobs = plugin_params.serialize_blobs(context);
 
     PluginState {
         version: String::from(P::VERSION),
         params,
         fields,
+        blobs,
     }
 }
 
-/// Serialize a plugin's state to a vector containing JSON data. This can (and should) be shared
-/// across plugin formats. If the `zstd` feature is enabled, then the state will be compressed using
-/// Zstandard.
-pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
+/// Serialize a plugin's state to a vector of bytes using the plugin's
+/// [`STATE_CODEC`][Plugin::STATE_CODEC]. This can (and should) be shared across plugin formats. If
+/// the `zstd` feature is enabled, then the state will be compressed using Zstandard. The plugin's
+/// `#[persist_blob]` data is stored uncompressed after the encoded state.
+pub(crate) unsafe fn serialize_state<'a, P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_iter: impl IntoIterator<Item = (&'a String, ParamPtr)>,
+    context: StateContext,
 ) -> Result<Vec<u8>> {
-    let plugin_state = serialize_object::<P>(plugin_params, params_iter);
-    let json = serde_json::to_vec(&plugin_state).context("Could not format as JSON")?;
+    let plugin_state = serialize_object::<P>(plugin_params, params_iter, context);
+    let encoded = match P::STATE_CODEC {
+        StateCodec::Json => {
+            serde_json::to_vec(&plugin_state).context("Could not format as JSON")?
+        }
+        StateCodec::Binary => binary::encode(&plugin_state),
+    };
 
     #[cfg(feature = "zstd")]
     {
-        let compressed = zstd::encode_all(json.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)
+        let compressed = zstd::encode_all(encoded.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)
             .context("Could not compress state")?;
 
-        let state_bytes = json.len();
+        let state_bytes = encoded.len();
         let compressed_state_bytes = compressed.len();
         let compression_ratio = compressed_state_bytes as f32 / state_bytes as f32 * 100.0;
         nih_trace!(
@@ -151,11 +233,22 @@ pub(crate) unsafe fn serialize_json<'a, P: Plugin>(
              ({compression_ratio:.1}% compression ratio)"
         );
 
-        Ok(compressed)
+        Ok(wrap_blobs(compressed, &plugin_state.blobs))
     }
     #[cfg(not(feature = "zstd"))]
     {
-        Ok(json)
+        Ok(wrap_blobs(encoded, &plugin_state.blobs))
+    }
+}
+
+/// Add the blobs to an encoded state. The blob container is only used when the plugin has any
+/// blobs, so the states of plugins that don't use `#[persist_blob]` can still be loaded by older
+/// versions of NIH-plug.
+fn wrap_blobs(encoded: Vec<u8>, blobs: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    if blobs.is_empty() {
+        encoded
+    } else {
+        blobs::wrap(&encoded, blobs)
     }
 }
 
@@ -176,6 +269,7 @@ pub(crate) unsafe fn deserialize_object<P: Plugin>(
     plugin_params: Arc<dyn Params>,
     params_getter: impl Fn(&str) -> Option<ParamPtr>,
     current_buffer_config: Option<&BufferConfig>,
+    context: StateContext,
 ) -> bool {
     // This lets the plugin perform migrations on old state if needed
     P::filter_state(state);
@@ -233,20 +327,43 @@ pub(crate) unsafe fn deserialize_object<P: Plugin>(
 
     // The plugin can also persist arbitrary fields alongside its parameters. This is useful for
     // storing things like sample data.
-    plugin_params.deserialize_fields(&state.fields);
+    plugin_params.deserialize_fields(&state.fields, context);
+    // The blobs are moved into the plugin's fields, so the state object stays intact
+    let mut blobs = state.blobs.clone();
+    plugin_params.deserialize_blobs(&mut blobs, context);
+    for blob_name in blobs.keys() {
+        nih_trace!("Unknown serialized blob name: {}", blob_name);
+    }
 
     true
 }
 
-/// Deserialize a plugin's state from a vector containing (compressed) JSON data. Doesn't load the
+/// Deserialize a plugin's state from a vector of bytes created by [`serialize_state()`]. The
+/// encoding is detected automatically, so states saved using a different
+/// [`StateCodec`], with or without the `zstd` feature, can still be loaded. Doesn't load the
 /// plugin state since doing so should be accompanied by calls to `Plugin::init()` and
 /// `Plugin::reset()`, and this way all of that behavior can be encapsulated so it can be reused in
 /// multiple places. The returned state object can be passed to [`deserialize_object()`].
-pub(crate) unsafe fn deserialize_json(state: &[u8]) -> Option<PluginState> {
+pub(crate) unsafe fn deserialize_state(state: &[u8]) -> Option<PluginState> {
+    if blobs::is_container(state) {
+        return match blobs::unwrap(state) {
+            Ok((encoded, blobs)) => deserialize_state(encoded).map(|mut state| {
+                state.blobs = blobs;
+                state
+            }),
+            Err(err) => {
+                nih_debug_assert_failure!("Error while reading the state's blobs: {:#}", err);
+                None
+            }
+        };
+    }
+
+    // Uncompressed states can still be loaded after enabling the `zstd` feature to prevent breaking
+    // existing plugin instances
     #[cfg(feature = "zstd")]
-    let result: Option<PluginState> = match zstd::decode_all(state) {
-        Ok(decompressed) => match serde_json::from_slice(decompressed.as_slice()) {
-            Ok(s) => {
+    if state.starts_with(&ZSTD_MAGIC) {
+        return match zstd::decode_all(state) {
+            Ok(decompressed) => {
                 let state_bytes = decompressed.len();
                 let compressed_state_bytes = state.len();
                 let compression_ratio = compressed_state_bytes as f32 / state_bytes as f32 * 100.0;
@@ -255,40 +372,95 @@ pub(crate) unsafe fn deserialize_json(state: &[u8]) -> Option<PluginState> {
                      ({compression_ratio:.1}% compression ratio)"
                 );
 
-                Some(s)
+                decode_state(&decompressed)
             }
             Err(err) => {
-                nih_debug_assert_failure!("Error while deserializing state: {}", err);
+                nih_debug_assert_failure!("Error while decompressing state: {}", err);
                 None
             }
-        },
-        // Uncompressed state files can still be loaded after enabling this feature to prevent
-        // breaking existing plugin instances
-        Err(zstd_err) => match serde_json::from_slice(state) {
-            Ok(s) => {
-                nih_trace!("Older uncompressed state found");
-                Some(s)
-            }
-            Err(json_err) => {
-                nih_debug_assert_failure!(
-                    "Error while deserializing state as either compressed or uncompressed state: \
-                     {}, {}",
-                    zstd_err,
-                    json_err
-                );
-                None
-            }
-        },
-    };
+        };
+    }
 
     #[cfg(not(feature = "zstd"))]
-    let result: Option<PluginState> = match serde_json::from_slice(state) {
+    if state.starts_with(&ZSTD_MAGIC) {
+        nih_debug_assert_failure!(
+            "Found a compressed state, but the plugin was compiled without the 'zstd' feature"
+        );
+        return None;
+    }
+
+    decode_state(state)
+}
+
+/// Decode an uncompressed state, detecting whether it uses the binary encoding or JSON.
+fn decode_state(state: &[u8]) -> Option<PluginState> {
+    let result = if binary::is_binary(state) {
+        binary::decode(state)
+    } else {
+        serde_json::from_slice(state).context("Could not parse the state as JSON")
+    };
+
+    match result {
         Ok(s) => Some(s),
         Err(err) => {
-            nih_debug_assert_failure!("Error while deserializing state: {}", err);
+            nih_debug_assert_failure!("Error while deserializing state: {:#}", err);
             None
         }
-    };
+    }
+}
 
-    result
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_state() -> PluginState {
+        PluginState {
+            version: String::from("1.0.0"),
+            params: BTreeMap::from([(String::from("gain"), ParamValue::F32(0.5))]),
+            fields: BTreeMap::from([(String::from("field"), String::from("[1,2,3]"))]),
+            blobs: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn detect_json_state() {
+        let json = serde_json::to_vec(&make_state()).unwrap();
+        let state = unsafe { deserialize_state(&json) }.unwrap();
+
+        assert_eq!(state.version, "1.0.0");
+        assert_eq!(state.fields, make_state().fields);
+    }
+
+    #[test]
+    fn detect_binary_state() {
+        let encoded = binary::encode(&make_state());
+        let state = unsafe { deserialize_state(&encoded) }.unwrap();
+
+        assert_eq!(state.version, "1.0.0");
+        assert_eq!(state.fields, make_state().fields);
+    }
+
+    #[test]
+    fn detect_blobs() {
+        let blobs = BTreeMap::from([(String::from("samples"), vec![0, 1, 2, 3])]);
+        let encoded = wrap_blobs(binary::encode(&make_state()), &blobs);
+        let state = unsafe { deserialize_state(&encoded) }.unwrap();
+
+        assert_eq!(state.fields, make_state().fields);
+        assert_eq!(state.blobs, blobs);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn detect_compressed_states() {
+        for encoded in [
+            serde_json::to_vec(&make_state()).unwrap(),
+            binary::encode(&make_state()),
+        ] {
+            let compressed = zstd::encode_all(encoded.as_slice(), 0).unwrap();
+            let state = unsafe { deserialize_state(&compressed) }.unwrap();
+
+            assert_eq!(state.fields, make_state().fields);
+        }
+    }
 }
@@ -0,0 +1,242 @@
+//! A compact binary encoding for [`PluginState`] objects, used when a plugin sets
+//! [`Plugin::STATE_CODEC`][crate::prelude::Plugin::STATE_CODEC] to [`StateCodec::Binary`][super::StateCodec::Binary].
+//!
+//! The encoding starts with [`MAGIC`] followed by a single format version byte. All integers are
+//! stored in little endian byte order, and strings are stored as a `u32` byte length followed by
+//! the string's UTF-8 data. After the header the state's version string is stored, followed by a
+//! `u32` parameter count and that many `(id, tag, value)` triples, and finally a `u32` field count
+//! and that many `(key, value)` string pairs. The persistent fields' serialized values are stored
+//! as is, so they don't need to be escaped like they would be when using JSON.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+use super::{ParamValue, PluginState};
+
+/// The bytes every binary state starts with. This is used to distinguish binary states from JSON
+/// states when loading state.
+pub const MAGIC: &[u8; 4] = b"NIHS";
+/// The version of the binary encoding. Incremented when the encoding changes in an incompatible
+/// way.
+const FORMAT_VERSION: u8 = 1;
+
+const TAG_F32: u8 = 0;
+const TAG_I32: u8 = 1;
+const TAG_BOOL: u8 = 2;
+const TAG_STRING: u8 = 3;
+
+/// Whether `bytes` contains a state encoded with [`encode()`].
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encode a plugin state using the binary encoding.
+pub fn encode(state: &PluginState) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(
+        MAGIC.len()
+            + 1
+            + state.version.len()
+            + state
+                .fields
+                .iter()
+                .map(|(key, value)| key.len() + value.len() + 8)
+                .sum::<usize>()
+            + state.params.len() * 16,
+    );
+
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(FORMAT_VERSION);
+    write_str(&mut bytes, &state.version);
+
+    write_u32(&mut bytes, state.params.len() as u32);
+    for (param_id, value) in &state.params {
+        write_str(&mut bytes, param_id);
+        match value {
+            ParamValue::F32(value) => {
+                bytes.push(TAG_F32);
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ParamValue::I32(value) => {
+                bytes.push(TAG_I32);
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            ParamValue::Bool(value) => {
+                bytes.push(TAG_BOOL);
+                bytes.push(*value as u8);
+            }
+            ParamValue::String(value) => {
+                bytes.push(TAG_STRING);
+                write_str(&mut bytes, value);
+            }
+        }
+    }
+
+    write_u32(&mut bytes, state.fields.len() as u32);
+    for (key, value) in &state.fields {
+        write_str(&mut bytes, key);
+        write_str(&mut bytes, value);
+    }
+
+    bytes
+}
+
+/// Decode a plugin state encoded with [`encode()`].
+pub fn decode(bytes: &[u8]) -> Result<PluginState> {
+    let mut reader = Reader::new(bytes);
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        anyhow::bail!("The data is not a binary plugin state");
+    }
+    let format_version = reader.u8()?;
+    if format_version != FORMAT_VERSION {
+        anyhow::bail!("Unsupported binary state format version {format_version}");
+    }
+
+    let version = reader
+        .string()
+        .context("Could not read the state's version")?;
+
+    let mut params = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let param_id = reader.string().context("Could not read a parameter ID")?;
+        let value = match reader.u8()? {
+            TAG_F32 => ParamValue::F32(f32::from_le_bytes(reader.array()?)),
+            TAG_I32 => ParamValue::I32(i32::from_le_bytes(reader.array()?)),
+            TAG_BOOL => ParamValue::Bool(reader.u8()? != 0),
+            TAG_STRING => ParamValue::String(reader.string()?),
+            tag => anyhow::bail!("Unknown value type {tag} for parameter '{param_id}'"),
+        };
+
+        params.insert(param_id, value);
+    }
+
+    let mut fields = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let key = reader.string().context("Could not read a field's key")?;
+        let value = reader
+            .string()
+            .with_context(|| format!("Could not read field '{key}'"))?;
+
+        fields.insert(key, value);
+    }
+
+    if !reader.bytes.is_empty() {
+        anyhow::bail!(
+            "Found {} trailing bytes after the state",
+            reader.bytes.len()
+        );
+    }
+
+    Ok(PluginState {
+        version,
+        params,
+        fields,
+        blobs: BTreeMap::new(),
+    })
+}
+
+pub(super) fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(super) fn write_str(bytes: &mut Vec<u8>, string: &str) {
+    write_u32(bytes, string.len() as u32);
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+/// A cursor over the encoded state that returns an error when reading past the end of the data.
+pub(super) struct Reader<'a> {
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            anyhow::bail!("Unexpected end of data");
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?;
+
+        String::from_utf8(bytes.to_vec()).context("Invalid UTF-8 data")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_state() -> PluginState {
+        PluginState {
+            version: String::from("1.0.0"),
+            params: BTreeMap::from([
+                (String::from("gain"), ParamValue::F32(-6.5)),
+                (String::from("voices"), ParamValue::I32(-3)),
+                (String::from("bypass"), ParamValue::Bool(true)),
+                (String::from("mode"), ParamValue::String(String::from("lp"))),
+            ]),
+            fields: BTreeMap::from([(
+                String::from("curve"),
+                String::from(r#"{"points":[[0.0,1.0],[0.5,"\n"]]}"#),
+            )]),
+            blobs: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let state = make_state();
+        let encoded = encode(&state);
+        assert!(is_binary(&encoded));
+
+        let decoded = decode(&encoded).unwrap();
+        assert_eq!(decoded.version, state.version);
+        assert_eq!(decoded.fields, state.fields);
+        assert_eq!(
+            serde_json::to_string(&decoded.params).unwrap(),
+            serde_json::to_string(&state.params).unwrap()
+        );
+    }
+
+    #[test]
+    fn truncated_data() {
+        let encoded = encode(&make_state());
+        for len in 0..encoded.len() {
+            assert!(decode(&encoded[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn json_is_not_binary() {
+        let json = serde_json::to_vec(&make_state()).unwrap();
+        assert!(!is_binary(&json));
+        assert!(decode(&json).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! A container for storing a plugin's `#[persist_blob]` data as raw bytes next to its encoded state.
+//! This is used for CLAP and VST3 state streams as well as for preset files. States without any
+//! blobs are stored without this container so they stay compatible with older versions of
+//! NIH-plug.
+//!
+//! The container starts with [`MAGIC`] followed by a single format version byte. Then comes the
+//! encoded state as a `u64` byte length followed by the data, a `u32` blob count, and that many
+//! `(key, data)` pairs. Keys are stored as a `u32` byte length followed by the key's UTF-8 data,
+//! and the blobs' data is stored as a `u64` byte length followed by the raw bytes. All integers
+//! are stored in little endian byte order.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+use super::binary::{write_str, write_u32, Reader};
+
+/// The bytes every container starts with.
+pub const MAGIC: &[u8; 4] = b"NIHB";
+/// The version of the container format. Incremented when the format changes in an incompatible
+/// way.
+const FORMAT_VERSION: u8 = 1;
+
+/// Whether `bytes` was created with [`wrap()`].
+pub fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Store the encoded state `data` together with the plugin's blobs.
+pub fn wrap(data: &[u8], blobs: &BTreeMap<String, Vec<u8>>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(
+        MAGIC.len()
+            + 13
+            + data.len()
+            + blobs
+                .iter()
+                .map(|(key, blob)| key.len() + blob.len() + 12)
+                .sum::<usize>(),
+    );
+
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(FORMAT_VERSION);
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(data);
+
+    write_u32(&mut bytes, blobs.len() as u32);
+    for (key, blob) in blobs {
+        write_str(&mut bytes, key);
+        bytes.extend_from_slice(&(blob.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(blob);
+    }
+
+    bytes
+}
+
+/// Split a container created with [`wrap()`] back into the encoded state and the plugin's blobs.
+pub fn unwrap(bytes: &[u8]) -> Result<(&[u8], BTreeMap<String, Vec<u8>>)> {
+    let mut reader = Reader::new(bytes);
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        anyhow::bail!("The data does not contain any blobs");
+    }
+    let format_version = reader.u8()?;
+    if format_version != FORMAT_VERSION {
+        anyhow::bail!("Unsupported blob container format version {format_version}");
+    }
+
+    let data_len = reader.u64()? as usize;
+    let data = reader
+        .take(data_len)
+        .context("Could not read the encoded state")?;
+
+    let mut blobs = BTreeMap::new();
+    for _ in 0..reader.u32()? {
+        let key = reader.string().context("Could not read a blob's key")?;
+        let blob_len = reader.u64()? as usize;
+        let blob = reader
+            .take(blob_len)
+            .with_context(|| format!("Could not read blob '{key}'"))?;
+
+        blobs.insert(key, blob.to_vec());
+    }
+
+    if !reader.bytes.is_empty() {
+        anyhow::bail!(
+            "Found {} trailing bytes after the blobs",
+            reader.bytes.len()
+        );
+    }
+
+    Ok((data, blobs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let data = br#"{"params":{},"fields":{}}"#;
+        let blobs = BTreeMap::from([
+            (String::from("empty"), Vec::new()),
+            (String::from("samples"), (0..=255).collect::<Vec<u8>>()),
+        ]);
+
+        let wrapped = wrap(data, &blobs);
+        assert!(is_container(&wrapped));
+
+        let (unwrapped_data, unwrapped_blobs) = unwrap(&wrapped).unwrap();
+        assert_eq!(unwrapped_data, data);
+        assert_eq!(unwrapped_blobs, blobs);
+    }
+
+    #[test]
+    fn truncated_data() {
+        let blobs = BTreeMap::from([(String::from("samples"), vec![1, 2, 3])]);
+        let wrapped = wrap(b"{}", &blobs);
+        for len in 0..wrapped.len() {
+            assert!(unwrap(&wrapped[..len]).is_err());
+        }
+    }
+}
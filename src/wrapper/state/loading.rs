@@ -0,0 +1,208 @@
+//! Tracking for state loads that continue in the background after the plugin's state has been
+//! restored. See [`InitContext::begin_state_load()`][crate::prelude::InitContext::begin_state_load()].
+
+use atomic_float::AtomicF32;
+use parking_lot::{Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::prelude::{AuxiliaryBuffers, Buffer, Sample};
+
+/// What the wrapper should do with the audio thread while an asynchronous state load is in
+/// progress.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StateLoadBehavior {
+    /// Don't call the plugin's process function and output silence instead until the load has
+    /// finished.
+    #[default]
+    Silence,
+    /// Keep calling the plugin's process function as usual. The plugin is then responsible for
+    /// continuing to use its previous state until the new state is ready.
+    KeepProcessing,
+}
+
+/// A handle for a state load that's running in the background, obtained through
+/// [`InitContext::begin_state_load()`][crate::prelude::InitContext::begin_state_load()]. This can
+/// be moved into a [`Plugin::BackgroundTask`][crate::prelude::Plugin::BackgroundTask] to report
+/// the load's progress to the editor. The load is considered to be finished once this handle is
+/// dropped or once [`finish()`][Self::finish()] is called.
+#[derive(Debug)]
+pub struct StateLoadHandle {
+    tracker: Arc<StateLoadTracker>,
+    id: u32,
+}
+
+/// Keeps track of the plugin's asynchronous state loads. Each wrapper has one of these. Starting a
+/// new load supersedes the previous one, so only the most recent load's handle can still update the
+/// progress or finish the load.
+#[derive(Debug, Default)]
+pub struct StateLoadTracker {
+    /// The ID assigned to the most recently started load.
+    last_id: AtomicU32,
+    /// The ID of the load that's currently in progress, or 0 if there is no active load.
+    active_id: AtomicU32,
+    /// Whether the audio thread should output silence while the active load is in progress.
+    silence: AtomicBool,
+    /// The active load's progress, between 0 and 1.
+    progress: AtomicF32,
+
+    /// Used to wait for the active load to finish when rendering offline.
+    finished_lock: Mutex<()>,
+    finished_cond: Condvar,
+}
+
+impl StateLoadHandle {
+    /// Update the load's progress. `progress` is clamped to `[0, 1]`. This does nothing if the load
+    /// has been superseded by a newer load.
+    pub fn set_progress(&self, progress: f32) {
+        if self.is_active() {
+            self.tracker
+                .progress
+                .store(progress.clamp(0.0, 1.0), Ordering::Relaxed);
+        }
+    }
+
+    /// Whether this load has been superseded by a newer one, for instance because the host restored
+    /// another state while this load was still in progress. Background tasks can check this to stop
+    /// doing unnecessary work.
+    pub fn is_superseded(&self) -> bool {
+        !self.is_active()
+    }
+
+    /// Mark the load as finished. This is the same as dropping the handle.
+    pub fn finish(self) {}
+
+    fn is_active(&self) -> bool {
+        self.tracker.active_id.load(Ordering::Acquire) == self.id
+    }
+}
+
+impl Drop for StateLoadHandle {
+    fn drop(&mut self) {
+        let _guard = self.tracker.finished_lock.lock();
+        if self
+            .tracker
+            .active_id
+            .compare_exchange(self.id, 0, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+        {
+            self.tracker.finished_cond.notify_all();
+        }
+    }
+}
+
+impl StateLoadTracker {
+    /// Start a new load, superseding the previous one if it's still in progress.
+    pub fn begin(self: &Arc<Self>, behavior: StateLoadBehavior) -> StateLoadHandle {
+        // 0 is used to indicate that there's no active load
+        let mut id = self.last_id.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+        if id == 0 {
+            id = self.last_id.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+        }
+
+        self.progress.store(0.0, Ordering::Relaxed);
+        self.silence
+            .store(behavior == StateLoadBehavior::Silence, Ordering::Relaxed);
+        self.active_id.store(id, Ordering::Release);
+
+        StateLoadHandle {
+            tracker: self.clone(),
+            id,
+        }
+    }
+
+    /// The active load's progress, or `None` if no load is in progress.
+    pub fn progress(&self) -> Option<f32> {
+        if self.active_id.load(Ordering::Acquire) != 0 {
+            Some(self.progress.load(Ordering::Relaxed))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the audio thread should output silence instead of calling the plugin's process
+    /// function.
+    pub fn should_silence(&self) -> bool {
+        self.active_id.load(Ordering::Acquire) != 0 && self.silence.load(Ordering::Relaxed)
+    }
+
+    /// Block until the active load has finished. Used when rendering offline, since outputting
+    /// silence there would end up in the rendered file. This is called from the audio thread, so
+    /// the lock is only taken when a load is actually in progress.
+    pub fn wait(&self) {
+        if self.active_id.load(Ordering::Acquire) == 0 {
+            return;
+        }
+
+        let mut guard = self.finished_lock.lock();
+        while self.active_id.load(Ordering::Acquire) != 0 {
+            self.finished_cond.wait(&mut guard);
+        }
+    }
+}
+
+/// Clear the main and auxiliary output buffers and mark them as silent. Used instead of calling the
+/// plugin's process function while [`StateLoadTracker::should_silence()`] returns true.
+pub(crate) fn silence_outputs<S: Sample>(buffer: &mut Buffer<S>, aux: &mut AuxiliaryBuffers<S>) {
+    for channel_idx in 0..buffer.channels() {
+        buffer.set_output_silent(channel_idx);
+    }
+    for aux_buffer in aux.outputs.iter_mut() {
+        for channel_idx in 0..aux_buffer.channels() {
+            aux_buffer.set_output_silent(channel_idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_finishes_load() {
+        let tracker = Arc::new(StateLoadTracker::default());
+        assert_eq!(tracker.progress(), None);
+
+        let handle = tracker.begin(StateLoadBehavior::Silence);
+        assert!(tracker.should_silence());
+        handle.set_progress(0.5);
+        assert_eq!(tracker.progress(), Some(0.5));
+
+        handle.finish();
+        assert_eq!(tracker.progress(), None);
+        assert!(!tracker.should_silence());
+        tracker.wait();
+    }
+
+    #[test]
+    fn newer_load_supersedes() {
+        let tracker = Arc::new(StateLoadTracker::default());
+        let old_handle = tracker.begin(StateLoadBehavior::Silence);
+        let new_handle = tracker.begin(StateLoadBehavior::KeepProcessing);
+        assert!(old_handle.is_superseded());
+        assert!(!new_handle.is_superseded());
+        assert!(!tracker.should_silence());
+
+        // The old handle can no longer touch the new load
+        old_handle.set_progress(1.0);
+        drop(old_handle);
+        assert_eq!(tracker.progress(), Some(0.0));
+
+        drop(new_handle);
+        assert_eq!(tracker.progress(), None);
+    }
+
+    #[test]
+    fn wait_for_background_load() {
+        let tracker = Arc::new(StateLoadTracker::default());
+        let handle = tracker.begin(StateLoadBehavior::Silence);
+        let thread = std::thread::spawn(move || {
+            handle.set_progress(1.0);
+            handle.finish();
+        });
+
+        tracker.wait();
+        assert_eq!(tracker.progress(), None);
+        thread.join().unwrap();
+    }
+}
@@ -1,13 +1,18 @@
 use backtrace::Backtrace;
 use std::cmp;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::os::raw::c_char;
 
+use crate::prelude::{AudioIOLayout, ParamFlags, ParamPtr, Plugin};
 use crate::util::permit_alloc;
 
 pub(crate) mod buffer_management;
+pub(crate) mod bypass;
 #[cfg(debug_assertions)]
 pub(crate) mod context_checks;
+pub(crate) mod snapshots;
 
 /// The bit that controls flush-to-zero behavior for denormals in 32 and 64-bit floating point
 /// numbers on x86 family architectures. Rust 1.75 deprecated the built in functions for controlling
@@ -36,18 +41,116 @@ compile_error!("The 'assert_process_allocs' feature does not work correctly in c
 #[global_allocator]
 static A: assert_no_alloc::AllocDisabler = assert_no_alloc::AllocDisabler;
 
-/// A Rabin fingerprint based string hash for parameter ID strings.
-pub fn hash_param_id(id: &str) -> u32 {
-    let mut hash: u32 = 0;
-    for char in id.bytes() {
-        hash = hash.wrapping_mul(31).wrapping_add(char as u32);
+// `MAX_PARAM_ID_LEN` and `hash_param_id()` are shared with the `Params` derive macro
+include!("../../nih_plug_derive/src/param_id.rs");
+
+/// Check whether a parameter ID is valid. Parameter IDs need to be non-empty, may not be longer
+/// than [`MAX_PARAM_ID_LEN`] bytes, and they may only contain ASCII letters, digits, underscores,
+/// hyphens, and periods. Returns a description of the problem if the ID is not valid.
+pub fn check_param_id(id: &str) -> Option<String> {
+    if id.is_empty() {
+        Some(String::from("parameter IDs cannot be empty"))
+    } else if id.len() > MAX_PARAM_ID_LEN {
+        Some(format!(
+            "parameter IDs cannot be longer than {MAX_PARAM_ID_LEN} bytes"
+        ))
+    } else if !id
+        .bytes()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.'))
+    {
+        Some(String::from(
+            "parameter IDs may only contain ASCII letters, digits, underscores, hyphens, and \
+             periods",
+        ))
+    } else {
+        None
+    }
+}
+
+/// Check the parameter IDs returned by [`Params::param_map()`][crate::prelude::Params::param_map()]
+/// after nested and array parameters have been expanded. This catches invalid IDs, duplicate IDs,
+/// and different IDs that hash to the same value with [`hash_param_id()`]. Returns a description
+/// for every problem that was found.
+pub(crate) fn validate_param_ids<'a>(param_ids: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut ids_by_hash: HashMap<u32, &str> = HashMap::new();
+    for id in param_ids {
+        if let Some(problem) = check_param_id(id) {
+            problems.push(format!("Invalid parameter ID '{}': {}", id, problem));
+        }
+
+        match ids_by_hash.entry(hash_param_id(id)) {
+            Entry::Occupied(entry) if *entry.get() == id => {
+                problems.push(format!("Duplicate parameter ID '{}'", id));
+            }
+            Entry::Occupied(entry) => problems.push(format!(
+                "Parameter IDs '{}' and '{}' have the same hash, consider renaming one of them",
+                entry.get(),
+                id
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(id);
+            }
+        }
+    }
+
+    problems
+}
+
+/// Perform the debug assertions on the plugin's parameters that are shared between all wrappers.
+/// This checks the parameter IDs using [`validate_param_ids()`], and it makes sure there's at most
+/// one bypass parameter.
+pub(crate) fn check_param_map(param_map: &[(String, ParamPtr, String)]) {
+    if !cfg!(debug_assertions) {
+        return;
     }
 
-    // In VST3 the last bit is reserved for parameters provided by the host
-    // https://developer.steinberg.help/display/VST/Parameters+and+Automation
-    hash &= !(1 << 31);
+    for problem in validate_param_ids(param_map.iter().map(|(id, _, _)| id.as_str())) {
+        nih_debug_assert_failure!("{}, weird things may happen", problem);
+    }
+
+    let mut bypass_param_exists = false;
+    for (_, ptr, _) in param_map {
+        let flags = unsafe { ptr.flags() };
+        let is_bypass = flags.contains(ParamFlags::BYPASS);
+
+        if is_bypass && bypass_param_exists {
+            nih_debug_assert_failure!(
+                "Duplicate bypass parameters found, the host will only use the first one"
+            );
+        }
+
+        bypass_param_exists |= is_bypass;
+    }
+}
 
-    hash
+/// The audio IO layout to use for a layout requested by the host, or `None` if the plugin doesn't
+/// support it. If one of the plugin's predefined layouts has the same ports and channel layouts,
+/// then that layout is returned so its port names and channel layouts are kept. Otherwise the
+/// requested layout is used if the plugin accepts it through
+/// [`Plugin::accept_audio_io_layout()`].
+pub fn supported_audio_io_layout<P: Plugin>(layout: &AudioIOLayout) -> Option<AudioIOLayout> {
+    find_matching_audio_io_layout(P::AUDIO_IO_LAYOUTS, layout)
+        .or_else(|| P::accept_audio_io_layout(layout).then_some(*layout))
+}
+
+/// Find the layout in `layouts` with the same channel counts and effective main channel layouts as
+/// `layout`. Port names and explicitly set channel layouts that match the defaults are ignored.
+fn find_matching_audio_io_layout(
+    layouts: &[AudioIOLayout],
+    layout: &AudioIOLayout,
+) -> Option<AudioIOLayout> {
+    layouts
+        .iter()
+        .find(|candidate| {
+            candidate.main_input_channels == layout.main_input_channels
+                && candidate.main_output_channels == layout.main_output_channels
+                && candidate.aux_input_ports == layout.aux_input_ports
+                && candidate.aux_output_ports == layout.aux_output_ports
+                && candidate.main_input_layout() == layout.main_input_layout()
+                && candidate.main_output_layout() == layout.main_output_layout()
+        })
+        .copied()
 }
 
 /// The equivalent of the `strlcpy()` C function. Copy `src` to `dest` as a null-terminated
@@ -311,3 +414,113 @@ mod miri {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+    use crate::prelude::{new_nonzero_u32, ChannelLayout, PortChannelLayouts, PortNames};
+
+    const STEREO: AudioIOLayout = AudioIOLayout {
+        main_input_channels: Some(new_nonzero_u32(2)),
+        main_output_channels: Some(new_nonzero_u32(2)),
+        names: PortNames {
+            layout: Some("Stereo"),
+            ..PortNames::const_default()
+        },
+        ..AudioIOLayout::const_default()
+    };
+    const QUAD: AudioIOLayout = AudioIOLayout {
+        main_input_channels: Some(new_nonzero_u32(4)),
+        main_output_channels: Some(new_nonzero_u32(4)),
+        channel_layouts: PortChannelLayouts {
+            main_input: Some(ChannelLayout::Quad),
+            main_output: Some(ChannelLayout::Quad),
+            ..PortChannelLayouts::const_default()
+        },
+        ..AudioIOLayout::const_default()
+    };
+
+    #[test]
+    fn valid_param_ids() {
+        assert!(validate_param_ids(["gain", "mix_1", "lr24-lp", "v1.2"]).is_empty());
+    }
+
+    #[test]
+    fn invalid_param_ids() {
+        assert!(check_param_id("").is_some());
+        assert!(check_param_id("has spaces").is_some());
+        assert!(check_param_id("ünicode").is_some());
+        assert!(check_param_id(&"a".repeat(MAX_PARAM_ID_LEN)).is_none());
+        assert!(check_param_id(&"a".repeat(MAX_PARAM_ID_LEN + 1)).is_some());
+    }
+
+    #[test]
+    fn duplicate_param_ids() {
+        assert_eq!(validate_param_ids(["gain", "mix", "gain"]).len(), 1);
+    }
+
+    #[test]
+    fn matching_audio_io_layout() {
+        // The names and the implied stereo channel layout don't need to match, and the predefined
+        // layout is returned
+        let requested = AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(2)),
+            main_output_channels: Some(new_nonzero_u32(2)),
+            channel_layouts: PortChannelLayouts {
+                main_input: Some(ChannelLayout::Stereo),
+                main_output: Some(ChannelLayout::Stereo),
+                ..PortChannelLayouts::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        };
+        assert_eq!(
+            find_matching_audio_io_layout(&[QUAD, STEREO], &requested),
+            Some(STEREO)
+        );
+
+        // Four channels without a channel layout are not quad
+        let requested = AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(4)),
+            main_output_channels: Some(new_nonzero_u32(4)),
+            ..AudioIOLayout::const_default()
+        };
+        assert_eq!(
+            find_matching_audio_io_layout(&[QUAD, STEREO], &requested),
+            None
+        );
+        let requested = AudioIOLayout {
+            channel_layouts: QUAD.channel_layouts,
+            ..requested
+        };
+        assert_eq!(
+            find_matching_audio_io_layout(&[QUAD, STEREO], &requested),
+            Some(QUAD)
+        );
+    }
+
+    #[test]
+    fn mismatched_audio_io_layout() {
+        const STEREO_PORT: NonZeroU32 = new_nonzero_u32(2);
+
+        let requested = AudioIOLayout {
+            main_input_channels: None,
+            ..STEREO
+        };
+        assert_eq!(find_matching_audio_io_layout(&[STEREO], &requested), None);
+
+        let requested = AudioIOLayout {
+            aux_input_ports: &[STEREO_PORT],
+            ..STEREO
+        };
+        assert_eq!(find_matching_audio_io_layout(&[STEREO], &requested), None);
+    }
+
+    #[test]
+    fn colliding_param_ids() {
+        // These are different IDs with the same Rabin fingerprint
+        assert_eq!(hash_param_id("Aa"), hash_param_id("BB"));
+        assert_eq!(validate_param_ids(["Aa", "BB"]).len(), 1);
+    }
+}
@@ -2,91 +2,124 @@
 
 use std::num::NonZeroU32;
 use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::prelude::{AudioIOLayout, Buffer};
+use crate::buffer::channel_mask;
+use crate::prelude::{AudioIOLayout, Buffer, Sample};
 
 /// Buffers created using [`create_buffers`]. At some point the main `Plugin::process()` should
-/// probably also take an argument like this instead of main+aux buffers if we also want to provide
-/// access to overflowing input channels for e.g. stereo to mono plugins.
-pub struct Buffers<'a, 'buffer: 'a> {
-    pub main_buffer: &'a mut Buffer<'buffer>,
+/// probably also take an argument like this instead of main+aux buffers.
+pub struct Buffers<'a, 'buffer: 'a, S = f32> {
+    pub main_buffer: &'a mut Buffer<'buffer, S>,
+    /// A copy of the full main input if the main input and output channel counts differ. See
+    /// `AuxiliaryBuffers::main_input`.
+    pub main_input: Option<&'a mut Buffer<'buffer, S>>,
 
     // We can't use `AuxiliaryBuffers` here directly because we need different lifetimes for `'a`
     // and `'buffer` while `AuxiliaryBuffers` uses the same lifetime for both.
-    pub aux_inputs: &'a mut [Buffer<'buffer>],
-    pub aux_outputs: &'a mut [Buffer<'buffer>],
+    pub aux_inputs: &'a mut [Buffer<'buffer, S>],
+    pub aux_outputs: &'a mut [Buffer<'buffer, S>],
+    pub aux_inputs_active: &'a [bool],
+    pub aux_outputs_active: &'a [bool],
 }
 
 /// A helper for safely creating and initializing [`Buffer`]s based on the host's input and output
 /// buffers.
-pub struct BufferManager {
+pub struct BufferManager<S: Sample = f32> {
     // These are the storage backing the fields in `BufferSource`. The wrapper needs to set these
     // values to match the channel pointers provided by the host. If audio buffers are not provided
     // for a bus, then they should be set to `None`. This helper will then copy data to the buffers
     // or fill them with zeroes if there is no data, while also accounting for in-place main IO
     // buffers.
-    main_input_channel_pointers: Option<ChannelPointers>,
-    main_output_channel_pointers: Option<ChannelPointers>,
-    aux_input_channel_pointers: Vec<Option<ChannelPointers>>,
-    aux_output_channel_pointers: Vec<Option<ChannelPointers>>,
+    main_input_channel_pointers: Option<ChannelPointers<S>>,
+    main_output_channel_pointers: Option<ChannelPointers<S>>,
+    aux_input_channel_pointers: Vec<Option<ChannelPointers<S>>>,
+    aux_output_channel_pointers: Vec<Option<ChannelPointers<S>>>,
 
     /// The backing buffers that will be filled during `create_buffers`. This `'static` lifetime
     /// will be shortened when returning a reference to these buffers in `create_buffers` to match
     /// the function's lifetime.
-    main_buffer: Buffer<'static>,
+    main_buffer: Buffer<'static, S>,
+    /// A copy of the host's main input data. This is only used when the main input and output
+    /// ports have different channel counts, since otherwise the main input is already available
+    /// through the in-place `main_buffer`. Backed by `main_input_storage`, just like the auxiliary
+    /// inputs.
+    main_input_buffer: Option<Buffer<'static, S>>,
+    main_input_storage: Vec<Vec<S>>,
 
-    aux_input_buffers: Vec<Buffer<'static>>,
+    aux_input_buffers: Vec<Buffer<'static, S>>,
     /// Stores the data to back `aux_input_buffers`. We need to copy the host's auxiliary input
     /// buffers to our own first because the `Buffer` API is designed around mutable buffers, and
     /// the host may reuse its input buffers between plugins.
-    aux_input_storage: Vec<Vec<Vec<f32>>>,
+    aux_input_storage: Vec<Vec<Vec<S>>>,
 
-    aux_output_buffers: Vec<Buffer<'static>>,
+    aux_output_buffers: Vec<Buffer<'static, S>>,
+
+    /// Whether the host has activated the auxiliary ports. These are reset to `true` at the start
+    /// of `create_buffers()` and can be changed through the `BufferSource`.
+    aux_inputs_active: Vec<bool>,
+    aux_outputs_active: Vec<bool>,
+
+    /// The host's constant flags for the input ports. These are reset to 0 at the start of
+    /// `create_buffers()` and can be set through the `BufferSource`.
+    main_input_constant_mask: u64,
+    aux_input_constant_masks: Vec<u64>,
+    /// The output constant flags set by the plugin, combined over all blocks in the current process
+    /// call. See `accumulate_output_constant_masks()`.
+    main_output_constant_mask: u64,
+    aux_output_constant_masks: Vec<u64>,
+    /// The `sample_offset` and `num_samples` passed to the last `create_buffers()` call.
+    sample_offset: usize,
+    num_samples: usize,
 }
 
 // SAFETY: The raw pointers in the `ChannelPointers` fields/vectors are only used as scratch storage
 //         inside of the `create_buffers()` function.
-unsafe impl Send for BufferManager {}
-unsafe impl Sync for BufferManager {}
+unsafe impl<S: Sample> Send for BufferManager<S> {}
+unsafe impl<S: Sample> Sync for BufferManager<S> {}
 
 /// Host data that the plugin's [`Buffer`]s should be created from. Leave these fields as `None`
 /// values
 #[derive(Debug)]
-pub struct BufferSource<'a> {
-    pub main_input_channel_pointers: &'a mut Option<ChannelPointers>,
-    pub main_output_channel_pointers: &'a mut Option<ChannelPointers>,
-    pub aux_input_channel_pointers: &'a mut [Option<ChannelPointers>],
-    pub aux_output_channel_pointers: &'a mut [Option<ChannelPointers>],
+pub struct BufferSource<'a, S = f32> {
+    pub main_input_channel_pointers: &'a mut Option<ChannelPointers<S>>,
+    pub main_output_channel_pointers: &'a mut Option<ChannelPointers<S>>,
+    pub aux_input_channel_pointers: &'a mut [Option<ChannelPointers<S>>],
+    pub aux_output_channel_pointers: &'a mut [Option<ChannelPointers<S>>],
+    /// Whether the host has activated the auxiliary input ports. All ports are considered to be
+    /// active unless these are set to `false`.
+    pub aux_inputs_active: &'a mut [bool],
+    pub aux_outputs_active: &'a mut [bool],
+    /// The host's constant flags for the main and auxiliary inputs, where bit `n` indicates that
+    /// every sample in channel `n` has the same value. These are 0 unless set.
+    pub main_input_constant_mask: &'a mut u64,
+    pub aux_input_constant_masks: &'a mut [u64],
+}
+
+/// Keeps track of which of the plugin's auxiliary ports have been activated by the host through
+/// CLAP's `audio-ports-activation` extension or VST3's `IComponent::activateBus()`. This can be
+/// updated from any thread. All ports are active by default.
+#[derive(Debug, Default)]
+pub struct AuxPortActivation {
+    inputs: Vec<AtomicBool>,
+    outputs: Vec<AtomicBool>,
 }
 
 /// Pointers to raw multichannel audio data for this port.
 #[derive(Debug, Clone, Copy)]
-pub struct ChannelPointers {
-    /// A raw pointer to an array of f32 arrays, containing one array for each channel. `ptrs` must
-    /// contain (at least) `num_channel` `*const f32`s, and each of those inner arrays must contain
-    /// (at least) `num_samples` `f32` values.
-    pub ptrs: NonNull<*mut f32>,
+pub struct ChannelPointers<S = f32> {
+    /// A raw pointer to an array of sample arrays, containing one array for each channel. `ptrs`
+    /// must contain (at least) `num_channel` `*const S`s, and each of those inner arrays must
+    /// contain (at least) `num_samples` `S` values.
+    pub ptrs: NonNull<*mut S>,
     /// The number of audio channels used for this port.
     pub num_channels: usize,
 }
 
-impl BufferManager {
+impl<S: Sample> BufferManager<S> {
     /// Initialize managed buffers for a specific audio IO layout. The actual buffers can be set up
     /// using channel pointer data using [`create_buffers()`][Self::create_buffers()].
     pub fn for_audio_io_layout(max_buffer_size: usize, audio_io_layout: AudioIOLayout) -> Self {
-        nih_debug_assert!(
-            audio_io_layout
-                .main_input_channels
-                .map(NonZeroU32::get)
-                .unwrap_or(0)
-                <= audio_io_layout
-                    .main_output_channels
-                    .map(NonZeroU32::get)
-                    .unwrap_or(0),
-            "Stereo-to-mono and other many-to-few audio channel configurations are currently not \
-             supported"
-        );
-
         // The buffers are preallocated so that `create_buffers()` can be called without having to
         // allocate
         let mut main_buffer = Buffer::default();
@@ -102,6 +135,31 @@ impl BufferManager {
             })
         };
 
+        let num_main_input_channels = audio_io_layout
+            .main_input_channels
+            .map(NonZeroU32::get)
+            .unwrap_or(0);
+        let (main_input_buffer, main_input_storage) = if num_main_input_channels
+            != audio_io_layout
+                .main_output_channels
+                .map(NonZeroU32::get)
+                .unwrap_or(0)
+        {
+            let mut buffer = Buffer::default();
+            unsafe {
+                buffer.set_slices(0, |slices| {
+                    slices.resize_with(num_main_input_channels as usize, || &mut []);
+                })
+            };
+
+            (
+                Some(buffer),
+                vec![vec![S::ZERO; max_buffer_size]; num_main_input_channels as usize],
+            )
+        } else {
+            (None, Vec::new())
+        };
+
         let mut aux_input_buffers = Vec::with_capacity(audio_io_layout.aux_input_ports.len());
         let mut aux_input_storage = Vec::with_capacity(audio_io_layout.aux_input_ports.len());
         for num_channels in audio_io_layout.aux_input_ports {
@@ -114,7 +172,7 @@ impl BufferManager {
 
             aux_input_buffers.push(buffer);
             aux_input_storage.push(vec![
-                vec![0.0; max_buffer_size];
+                vec![S::ZERO; max_buffer_size];
                 num_channels.get() as usize
             ]);
         }
@@ -138,11 +196,23 @@ impl BufferManager {
             aux_output_channel_pointers: vec![None; audio_io_layout.aux_output_ports.len()],
 
             main_buffer,
+            main_input_buffer,
+            main_input_storage,
 
             aux_input_buffers,
             aux_input_storage,
 
             aux_output_buffers,
+
+            aux_inputs_active: vec![true; audio_io_layout.aux_input_ports.len()],
+            aux_outputs_active: vec![true; audio_io_layout.aux_output_ports.len()],
+
+            main_input_constant_mask: 0,
+            aux_input_constant_masks: vec![0; audio_io_layout.aux_input_ports.len()],
+            main_output_constant_mask: 0,
+            aux_output_constant_masks: vec![0; audio_io_layout.aux_output_ports.len()],
+            sample_offset: 0,
+            num_samples: 0,
         }
     }
 
@@ -168,18 +238,28 @@ impl BufferManager {
         &'a mut self,
         sample_offset: usize,
         num_samples: usize,
-        set_buffer_sources: impl FnOnce(&mut BufferSource),
-    ) -> Buffers<'a, 'buffer> {
+        set_buffer_sources: impl FnOnce(&mut BufferSource<S>),
+    ) -> Buffers<'a, 'buffer, S> {
         // Make sure the caller can't forget to unset previously set values
         self.main_input_channel_pointers = None;
         self.main_output_channel_pointers = None;
         self.aux_input_channel_pointers.fill(None);
         self.aux_output_channel_pointers.fill(None);
+        self.aux_inputs_active.fill(true);
+        self.aux_outputs_active.fill(true);
+        self.main_input_constant_mask = 0;
+        self.aux_input_constant_masks.fill(0);
+        self.sample_offset = sample_offset;
+        self.num_samples = num_samples;
         set_buffer_sources(&mut BufferSource {
             main_input_channel_pointers: &mut self.main_input_channel_pointers,
             main_output_channel_pointers: &mut self.main_output_channel_pointers,
             aux_input_channel_pointers: &mut self.aux_input_channel_pointers,
             aux_output_channel_pointers: &mut self.aux_output_channel_pointers,
+            aux_inputs_active: &mut self.aux_inputs_active,
+            aux_outputs_active: &mut self.aux_outputs_active,
+            main_input_constant_mask: &mut self.main_input_constant_mask,
+            aux_input_constant_masks: &mut self.aux_input_constant_masks,
         });
 
         // The main buffer points directly to the main output pointers
@@ -248,72 +328,63 @@ impl BufferManager {
             if input_channel_pointers.num_channels < output_channel_pointers.num_channels {
                 self.main_buffer.set_slices(num_samples, |output_slices| {
                     for slice in &mut output_slices[input_channel_pointers.num_channels..] {
-                        slice.fill(0.0);
+                        slice.fill(S::ZERO);
                     }
                 });
             }
+
+            // Those zeroed channels are also constant
+            let input_channels = channel_mask(input_channel_pointers.num_channels);
+            self.main_buffer.set_input_constant_mask(
+                (self.main_input_constant_mask & input_channels) | !input_channels,
+            );
+        } else {
+            self.main_buffer.set_input_constant_mask(0);
+        }
+
+        // The full main input is only made available separately if the main input and output
+        // channel counts differ. This works the same way as the auxiliary inputs below.
+        if let Some(main_input_buffer) = &mut self.main_input_buffer {
+            fill_input_buffer(
+                main_input_buffer,
+                &mut self.main_input_storage,
+                self.main_input_channel_pointers,
+                true,
+                self.main_input_constant_mask,
+                sample_offset,
+                num_samples,
+            );
         }
 
         // Because NIH-plug's `Buffer` type is geared around in-place processing, auxiliary inputs
         // need to be copied to our own buffers first (backed by the 'storage' vectors on this
         // object). That way the plugin can modify those buffers like any other buffers.
-        for (input_channel_pointers, (input_storage, input_buffer)) in
-            self.aux_input_channel_pointers.iter().zip(
+        for (
+            (input_channel_pointers, (&input_active, &input_constant_mask)),
+            (input_storage, input_buffer),
+        ) in self
+            .aux_input_channel_pointers
+            .iter()
+            .zip(
+                self.aux_inputs_active
+                    .iter()
+                    .zip(self.aux_input_constant_masks.iter()),
+            )
+            .zip(
                 self.aux_input_storage
                     .iter_mut()
                     .zip(self.aux_input_buffers.iter_mut()),
             )
         {
-            // Since these buffers are backed by our own storage, we can fill them with zeroes if
-            // the pointers are missing for whatever reason that might be
-            nih_debug_assert!(input_channel_pointers.is_some());
-            match input_channel_pointers {
-                Some(input_channel_pointers) => {
-                    nih_debug_assert_eq!(input_channel_pointers.num_channels, input_storage.len());
-                    for (channel_idx, channel) in input_storage
-                        .iter_mut()
-                        .enumerate()
-                        .take(input_channel_pointers.num_channels)
-                    {
-                        let input_channel_pointer =
-                            input_channel_pointers.ptrs.as_ptr().add(channel_idx);
-
-                        nih_debug_assert!(num_samples <= channel.capacity());
-                        channel.resize(num_samples, 0.0);
-                        channel.copy_from_slice(std::slice::from_raw_parts_mut(
-                            (*input_channel_pointer).add(sample_offset),
-                            num_samples,
-                        ))
-                    }
-
-                    // In case we were provided too few channels we'll fill the rest with zeroes to
-                    // avoid unexpected situations
-                    for channel in input_storage
-                        .iter_mut()
-                        .skip(input_channel_pointers.num_channels)
-                    {
-                        channel.fill(0.0);
-                    }
-                }
-                None => {
-                    for channel in input_storage.iter_mut() {
-                        channel.fill(0.0);
-                    }
-                }
-            }
-
-            input_buffer.set_slices(num_samples, |input_slices| {
-                // Since we initialized both `input_buffer` and `input_storage` this invariant
-                // should never fail unless we made an error ourselves
-                debug_assert_eq!(input_slices.len(), input_storage.len());
-
-                for (channel_slice, channel_storage) in
-                    input_slices.iter_mut().zip(input_storage.iter_mut())
-                {
-                    // SAFETY: `channel_storage` is no longer used accessed directly after this
-                    *channel_slice = &mut *(channel_storage.as_mut_slice() as *mut [f32]);
-                }
-            });
+            fill_input_buffer(
+                input_buffer,
+                input_storage,
+                *input_channel_pointers,
+                input_active,
+                input_constant_mask,
+                sample_offset,
+                num_samples,
+            );
         }
 
         // The auxiliary output buffers can point directly to the host's buffers. This logic is the
@@ -345,7 +416,7 @@ impl BufferManager {
 
                             // The host may not zero out the buffers, and assume the plugin always
                             // write something there
-                            output_slice.fill(0.0);
+                            output_slice.fill(S::ZERO);
                         }
 
                         // If the caller/host should have provided buffer pointers but didn't then
@@ -360,23 +431,295 @@ impl BufferManager {
                     }
                 }
             });
+
+            // The outputs have just been filled with zeroes
+            output_buffer.set_input_constant_mask(match output_channel_pointers {
+                Some(output_channel_pointers) => channel_mask(output_channel_pointers.num_channels),
+                None => 0,
+            });
         }
 
         // SAFETY: The 'static lifetimes on the objects are needed so we can store the buffers.
         //         Their actual lifetimes are `'a`, so we need to shrink them here. The contents are
         //         valid for as long as the returned object is borrowed.
-        std::mem::transmute::<Buffers<'a, 'static>, Buffers<'a, 'buffer>>(Buffers {
+        std::mem::transmute::<Buffers<'a, 'static, S>, Buffers<'a, 'buffer, S>>(Buffers {
             main_buffer: &mut self.main_buffer,
+            main_input: self.main_input_buffer.as_mut(),
             aux_inputs: &mut self.aux_input_buffers,
             aux_outputs: &mut self.aux_output_buffers,
+            aux_inputs_active: &self.aux_inputs_active,
+            aux_outputs_active: &self.aux_outputs_active,
         })
     }
+
+    /// Combine the output constant flags the plugin has set on the buffers returned from the last
+    /// [`create_buffers()`][Self::create_buffers()] call with those of the earlier blocks in the
+    /// same process call. This needs to be called after processing every block. A channel is only
+    /// considered to be constant for the entire process call if the plugin marked it as constant in
+    /// every block and if every block used the same value.
+    ///
+    /// # Safety
+    ///
+    /// The channel pointers passed to the last `create_buffers()` call must still be valid.
+    pub unsafe fn accumulate_output_constant_masks(&mut self) {
+        self.main_output_constant_mask = combine_constant_masks(
+            self.main_output_constant_mask,
+            self.main_buffer.output_constant_mask(),
+            self.main_output_channel_pointers,
+            self.sample_offset,
+            self.num_samples,
+        );
+
+        for ((combined_mask, buffer), channel_pointers) in self
+            .aux_output_constant_masks
+            .iter_mut()
+            .zip(self.aux_output_buffers.iter())
+            .zip(self.aux_output_channel_pointers.iter())
+        {
+            *combined_mask = combine_constant_masks(
+                *combined_mask,
+                buffer.output_constant_mask(),
+                *channel_pointers,
+                self.sample_offset,
+                self.num_samples,
+            );
+        }
+    }
+
+    /// The main output's constant flags for the current process call, as computed by
+    /// [`accumulate_output_constant_masks()`][Self::accumulate_output_constant_masks()]. If
+    /// `silent_only` is set, then only the channels whose constant value is zero are included.
+    ///
+    /// # Safety
+    ///
+    /// The channel pointers passed to the last `create_buffers()` call must still be valid.
+    pub unsafe fn main_output_constant_mask(&self, silent_only: bool) -> u64 {
+        if silent_only && self.sample_offset + self.num_samples == 0 {
+            0
+        } else if silent_only {
+            silent_channels(
+                self.main_output_constant_mask,
+                self.main_output_channel_pointers,
+            )
+        } else {
+            self.main_output_constant_mask
+        }
+    }
+
+    /// The same as [`main_output_constant_mask()`][Self::main_output_constant_mask()], but for
+    /// the auxiliary output with index `aux_output_idx`. Returns 0 if the index is out of bounds.
+    ///
+    /// # Safety
+    ///
+    /// The channel pointers passed to the last `create_buffers()` call must still be valid.
+    pub unsafe fn aux_output_constant_mask(&self, aux_output_idx: usize, silent_only: bool) -> u64 {
+        let Some(&mask) = self.aux_output_constant_masks.get(aux_output_idx) else {
+            return 0;
+        };
+
+        if silent_only && self.sample_offset + self.num_samples == 0 {
+            0
+        } else if silent_only {
+            silent_channels(mask, self.aux_output_channel_pointers[aux_output_idx])
+        } else {
+            mask
+        }
+    }
+}
+
+/// Copy the host's data for an input port to our own `storage` and point `buffer` to that storage.
+/// Since these buffers are backed by our own storage, the plugin can safely overwrite them, and we
+/// can fill them with zeroes if the pointers are missing for whatever reason that might be. Hosts
+/// may not provide any data for deactivated ports, so those are always filled with zeroes.
+unsafe fn fill_input_buffer<S: Sample>(
+    buffer: &mut Buffer<'static, S>,
+    storage: &mut [Vec<S>],
+    channel_pointers: Option<ChannelPointers<S>>,
+    active: bool,
+    constant_mask: u64,
+    sample_offset: usize,
+    num_samples: usize,
+) {
+    nih_debug_assert!(channel_pointers.is_some() || !active);
+    let constant_mask = match channel_pointers {
+        Some(channel_pointers) if active => {
+            nih_debug_assert_eq!(channel_pointers.num_channels, storage.len());
+            for (channel_idx, channel) in storage
+                .iter_mut()
+                .enumerate()
+                .take(channel_pointers.num_channels)
+            {
+                let channel_pointer = channel_pointers.ptrs.as_ptr().add(channel_idx);
+
+                nih_debug_assert!(num_samples <= channel.capacity());
+                channel.resize(num_samples, S::ZERO);
+                channel.copy_from_slice(std::slice::from_raw_parts_mut(
+                    (*channel_pointer).add(sample_offset),
+                    num_samples,
+                ))
+            }
+
+            // In case we were provided too few channels we'll fill the rest with zeroes to avoid
+            // unexpected situations
+            for channel in storage.iter_mut().skip(channel_pointers.num_channels) {
+                channel.resize(num_samples, S::ZERO);
+                channel.fill(S::ZERO);
+            }
+
+            // Those zeroed channels are also constant
+            let input_channels = channel_mask(channel_pointers.num_channels);
+            (constant_mask & input_channels) | !input_channels
+        }
+        _ => {
+            for channel in storage.iter_mut() {
+                channel.resize(num_samples, S::ZERO);
+                channel.fill(S::ZERO);
+            }
+
+            u64::MAX
+        }
+    };
+
+    buffer.set_slices(num_samples, |slices| {
+        // Since we initialized both `buffer` and `storage` this invariant should never fail unless
+        // we made an error ourselves
+        debug_assert_eq!(slices.len(), storage.len());
+
+        for (channel_slice, channel_storage) in slices.iter_mut().zip(storage.iter_mut()) {
+            // SAFETY: `channel_storage` is no longer used accessed directly after this
+            *channel_slice = &mut *(channel_storage.as_mut_slice() as *mut [S]);
+        }
+    });
+    buffer.set_input_constant_mask(constant_mask);
+}
+
+/// Combine an output port's constant flags from the previous blocks with those from the block
+/// starting at `sample_offset`. A channel stays constant if it's constant in both and if the new
+/// block has the same value as the start of the buffer. Empty blocks don't change anything.
+unsafe fn combine_constant_masks<S: Sample>(
+    combined_mask: u64,
+    block_mask: u64,
+    channel_pointers: Option<ChannelPointers<S>>,
+    sample_offset: usize,
+    num_samples: usize,
+) -> u64 {
+    let Some(channel_pointers) = channel_pointers else {
+        return 0;
+    };
+
+    let mut mask = block_mask & channel_mask(channel_pointers.num_channels);
+    if sample_offset == 0 {
+        return mask;
+    } else if num_samples == 0 {
+        return combined_mask;
+    }
+
+    mask &= combined_mask;
+    for channel_idx in 0..channel_pointers.num_channels.min(64) {
+        if mask & (1 << channel_idx) != 0 {
+            let channel_pointer = *channel_pointers.ptrs.as_ptr().add(channel_idx);
+            if *channel_pointer != *channel_pointer.add(sample_offset) {
+                mask &= !(1 << channel_idx);
+            }
+        }
+    }
+
+    mask
+}
+
+/// Only keep the channels from a constant mask whose first sample is zero. The buffer must contain
+/// at least one sample.
+unsafe fn silent_channels<S: Sample>(
+    mask: u64,
+    channel_pointers: Option<ChannelPointers<S>>,
+) -> u64 {
+    let Some(channel_pointers) = channel_pointers else {
+        return 0;
+    };
+
+    let mut silent_mask = 0;
+    for channel_idx in 0..channel_pointers.num_channels.min(64) {
+        if mask & (1 << channel_idx) != 0
+            && *(*channel_pointers.ptrs.as_ptr().add(channel_idx)) == S::ZERO
+        {
+            silent_mask |= 1 << channel_idx;
+        }
+    }
+
+    silent_mask
+}
+
+impl AuxPortActivation {
+    /// Track the activation state for enough auxiliary ports to support all of the plugin's audio
+    /// IO layouts.
+    pub fn for_audio_io_layouts(audio_io_layouts: &[AudioIOLayout]) -> Self {
+        let max_aux_inputs = audio_io_layouts
+            .iter()
+            .map(|layout| layout.aux_input_ports.len())
+            .max()
+            .unwrap_or(0);
+        let max_aux_outputs = audio_io_layouts
+            .iter()
+            .map(|layout| layout.aux_output_ports.len())
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            inputs: (0..max_aux_inputs).map(|_| AtomicBool::new(true)).collect(),
+            outputs: (0..max_aux_outputs)
+                .map(|_| AtomicBool::new(true))
+                .collect(),
+        }
+    }
+
+    /// Activate or deactivate an auxiliary input or output port. Returns `false` if the port index
+    /// is out of bounds.
+    pub fn set_active(&self, is_input: bool, aux_port_idx: usize, active: bool) -> bool {
+        let ports = if is_input {
+            &self.inputs
+        } else {
+            &self.outputs
+        };
+        match ports.get(aux_port_idx) {
+            Some(port) => {
+                port.store(active, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mark all ports as active again. Used when the host changes the audio IO layout.
+    pub fn reset(&self) {
+        for port in self.inputs.iter().chain(self.outputs.iter()) {
+            port.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Copy the activation state to a [`BufferSource`]'s `aux_inputs_active` and
+    /// `aux_outputs_active` fields.
+    pub fn copy_to<S>(&self, buffer_source: &mut BufferSource<S>) {
+        for (active, port) in buffer_source
+            .aux_inputs_active
+            .iter_mut()
+            .zip(self.inputs.iter())
+        {
+            *active = port.load(Ordering::Relaxed);
+        }
+        for (active, port) in buffer_source
+            .aux_outputs_active
+            .iter_mut()
+            .zip(self.outputs.iter())
+        {
+            *active = port.load(Ordering::Relaxed);
+        }
+    }
 }
 
 #[cfg(any(miri, test))]
 mod miri {
     use super::*;
-    use crate::prelude::{new_nonzero_u32, PortNames};
+    use crate::prelude::{new_nonzero_u32, PortChannelLayouts, PortNames};
 
     const BUFFER_SIZE: usize = 512;
     const NUM_MAIN_INPUT_CHANNELS: usize = 1;
@@ -391,6 +734,7 @@ mod miri {
         aux_input_ports: &[new_nonzero_u32(NUM_AUX_CHANNELS as u32); NUM_AUX_PORTS],
         aux_output_ports: &[new_nonzero_u32(NUM_AUX_CHANNELS as u32); NUM_AUX_PORTS],
         names: PortNames::const_default(),
+        channel_layouts: PortChannelLayouts::const_default(),
     };
 
     #[test]
@@ -501,4 +845,195 @@ mod miri {
             }
         }
     }
+
+    #[test]
+    fn buffer_io_f64() {
+        // This value cannot be represented exactly as an `f32`
+        const VALUE: f64 = 0.1 + 1e-12;
+
+        let mut main_input_storage = vec![vec![VALUE; BUFFER_SIZE]; NUM_MAIN_INPUT_CHANNELS];
+        let mut main_output_storage = vec![vec![0.0f64; BUFFER_SIZE]; NUM_MAIN_OUTPUT_CHANNELS];
+        let mut main_input_channel_pointers: Vec<*mut f64> = main_input_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+        let mut main_output_channel_pointers: Vec<*mut f64> = main_output_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+
+        let audio_io_layout = AudioIOLayout {
+            aux_input_ports: &[],
+            aux_output_ports: &[],
+            ..AUDIO_IO_LAYOUT
+        };
+        let mut buffer_manager: BufferManager<f64> =
+            BufferManager::for_audio_io_layout(BUFFER_SIZE, audio_io_layout);
+        let buffers = unsafe {
+            buffer_manager.create_buffers(0, BUFFER_SIZE, |buffer_sources| {
+                *buffer_sources.main_output_channel_pointers = Some(ChannelPointers {
+                    ptrs: NonNull::new(main_output_channel_pointers.as_mut_ptr()).unwrap(),
+                    num_channels: main_output_channel_pointers.len(),
+                });
+                *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
+                    ptrs: NonNull::new(main_input_channel_pointers.as_mut_ptr()).unwrap(),
+                    num_channels: main_input_channel_pointers.len(),
+                });
+            })
+        };
+
+        // The output channel without a matching input channel is filled with zeroes
+        assert_eq!(buffers.main_buffer.input_silent_mask(), 0b10);
+        for (_, block) in buffers.main_buffer.iter_blocks(64) {
+            for channel in block {
+                for sample in channel {
+                    *sample += 1e-12;
+                }
+            }
+        }
+
+        assert!(main_output_storage[0]
+            .iter()
+            .all(|sample| *sample == VALUE + 1e-12));
+        assert!(main_output_storage[1].iter().all(|sample| *sample == 1e-12));
+        assert!(main_input_storage[0].iter().all(|sample| *sample == VALUE));
+    }
+
+    #[test]
+    fn asymmetric_main_input() {
+        // A 4-in/2-out downmixer only gets the first two input channels in the main buffer
+        let mut main_input_storage: Vec<Vec<f32>> = (0..4)
+            .map(|channel_idx| vec![channel_idx as f32 + 1.0; BUFFER_SIZE])
+            .collect();
+        let mut main_output_storage = vec![vec![0.0f32; BUFFER_SIZE]; 2];
+        let mut main_input_channel_pointers: Vec<*mut f32> = main_input_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+        let mut main_output_channel_pointers: Vec<*mut f32> = main_output_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+
+        let audio_io_layout = AudioIOLayout {
+            main_input_channels: Some(new_nonzero_u32(4)),
+            main_output_channels: Some(new_nonzero_u32(2)),
+            ..AudioIOLayout::const_default()
+        };
+        let mut buffer_manager = BufferManager::for_audio_io_layout(BUFFER_SIZE, audio_io_layout);
+        let buffers = unsafe {
+            buffer_manager.create_buffers(0, BUFFER_SIZE, |buffer_sources| {
+                *buffer_sources.main_output_channel_pointers = Some(ChannelPointers {
+                    ptrs: NonNull::new(main_output_channel_pointers.as_mut_ptr()).unwrap(),
+                    num_channels: main_output_channel_pointers.len(),
+                });
+                *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
+                    ptrs: NonNull::new(main_input_channel_pointers.as_mut_ptr()).unwrap(),
+                    num_channels: main_input_channel_pointers.len(),
+                });
+            })
+        };
+
+        // Mix the third and fourth input channels into the outputs
+        let main_input = buffers.main_input.unwrap();
+        assert_eq!(main_input.channels(), 4);
+        assert_eq!(buffers.main_buffer.channels(), 2);
+        let main_input = main_input.as_slice();
+        for (channel_idx, output_channel) in buffers.main_buffer.as_slice().iter_mut().enumerate() {
+            for (output_sample, input_sample) in output_channel
+                .iter_mut()
+                .zip(main_input[channel_idx + 2].iter())
+            {
+                *output_sample += *input_sample;
+            }
+        }
+
+        assert!(main_output_storage[0].iter().all(|sample| *sample == 4.0));
+        assert!(main_output_storage[1].iter().all(|sample| *sample == 6.0));
+        assert!(main_input_storage[3].iter().all(|sample| *sample == 4.0));
+    }
+
+    #[test]
+    fn aux_port_activation() {
+        let mut main_io_storage = vec![vec![0.0f32; BUFFER_SIZE]; NUM_MAIN_OUTPUT_CHANNELS];
+        let mut aux_input_storage =
+            vec![vec![vec![1.0f32; BUFFER_SIZE]; NUM_AUX_CHANNELS]; NUM_AUX_PORTS];
+        let mut aux_output_storage =
+            vec![vec![vec![0.0f32; BUFFER_SIZE]; NUM_AUX_CHANNELS]; NUM_AUX_PORTS];
+
+        let mut main_io_channel_pointers: Vec<*mut f32> = main_io_storage
+            .iter_mut()
+            .map(|channel_slice| channel_slice.as_mut_ptr())
+            .collect();
+        let mut aux_input_channel_pointers: Vec<Vec<*mut f32>> = aux_input_storage
+            .iter_mut()
+            .map(|storage| storage.iter_mut().map(|c| c.as_mut_ptr()).collect())
+            .collect();
+        let mut aux_output_channel_pointers: Vec<Vec<*mut f32>> = aux_output_storage
+            .iter_mut()
+            .map(|storage| storage.iter_mut().map(|c| c.as_mut_ptr()).collect())
+            .collect();
+        let mut set_pointers = |buffer_sources: &mut BufferSource| {
+            *buffer_sources.main_output_channel_pointers = Some(ChannelPointers {
+                ptrs: NonNull::new(main_io_channel_pointers.as_mut_ptr()).unwrap(),
+                num_channels: main_io_channel_pointers.len(),
+            });
+            *buffer_sources.main_input_channel_pointers = Some(ChannelPointers {
+                ptrs: NonNull::new(main_io_channel_pointers.as_mut_ptr()).unwrap(),
+                num_channels: NUM_MAIN_INPUT_CHANNELS,
+            });
+            for (source, pointers) in buffer_sources
+                .aux_input_channel_pointers
+                .iter_mut()
+                .zip(aux_input_channel_pointers.iter_mut())
+            {
+                *source = Some(ChannelPointers {
+                    ptrs: NonNull::new(pointers.as_mut_ptr()).unwrap(),
+                    num_channels: pointers.len(),
+                });
+            }
+            for (source, pointers) in buffer_sources
+                .aux_output_channel_pointers
+                .iter_mut()
+                .zip(aux_output_channel_pointers.iter_mut())
+            {
+                *source = Some(ChannelPointers {
+                    ptrs: NonNull::new(pointers.as_mut_ptr()).unwrap(),
+                    num_channels: pointers.len(),
+                });
+            }
+        };
+
+        let activation = AuxPortActivation::for_audio_io_layouts(&[AUDIO_IO_LAYOUT]);
+        assert!(activation.set_active(true, 1, false));
+        assert!(activation.set_active(false, 0, false));
+        assert!(!activation.set_active(true, NUM_AUX_PORTS, false));
+
+        let mut buffer_manager = BufferManager::for_audio_io_layout(BUFFER_SIZE, AUDIO_IO_LAYOUT);
+        let buffers = unsafe {
+            buffer_manager.create_buffers(0, BUFFER_SIZE, |buffer_sources| {
+                set_pointers(buffer_sources);
+                activation.copy_to(buffer_sources);
+            })
+        };
+        assert_eq!(buffers.aux_inputs_active, &[true, false]);
+        assert_eq!(buffers.aux_outputs_active, &[false, true]);
+        // Deactivated inputs are always silent
+        assert_eq!(buffers.aux_inputs[0].as_slice_immutable()[0][0], 1.0);
+        assert_eq!(buffers.aux_inputs[1].as_slice_immutable()[0][0], 0.0);
+
+        // The activation state needs to be copied again for every block
+        let buffers = unsafe { buffer_manager.create_buffers(0, BUFFER_SIZE, &mut set_pointers) };
+        assert_eq!(buffers.aux_inputs_active, &[true, true]);
+
+        activation.reset();
+        let buffers = unsafe {
+            buffer_manager.create_buffers(0, BUFFER_SIZE, |buffer_sources| {
+                set_pointers(buffer_sources);
+                activation.copy_to(buffer_sources);
+            })
+        };
+        assert_eq!(buffers.aux_inputs_active, &[true, true]);
+        assert_eq!(buffers.aux_outputs_active, &[true, true]);
+    }
 }
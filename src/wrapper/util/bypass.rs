@@ -0,0 +1,232 @@
+//! Latency compensated bypassing for plugins that set
+//! [`Plugin::MANAGED_BYPASS`][crate::prelude::Plugin::MANAGED_BYPASS].
+
+use crate::buffer::{Buffer, Sample};
+use crate::prelude::{ParamFlags, ParamPtr, Plugin, ProcessStatus};
+use crate::util::LatencyCompensator;
+
+/// How long it takes to fade between the plugin's output and the dry signal when the bypass
+/// parameter changes.
+const CROSSFADE_MS: f32 = 10.0;
+
+/// Crossfades between the plugin's processed output and a latency compensated copy of the
+/// unprocessed main buffer, depending on the value of the plugin's bypass parameter. The dry signal
+/// is always kept up to date, so engaging the bypass does not cause any gaps or clicks.
+pub struct ManagedBypass<S: Sample = f32> {
+    /// The plugin's bypass parameter. The bypass is not managed by the wrapper when this is `None`,
+    /// in which case the plugin is processed as is.
+    bypass_param: Option<ParamPtr>,
+    /// The contents of the main buffer from before the plugin processed it. These vectors have
+    /// enough capacity for the maximum buffer size.
+    dry_buffers: Vec<Vec<S>>,
+    /// Delays `dry_buffers` by the plugin's latency so they line up with the processed signal.
+    latency_compensator: LatencyCompensator<S>,
+    /// The current crossfade position. The output is fully processed at 0.0 and fully dry at 1.0.
+    bypass_mix: f32,
+    /// How much `bypass_mix` changes per sample while crossfading.
+    bypass_mix_step: f32,
+}
+
+impl<S: Sample> Default for ManagedBypass<S> {
+    fn default() -> Self {
+        Self {
+            bypass_param: None,
+            dry_buffers: Vec::new(),
+            latency_compensator: LatencyCompensator::new(0, 0),
+            bypass_mix: 0.0,
+            bypass_mix_step: 0.0,
+        }
+    }
+}
+
+impl<S: Sample> ManagedBypass<S> {
+    /// Set up bypassing for a plugin with `num_channels` main output channels. If the plugin does
+    /// not set [`Plugin::MANAGED_BYPASS`] then this returns an inactive object that simply calls
+    /// the plugin's process function. `params` should contain all of the plugin's parameters.
+    /// `latency` is the plugin's latency at the time of activation. Since latency changes only take
+    /// effect when the plugin is reactivated, this is also the largest latency that can be
+    /// compensated for until then.
+    pub fn for_plugin<'a, P: Plugin>(
+        params: impl IntoIterator<Item = &'a ParamPtr>,
+        num_channels: usize,
+        max_buffer_size: usize,
+        sample_rate: f32,
+        latency: u32,
+    ) -> Self {
+        if !P::MANAGED_BYPASS {
+            return Self::default();
+        }
+
+        let bypass_param = params
+            .into_iter()
+            .find(|param| unsafe { param.flags() }.contains(ParamFlags::BYPASS))
+            .copied();
+        nih_debug_assert!(
+            bypass_param.is_some(),
+            "'Plugin::MANAGED_BYPASS' is set but the plugin does not have a bypass parameter"
+        );
+
+        let mut latency_compensator = LatencyCompensator::new(num_channels, latency as usize);
+        latency_compensator.set_latency(latency);
+        let bypass_mix = match bypass_param {
+            Some(param) if unsafe { param.modulated_normalized_value() } >= 0.5 => 1.0,
+            _ => 0.0,
+        };
+
+        Self {
+            bypass_param,
+            dry_buffers: (0..num_channels)
+                .map(|_| Vec::with_capacity(max_buffer_size))
+                .collect(),
+            latency_compensator,
+            bypass_mix,
+            bypass_mix_step: 1.0 / (sample_rate * (CROSSFADE_MS / 1000.0)),
+        }
+    }
+
+    /// Clear the latency compensation delay line. Called when the plugin gets reset.
+    pub fn reset(&mut self) {
+        self.latency_compensator.reset();
+    }
+
+    /// Call `process` to let the plugin process `buffer`, and then crossfade the result with the
+    /// latency compensated dry signal as needed. `latency` is the plugin's current latency.
+    pub fn process(
+        &mut self,
+        buffer: &mut Buffer<S>,
+        latency: u32,
+        process: impl FnOnce(&mut Buffer<S>) -> ProcessStatus,
+    ) -> ProcessStatus {
+        let bypass_param = match self.bypass_param {
+            Some(bypass_param) => bypass_param,
+            None => return process(buffer),
+        };
+
+        for (dry_buffer, channel) in self
+            .dry_buffers
+            .iter_mut()
+            .zip(buffer.as_slice_immutable().iter())
+        {
+            // These vectors have enough capacity for the maximum buffer size, so this doesn't
+            // allocate
+            dry_buffer.clear();
+            dry_buffer.extend_from_slice(channel);
+        }
+
+        let result = process(buffer);
+
+        // If the latency increased while the plugin is active, then the dry signal can only be
+        // fully compensated after the host has restarted the plugin in response to that change
+        let max_latency = self.latency_compensator.max_latency() as u32;
+        self.latency_compensator
+            .set_latency(latency.min(max_latency));
+        self.latency_compensator
+            .process_slices(&mut self.dry_buffers);
+
+        let target_mix = if unsafe { bypass_param.modulated_normalized_value() } >= 0.5 {
+            1.0
+        } else {
+            0.0
+        };
+        if self.bypass_mix == 0.0 && target_mix == 0.0 {
+            return result;
+        }
+
+        let num_samples = buffer.samples();
+        let num_mixed_channels = buffer.channels().min(self.dry_buffers.len());
+        let output = buffer.as_slice();
+        for sample_idx in 0..num_samples {
+            self.bypass_mix = if target_mix > self.bypass_mix {
+                (self.bypass_mix + self.bypass_mix_step).min(target_mix)
+            } else {
+                (self.bypass_mix - self.bypass_mix_step).max(target_mix)
+            };
+
+            let dry_gain = S::from(self.bypass_mix);
+            let wet_gain = S::from(1.0 - self.bypass_mix);
+            for (channel, dry_buffer) in output.iter_mut().zip(self.dry_buffers.iter()) {
+                channel[sample_idx] =
+                    (channel[sample_idx] * wet_gain) + (dry_buffer[sample_idx] * dry_gain);
+            }
+        }
+
+        // The plugin may have marked its own output as constant, but the dry signal that's now
+        // mixed in probably isn't
+        for channel_idx in 0..num_mixed_channels {
+            buffer.set_output_constant(channel_idx, false);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{BoolParam, Param};
+
+    #[test]
+    fn crossfade_to_delayed_dry_signal() {
+        let bypass = BoolParam::new("Bypass", true).make_bypass();
+        let mut latency_compensator = LatencyCompensator::new(1, 4);
+        latency_compensator.set_latency(2);
+        let mut managed_bypass = ManagedBypass {
+            bypass_param: Some(bypass.as_ptr()),
+            dry_buffers: vec![Vec::with_capacity(8)],
+            latency_compensator,
+            bypass_mix: 0.0,
+            bypass_mix_step: 0.25,
+        };
+
+        let mut real_buffers = [vec![1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(8, |output_slices| {
+                *output_slices = real_buffers.iter_mut().map(|b| b.as_mut_slice()).collect();
+            })
+        };
+
+        // The plugin outputs silence, so the output only contains the faded in dry signal
+        managed_bypass.process(&mut buffer, 2, |buffer| {
+            for channel in buffer.as_slice() {
+                channel.fill(0.0);
+            }
+
+            ProcessStatus::Normal
+        });
+
+        assert_eq!(real_buffers[0], [0.0, 0.0, 0.75, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn clear_constant_flags_while_bypassed() {
+        let bypass = BoolParam::new("Bypass", true).make_bypass();
+        let mut managed_bypass = ManagedBypass {
+            bypass_param: Some(bypass.as_ptr()),
+            dry_buffers: vec![Vec::with_capacity(4), Vec::with_capacity(4)],
+            latency_compensator: LatencyCompensator::new(2, 0),
+            bypass_mix: 1.0,
+            bypass_mix_step: 0.25,
+        };
+
+        let mut real_buffers = [vec![1.0f32, 2.0, 3.0, 4.0], vec![0.0f32; 4]];
+        let mut buffer = Buffer::default();
+        unsafe {
+            buffer.set_slices(4, |output_slices| {
+                *output_slices = real_buffers.iter_mut().map(|b| b.as_mut_slice()).collect();
+            })
+        };
+
+        // The plugin doesn't do anything while it's bypassed, so it marks its output as silent.
+        // That's no longer true once the dry signal has been mixed back in.
+        managed_bypass.process(&mut buffer, 0, |buffer| {
+            buffer.set_output_silent(0);
+            buffer.set_output_silent(1);
+
+            ProcessStatus::Normal
+        });
+
+        assert_eq!(buffer.output_constant_mask(), 0);
+        assert_eq!(real_buffers[0], [1.0, 2.0, 3.0, 4.0]);
+    }
+}
@@ -0,0 +1,122 @@
+//! In-memory state snapshots and A/B comparisons for the [`GuiContext`].
+
+use crossbeam::atomic::AtomicCell;
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+
+use crate::prelude::{AbSlot, GuiContext, ParamPtr, PluginState};
+use crate::wrapper::state;
+
+/// Named in-memory snapshots of a plugin's state. Each wrapper has one of these to implement the
+/// snapshot functions on its [`GuiContext`]. These snapshots are not persisted along with the
+/// plugin's state.
+#[derive(Debug, Default)]
+pub struct Snapshots {
+    /// The captured snapshots, indexed by name.
+    snapshots: Mutex<BTreeMap<String, PluginState>>,
+    /// The A/B slot that's currently active. The other slot's state is stored as a snapshot.
+    active_ab_slot: AtomicCell<AbSlot>,
+}
+
+impl Snapshots {
+    /// Store `state` under `name`, overwriting any existing snapshot with the same name.
+    pub fn capture(&self, name: &str, state: PluginState) {
+        self.snapshots.lock().insert(name.to_owned(), state);
+    }
+
+    /// Get a copy of the snapshot called `name`, if it exists.
+    pub fn get(&self, name: &str) -> Option<PluginState> {
+        self.snapshots.lock().get(name).cloned()
+    }
+
+    /// Remove the snapshot called `name`. Returns whether the snapshot existed.
+    pub fn remove(&self, name: &str) -> bool {
+        self.snapshots.lock().remove(name).is_some()
+    }
+
+    /// The names of all snapshots, in alphabetical order.
+    pub fn names(&self) -> Vec<String> {
+        self.snapshots.lock().keys().cloned().collect()
+    }
+
+    /// The currently active A/B slot.
+    pub fn active_ab_slot(&self) -> AbSlot {
+        self.active_ab_slot.load()
+    }
+
+    /// Switch to another A/B slot. The current state is stored in the slot that's currently
+    /// active, and the selected slot's state is recalled using [`recall_snapshot()`]. If the
+    /// selected slot is empty, then it starts out as a copy of the current state.
+    ///
+    /// # Safety
+    ///
+    /// `params_getter` must only return pointers to parameters that are still alive.
+    pub unsafe fn select_ab_slot(
+        &self,
+        context: &dyn GuiContext,
+        params_getter: impl Fn(&str) -> Option<ParamPtr>,
+        slot: AbSlot,
+    ) {
+        let current_slot = self.active_ab_slot.load();
+        if slot == current_slot {
+            return;
+        }
+
+        let current_state = context.get_state();
+        match self.get(slot.snapshot_name()) {
+            Some(snapshot) => recall_snapshot(context, params_getter, snapshot),
+            None => self.capture(slot.snapshot_name(), current_state.clone()),
+        }
+        self.capture(current_slot.snapshot_name(), current_state);
+
+        self.active_ab_slot.store(slot);
+    }
+}
+
+/// Recall a snapshot from the GUI thread. Every parameter whose value differs from the value in the
+/// snapshot gets changed using a full begin-set-end gesture so the host can record the change. If
+/// the snapshot's persistent fields or blobs differ from the plugin's current ones, then the entire
+/// state is also restored through [`GuiContext::set_state()`].
+///
+/// # Safety
+///
+/// `params_getter` must only return pointers to parameters that are still alive.
+pub unsafe fn recall_snapshot(
+    context: &dyn GuiContext,
+    params_getter: impl Fn(&str) -> Option<ParamPtr>,
+    snapshot: PluginState,
+) {
+    for (param_id, param_value) in &snapshot.params {
+        let param_ptr = match params_getter(param_id) {
+            Some(ptr) => ptr,
+            None => {
+                nih_debug_assert_failure!("Unknown parameter in snapshot: {}", param_id);
+                continue;
+            }
+        };
+        let normalized_value = match state::param_value_to_normalized(param_ptr, param_value) {
+            Some(normalized_value) => normalized_value,
+            None => {
+                nih_debug_assert_failure!(
+                    "Invalid snapshot value {:?} for parameter \"{}\"",
+                    param_value,
+                    param_id
+                );
+                continue;
+            }
+        };
+
+        if normalized_value != param_ptr.unmodulated_normalized_value() {
+            context.raw_begin_set_parameter(param_ptr);
+            context.raw_set_parameter_normalized(param_ptr, normalized_value);
+            context.raw_end_set_parameter(param_ptr);
+        }
+    }
+
+    // Persistent fields can't be changed through parameter gestures. Restoring the state here will
+    // also set the parameters again, but at this point they already have the correct values.
+    let current_state = context.get_state();
+    if current_state.fields != snapshot.fields || current_state.blobs != snapshot.blobs {
+        context.set_state(snapshot);
+    }
+}
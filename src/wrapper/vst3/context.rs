@@ -1,16 +1,21 @@
 use atomic_refcell::AtomicRefMut;
 use std::cell::Cell;
 use std::collections::VecDeque;
+use std::path::Path;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use vst3_sys::vst::IComponentHandler;
 
 use crate::prelude::{
-    GuiContext, InitContext, ParamPtr, PluginApi, PluginNoteEvent, PluginState, ProcessContext,
+    AbSlot, GuiContext, InitContext, ParamIndication, ParamPtr, PluginApi, PluginNoteEvent,
+    PluginState, ProcessContext, StateContext, StateLoadBehavior, StateLoadHandle, TrackInfo,
     Transport, Vst3Plugin,
 };
 
 use super::inner::{Task, WrapperInner};
+use crate::preset::{self, PresetMetadata};
+use crate::wrapper::state;
+use crate::wrapper::util::snapshots;
 
 /// An [`InitContext`] implementation for the wrapper.
 ///
@@ -24,6 +29,8 @@ use super::inner::{Task, WrapperInner};
 pub(crate) struct WrapperInitContext<'a, P: Vst3Plugin> {
     pub(super) inner: &'a WrapperInner<P>,
     pub(super) pending_requests: PendingInitContextRequests,
+    /// The context of the state that was loaded before this initialization, if any.
+    pub(super) state_context: Option<StateContext>,
 }
 
 /// Any requests that should be sent out when the [`WrapperInitContext`] is dropped. See that
@@ -71,6 +78,15 @@ impl<P: Vst3Plugin> InitContext<P> for WrapperInitContext<'_, P> {
         (self.inner.task_executor.lock())(task);
     }
 
+    fn execute_background(&self, task: P::BackgroundTask) {
+        let task_posted = self.inner.schedule_background(Task::PluginTask(task));
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+    }
+
+    fn begin_state_load(&self, behavior: StateLoadBehavior) -> StateLoadHandle {
+        self.inner.state_load_tracker.begin(behavior)
+    }
+
     fn set_latency_samples(&self, samples: u32) {
         // See this struct's docstring
         self.pending_requests.latency_changed.set(Some(samples));
@@ -79,6 +95,14 @@ impl<P: Vst3Plugin> InitContext<P> for WrapperInitContext<'_, P> {
     fn set_current_voice_capacity(&self, _capacity: u32) {
         // This is only supported by CLAP
     }
+
+    fn state_context(&self) -> Option<StateContext> {
+        self.state_context
+    }
+
+    fn track_info(&self) -> Option<TrackInfo> {
+        self.inner.track_info.read().clone()
+    }
 }
 
 impl<P: Vst3Plugin> ProcessContext<P> for WrapperProcessContext<'_, P> {
@@ -222,10 +246,106 @@ impl<P: Vst3Plugin> GuiContext for WrapperGuiContext<P> {
     }
 
     fn get_state(&self) -> PluginState {
-        self.inner.get_state_object()
+        self.inner.get_state_object(StateContext::Duplicate)
     }
 
     fn set_state(&self, state: PluginState) {
-        self.inner.set_state_object_from_gui(state)
+        self.inner
+            .set_state_object_from_gui(state, StateContext::Duplicate)
+    }
+
+    fn capture_snapshot(&self, name: &str) {
+        self.inner
+            .snapshots
+            .capture(name, self.inner.get_state_object(StateContext::Duplicate))
+    }
+
+    fn recall_snapshot(&self, name: &str) -> bool {
+        match self.inner.snapshots.get(name) {
+            Some(snapshot) => {
+                unsafe {
+                    snapshots::recall_snapshot(
+                        self,
+                        state::make_params_getter(
+                            &self.inner.param_by_hash,
+                            &self.inner.param_id_to_hash,
+                        ),
+                        snapshot,
+                    )
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove_snapshot(&self, name: &str) -> bool {
+        self.inner.snapshots.remove(name)
+    }
+
+    fn snapshot_names(&self) -> Vec<String> {
+        self.inner.snapshots.names()
+    }
+
+    fn active_ab_slot(&self) -> AbSlot {
+        self.inner.snapshots.active_ab_slot()
+    }
+
+    fn select_ab_slot(&self, slot: AbSlot) {
+        unsafe {
+            self.inner.snapshots.select_ab_slot(
+                self,
+                state::make_params_getter(&self.inner.param_by_hash, &self.inner.param_id_to_hash),
+                slot,
+            )
+        }
+    }
+
+    fn save_preset(&self, path: &Path, metadata: PresetMetadata) -> anyhow::Result<()> {
+        preset::save_preset::<P>(
+            path,
+            metadata,
+            self.inner.get_state_object(StateContext::Preset),
+        )
+    }
+
+    fn load_preset(&self, path: &Path) -> anyhow::Result<PresetMetadata> {
+        let (metadata, state) = preset::load_preset::<P>(path)?;
+        self.inner
+            .set_state_object_from_gui(state, StateContext::Preset);
+
+        Ok(metadata)
+    }
+
+    fn state_load_progress(&self) -> Option<f32> {
+        self.inner.state_load_tracker.progress()
+    }
+
+    fn provides_ticks(&self) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn register_fd(&self, _fd: i32) -> bool {
+        // This is only supported by CLAP
+        false
+    }
+
+    fn unregister_fd(&self, _fd: i32) {
+        // This is only supported by CLAP
+    }
+
+    fn track_info(&self) -> Option<TrackInfo> {
+        self.inner.track_info.read().clone()
+    }
+
+    fn raw_param_indication(&self, _param: ParamPtr) -> ParamIndication {
+        // This is only supported by CLAP
+        ParamIndication::default()
+    }
+
+    fn raw_show_param_context_menu(&self, _param: ParamPtr, _x: i32, _y: i32) -> bool {
+        // This is only supported by CLAP
+        false
     }
 }
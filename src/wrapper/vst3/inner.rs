@@ -2,7 +2,7 @@ use atomic_refcell::AtomicRefCell;
 use crossbeam::atomic::AtomicCell;
 use crossbeam::channel::{self, SendTimeoutError};
 use parking_lot::{Mutex, RwLock};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
@@ -16,13 +16,16 @@ use super::util::{ObjectPtr, VstPtr, VST3_MIDI_PARAMS_END, VST3_MIDI_PARAMS_STAR
 use super::view::WrapperView;
 use crate::event_loop::{EventLoop, MainThreadExecutor, OsEventLoop};
 use crate::prelude::{
-    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, MidiConfig, ParamFlags, ParamPtr, Params,
-    Plugin, PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor, Transport, Vst3Plugin,
+    AsyncExecutor, AudioIOLayout, BufferConfig, Editor, MidiConfig, ParamPtr, Params, Plugin,
+    PluginNoteEvent, ProcessMode, ProcessStatus, TaskExecutor, TrackInfo, Transport, Vst3Plugin,
 };
 use crate::util::permit_alloc;
-use crate::wrapper::state::{self, PluginState};
-use crate::wrapper::util::buffer_management::BufferManager;
-use crate::wrapper::util::{hash_param_id, process_wrapper};
+use crate::wrapper::state::loading::StateLoadTracker;
+use crate::wrapper::state::{self, PluginState, StateContext};
+use crate::wrapper::util::buffer_management::{AuxPortActivation, BufferManager};
+use crate::wrapper::util::bypass::ManagedBypass;
+use crate::wrapper::util::snapshots::Snapshots;
+use crate::wrapper::util::{check_param_map, hash_param_id, process_wrapper};
 
 /// The actual wrapper bits. We need this as an `Arc<T>` so we can safely use our event loop API.
 /// Since we can't combine that with VST3's interior reference counting this just has to be moved to
@@ -60,6 +63,9 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// TODO: Is there a better type for Send+Sync late initialization?
     pub event_loop: AtomicRefCell<Option<OsEventLoop<Task<P>, Self>>>,
 
+    /// Whether the plugin is currently active. In other words, the last state
+    /// `IComponent::setActive()` has been called with.
+    pub is_active: AtomicBool,
     /// Whether the plugin is currently processing audio. In other words, the last state
     /// `IAudioProcessor::setActive()` has been called with.
     pub is_processing: AtomicBool,
@@ -75,12 +81,30 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     pub current_process_mode: AtomicCell<ProcessMode>,
     /// The last process status returned by the plugin. This is used for tail handling.
     pub last_process_status: AtomicCell<ProcessStatus>,
-    /// The current latency in samples, as set by the plugin through the [`InitContext`] and the
-    /// [`ProcessContext`].
+    /// The latency in samples reported to the host through `IAudioProcessor::getLatencySamples()`.
+    /// This only changes while the plugin is inactive, so latency changes requested while the
+    /// plugin is active are applied when the host deactivates or reactivates the plugin in response
+    /// to the `kLatencyChanged` restart.
     pub current_latency: AtomicU32,
+    /// The latency in samples most recently set by the plugin through the [`InitContext`] and the
+    /// [`ProcessContext`].
+    pub requested_latency: AtomicU32,
     /// A data structure that helps manage and create buffers for all of the plugin's inputs and
     /// outputs based on channel pointers provided by the host.
     pub buffer_manager: AtomicRefCell<BufferManager>,
+    /// The same as `buffer_manager`, but for double precision processing. This is only allocated
+    /// when the plugin sets [`Plugin::SUPPORTS_F64`][crate::prelude::Plugin::SUPPORTS_F64].
+    pub buffer_manager_f64: AtomicRefCell<BufferManager<f64>>,
+    /// Crossfades to the latency compensated dry signal when the plugin sets
+    /// [`Plugin::MANAGED_BYPASS`][crate::prelude::Plugin::MANAGED_BYPASS] and its bypass parameter
+    /// is enabled. Set up in `IComponent::setActive()`.
+    pub managed_bypass: AtomicRefCell<ManagedBypass>,
+    /// The same as `managed_bypass`, but for double precision processing.
+    pub managed_bypass_f64: AtomicRefCell<ManagedBypass<f64>>,
+    /// Which auxiliary busses the host has activated through `IComponent::activateBus()`. This is
+    /// copied to the [`AuxiliaryBuffers`][crate::prelude::AuxiliaryBuffers] for every process
+    /// call.
+    pub aux_port_activation: AuxPortActivation,
     /// The incoming events for the plugin, if `P::ACCEPTS_MIDI` is set. If
     /// `P::SAMPLE_ACCURATE_AUTOMATION`, this is also read in lockstep with the parameter change
     /// block splitting.
@@ -115,9 +139,20 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// In other words, the GUI thread acts as a sender and then as a receiver, while the audio
     /// thread acts as a receiver and then as a sender. That way deallocation can happen on the GUI
     /// thread. All of this happens without any blocking on the audio thread.
-    pub updated_state_sender: channel::Sender<PluginState>,
+    pub updated_state_sender: channel::Sender<(PluginState, StateContext)>,
     /// The receiver belonging to [`new_state_sender`][Self::new_state_sender].
-    pub updated_state_receiver: channel::Receiver<PluginState>,
+    pub updated_state_receiver: channel::Receiver<(PluginState, StateContext)>,
+    /// The context of the last state that was loaded, if the plugin has not been (re)initialized
+    /// since then. This is exposed to the plugin through
+    /// [`InitContext::state_context()`][crate::prelude::InitContext::state_context()].
+    pub pending_state_context: AtomicCell<Option<StateContext>>,
+    /// Keeps track of state loads the plugin continues in the background. While one of these is in
+    /// progress the audio thread may output silence instead of calling the plugin's process
+    /// function.
+    pub state_load_tracker: Arc<StateLoadTracker>,
+    /// Information about the plugin's track, sent by the host through
+    /// [`IInfoListener`][vst3_sys::vst::IInfoListener].
+    pub track_info: RwLock<Option<TrackInfo>>,
 
     /// The keys from `param_map` in a stable order.
     pub param_hashes: Vec<u32>,
@@ -137,6 +172,14 @@ pub(crate) struct WrapperInner<P: Vst3Plugin> {
     /// having to add a setter function to the parameter (or even worse, have it be completely
     /// untyped).
     pub param_ptr_to_hash: HashMap<ParamPtr, u32>,
+    /// Pointers to all parameters with per-sample modulation buffers. VST3 does not have any
+    /// parameter modulation, but these buffers still need to match the size of the blocks passed to
+    /// the plugin.
+    pub modulation_buffer_params: Vec<ParamPtr>,
+
+    /// In-memory state snapshots and the A/B comparison state, managed through the
+    /// [`GuiContext`][crate::prelude::GuiContext].
+    pub snapshots: Snapshots,
 }
 
 /// Tasks that can be sent from the plugin to be executed on the main thread in a non-blocking
@@ -157,6 +200,8 @@ pub enum Task<P: Plugin> {
     /// Request the editor to be resized according to its current size. Right now there is no way to
     /// handle "denied resize" requests yet.
     RequestResize,
+    /// Inform the editor that the track information has changed.
+    TrackInfoChanged,
 }
 
 /// VST3 makes audio processing pretty complicated. In order to support both block splitting for
@@ -210,31 +255,9 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             })
             .collect();
         if cfg!(debug_assertions) {
-            let param_map = params.param_map();
-            let param_ids: HashSet<_> = param_id_hashes_ptrs_groups
-                .iter()
-                .map(|(id, _, _, _)| id.clone())
-                .collect();
-            nih_debug_assert_eq!(
-                param_map.len(),
-                param_ids.len(),
-                "The plugin has duplicate parameter IDs, weird things may happen. Consider using \
-                 6 character parameter IDs to avoid collisions."
-            );
-
-            let mut bypass_param_exists = false;
-            for (id, hash, ptr, _) in &param_id_hashes_ptrs_groups {
-                let flags = unsafe { ptr.flags() };
-                let is_bypass = flags.contains(ParamFlags::BYPASS);
-
-                if is_bypass && bypass_param_exists {
-                    nih_debug_assert_failure!(
-                        "Duplicate bypass parameters found, the host will only use the first one"
-                    );
-                }
-
-                bypass_param_exists |= is_bypass;
+            check_param_map(&params.param_map());
 
+            for (id, hash, _, _) in &param_id_hashes_ptrs_groups {
                 if P::MIDI_INPUT >= MidiConfig::MidiCCs
                     && (VST3_MIDI_PARAMS_START..VST3_MIDI_PARAMS_END).contains(hash)
                 {
@@ -269,6 +292,11 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             .iter()
             .map(|(id, hash, _, _)| (id.clone(), *hash))
             .collect();
+        let modulation_buffer_params = param_id_hashes_ptrs_groups
+            .iter()
+            .filter(|(_, _, ptr, _)| unsafe { ptr.modulation_buffer() }.is_some())
+            .map(|(_, _, ptr, _)| *ptr)
+            .collect();
         let param_ptr_to_hash = param_id_hashes_ptrs_groups
             .into_iter()
             .map(|(_, hash, ptr, _)| (ptr, hash))
@@ -287,6 +315,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
 
             event_loop: AtomicRefCell::new(None),
 
+            is_active: AtomicBool::new(false),
             is_processing: AtomicBool::new(false),
             // Some hosts, like the current version of Bitwig and Ardour at the time of writing,
             // will try using the plugin's default not yet initialized bus arrangement. Because of
@@ -299,18 +328,29 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             current_process_mode: AtomicCell::new(ProcessMode::Realtime),
             last_process_status: AtomicCell::new(ProcessStatus::Normal),
             current_latency: AtomicU32::new(0),
+            requested_latency: AtomicU32::new(0),
             // This is initialized just before calling `Plugin::initialize()` so that during the
             // process call buffers can be initialized without any allocations
             buffer_manager: AtomicRefCell::new(BufferManager::for_audio_io_layout(
                 0,
                 AudioIOLayout::default(),
             )),
+            buffer_manager_f64: AtomicRefCell::new(BufferManager::for_audio_io_layout(
+                0,
+                AudioIOLayout::default(),
+            )),
+            managed_bypass: AtomicRefCell::new(ManagedBypass::default()),
+            managed_bypass_f64: AtomicRefCell::new(ManagedBypass::default()),
+            aux_port_activation: AuxPortActivation::for_audio_io_layouts(P::AUDIO_IO_LAYOUTS),
             input_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
             output_events: AtomicRefCell::new(VecDeque::with_capacity(1024)),
             note_expression_controller: AtomicRefCell::new(NoteExpressionController::default()),
             process_events: AtomicRefCell::new(Vec::with_capacity(4096)),
             updated_state_sender,
             updated_state_receiver,
+            pending_state_context: AtomicCell::new(None),
+            state_load_tracker: Arc::default(),
+            track_info: RwLock::new(None),
 
             param_hashes,
             param_by_hash,
@@ -318,6 +358,9 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             param_units,
             param_id_to_hash,
             param_ptr_to_hash,
+            modulation_buffer_params,
+
+            snapshots: Snapshots::default(),
         });
 
         // FIXME: Right now this is safe, but if we are going to have a singleton main thread queue
@@ -369,6 +412,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
         WrapperInitContext {
             inner: self,
             pending_requests: Default::default(),
+            state_context: self.pending_state_context.take(),
         }
     }
 
@@ -463,20 +507,24 @@ impl<P: Vst3Plugin> WrapperInner<P> {
 
     /// Get the plugin's state object, may be called by the plugin's GUI as part of its own preset
     /// management. The wrapper doesn't use these functions and serializes and deserializes directly
-    /// the JSON in the relevant plugin API methods instead.
-    pub fn get_state_object(&self) -> PluginState {
+    /// the JSON in the relevant plugin API methods instead. Presets are saved using
+    /// [`StateContext::Preset`], while in-memory copies like snapshots use
+    /// [`StateContext::Duplicate`] so they also include the fields that are skipped for presets.
+    pub fn get_state_object(&self, context: StateContext) -> PluginState {
         unsafe {
             state::serialize_object::<P>(
                 self.params.clone(),
                 state::make_params_iter(&self.param_by_hash, &self.param_id_to_hash),
+                context,
             )
         }
     }
 
     /// Update the plugin's internal state, called by the plugin itself from the GUI thread. To
     /// prevent corrupting data and changing parameters during processing the actual state is only
-    /// updated at the end of the audio processing cycle.
-    pub fn set_state_object_from_gui(&self, mut state: PluginState) {
+    /// updated at the end of the audio processing cycle. `context` should match the context the
+    /// state was saved with.
+    pub fn set_state_object_from_gui(&self, mut state: PluginState, context: StateContext) {
         // Use a loop and timeouts to handle the super rare edge case when this function gets called
         // between a process call and the host disabling the plugin
         loop {
@@ -488,7 +536,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 // deallocated without blocking the audio thread.
                 match self
                     .updated_state_sender
-                    .send_timeout(state, Duration::from_secs(1))
+                    .send_timeout((state, context), Duration::from_secs(1))
                 {
                     Ok(_) => {
                         // As mentioned above, the state object will be passed back to this thread
@@ -497,7 +545,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                         drop(state);
                         break;
                     }
-                    Err(SendTimeoutError::Timeout(value)) => {
+                    Err(SendTimeoutError::Timeout((value, _))) => {
                         state = value;
                         continue;
                     }
@@ -509,7 +557,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             } else {
                 // Otherwise we'll set the state right here and now, since this function should be
                 // called from a GUI thread
-                self.set_state_inner(&mut state);
+                self.set_state_inner(&mut state, context);
                 break;
             }
         }
@@ -528,14 +576,30 @@ impl<P: Vst3Plugin> WrapperInner<P> {
 
     pub fn set_latency_samples(&self, samples: u32) {
         // Only trigger a restart if it's actually needed
-        let old_latency = self.current_latency.swap(samples, Ordering::SeqCst);
+        let old_latency = self.requested_latency.swap(samples, Ordering::SeqCst);
         if old_latency != samples {
+            // While the plugin is active the reported latency stays the same until the host has
+            // deactivated the plugin in response to the restart
+            if !self.is_active.load(Ordering::SeqCst) {
+                self.apply_requested_latency();
+            }
+
             let task_posted =
                 self.schedule_gui(Task::TriggerRestart(RestartFlags::kLatencyChanged as i32));
             nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
         }
     }
 
+    /// Report the latency most recently requested by the plugin to the host. Called whenever the
+    /// plugin gets activated or deactivated, since VST3 hosts only query the latency while the
+    /// plugin is inactive.
+    pub fn apply_requested_latency(&self) {
+        self.current_latency.store(
+            self.requested_latency.load(Ordering::SeqCst),
+            Ordering::SeqCst,
+        );
+    }
+
     /// Immediately set the plugin state. Returns `false` if the deserialization failed. The plugin
     /// state is set from a couple places, so this function aims to deduplicate that. Includes
     /// `permit_alloc()`s around the deserialization and initialization for the use case where
@@ -546,7 +610,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
     /// # Notes
     ///
     /// `self.plugin` must _not_ be locked while calling this function or it will deadlock.
-    pub fn set_state_inner(&self, state: &mut PluginState) -> bool {
+    pub fn set_state_inner(&self, state: &mut PluginState, context: StateContext) -> bool {
         let audio_io_layout = self.current_audio_io_layout.load();
         let buffer_config = self.current_buffer_config.load();
 
@@ -562,6 +626,7 @@ impl<P: Vst3Plugin> WrapperInner<P> {
                 self.params.clone(),
                 state::make_params_getter(&self.param_by_hash, &self.param_id_to_hash),
                 buffer_config.as_ref(),
+                context,
             )
         });
         if !success {
@@ -569,6 +634,9 @@ impl<P: Vst3Plugin> WrapperInner<P> {
             return false;
         }
 
+        // The plugin can query this context when it gets (re)initialized
+        self.pending_state_context.store(Some(context));
+
         // If the plugin was already initialized then it needs to be reinitialized
         if let Some(buffer_config) = buffer_config {
             // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
@@ -648,6 +716,13 @@ impl<P: Vst3Plugin> MainThreadExecutor<Task<P>> for WrapperInner<P> {
                 },
                 None => nih_debug_assert_failure!("Can't resize a closed editor"),
             },
+            Task::TrackInfoChanged => {
+                if self.plug_view.read().is_some() {
+                    if let Some(editor) = self.editor.borrow().as_ref() {
+                        editor.lock().track_info_changed();
+                    }
+                }
+            }
         }
     }
 }
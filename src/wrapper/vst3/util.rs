@@ -1,10 +1,12 @@
 use std::cmp;
 use std::ops::Deref;
 use vst3_sys::interfaces::IUnknown;
-use vst3_sys::vst::TChar;
+use vst3_sys::vst::{SpeakerArrangement, TChar};
 use vst3_sys::ComInterface;
 use widestring::U16CString;
 
+use crate::prelude::{AmbisonicLayout, AmbisonicNormalization, AmbisonicOrdering, ChannelLayout};
+
 /// When `Plugin::MIDI_INPUT` is set to `MidiConfig::MidiCCs` or higher then we'll register 130*16
 /// additional parameters to handle MIDI CCs, channel pressure, and pitch bend, in that order.
 /// vst3-sys doesn't expose these constants.
@@ -19,6 +21,83 @@ pub const VST3_MIDI_PARAMS_START: u32 = VST3_MIDI_PARAMS_END - VST3_MIDI_NUM_PAR
 /// The (exclusive) end of the MIDI CC parameter range. Anything above this is reserved by the host.
 pub const VST3_MIDI_PARAMS_END: u32 = 1 << 31;
 
+/// The `kSpeakerACN0` through `kSpeakerACN15` speaker bits used for up to third order ambisonics,
+/// in ACN order. vst3-sys doesn't expose these constants.
+const VST3_SPEAKER_ACN: [SpeakerArrangement; 16] = [
+    1 << 20,
+    1 << 32,
+    1 << 33,
+    1 << 34,
+    1 << 38,
+    1 << 39,
+    1 << 40,
+    1 << 41,
+    1 << 42,
+    1 << 43,
+    1 << 44,
+    1 << 45,
+    1 << 46,
+    1 << 47,
+    1 << 48,
+    1 << 49,
+];
+
+/// Convert a channel layout to a VST3 speaker arrangement. The variants in the
+/// [`Speaker`][crate::prelude::Speaker] enum have the same order as VST3's speaker bits, so most
+/// layouts map directly to a speaker mask. VST3 only supports up to third order AmbiX (ACN ordering
+/// and SN3D normalization) ambisonics. Returns `None` for layouts that can't be represented in
+/// VST3.
+pub fn speaker_arrangement_for_layout(layout: ChannelLayout) -> Option<SpeakerArrangement> {
+    match layout {
+        // VST3 has a dedicated speaker for mono ports
+        ChannelLayout::Mono => Some(vst3_sys::vst::kMono),
+        ChannelLayout::Ambisonic(layout)
+            if layout.ordering == AmbisonicOrdering::Acn
+                && layout.normalization == AmbisonicNormalization::Sn3d
+                && layout.order <= 3 =>
+        {
+            let num_channels = ChannelLayout::Ambisonic(layout).num_channels() as usize;
+            Some(
+                VST3_SPEAKER_ACN[..num_channels]
+                    .iter()
+                    .fold(0, |arrangement, speaker| arrangement | speaker),
+            )
+        }
+        ChannelLayout::Ambisonic(_) => None,
+        layout => layout.speaker_mask(),
+    }
+}
+
+/// The inverse of [`speaker_arrangement_for_layout()`]. Returns `None` if the arrangement does not
+/// correspond to one of the predefined channel layouts or to a supported ambisonic layout.
+pub fn layout_for_speaker_arrangement(arrangement: SpeakerArrangement) -> Option<ChannelLayout> {
+    if arrangement == vst3_sys::vst::kMono {
+        return Some(ChannelLayout::Mono);
+    }
+
+    // Ambisonic arrangements use the first `(order + 1)^2` ACN speakers
+    let num_acn_channels = VST3_SPEAKER_ACN
+        .iter()
+        .take_while(|speaker| arrangement & **speaker != 0)
+        .count();
+    if num_acn_channels > 0 {
+        let order = (num_acn_channels as f64).sqrt() as u32 - 1;
+        let layout = ChannelLayout::Ambisonic(AmbisonicLayout {
+            order,
+            ordering: AmbisonicOrdering::Acn,
+            normalization: AmbisonicNormalization::Sn3d,
+        });
+
+        return if speaker_arrangement_for_layout(layout) == Some(arrangement) {
+            Some(layout)
+        } else {
+            None
+        };
+    }
+
+    ChannelLayout::from_speaker_mask(arrangement)
+}
+
 /// Early exit out of a VST3 function when one of the passed pointers is null
 macro_rules! check_null_ptr {
     ($ptr:expr $(, $ptrs:expr)* $(, )?) => {
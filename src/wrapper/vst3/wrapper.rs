@@ -1,3 +1,4 @@
+use atomic_refcell::AtomicRefCell;
 use std::borrow::Borrow;
 use std::ffi::c_void;
 use std::mem::{self, MaybeUninit};
@@ -10,34 +11,45 @@ use vst3_sys::base::{kInvalidArgument, kNoInterface, kResultFalse, kResultOk, tr
 use vst3_sys::base::{IBStream, IPluginBase};
 use vst3_sys::utils::SharedVstPtr;
 use vst3_sys::vst::{
-    kNoParamId, kNoParentUnitId, kNoProgramListId, kRootUnitId, Event, EventTypes, IAudioProcessor,
-    IComponent, IEditController, IEventList, IMidiMapping, INoteExpressionController,
-    IParamValueQueue, IParameterChanges, IProcessContextRequirements, IUnitInfo,
-    LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription, NoteOffEvent,
-    NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo, TChar, UnitInfo,
+    kNoParamId, kNoParentUnitId, kNoProgramListId, kRootUnitId, Event, EventTypes, IAttributeList,
+    IAudioProcessor, IComponent, IEditController, IEventList, IInfoListener, IMidiMapping,
+    INoteExpressionController, IParamValueQueue, IParameterChanges, IProcessContextRequirements,
+    IUnitInfo, LegacyMidiCCOutEvent, NoteExpressionTypeInfo, NoteExpressionValueDescription,
+    NoteOffEvent, NoteOnEvent, ParameterFlags, PolyPressureEvent, ProgramListInfo,
+    SpeakerArrangement, TChar, UnitInfo,
 };
 use vst3_sys::VST3;
 use widestring::U16CStr;
 
-use super::inner::{ProcessEvent, WrapperInner};
+use super::context::WrapperProcessContext;
+use super::inner::{ProcessEvent, Task, WrapperInner};
 use super::note_expressions::{self, NoteExpressionController};
 use super::util::{
-    u16strlcpy, VstPtr, VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS, VST3_MIDI_PARAMS_START,
+    layout_for_speaker_arrangement, speaker_arrangement_for_layout, u16strlcpy, VstPtr,
+    VST3_MIDI_CCS, VST3_MIDI_NUM_PARAMS, VST3_MIDI_PARAMS_START,
 };
 use super::util::{VST3_MIDI_CHANNELS, VST3_MIDI_PARAMS_END};
 use super::view::WrapperView;
 use crate::prelude::{
-    AuxiliaryBuffers, BufferConfig, MidiConfig, NoteEvent, ParamFlags, ProcessMode, ProcessStatus,
-    SysExMessage, Transport, Vst3Plugin,
+    AudioIOLayout, AuxiliaryBuffers, Buffer, BufferConfig, ChannelLayout, HostColor, MidiConfig,
+    NoteEvent, ParamFlags, ProcessMode, ProcessStatus, Sample, SysExMessage, TrackInfo, Transport,
+    Vst3Plugin,
 };
 use crate::util::permit_alloc;
-use crate::wrapper::state;
+use crate::wrapper::state::{self, loading, StateContext};
 use crate::wrapper::util::buffer_management::{BufferManager, ChannelPointers};
-use crate::wrapper::util::{clamp_input_event_timing, clamp_output_event_timing, process_wrapper};
+use crate::wrapper::util::bypass::ManagedBypass;
+use crate::wrapper::util::{
+    clamp_input_event_timing, clamp_output_event_timing, process_wrapper, supported_audio_io_layout,
+};
 
 // Alias needed for the VST3 attribute macro
 use vst3_sys as vst3_com;
 
+// Channel context keys missing from vst3-sys
+const VST3_CHANNEL_NAME_KEY: &[u8] = b"channel name\0";
+const VST3_CHANNEL_COLOR_KEY: &[u8] = b"channel color\0";
+
 #[VST3(implements(
     IComponent,
     IEditController,
@@ -45,7 +57,8 @@ use vst3_sys as vst3_com;
     IMidiMapping,
     INoteExpressionController,
     IProcessContextRequirements,
-    IUnitInfo
+    IUnitInfo,
+    IInfoListener
 ))]
 pub struct Wrapper<P: Vst3Plugin> {
     inner: Arc<WrapperInner<P>>,
@@ -55,6 +68,193 @@ impl<P: Vst3Plugin> Wrapper<P> {
     pub fn new() -> Box<Self> {
         Self::allocate(WrapperInner::new())
     }
+
+    /// Create the buffers for a single block of audio from the host's channel pointers, and then
+    /// let the plugin process them. Used with a `BufferManager<f32>` or a `BufferManager<f64>`
+    /// depending on the host's symbolic sample size. `process_plugin` calls the corresponding
+    /// process function on the plugin.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn process_block<S: Sample>(
+        &self,
+        buffer_manager: &AtomicRefCell<BufferManager<S>>,
+        managed_bypass: &AtomicRefCell<ManagedBypass<S>>,
+        data: &vst3_sys::vst::ProcessData,
+        block_start: usize,
+        block_len: usize,
+        transport: Transport,
+        process_plugin: impl FnOnce(
+            &mut P,
+            &mut Buffer<S>,
+            &mut AuxiliaryBuffers<S>,
+            &mut WrapperProcessContext<'_, P>,
+        ) -> ProcessStatus,
+    ) -> ProcessStatus {
+        let current_audio_io_layout = self.inner.current_audio_io_layout.load();
+        let has_main_input = current_audio_io_layout.main_input_channels.is_some();
+        let has_main_output = current_audio_io_layout.main_output_channels.is_some();
+        let aux_input_start_idx = if has_main_input { 1 } else { 0 };
+        let aux_output_start_idx = if has_main_output { 1 } else { 0 };
+
+        // The buffer manager preallocated buffer slices for all the IO and storage for
+        // any axuiliary inputs.
+        let mut buffer_manager = buffer_manager.borrow_mut();
+        let buffers = buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
+            if data.num_outputs > 0
+                && !data.outputs.is_null()
+                && !(*data.outputs).buffers.is_null()
+                && has_main_output
+            {
+                let audio_output = &*data.outputs;
+                let ptrs = NonNull::new(audio_output.buffers as *mut *mut S).unwrap();
+                let num_channels = audio_output.num_channels as usize;
+
+                *buffer_source.main_output_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+            }
+
+            if data.num_inputs > 0
+                && !data.inputs.is_null()
+                && !(*data.inputs).buffers.is_null()
+                && has_main_input
+            {
+                let audio_input = &*data.inputs;
+                let ptrs = NonNull::new(audio_input.buffers as *mut *mut S).unwrap();
+                let num_channels = audio_input.num_channels as usize;
+
+                *buffer_source.main_input_channel_pointers =
+                    Some(ChannelPointers { ptrs, num_channels });
+                // Silent channels are also constant
+                *buffer_source.main_input_constant_mask = audio_input.silence_flags;
+            }
+
+            if !data.inputs.is_null() {
+                for (aux_input_no, (aux_input_channel_pointers, aux_input_constant_mask)) in
+                    buffer_source
+                        .aux_input_channel_pointers
+                        .iter_mut()
+                        .zip(buffer_source.aux_input_constant_masks.iter_mut())
+                        .enumerate()
+                {
+                    let aux_input_idx = aux_input_no + aux_input_start_idx;
+                    if aux_input_idx > data.num_outputs as usize {
+                        break;
+                    }
+
+                    let audio_input = &*data.inputs.add(aux_input_idx);
+                    match NonNull::new(audio_input.buffers as *mut *mut S) {
+                        Some(ptrs) => {
+                            let num_channels = audio_input.num_channels as usize;
+
+                            *aux_input_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                            *aux_input_constant_mask = audio_input.silence_flags;
+                        }
+                        None => continue,
+                    }
+                }
+            }
+
+            if !data.outputs.is_null() {
+                for (aux_output_no, aux_output_channel_pointers) in buffer_source
+                    .aux_output_channel_pointers
+                    .iter_mut()
+                    .enumerate()
+                {
+                    let aux_output_idx = aux_output_no + aux_output_start_idx;
+                    if aux_output_idx > data.num_outputs as usize {
+                        break;
+                    }
+
+                    let audio_output = &*data.outputs.add(aux_output_idx);
+                    match NonNull::new(audio_output.buffers as *mut *mut S) {
+                        Some(ptrs) => {
+                            let num_channels = audio_output.num_channels as usize;
+
+                            *aux_output_channel_pointers =
+                                Some(ChannelPointers { ptrs, num_channels });
+                        }
+                        None => continue,
+                    }
+                }
+            }
+
+            self.inner.aux_port_activation.copy_to(buffer_source);
+        });
+
+        // We already checked whether the host has initiated a parameter flush, but in
+        // case it still did something unexpected that we did not catch we'll still try
+        // to prevent processing audio when the slices don't contain the values we
+        // expect.
+        let mut buffer_is_valid = true;
+        for output_buffer_slice in buffers.main_buffer.as_slice_immutable().iter().chain(
+            buffers
+                .aux_outputs
+                .iter()
+                .flat_map(|buffer| buffer.as_slice_immutable().iter()),
+        ) {
+            if output_buffer_slice.is_empty() {
+                buffer_is_valid = false;
+                break;
+            }
+        }
+        nih_debug_assert!(buffer_is_valid);
+
+        let result = if buffer_is_valid {
+            // When rendering offline we can't output silence, so we'll wait for
+            // asynchronous state loads to finish instead. Parking the thread may allocate.
+            if self.inner.current_process_mode.load() == ProcessMode::Offline {
+                permit_alloc(|| self.inner.state_load_tracker.wait());
+            }
+
+            let mut aux = AuxiliaryBuffers {
+                main_input: buffers.main_input,
+                inputs: buffers.aux_inputs,
+                outputs: buffers.aux_outputs,
+                inputs_active: buffers.aux_inputs_active,
+                outputs_active: buffers.aux_outputs_active,
+            };
+            if self.inner.state_load_tracker.should_silence() {
+                loading::silence_outputs(buffers.main_buffer, &mut aux);
+                ProcessStatus::Normal
+            } else {
+                // NOTE: `parking_lot`'s mutexes sometimes allocate because of their use
+                //       of thread locals
+                let mut plugin = permit_alloc(|| self.inner.plugin.lock());
+                let mut context = self.inner.make_process_context(transport);
+                let result = managed_bypass.borrow_mut().process(
+                    buffers.main_buffer,
+                    self.inner.requested_latency.load(Ordering::SeqCst),
+                    |buffer| process_plugin(&mut plugin, buffer, &mut aux, &mut context),
+                );
+                self.inner.last_process_status.store(result);
+                result
+            }
+        } else {
+            ProcessStatus::Normal
+        };
+        buffer_manager.accumulate_output_constant_masks();
+
+        result
+    }
+
+    /// Copy the silent output flags accumulated by a buffer manager during the last process call to
+    /// the host's output buffers. VST3 only supports flagging silent outputs, so constant outputs
+    /// with other values are not passed on to the host.
+    unsafe fn write_output_silence_flags<S: Sample>(
+        buffer_manager: &BufferManager<S>,
+        data: &vst3_sys::vst::ProcessData,
+        aux_output_start_idx: usize,
+    ) {
+        for output_idx in 0..data.num_outputs as usize {
+            let silence_flags = match output_idx.checked_sub(aux_output_start_idx) {
+                None => buffer_manager.main_output_constant_mask(true),
+                Some(aux_output_idx) => {
+                    buffer_manager.aux_output_constant_mask(aux_output_idx, true)
+                }
+            };
+            (*data.outputs.add(output_idx)).silence_flags = silence_flags;
+        }
+    }
 }
 
 impl<P: Vst3Plugin> Drop for Wrapper<P> {
@@ -308,12 +508,13 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
         type_: vst3_sys::vst::MediaType,
         dir: vst3_sys::vst::BusDirection,
         index: i32,
-        _state: vst3_sys::base::TBool,
+        state: vst3_sys::base::TBool,
     ) -> tresult {
         let current_audio_io_layout = self.inner.current_audio_io_layout.load();
 
-        // We don't support this, but the validator will get very angry with us if we let it know
-        // that
+        // The activation state of the auxiliary busses is passed to the plugin through
+        // `AuxiliaryBuffers`. The main busses and the event busses are always considered to be
+        // active.
         match (type_, dir, index) {
             (t, d, _)
                 if t == vst3_sys::vst::MediaTypes::kAudio as i32
@@ -327,6 +528,14 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                 let aux_busses = current_audio_io_layout.aux_input_ports.len() as i32;
 
                 if (0..main_busses + aux_busses).contains(&index) {
+                    if index >= main_busses {
+                        self.inner.aux_port_activation.set_active(
+                            true,
+                            (index - main_busses) as usize,
+                            state != 0,
+                        );
+                    }
+
                     kResultOk
                 } else {
                     kInvalidArgument
@@ -344,6 +553,14 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                 let aux_busses = current_audio_io_layout.aux_output_ports.len() as i32;
 
                 if (0..main_busses + aux_busses).contains(&index) {
+                    if index >= main_busses {
+                        self.inner.aux_port_activation.set_active(
+                            false,
+                            (index - main_busses) as usize,
+                            state != 0,
+                        );
+                    }
+
                     kResultOk
                 } else {
                     kInvalidArgument
@@ -376,6 +593,9 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                 // Before initializing the plugin, make sure all smoothers are set the the default values
                 for param in self.inner.param_by_hash.values() {
                     param.update_smoother(buffer_config.sample_rate, true);
+                    if let Some(modulation_buffer) = param.modulation_buffer() {
+                        modulation_buffer.resize(buffer_config.max_buffer_size as usize);
+                    }
                 }
 
                 // NOTE: This needs to be dropped after the `plugin` lock to avoid deadlocks
@@ -394,6 +614,42 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
                         buffer_config.max_buffer_size as usize,
                         audio_io_layout,
                     );
+                    if P::SUPPORTS_F64 {
+                        *self.inner.buffer_manager_f64.borrow_mut() =
+                            BufferManager::for_audio_io_layout(
+                                buffer_config.max_buffer_size as usize,
+                                audio_io_layout,
+                            );
+                    }
+
+                    // This includes any latency changes made in `Plugin::initialize()`
+                    self.inner.apply_requested_latency();
+
+                    // The dry signal for the managed bypass is delayed by the plugin's current
+                    // latency
+                    let latency = self.inner.current_latency.load(Ordering::SeqCst);
+                    let num_output_channels = audio_io_layout
+                        .main_output_channels
+                        .map(NonZeroU32::get)
+                        .unwrap_or_default() as usize;
+                    *self.inner.managed_bypass.borrow_mut() = ManagedBypass::for_plugin::<P>(
+                        self.inner.param_by_hash.values(),
+                        num_output_channels,
+                        buffer_config.max_buffer_size as usize,
+                        buffer_config.sample_rate,
+                        latency,
+                    );
+                    if P::SUPPORTS_F64 {
+                        *self.inner.managed_bypass_f64.borrow_mut() = ManagedBypass::for_plugin::<P>(
+                            self.inner.param_by_hash.values(),
+                            num_output_channels,
+                            buffer_config.max_buffer_size as usize,
+                            buffer_config.sample_rate,
+                            latency,
+                        );
+                    }
+
+                    self.inner.is_active.store(true, Ordering::SeqCst);
 
                     kResultOk
                 } else {
@@ -402,7 +658,9 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
             }
             (true, None) => kResultFalse,
             (false, _) => {
+                self.inner.is_active.store(false, Ordering::SeqCst);
                 self.inner.plugin.lock().deactivate();
+                self.inner.apply_requested_latency();
 
                 kResultOk
             }
@@ -449,9 +707,13 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
             return kResultFalse;
         }
 
-        match state::deserialize_json(&read_buffer) {
+        match state::deserialize_state(&read_buffer) {
             Some(mut state) => {
-                if self.inner.set_state_inner(&mut state) {
+                // VST3 doesn't tell the plugin why its state is being loaded
+                if self
+                    .inner
+                    .set_state_inner(&mut state, StateContext::Project)
+                {
                     nih_trace!("Loaded state ({} bytes)", read_buffer.len());
                     kResultOk
                 } else {
@@ -467,9 +729,10 @@ impl<P: Vst3Plugin> IComponent for Wrapper<P> {
 
         let state = state.upgrade().unwrap();
 
-        let serialized = state::serialize_json::<P>(
+        let serialized = state::serialize_state::<P>(
             self.inner.params.clone(),
             state::make_params_iter(&self.inner.param_by_hash, &self.inner.param_id_to_hash),
+            StateContext::Project,
         );
         match serialized {
             Ok(serialized) => {
@@ -716,66 +979,130 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             return kInvalidArgument;
         }
 
+        // Mono and stereo ports only need to have the correct number of channels, but surround and
+        // ambisonic ports with an explicit channel layout also need to use the same speakers
+        let port_matches = |arrangement: SpeakerArrangement,
+                            num_channels: u32,
+                            layout: Option<ChannelLayout>| {
+            match layout.and_then(speaker_arrangement_for_layout) {
+                Some(layout_arrangement) if num_channels > 2 => arrangement == layout_arrangement,
+                _ => arrangement.count_ones() == num_channels,
+            }
+        };
+
         // NIH-plug no longer supports flexible IO layouts. Instead we'll try to find an audio IO
         // layout that matches the host's requested layout.
-        let matching_layout = P::AUDIO_IO_LAYOUTS
-            .iter()
-            .find(|layout| {
-                // If the number of ports/busses doesn't match then we can immediately discard the
-                // layout. VST3 doesn't allow for optional switchable ports like CLAP does. Only the
-                // channel counts can change.
-                let num_layout_ins = if layout.main_input_channels.is_some() {
-                    1
-                } else {
-                    0
-                } + layout.aux_input_ports.len();
-                let num_layout_outs = if layout.main_output_channels.is_some() {
-                    1
-                } else {
-                    0
-                } + layout.aux_output_ports.len();
-                if num_ins as usize != num_layout_ins || num_outs as usize != num_layout_outs {
+        let input_arrangements = std::slice::from_raw_parts(inputs, num_ins as usize);
+        let output_arrangements = std::slice::from_raw_parts(outputs, num_outs as usize);
+        let layout_matches = |layout: &AudioIOLayout| {
+            // If the number of ports/busses doesn't match then we can immediately discard the
+            // layout. VST3 doesn't allow for optional switchable ports like CLAP does. Only the
+            // channel counts can change.
+            let num_layout_ins = if layout.main_input_channels.is_some() {
+                1
+            } else {
+                0
+            } + layout.aux_input_ports.len();
+            let num_layout_outs = if layout.main_output_channels.is_some() {
+                1
+            } else {
+                0
+            } + layout.aux_output_ports.len();
+            if num_ins as usize != num_layout_ins || num_outs as usize != num_layout_outs {
+                return false;
+            }
+
+            // The main ports always come first, followed by the auxiliary ports
+            let mut input_arrangements = input_arrangements.iter();
+            if let Some(main_input_channels) = layout.main_input_channels {
+                let arrangement = *input_arrangements.next().unwrap();
+                if !port_matches(
+                    arrangement,
+                    main_input_channels.get(),
+                    layout.main_input_layout(),
+                ) {
                     return false;
                 }
+            }
+            for (aux_input_idx, (arrangement, channel_count)) in
+                input_arrangements.zip(layout.aux_input_ports).enumerate()
+            {
+                if !port_matches(
+                    *arrangement,
+                    channel_count.get(),
+                    layout.aux_input_layout(aux_input_idx),
+                ) {
+                    return false;
+                }
+            }
 
-                // NOTE: We completely ignore the speaker arrangements and only look at the channel
-                //       counts here. This may cause issues at some point, but it works for now.
-                let has_main_input = layout.main_input_channels.is_some();
-                let aux_input_start_idx = if has_main_input { 0 } else { 1 };
-                if has_main_input
-                    && (*inputs).count_ones() != layout.main_input_channels.unwrap().get()
-                {
+            let mut output_arrangements = output_arrangements.iter();
+            if let Some(main_output_channels) = layout.main_output_channels {
+                let arrangement = *output_arrangements.next().unwrap();
+                if !port_matches(
+                    arrangement,
+                    main_output_channels.get(),
+                    layout.main_output_layout(),
+                ) {
                     return false;
                 }
-                for (aux_input_idx, channel_count) in layout.aux_input_ports.iter().enumerate() {
-                    if (*inputs.add(aux_input_idx + aux_input_start_idx)).count_ones()
-                        != channel_count.get()
-                    {
-                        return false;
-                    }
+            }
+            for (aux_output_idx, (arrangement, channel_count)) in
+                output_arrangements.zip(layout.aux_output_ports).enumerate()
+            {
+                if !port_matches(
+                    *arrangement,
+                    channel_count.get(),
+                    layout.aux_output_layout(aux_output_idx),
+                ) {
+                    return false;
                 }
+            }
 
-                let has_main_output = layout.main_output_channels.is_some();
-                let aux_output_start_idx = if has_main_output { 0 } else { 1 };
-                if (*outputs).count_ones()
-                    != layout
-                        .main_output_channels
-                        .map(NonZeroU32::get)
-                        .unwrap_or_default()
+            true
+        };
+        let matching_layout = P::AUDIO_IO_LAYOUTS
+            .iter()
+            .find(|layout| layout_matches(layout))
+            .copied()
+            .or_else(|| {
+                // If none of the predefined layouts match, then the plugin may still accept the
+                // requested main port configurations through `Plugin::accept_audio_io_layout()`.
+                // The port counts and the auxiliary ports are taken from the current layout.
+                let mut layout = self.inner.current_audio_io_layout.load();
+                if let (Some(_), Some(&arrangement)) =
+                    (layout.main_input_channels, input_arrangements.first())
                 {
-                    return false;
+                    layout.main_input_channels = NonZeroU32::new(arrangement.count_ones());
+                    layout.channel_layouts.main_input = layout_for_speaker_arrangement(arrangement);
                 }
-                for (aux_output_idx, channel_count) in layout.aux_output_ports.iter().enumerate() {
-                    if (*outputs.add(aux_output_idx + aux_output_start_idx)).count_ones()
-                        != channel_count.get()
-                    {
-                        return false;
-                    }
+                if let (Some(_), Some(&arrangement)) =
+                    (layout.main_output_channels, output_arrangements.first())
+                {
+                    layout.main_output_channels = NonZeroU32::new(arrangement.count_ones());
+                    layout.channel_layouts.main_output =
+                        layout_for_speaker_arrangement(arrangement);
                 }
 
-                true
-            })
-            .copied();
+                // Surround arrangements that can't be represented as a `ChannelLayout` are
+                // rejected since the host would otherwise get a different arrangement back
+                let is_representable =
+                    |num_channels: Option<NonZeroU32>, channel_layout: Option<ChannelLayout>| {
+                        channel_layout.is_some() || num_channels.map_or(0, NonZeroU32::get) <= 2
+                    };
+                if is_representable(
+                    layout.main_input_channels,
+                    layout.channel_layouts.main_input,
+                ) && is_representable(
+                    layout.main_output_channels,
+                    layout.channel_layouts.main_output,
+                ) && layout_matches(&layout)
+                {
+                    supported_audio_io_layout::<P>(&layout)
+                } else {
+                    None
+                }
+            });
 
         match matching_layout {
             Some(layout) => {
@@ -815,14 +1142,20 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
         };
 
         let current_audio_io_layout = self.inner.current_audio_io_layout.load();
-        let num_channels = if dir == vst3_sys::vst::BusDirections::kInput as i32 {
+        let (num_channels, layout) = if dir == vst3_sys::vst::BusDirections::kInput as i32 {
             let has_main_input = current_audio_io_layout.main_input_channels.is_some();
             let aux_input_start_idx = if has_main_input { 1 } else { 0 };
             let aux_input_idx = (index - aux_input_start_idx).max(0) as usize;
             if index == 0 && has_main_input {
-                current_audio_io_layout.main_input_channels.unwrap().get()
+                (
+                    current_audio_io_layout.main_input_channels.unwrap().get(),
+                    current_audio_io_layout.main_input_layout(),
+                )
             } else if aux_input_idx < current_audio_io_layout.aux_input_ports.len() {
-                current_audio_io_layout.aux_input_ports[aux_input_idx].get()
+                (
+                    current_audio_io_layout.aux_input_ports[aux_input_idx].get(),
+                    current_audio_io_layout.aux_input_layout(aux_input_idx),
+                )
             } else {
                 return kInvalidArgument;
             }
@@ -831,16 +1164,26 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             let aux_output_start_idx = if has_main_output { 1 } else { 0 };
             let aux_output_idx = (index - aux_output_start_idx).max(0) as usize;
             if index == 0 && has_main_output {
-                current_audio_io_layout.main_output_channels.unwrap().get()
+                (
+                    current_audio_io_layout.main_output_channels.unwrap().get(),
+                    current_audio_io_layout.main_output_layout(),
+                )
             } else if aux_output_idx < current_audio_io_layout.aux_output_ports.len() {
-                current_audio_io_layout.aux_output_ports[aux_output_idx].get()
+                (
+                    current_audio_io_layout.aux_output_ports[aux_output_idx].get(),
+                    current_audio_io_layout.aux_output_layout(aux_output_idx),
+                )
             } else {
                 return kInvalidArgument;
             }
         } else {
             return kInvalidArgument;
         };
-        let channel_map = channel_count_to_map(num_channels);
+        // Ports with an explicit channel layout use the matching speaker arrangement, and other
+        // ports get a default arrangement based on the channel count
+        let channel_map = layout
+            .and_then(speaker_arrangement_for_layout)
+            .unwrap_or_else(|| channel_count_to_map(num_channels));
 
         nih_debug_assert_eq!(num_channels, channel_map.count_ones());
         *arr = channel_map;
@@ -849,7 +1192,10 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
     }
 
     unsafe fn can_process_sample_size(&self, symbolic_sample_size: i32) -> tresult {
-        if symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32 {
+        if symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
+            || (P::SUPPORTS_F64
+                && symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample64 as i32)
+        {
             kResultOk
         } else {
             kResultFalse
@@ -865,9 +1211,11 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
 
         // There's no special handling for offline processing at the moment
         let setup = &*setup;
-        nih_debug_assert_eq!(
-            setup.symbolic_sample_size,
-            vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
+        nih_debug_assert!(
+            setup.symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
+                || (P::SUPPORTS_F64
+                    && setup.symbolic_sample_size
+                        == vst3_sys::vst::SymbolicSampleSizes::kSample64 as i32)
         );
 
         // This is needed when activating the plugin and when restoring state
@@ -922,6 +1270,8 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             };
 
             process_wrapper(|| plugin.reset());
+            self.inner.managed_bypass.borrow_mut().reset();
+            self.inner.managed_bypass_f64.borrow_mut().reset();
         }
 
         // We don't have any special handling for suspending and resuming plugins, yet
@@ -946,18 +1296,22 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 .sample_rate;
 
             nih_debug_assert!(data.num_inputs >= 0 && data.num_outputs >= 0);
-            nih_debug_assert_eq!(
-                data.symbolic_sample_size,
-                vst3_sys::vst::SymbolicSampleSizes::kSample32 as i32
-            );
             nih_debug_assert!(data.num_samples >= 0);
 
             let total_buffer_len = data.num_samples as usize;
+            let use_f64 =
+                data.symbolic_sample_size == vst3_sys::vst::SymbolicSampleSizes::kSample64 as i32;
+            nih_debug_assert!(
+                P::SUPPORTS_F64 || !use_f64,
+                "The host requested double precision processing even though the plugin does not \
+                 support it"
+            );
+            if use_f64 && !P::SUPPORTS_F64 {
+                return kResultFalse;
+            }
 
             let current_audio_io_layout = self.inner.current_audio_io_layout.load();
-            let has_main_input = current_audio_io_layout.main_input_channels.is_some();
             let has_main_output = current_audio_io_layout.main_output_channels.is_some();
-            let aux_input_start_idx = if has_main_input { 1 } else { 0 };
             let aux_output_start_idx = if has_main_output { 1 } else { 0 };
 
             // NOTE: VST3 hosts may trigger a 'parameter flush' by calling the process function for
@@ -1170,6 +1524,16 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 })
             });
 
+            // VST3 does not have any parameter modulation, but the modulation buffers should still
+            // match the size of the blocks passed to the plugin
+            if !is_param_flush {
+                for param in &self.inner.modulation_buffer_params {
+                    if let Some(modulation_buffer) = param.modulation_buffer() {
+                        modulation_buffer.start_block(total_buffer_len);
+                    }
+                }
+            }
+
             let mut block_start = 0usize;
             let mut block_end;
             let mut event_start_idx = 0;
@@ -1226,108 +1590,6 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                     // and we can start preparing audio processing
                     let block_len = block_end - block_start;
 
-                    // The buffer manager preallocated buffer slices for all the IO and storage for
-                    // any axuiliary inputs.
-                    let mut buffer_manager = self.inner.buffer_manager.borrow_mut();
-                    let buffers =
-                        buffer_manager.create_buffers(block_start, block_len, |buffer_source| {
-                            if data.num_outputs > 0
-                                && !data.outputs.is_null()
-                                && !(*data.outputs).buffers.is_null()
-                                && has_main_output
-                            {
-                                let audio_output = &*data.outputs;
-                                let ptrs =
-                                    NonNull::new(audio_output.buffers as *mut *mut f32).unwrap();
-                                let num_channels = audio_output.num_channels as usize;
-
-                                *buffer_source.main_output_channel_pointers =
-                                    Some(ChannelPointers { ptrs, num_channels });
-                            }
-
-                            if data.num_inputs > 0
-                                && !data.inputs.is_null()
-                                && !(*data.inputs).buffers.is_null()
-                                && has_main_input
-                            {
-                                let audio_input = &*data.inputs;
-                                let ptrs =
-                                    NonNull::new(audio_input.buffers as *mut *mut f32).unwrap();
-                                let num_channels = audio_input.num_channels as usize;
-
-                                *buffer_source.main_input_channel_pointers =
-                                    Some(ChannelPointers { ptrs, num_channels });
-                            }
-
-                            if !data.inputs.is_null() {
-                                for (aux_input_no, aux_input_channel_pointers) in buffer_source
-                                    .aux_input_channel_pointers
-                                    .iter_mut()
-                                    .enumerate()
-                                {
-                                    let aux_input_idx = aux_input_no + aux_input_start_idx;
-                                    if aux_input_idx > data.num_outputs as usize {
-                                        break;
-                                    }
-
-                                    let audio_input = &*data.inputs.add(aux_input_idx);
-                                    match NonNull::new(audio_input.buffers as *mut *mut f32) {
-                                        Some(ptrs) => {
-                                            let num_channels = audio_input.num_channels as usize;
-
-                                            *aux_input_channel_pointers =
-                                                Some(ChannelPointers { ptrs, num_channels });
-                                        }
-                                        None => continue,
-                                    }
-                                }
-                            }
-
-                            if !data.outputs.is_null() {
-                                for (aux_output_no, aux_output_channel_pointers) in buffer_source
-                                    .aux_output_channel_pointers
-                                    .iter_mut()
-                                    .enumerate()
-                                {
-                                    let aux_output_idx = aux_output_no + aux_output_start_idx;
-                                    if aux_output_idx > data.num_outputs as usize {
-                                        break;
-                                    }
-
-                                    let audio_output = &*data.outputs.add(aux_output_idx);
-                                    match NonNull::new(audio_output.buffers as *mut *mut f32) {
-                                        Some(ptrs) => {
-                                            let num_channels = audio_output.num_channels as usize;
-
-                                            *aux_output_channel_pointers =
-                                                Some(ChannelPointers { ptrs, num_channels });
-                                        }
-                                        None => continue,
-                                    }
-                                }
-                            }
-                        });
-
-                    // We already checked whether the host has initiated a parameter flush, but in
-                    // case it still did something unexpected that we did not catch we'll still try
-                    // to prevent processing audio when the slices don't contain the values we
-                    // expect.
-                    let mut buffer_is_valid = true;
-                    for output_buffer_slice in
-                        buffers.main_buffer.as_slice_immutable().iter().chain(
-                            buffers
-                                .aux_outputs
-                                .iter()
-                                .flat_map(|buffer| buffer.as_slice_immutable().iter()),
-                        )
-                    {
-                        if output_buffer_slice.is_empty() {
-                            buffer_is_valid = false;
-                            break;
-                        }
-                    }
-                    nih_debug_assert!(buffer_is_valid);
-
                     // Some of the fields are left empty because VST3 does not provide this
                     // information, but the methods on [`Transport`] can reconstruct these values
                     // from the other fields
@@ -1389,20 +1651,32 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                         }
                     }
 
-                    let result = if buffer_is_valid {
-                        // NOTE: `parking_lot`'s mutexes sometimes allocate because of their use of
-                        //       thread locals
-                        let mut plugin = permit_alloc(|| self.inner.plugin.lock());
-                        let mut aux = AuxiliaryBuffers {
-                            inputs: buffers.aux_inputs,
-                            outputs: buffers.aux_outputs,
-                        };
-                        let mut context = self.inner.make_process_context(transport);
-                        let result = plugin.process(buffers.main_buffer, &mut aux, &mut context);
-                        self.inner.last_process_status.store(result);
-                        result
+                    for param in &self.inner.modulation_buffer_params {
+                        if let Some(modulation_buffer) = param.modulation_buffer() {
+                            modulation_buffer.set_block(block_start, block_len);
+                        }
+                    }
+
+                    let result = if use_f64 {
+                        self.process_block(
+                            &self.inner.buffer_manager_f64,
+                            &self.inner.managed_bypass_f64,
+                            data,
+                            block_start,
+                            block_len,
+                            transport,
+                            |plugin, buffer, aux, context| plugin.process_f64(buffer, aux, context),
+                        )
                     } else {
-                        ProcessStatus::Normal
+                        self.process_block(
+                            &self.inner.buffer_manager,
+                            &self.inner.managed_bypass,
+                            data,
+                            block_start,
+                            block_len,
+                            transport,
+                            |plugin, buffer, aux, context| plugin.process(buffer, aux, context),
+                        )
                     };
 
                     match result {
@@ -1646,6 +1920,24 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
                 }
             };
 
+            // The plugin can mark its outputs as silent, so the host can skip work in the following
+            // plugins
+            if !is_param_flush && !data.outputs.is_null() {
+                if use_f64 {
+                    Self::write_output_silence_flags(
+                        &self.inner.buffer_manager_f64.borrow(),
+                        data,
+                        aux_output_start_idx,
+                    );
+                } else {
+                    Self::write_output_silence_flags(
+                        &self.inner.buffer_manager.borrow(),
+                        data,
+                        aux_output_start_idx,
+                    );
+                }
+            }
+
             // After processing audio, we'll check if the editor has sent us updated plugin state.
             // We'll restore that here on the audio thread to prevent changing the values during the
             // process call and also to prevent inconsistent state when the host also wants to load
@@ -1653,12 +1945,12 @@ impl<P: Vst3Plugin> IAudioProcessor for Wrapper<P> {
             // FIXME: Zero capacity channels allocate on receiving, find a better alternative that
             //        doesn't do that
             let updated_state = permit_alloc(|| self.inner.updated_state_receiver.try_recv());
-            if let Ok(mut state) = updated_state {
-                self.inner.set_state_inner(&mut state);
+            if let Ok((mut state, context)) = updated_state {
+                self.inner.set_state_inner(&mut state, context);
 
                 // We'll pass the state object back to the GUI thread so deallocation can happen
                 // there without potentially blocking the audio thread
-                if let Err(err) = self.inner.updated_state_sender.send(state) {
+                if let Err(err) = self.inner.updated_state_sender.send((state, context)) {
                     nih_debug_assert_failure!(
                         "Failed to send state object back to GUI thread: {}",
                         err
@@ -1893,3 +2185,55 @@ impl<P: Vst3Plugin> IUnitInfo for Wrapper<P> {
         kInvalidArgument
     }
 }
+
+impl<P: Vst3Plugin> IInfoListener for Wrapper<P> {
+    unsafe fn set_channel_context_infos(&self, list: SharedVstPtr<dyn IAttributeList>) -> tresult {
+        let list = match list.upgrade() {
+            Some(list) => list,
+            None => return kInvalidArgument,
+        };
+
+        let mut name_buffer = [0 as TChar; 128];
+        let name = if list.get_string(
+            VST3_CHANNEL_NAME_KEY.as_ptr() as *const _,
+            name_buffer.as_mut_ptr(),
+            mem::size_of_val(&name_buffer) as u32,
+        ) == kResultOk
+        {
+            // Just in case the host didn't null terminate the name
+            name_buffer[name_buffer.len() - 1] = 0;
+            U16CStr::from_ptr_str(name_buffer.as_ptr() as *const u16)
+                .to_string()
+                .ok()
+        } else {
+            None
+        };
+
+        // This is an ARGB `ColorSpec` stored in an `int64`
+        let mut color_spec = 0i64;
+        let color = if list.get_int(VST3_CHANNEL_COLOR_KEY.as_ptr() as *const _, &mut color_spec)
+            == kResultOk
+        {
+            Some(HostColor {
+                red: (color_spec >> 16) as u8,
+                green: (color_spec >> 8) as u8,
+                blue: color_spec as u8,
+                alpha: (color_spec >> 24) as u8,
+            })
+        } else {
+            None
+        };
+
+        *self.inner.track_info.write() = Some(TrackInfo {
+            name,
+            color,
+            channel_count: None,
+            kind: None,
+        });
+
+        let task_posted = self.inner.schedule_gui(Task::TrackInfoChanged);
+        nih_debug_assert!(task_posted, "The task queue is full, dropping task...");
+
+        kResultOk
+    }
+}